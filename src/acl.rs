@@ -0,0 +1,106 @@
+//! 受限 ACL 使用者：掃描中遇到 NOPERM 的選用指令只記錄一次，之後整個掃描都不再送
+//!
+//! ACL 擋掉的是整個指令（例如 -object），每個 key 都會回同樣的錯誤；繼續送只是讓伺服器
+//! 回幾百萬次 NOPERM。第一次看到後關掉對應的收集項目，報表列出因此缺少的章節與需要的 ACL 規則。
+//! MEMORY USAGE / TYPE 是量測本身，被擋時沒有可用的結果，直接中止掃描。
+
+use crate::scan::ScanConfig;
+use redis::Value;
+use serde::{Deserialize, Serialize};
+
+/// 可以單獨關掉的收集項目
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Collector {
+    Ttl,
+    Idle,
+    Encoding,
+    Freq,
+    Elements,
+    IntStrings,
+}
+
+impl Collector {
+    /// 與 capabilities 的功能名稱相同
+    pub fn command(self) -> &'static str {
+        match self {
+            Collector::Ttl => "PTTL",
+            Collector::Idle => "OBJECT IDLETIME",
+            Collector::Encoding => "OBJECT ENCODING",
+            Collector::Freq => "OBJECT FREQ",
+            Collector::Elements => "STRLEN / LLEN / HLEN / SCARD / ZCARD / XLEN",
+            Collector::IntStrings => "GETRANGE",
+        }
+    }
+
+    fn acl_rule(self) -> &'static str {
+        match self {
+            Collector::Ttl => "+pttl",
+            Collector::Idle => "+object|idletime",
+            Collector::Encoding => "+object|encoding",
+            Collector::Freq => "+object|freq",
+            Collector::Elements => "+strlen +llen +hlen +scard +zcard +xlen",
+            Collector::IntStrings => "+getrange",
+        }
+    }
+
+    /// 關掉後報表缺少的內容
+    fn sections(self) -> &'static [&'static str] {
+        match self {
+            Collector::Ttl => &["到期時間軸", "殭屍 key", "TTL 政策建議", "Top key 的 TTL"],
+            Collector::Idle => &["殭屍 key", "Top key 的閒置時間", "Key 年齡的閒置下限"],
+            Collector::Encoding => &["結構開銷估算", "存成字串的整數", "Top key 的 encoding"],
+            Collector::Freq => &["Top key 的存取頻率"],
+            Collector::Elements => &["Top key 的元素數", "結構開銷估算", "依元素數的 Top N"],
+            Collector::IntStrings => &["存成字串的整數"],
+        }
+    }
+
+    /// 之後的批次不再送這個指令
+    pub fn disable(self, cfg: &mut ScanConfig) {
+        match self {
+            Collector::Ttl => cfg.collect_ttl = false,
+            Collector::Idle => cfg.collect_idle = false,
+            Collector::Encoding => cfg.collect_encoding = false,
+            Collector::Freq => cfg.collect_freq = false,
+            Collector::Elements => cfg.collect_cardinality = false,
+            Collector::IntStrings => cfg.int_strings = false,
+        }
+    }
+
+    pub fn denial(self, target: &str, error: &str) -> AclDenial {
+        AclDenial {
+            target: target.to_string(),
+            command: self.command().to_string(),
+            acl: self.acl_rule().to_string(),
+            sections: self.sections().iter().map(|s| s.to_string()).collect(),
+            error: error.to_string(),
+        }
+    }
+}
+
+/// 因 ACL 權限不足而關掉的收集項目
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AclDenial {
+    pub target: String,
+    pub command: String,
+    pub acl: String,           // 需要加上的 ACL 規則，例如 +object|idletime
+    pub sections: Vec<String>, // 因此缺少的報表內容
+    pub error: String,         // 第一次收到的錯誤回覆
+}
+
+/// 整個指令被 ACL 擋掉的錯誤回覆（NOPERM ... no permissions to run the 'x' command）
+///
+/// 只擋部分 key 的 NOPERM（~pattern 不符，"no permissions to access a key"）不算：
+/// 其他 key 仍可能成功，照一般的失敗 key 計算。
+pub fn is_denied(error: &str) -> bool {
+    error.contains("NOPERM") && error.contains("permissions to run")
+}
+
+/// 同 is_denied，回傳錯誤訊息
+pub fn denied_reply(v: &Value) -> Option<String> {
+    let Value::ServerError(e) = v else {
+        return None;
+    };
+    let msg = redis::RedisError::from(e.clone()).to_string();
+    is_denied(&msg).then_some(msg)
+}
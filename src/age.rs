@@ -0,0 +1,345 @@
+//! Key 年齡推算（--age-rules）：key 名稱中的時間戳、日期或遞增 ID，加上 OBJECT IDLETIME 當下限
+//!
+//! 規則檔每行 `pattern = 來源 [保留期限]`，例如 `order:* = unix 90d`、`log:* = date 30d`、
+//! `user:* = seq`，依檔案順序比對，第一個符合的生效；超過保留期限的 key 另外列出。
+//!
+//! seq（遞增 ID）沒有絕對時間：ID 越小建立得越早，所以一個 key 至少和
+//! 「ID 不小於它的 key 中最久的閒置時間」一樣老，需要同時收集 idle。
+
+use crate::cleanup::glob_match;
+use crate::format::parse_duration;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 年齡分桶的上限（不含）與標籤
+pub const AGE_BUCKETS: &[(u64, &str)] = &[
+    (3_600, "< 1h"),
+    (86_400, "1h ~ 1d"),
+    (7 * 86_400, "1d ~ 7d"),
+    (30 * 86_400, "7d ~ 30d"),
+    (90 * 86_400, "30d ~ 90d"),
+    (365 * 86_400, "90d ~ 1y"),
+    (u64::MAX, ">= 1y"),
+];
+pub const AGE_OLDEST: usize = 10; // 每條規則列出超過保留期限的最大 key 數
+pub const IDLE_ONLY: &str = "(其他 key)"; // 不符合任何規則，只有 OBJECT IDLETIME 下限
+const SEQ_MAX_KEYS: usize = 200_000; // seq 規則最多記住幾個 key，超過的只用自己的 idle 當年齡
+const MIN_TIMESTAMP: u64 = 946_684_800; // 2000-01-01，早於此值的數字不當作時間戳
+
+/// 建立時間的來源
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgeSource {
+    Unix,   // 名稱中 10 位數的 Unix 秒
+    UnixMs, // 13 位數的 Unix 毫秒
+    Date,   // 8 位數的 YYYYMMDD
+    Seq,    // 遞增 ID
+    Idle,   // 只有 OBJECT IDLETIME
+}
+
+impl AgeSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            AgeSource::Unix => "unix",
+            AgeSource::UnixMs => "unix_ms",
+            AgeSource::Date => "date",
+            AgeSource::Seq => "seq",
+            AgeSource::Idle => "idle",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "unix" => Some(AgeSource::Unix),
+            "unix_ms" => Some(AgeSource::UnixMs),
+            "date" => Some(AgeSource::Date),
+            "seq" => Some(AgeSource::Seq),
+            _ => None,
+        }
+    }
+
+    /// 名稱中第一段符合格式的數字 → 建立時間（Unix 秒）；seq 回傳 ID
+    fn extract(self, key: &str, now: u64) -> Option<u64> {
+        digit_runs(key).find_map(|run| match self {
+            AgeSource::Unix if run.len() == 10 => run.parse().ok().filter(|&t| plausible(t, now)),
+            AgeSource::UnixMs if run.len() == 13 => run
+                .parse::<u64>()
+                .ok()
+                .map(|ms| ms / 1000)
+                .filter(|&t| plausible(t, now)),
+            AgeSource::Date if run.len() == 8 => parse_date(run).filter(|&t| plausible(t, now)),
+            AgeSource::Seq if run.len() <= 19 => run.parse().ok(),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct AgeRule {
+    pattern: String,
+    source: AgeSource,
+    retention_secs: Option<u64>,
+}
+
+/// key pattern → 建立時間的來源與保留期限，依檔案順序比對，第一個符合的生效
+#[derive(Debug, Default)]
+pub struct AgeRules {
+    rules: Vec<AgeRule>,
+}
+
+impl AgeRules {
+    /// 讀取規則檔：每行 `pattern = unix|unix_ms|date|seq [保留期限]`，`#` 之後為註解
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| format!("無法讀取年齡規則 {}: {}", path.display(), e))?;
+
+        let mut rules = Vec::new();
+        for (no, line) in raw.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let bad = |why: &str| format!("{} 第 {} 行: {}", path.display(), no + 1, why);
+            let (pattern, spec) = line
+                .rsplit_once('=')
+                .map(|(p, s)| (p.trim(), s.trim()))
+                .filter(|(p, s)| !p.is_empty() && !s.is_empty())
+                .ok_or_else(|| bad("格式應為 PATTERN = unix|unix_ms|date|seq [保留期限]"))?;
+            let mut words = spec.split_whitespace();
+            let source = words
+                .next()
+                .and_then(AgeSource::parse)
+                .ok_or_else(|| bad("來源應為 unix、unix_ms、date 或 seq"))?;
+            let retention_secs = words
+                .next()
+                .map(|d| parse_duration(d).map(|d| d.as_secs()))
+                .transpose()
+                .map_err(|e| bad(&e))?;
+            rules.push(AgeRule {
+                pattern: pattern.to_string(),
+                source,
+                retention_secs,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    fn rule_of(&self, key: &str) -> Option<usize> {
+        self.rules.iter().position(|r| glob_match(&r.pattern, key))
+    }
+}
+
+/// 超過保留期限的 key
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AgedKey {
+    pub key: String,
+    pub mem: u64,
+    pub age_secs: u64,
+}
+
+/// 單一規則的年齡分布
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AgeGroup {
+    pub pattern: String,
+    pub source: AgeSource,
+    pub retention_secs: Option<u64>,
+    pub count: u64, // 推算出年齡的 key 數
+    pub total_mem: u64,
+    pub buckets: Vec<(u64, u64)>, // 與 AGE_BUCKETS 對應的 (count, mem)
+    pub unknown: u64,             // 符合規則但推算不出年齡（名稱中沒有時間、seq 沒有 idle）
+    pub unknown_mem: u64,
+    pub expired: u64, // 超過保留期限
+    pub expired_mem: u64,
+    pub oldest: Vec<AgedKey>, // 超過保留期限中最大的 AGE_OLDEST 個，依記憶體 desc
+}
+
+impl AgeGroup {
+    fn new(pattern: &str, source: AgeSource, retention_secs: Option<u64>) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            source,
+            retention_secs,
+            count: 0,
+            total_mem: 0,
+            buckets: vec![(0, 0); AGE_BUCKETS.len()],
+            unknown: 0,
+            unknown_mem: 0,
+            expired: 0,
+            expired_mem: 0,
+            oldest: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, key: &str, mem: u64, age_secs: Option<u64>) {
+        let Some(age) = age_secs else {
+            self.unknown += 1;
+            self.unknown_mem += mem;
+            return;
+        };
+        self.count += 1;
+        self.total_mem += mem;
+        let bucket = AGE_BUCKETS
+            .iter()
+            .position(|&(limit, _)| age < limit)
+            .unwrap_or(AGE_BUCKETS.len() - 1);
+        self.buckets[bucket].0 += 1;
+        self.buckets[bucket].1 += mem;
+
+        if self.retention_secs.is_some_and(|r| age > r) {
+            self.expired += 1;
+            self.expired_mem += mem;
+            self.keep_oldest(AgedKey {
+                key: key.to_string(),
+                mem,
+                age_secs: age,
+            });
+        }
+    }
+
+    fn keep_oldest(&mut self, k: AgedKey) {
+        if self.oldest.len() >= AGE_OLDEST
+            && self.oldest.last().is_some_and(|last| k.mem <= last.mem)
+        {
+            return;
+        }
+        self.oldest.push(k);
+        self.oldest
+            .sort_by(|a, b| b.mem.cmp(&a.mem).then_with(|| a.key.cmp(&b.key)));
+        self.oldest.truncate(AGE_OLDEST);
+    }
+
+    /// 合併另一個節點的同一條規則
+    pub fn absorb(&mut self, other: AgeGroup) {
+        self.count += other.count;
+        self.total_mem += other.total_mem;
+        for (b, o) in self.buckets.iter_mut().zip(other.buckets) {
+            b.0 += o.0;
+            b.1 += o.1;
+        }
+        self.unknown += other.unknown;
+        self.unknown_mem += other.unknown_mem;
+        self.expired += other.expired;
+        self.expired_mem += other.expired_mem;
+        for k in other.oldest {
+            self.keep_oldest(k);
+        }
+    }
+}
+
+/// 掃描中累計各規則的年齡；seq 規則要等掃描結束、看過所有 ID 才能推算
+pub struct AgeStats {
+    rules: Arc<AgeRules>,
+    now: u64,
+    groups: Vec<AgeGroup>, // 與 rules 對應，最後一個是 IDLE_ONLY
+    seq: Vec<Vec<(u64, u64, u64, String)>>, // 每條規則的 (ID, idle, mem, key)
+}
+
+impl AgeStats {
+    pub fn new(rules: Arc<AgeRules>, now: u64) -> Self {
+        let mut groups: Vec<AgeGroup> = rules
+            .rules
+            .iter()
+            .map(|r| AgeGroup::new(&r.pattern, r.source, r.retention_secs))
+            .collect();
+        groups.push(AgeGroup::new(IDLE_ONLY, AgeSource::Idle, None));
+        let seq = rules.rules.iter().map(|_| Vec::new()).collect();
+        Self {
+            rules,
+            now,
+            groups,
+            seq,
+        }
+    }
+
+    /// idle_secs 為 OBJECT IDLETIME；key 至少存在了這麼久
+    pub fn add_key(&mut self, key: &str, mem: u64, idle_secs: Option<u64>) {
+        let Some(i) = self.rules.rule_of(key) else {
+            if idle_secs.is_some() {
+                self.groups
+                    .last_mut()
+                    .expect("IDLE_ONLY")
+                    .add(key, mem, idle_secs);
+            }
+            return;
+        };
+        let source = self.rules.rules[i].source;
+        let extracted = source.extract(key, self.now);
+        if source == AgeSource::Seq {
+            if let (Some(id), Some(idle)) = (extracted, idle_secs) {
+                if self.seq[i].len() < SEQ_MAX_KEYS {
+                    self.seq[i].push((id, idle, mem, key.to_string()));
+                    return;
+                }
+            }
+            self.groups[i].add(key, mem, idle_secs);
+            return;
+        }
+        let created_age = extracted.map(|t| self.now.saturating_sub(t));
+        let age = match (created_age, idle_secs) {
+            (Some(a), Some(idle)) => Some(a.max(idle)),
+            (a, idle) => a.or(idle),
+        };
+        self.groups[i].add(key, mem, age);
+    }
+
+    pub fn into_reports(mut self) -> Vec<AgeGroup> {
+        for (i, mut keys) in std::mem::take(&mut self.seq).into_iter().enumerate() {
+            // ID 由大到小，年齡下限為目前看過的最大 idle
+            keys.sort_by_key(|k| std::cmp::Reverse(k.0));
+            let mut bound = 0;
+            for (_, idle, mem, key) in keys {
+                bound = bound.max(idle);
+                self.groups[i].add(&key, mem, Some(bound));
+            }
+        }
+        self.groups
+            .into_iter()
+            .filter(|g| g.count + g.unknown > 0)
+            .collect()
+    }
+}
+
+/// 依 pattern 與來源合併多個節點的結果，順序沿用第一次出現的順序
+pub fn merge_age_groups(groups: impl IntoIterator<Item = AgeGroup>) -> Vec<AgeGroup> {
+    let mut merged: Vec<AgeGroup> = Vec::new();
+    for g in groups {
+        match merged
+            .iter_mut()
+            .find(|m| m.pattern == g.pattern && m.source == g.source)
+        {
+            Some(m) => m.absorb(g),
+            None => merged.push(g),
+        }
+    }
+    merged
+}
+
+fn digit_runs(key: &str) -> impl Iterator<Item = &str> {
+    key.split(|c: char| !c.is_ascii_digit())
+        .filter(|run| !run.is_empty())
+}
+
+/// 2000 年之後、不超過現在一天（時鐘誤差）
+fn plausible(t: u64, now: u64) -> bool {
+    (MIN_TIMESTAMP..=now + 86_400).contains(&t)
+}
+
+/// "20240131" → 當天 00:00 UTC 的 Unix 秒
+fn parse_date(s: &str) -> Option<u64> {
+    let y: i64 = s[..4].parse().ok()?;
+    let m: i64 = s[4..6].parse().ok()?;
+    let d: i64 = s[6..].parse().ok()?;
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+    // days-from-civil（與 format::civil_from_unix 相反）
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days * 86_400).ok()
+}
@@ -0,0 +1,230 @@
+use crate::format::{Decimal, format_int, format_iso8601, now_unix};
+use crate::output::warning;
+use crate::progress::ProgressBar;
+use crate::report::Report;
+use crate::stats::KeyTypeCode;
+use crate::upload::post_json;
+use serde_json::{Value, json};
+use std::error::Error;
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+const PAGERDUTY_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+const OPSGENIE_URL: &str = "https://api.opsgenie.com/v2/alerts";
+const OFFENDERS: usize = 10; // 事件中附上的最大 key 數
+const OPSGENIE_MESSAGE_CHARS: usize = 130; // Opsgenie message 欄位上限
+const EARLY_ALERT_LINES: u64 = 20; // 掃描中最多印出幾行大 key 警告
+const EARLY_ALERT_POSTS: u64 = 5; // 掃描中最多送出幾次 webhook，避免洗版
+
+/// 觸發告警的硬門檻
+#[derive(Clone, Copy)]
+pub struct AlertThresholds {
+    pub max_key_bytes: u64, // 單一 key 超過此大小
+    pub max_used: f64,      // used_memory 超過 maxmemory 的此比例（0~1）
+}
+
+/// --alert-size：掃描中一量到超過門檻的 key 就提醒，不必等整份報表
+#[derive(Clone, Debug)]
+pub struct EarlyAlertConfig {
+    pub min_bytes: u64,
+    pub webhook: Option<String>, // 同時 POST 到此 URL（payload 含 text 欄位，可直接用 Slack incoming webhook）
+}
+
+/// 單一節點掃描中的大 key 提醒；webhook 在背景執行緒送出，不拖慢掃描
+pub struct EarlyAlerts {
+    cfg: EarlyAlertConfig,
+    target: String,
+    started: Instant,
+    count: u64,
+    sends: Vec<JoinHandle<Result<(), String>>>,
+}
+
+impl EarlyAlerts {
+    pub fn new(cfg: &EarlyAlertConfig, target: &str) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            target: target.to_string(),
+            started: Instant::now(),
+            count: 0,
+            sends: Vec::new(),
+        }
+    }
+
+    pub fn check(&mut self, pb: &ProgressBar, t: KeyTypeCode, key: &str, mem: u64) {
+        if mem <= self.cfg.min_bytes {
+            return;
+        }
+        self.count += 1;
+        let text = format!(
+            "🚨 {} 發現超過 {:.2} MB 的 key: {} {}（{:.2} MB，掃描開始後 {}s）",
+            self.target,
+            Decimal(self.cfg.min_bytes as f64 / 1024.0 / 1024.0),
+            t.name(),
+            key,
+            Decimal(mem as f64 / 1024.0 / 1024.0),
+            self.started.elapsed().as_secs()
+        );
+        if self.count <= EARLY_ALERT_LINES {
+            pb.suspend(|| warning!("{}", text));
+        }
+
+        let Some(url) = &self.cfg.webhook else {
+            return;
+        };
+        if self.count > EARLY_ALERT_POSTS {
+            return;
+        }
+        let body = json!({
+            "text": text,
+            "event": "oversized_key",
+            "target": self.target,
+            "type": t.name(),
+            "key": key,
+            "bytes": mem,
+            "threshold": self.cfg.min_bytes,
+            "elapsed_secs": self.started.elapsed().as_secs(),
+            "detected_at": format_iso8601(now_unix()),
+        });
+        let url = url.clone();
+        self.sends.push(std::thread::spawn(move || {
+            let body = serde_json::to_vec(&body).map_err(|e| e.to_string())?;
+            post_json(&url, body, None).map_err(|e| e.to_string())
+        }));
+    }
+
+    /// 等 webhook 送完；送出失敗只提醒，不影響掃描結果
+    pub fn finish(self) {
+        for send in self.sends {
+            match send.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warning!("⚠ 大 key 提醒 webhook 送出失敗: {}", e),
+                Err(_) => warning!("⚠ 大 key 提醒 webhook 執行緒異常結束"),
+            }
+        }
+        if self.count > EARLY_ALERT_LINES {
+            warning!(
+                "🚨 {} 共 {} 個 key 超過 {:.2} MB（只列出前 {} 個，其餘見報表）",
+                self.target,
+                format_int(self.count),
+                Decimal(self.cfg.min_bytes as f64 / 1024.0 / 1024.0),
+                EARLY_ALERT_LINES
+            );
+        }
+    }
+}
+
+/// 超過門檻的項目，每項一句描述；沒有超過時為空
+pub fn critical_findings(report: &Report, t: AlertThresholds) -> Vec<String> {
+    let mut findings = Vec::new();
+
+    let big: Vec<_> = report
+        .global_top(usize::MAX)
+        .into_iter()
+        .filter(|(_, k)| k.mem > t.max_key_bytes)
+        .collect();
+    if let Some((type_code, k)) = big.first() {
+        findings.push(format!(
+            "{} 個 key 超過 {:.2} MB，最大為 {} {}（{:.2} MB）",
+            big.len(),
+            Decimal(t.max_key_bytes as f64 / 1024.0 / 1024.0),
+            type_code.name(),
+            k.key,
+            Decimal(k.mem as f64 / 1024.0 / 1024.0)
+        ));
+    }
+
+    // 多節點合併的報表沒有 INFO memory，無法判斷使用率
+    let info_num = |name: &str| {
+        report
+            .memory_info
+            .as_ref()
+            .and_then(|m| m.get(name))
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+    let max = info_num("maxmemory").filter(|&m| m > 0);
+    if let (Some(used), Some(max)) = (info_num("used_memory"), max) {
+        let ratio = used as f64 / max as f64;
+        if ratio > t.max_used {
+            findings.push(format!(
+                "used_memory {:.2} MB 已達 maxmemory 的 {:.1}%（門檻 {:.0}%）",
+                Decimal(used as f64 / 1024.0 / 1024.0),
+                Decimal(ratio * 100.0),
+                Decimal(t.max_used * 100.0)
+            ));
+        }
+    }
+
+    findings
+}
+
+/// 事件的詳細內容：各項發現與最大的幾個 key（不含值預覽，避免資料外流到告警系統）
+fn details(report: &Report, findings: &[String]) -> Value {
+    let offenders: Vec<Value> = report
+        .global_top(OFFENDERS)
+        .into_iter()
+        .map(|(type_code, k)| json!({ "type": type_code.name(), "key": k.key, "bytes": k.mem }))
+        .collect();
+    json!({
+        "target": report.target,
+        "started_at": report.started_at_utc,
+        "finished_at": report.finished_at_utc,
+        "duration_ms": report.duration_ms,
+        "scanned": report.scanned,
+        "total_mem": report.total_mem(),
+        "findings": findings,
+        "notes": report.notes,
+        "top_keys": offenders,
+    })
+}
+
+fn summary(report: &Report, findings: &[String]) -> String {
+    format!(
+        "Redis {}（{} keys）: {}",
+        report.target,
+        format_int(report.scanned),
+        findings.join("；")
+    )
+}
+
+/// 以 PagerDuty Events API v2 觸發事件；同一個目標共用 dedup_key，重複掃描不會開出多張事件
+pub fn send_pagerduty(
+    report: &Report,
+    findings: &[String],
+    routing_key: &str,
+) -> Result<(), Box<dyn Error>> {
+    let body = json!({
+        "routing_key": routing_key,
+        "event_action": "trigger",
+        "dedup_key": format!("redis-top-keys:{}", report.target),
+        "payload": {
+            "summary": summary(report, findings),
+            "source": report.target,
+            "severity": "critical",
+            "component": "redis",
+            "custom_details": details(report, findings),
+        },
+    });
+    post_json(PAGERDUTY_URL, serde_json::to_vec(&body)?, None)
+}
+
+/// 以 Opsgenie Alert API 建立 P1 告警；alias 相同時 Opsgenie 會合併成同一筆
+pub fn send_opsgenie(
+    report: &Report,
+    findings: &[String],
+    api_key: &str,
+) -> Result<(), Box<dyn Error>> {
+    let message: String = summary(report, findings)
+        .chars()
+        .take(OPSGENIE_MESSAGE_CHARS)
+        .collect();
+    let body = json!({
+        "message": message,
+        "alias": format!("redis-top-keys:{}", report.target),
+        "description": serde_json::to_string_pretty(&details(report, findings))?,
+        "source": "redis-top-keys-analyzer",
+        "priority": "P1",
+        "details": { "target": report.target },
+    });
+    let auth = format!("GenieKey {}", api_key);
+    post_json(OPSGENIE_URL, serde_json::to_vec(&body)?, Some(&auth))
+}
@@ -0,0 +1,35 @@
+//! jemalloc 的 size class 近似，用來估算 Redis 物件實際佔用的配置大小
+
+/// 配置 n bytes 時 jemalloc 實際給的大小
+///
+/// 8、16，之後到 128 每 16 一級；再往上每個 2 的次方區間分 4 級（160、192、224、256、320…）。
+pub fn size_class(n: u64) -> u64 {
+    if n <= 8 {
+        return 8;
+    }
+    if n <= 128 {
+        return n.div_ceil(16) * 16;
+    }
+    let k = 63 - (n - 1).leading_zeros() as u64; // floor(log2(n - 1))
+    let spacing = 1u64 << (k - 2);
+    n.div_ceil(spacing) * spacing
+}
+
+/// embstr 物件（robj 16 bytes + sdshdr8 3 bytes + 內容 + 結尾 \0）的配置大小
+pub fn embstr_alloc(len: u64) -> u64 {
+    size_class(16 + 3 + len + 1)
+}
+
+/// 比 size_class(n) 小一級的 class（n 縮到這個大小以下才會換到較小的配置）
+pub fn lower_class(n: u64) -> u64 {
+    let class = size_class(n);
+    match class {
+        8 => 0,
+        16 => 8,
+        c if c <= 128 => c - 16,
+        c => {
+            let k = 63 - (c - 1).leading_zeros() as u64;
+            c - (1u64 << (k - 2))
+        }
+    }
+}
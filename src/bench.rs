@@ -0,0 +1,239 @@
+use crate::conn::RedisConn;
+use crate::format::format_int;
+use crate::scan::{ScanConfig, fetch_key_meta_batch};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SCAN_COUNTS: [u64; 5] = [500, 1000, 5000, 10_000, 20_000];
+const BATCH_SIZES: [usize; 5] = [200, 500, 1000, 2000, 5000];
+const SAMPLES: [Option<u64>; 3] = [None, Some(10), Some(0)];
+const PING_EVERY: Duration = Duration::from_millis(10); // 延遲探測的間隔
+const BASELINE_WINDOW: Duration = Duration::from_secs(1);
+
+/// 一組參數的試跑結果
+struct Trial {
+    cfg: ScanConfig,
+    keys_per_sec: f64,
+    max_batch_ms: f64,
+    ping_p50: f64,
+    ping_p99: f64,
+}
+
+/// 依序試跑 SCAN COUNT、batch size、MEMORY USAGE SAMPLES，每次只變動一個參數
+///
+/// 試跑期間用另一條連線持續 PING，以 p99 延遲代表對伺服器其他 client 的影響。
+/// SCAN cursor 在各組試跑之間接續，避免一直量到同一批 key。
+pub fn run(con: &mut RedisConn, trial: Duration, max_latency_ms: f64) -> redis::RedisResult<()> {
+    let (base_p50, base_p99) = measure_idle_latency(con)?;
+    println!(
+        "基準 PING 延遲（無掃描負載）: p50 {:.2} ms, p99 {:.2} ms\n",
+        base_p50, base_p99
+    );
+
+    let mut cursor: u64 = 0;
+    let mut best = ScanConfig::default();
+
+    print_header();
+
+    // SCAN COUNT
+    let mut trials = Vec::new();
+    for &scan_count in &SCAN_COUNTS {
+        let cfg = ScanConfig {
+            scan_count,
+            ..best.clone()
+        };
+        trials.push(run_trial(con, cfg, trial, &mut cursor)?);
+        print_trial(trials.last().unwrap(), max_latency_ms);
+    }
+    if let Some(cfg) = pick_fastest(&trials, max_latency_ms) {
+        best.scan_count = cfg.scan_count;
+    }
+
+    // batch size
+    let mut trials = Vec::new();
+    for &batch_size in &BATCH_SIZES {
+        let cfg = ScanConfig {
+            batch_size,
+            ..best.clone()
+        };
+        trials.push(run_trial(con, cfg, trial, &mut cursor)?);
+        print_trial(trials.last().unwrap(), max_latency_ms);
+    }
+    if let Some(cfg) = pick_fastest(&trials, max_latency_ms) {
+        best.batch_size = cfg.batch_size;
+    }
+
+    // SAMPLES：越多越準，只要吞吐量不低於預設的一半且延遲可接受，就選最準的
+    let mut trials = Vec::new();
+    for &samples in &SAMPLES {
+        let cfg = ScanConfig {
+            samples,
+            ..best.clone()
+        };
+        trials.push(run_trial(con, cfg, trial, &mut cursor)?);
+        print_trial(trials.last().unwrap(), max_latency_ms);
+    }
+    let default_rate = trials[0].keys_per_sec;
+    if let Some(t) = trials
+        .iter()
+        .rev()
+        .find(|t| t.ping_p99 <= max_latency_ms && t.keys_per_sec >= default_rate * 0.5)
+    {
+        best.samples = t.cfg.samples;
+    }
+
+    println!("\n{}", "=".repeat(120));
+    println!(
+        "建議設定: --scan-count {} --batch-size {}{}",
+        best.scan_count,
+        best.batch_size,
+        best.samples
+            .map(|n| format!(" --samples {}", n))
+            .unwrap_or_default()
+    );
+    println!(
+        "（條件: PING p99 <= {:.1} ms；試跑結果只反映目前的負載與網路狀況）",
+        max_latency_ms
+    );
+
+    Ok(())
+}
+
+fn print_header() {
+    println!(
+        "{:>12} {:>12} {:>10} {:>14} {:>16} {:>12} {:>12}",
+        "SCAN COUNT", "batch", "SAMPLES", "keys/s", "batch RTT max", "PING p50", "PING p99"
+    );
+    println!("{}", "-".repeat(120));
+}
+
+fn print_trial(t: &Trial, max_latency_ms: f64) {
+    let flag = if t.ping_p99 > max_latency_ms {
+        "  ⚠ 延遲超標"
+    } else {
+        ""
+    };
+    println!(
+        "{:>12} {:>12} {:>10} {:>14} {:>13.1} ms {:>9.2} ms {:>9.2} ms{}",
+        t.cfg.scan_count,
+        t.cfg.batch_size,
+        t.cfg
+            .samples
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| "預設".into()),
+        format_int(t.keys_per_sec as u64),
+        t.max_batch_ms,
+        t.ping_p50,
+        t.ping_p99,
+        flag
+    );
+}
+
+/// 延遲可接受的組合中吞吐量最高者
+fn pick_fastest(trials: &[Trial], max_latency_ms: f64) -> Option<ScanConfig> {
+    trials
+        .iter()
+        .filter(|t| t.ping_p99 <= max_latency_ms)
+        .max_by(|a, b| a.keys_per_sec.total_cmp(&b.keys_per_sec))
+        .map(|t| t.cfg.clone())
+}
+
+/// 以指定參數跑 SCAN + pipeline 一段時間（不做統計），同時量測 PING 延遲
+fn run_trial(
+    con: &mut RedisConn,
+    cfg: ScanConfig,
+    duration: Duration,
+    cursor: &mut u64,
+) -> redis::RedisResult<Trial> {
+    let probe = PingProbe::start(con)?;
+
+    let started = Instant::now();
+    let mut keys_done: u64 = 0;
+    let mut max_batch = Duration::ZERO;
+
+    while started.elapsed() < duration {
+        let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(*cursor)
+            .arg("COUNT")
+            .arg(cfg.scan_count)
+            .query(con)?;
+        *cursor = next_cursor;
+
+        for chunk in keys.chunks(cfg.batch_size) {
+            let t = Instant::now();
+            fetch_key_meta_batch(con, chunk, &cfg)?;
+            max_batch = max_batch.max(t.elapsed());
+            keys_done += chunk.len() as u64;
+        }
+
+        // 小資料庫一輪就掃完，直接從頭再來
+        if keys.is_empty() && *cursor == 0 && keys_done == 0 {
+            break;
+        }
+    }
+
+    let elapsed = started.elapsed().as_secs_f64().max(f64::EPSILON);
+    let mut rtts = probe.stop();
+
+    Ok(Trial {
+        cfg,
+        keys_per_sec: keys_done as f64 / elapsed,
+        max_batch_ms: max_batch.as_secs_f64() * 1000.0,
+        ping_p50: percentile(&mut rtts, 50.0),
+        ping_p99: percentile(&mut rtts, 99.0),
+    })
+}
+
+/// 沒有掃描負載時的 PING 延遲（p50, p99）
+fn measure_idle_latency(con: &RedisConn) -> redis::RedisResult<(f64, f64)> {
+    let probe = PingProbe::start(con)?;
+    thread::sleep(BASELINE_WINDOW);
+    let mut rtts = probe.stop();
+    Ok((percentile(&mut rtts, 50.0), percentile(&mut rtts, 99.0)))
+}
+
+/// 背景執行緒，用獨立連線每 PING_EVERY 送一次 PING 並記錄 RTT（毫秒）
+struct PingProbe {
+    stop: Arc<AtomicBool>,
+    handle: thread::JoinHandle<Vec<f64>>,
+}
+
+impl PingProbe {
+    fn start(main: &RedisConn) -> redis::RedisResult<Self> {
+        let mut con = main.open_another()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            let mut rtts = Vec::new();
+            while !flag.load(Ordering::Relaxed) {
+                let t = Instant::now();
+                if redis::cmd("PING").query::<String>(&mut con).is_err() {
+                    break;
+                }
+                rtts.push(t.elapsed().as_secs_f64() * 1000.0);
+                thread::sleep(PING_EVERY);
+            }
+            rtts
+        });
+
+        Ok(Self { stop, handle })
+    }
+
+    fn stop(self) -> Vec<f64> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.join().unwrap_or_default()
+    }
+}
+
+/// 百分位數（會排序傳入的資料）
+fn percentile(v: &mut [f64], p: f64) -> f64 {
+    if v.is_empty() {
+        return 0.0;
+    }
+    v.sort_by(|a, b| a.total_cmp(b));
+    let idx = ((p / 100.0) * (v.len() - 1) as f64).round() as usize;
+    v[idx.min(v.len() - 1)]
+}
@@ -0,0 +1,95 @@
+use crate::alloc::{lower_class, size_class};
+use crate::report::BinWasteReport;
+use crate::stats::{MAX_PREFIXES, NO_PREFIX, OTHER_PREFIX, prefix_of};
+use std::collections::HashMap;
+
+const MIN_MEM: u64 = 128; // 以下的 class 間距只有 16 bytes，不值得調整
+const NEAR_SHARE: f64 = 0.125; // 超過下一級 class 的部分不到 class 間距的 1/8 → 「剛好超過」
+
+/// 單一前綴的配置浪費
+#[derive(Default)]
+struct Counts {
+    count: u64,
+    mem: u64,
+    waste: u64,       // size class - MEMORY USAGE
+    near: u64,        // 剛好超過某個 class 的 key 數
+    reclaimable: u64, // near 的 key 縮小到下一級 class 可省下的 bytes
+}
+
+/// 依 jemalloc size class 估算各前綴 string 值的內部碎片
+///
+/// 把 MEMORY USAGE 當成單一配置：string 的值是一塊連續的 sds，誤差不大；
+/// 其他類型由許多小配置組成，套用單一 size class 沒有意義，所以不計入。
+pub struct BinWasteStats {
+    sep: char,
+    depth: usize,
+    map: HashMap<String, Counts>,
+}
+
+impl BinWasteStats {
+    pub fn new(sep: char, depth: usize) -> Self {
+        Self {
+            sep,
+            depth,
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn add_key(&mut self, key: &str, mem: u64) {
+        if mem <= MIN_MEM {
+            return;
+        }
+        let class = size_class(mem);
+        let lower = lower_class(mem);
+        let near = (mem - lower) as f64 <= (class - lower) as f64 * NEAR_SHARE;
+
+        let name = if self.depth == 0 {
+            NO_PREFIX
+        } else {
+            match prefix_of(key, self.sep, self.depth) {
+                Some(p) if self.map.len() < MAX_PREFIXES || self.map.contains_key(p) => p,
+                Some(_) => OTHER_PREFIX,
+                None => NO_PREFIX,
+            }
+        };
+        let c = match self.map.get_mut(name) {
+            Some(c) => c,
+            None => self.map.entry(name.to_owned()).or_default(),
+        };
+        c.count += 1;
+        c.mem += mem;
+        c.waste += class - mem;
+        if near {
+            c.near += 1;
+            c.reclaimable += class - lower;
+        }
+    }
+
+    /// 依可回收 bytes desc
+    pub fn into_reports(self) -> Vec<BinWasteReport> {
+        let sep = self.sep;
+        let mut v: Vec<BinWasteReport> = self
+            .map
+            .into_iter()
+            .map(|(p, c)| BinWasteReport {
+                prefix: if p == NO_PREFIX || p == OTHER_PREFIX {
+                    p
+                } else {
+                    format!("{}{}*", p, sep)
+                },
+                count: c.count,
+                total_mem: c.mem,
+                waste: c.waste,
+                near: c.near,
+                reclaimable: c.reclaimable,
+            })
+            .collect();
+        v.sort_by(|a, b| {
+            b.reclaimable
+                .cmp(&a.reclaimable)
+                .then_with(|| b.waste.cmp(&a.waste))
+                .then_with(|| a.prefix.cmp(&b.prefix))
+        });
+        v
+    }
+}
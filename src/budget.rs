@@ -0,0 +1,97 @@
+use crate::cleanup::glob_match;
+use crate::format::{Decimal, format_int, parse_size};
+use crate::report::Report;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// 讀取預算檔：每行 `pattern = size`，例如 `session:* = 4GB`，`#` 之後為註解
+///
+/// pattern 以 glob 比對報表中的前綴名稱（`user:*` 這種形式）。
+pub fn load_budgets(path: &Path) -> Result<Vec<(String, u64)>, Box<dyn Error>> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| format!("無法讀取預算檔 {}: {}", path.display(), e))?;
+
+    let mut budgets = Vec::new();
+    for (no, line) in raw.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let budget = parse_budget(line)
+            .map_err(|e| format!("{} 第 {} 行: {}", path.display(), no + 1, e))?;
+        budgets.push(budget);
+    }
+    Ok(budgets)
+}
+
+/// 各預算的實際用量（符合 pattern 的前綴加總），回傳超出預算的數量
+pub fn render_budgets(
+    report: &Report,
+    budgets: &[(String, u64)],
+    out: &mut impl Write,
+) -> io::Result<usize> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "記憶體預算")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    if report.prefixes.is_empty() {
+        writeln!(
+            out,
+            "  報表沒有前綴統計（舊版 snapshot 或 --prefix-depth 0），無法檢查預算"
+        )?;
+        return Ok(0);
+    }
+
+    writeln!(
+        out,
+        "{:<40} {:>15} {:>15} {:>15} {:>9}  狀態",
+        "Pattern", "Keys 數量", "實際 (MB)", "預算 (MB)", "使用率"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    let mut exceeded = 0;
+    for (pattern, budget) in budgets {
+        let matched: Vec<_> = report
+            .prefixes
+            .iter()
+            .filter(|p| glob_match(pattern, &p.prefix))
+            .collect();
+        let count: u64 = matched.iter().map(|p| p.count).sum();
+        let actual: u64 = matched.iter().map(|p| p.total_mem).sum();
+        let usage = if *budget > 0 {
+            actual as f64 / *budget as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        let status = if matched.is_empty() {
+            "沒有符合的前綴（檢查 --prefix-depth）"
+        } else if actual > *budget {
+            exceeded += 1;
+            "✖ 超出預算"
+        } else {
+            "✔"
+        };
+        writeln!(
+            out,
+            "{:<40} {:>15} {:>15.2} {:>15.2} {:>8.1}%  {}",
+            pattern,
+            format_int(count),
+            Decimal(actual as f64 / 1024.0 / 1024.0),
+            Decimal(*budget as f64 / 1024.0 / 1024.0),
+            Decimal(usage),
+            status
+        )?;
+    }
+
+    Ok(exceeded)
+}
+
+/// 解析 "pattern=size"
+pub fn parse_budget(s: &str) -> Result<(String, u64), String> {
+    let (pattern, size) = s
+        .rsplit_once('=')
+        .ok_or_else(|| format!("預算格式應為 PATTERN=SIZE: {}", s))?;
+    Ok((pattern.trim().to_string(), parse_size(size)?))
+}
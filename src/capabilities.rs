@@ -0,0 +1,175 @@
+//! 附錄：本次掃描實際送出的指令、自動啟用 / 關閉的功能與偵測到的伺服器能力
+//!
+//! 不同 Redis 版本或 maxmemory-policy 會讓同一組參數收集到不同的資料；把實際的行為記在報表裡，
+//! 之後比對兩份報表時才知道差異是資料造成的還是掃描方式不同。
+
+use crate::report::Report;
+use crate::scan::ScanConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 伺服器端資訊的來源，失敗原因記在 Report.unavailable
+const SERVER_SOURCES: &[&str] = &[
+    "MEMORY STATS",
+    "CLIENT LIST",
+    "FUNCTION LIST",
+    "INFO memory",
+    "INFO stats",
+];
+
+/// 單一功能是否使用與原因
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureState {
+    pub name: String,
+    pub enabled: bool,
+    pub reason: String,
+}
+
+/// 單一節點的掃描方式
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub target: String,
+    pub redis_version: Option<String>, // INFO server 取不到時為 None
+    pub redis_mode: Option<String>,
+    pub maxmemory_policy: Option<String>,
+    pub features: Vec<FeatureState>,  // 掃描中的選用指令
+    pub server: Vec<FeatureState>,    // 掃描後的伺服器端資訊
+    pub commands: Vec<(String, u64)>, // 依指令名稱送出的次數
+}
+
+/// requested 為使用者的設定，effective 為依 maxmemory-policy 調整後實際使用的設定
+pub fn describe(
+    target: &str,
+    server_info: Option<&BTreeMap<String, String>>,
+    report: &Report,
+    requested: &ScanConfig,
+    effective: &ScanConfig,
+    commands: Vec<(String, u64)>,
+) -> Capabilities {
+    let info = |name: &str| server_info.and_then(|m| m.get(name)).cloned();
+    let redis_version = info("redis_version");
+    let policy = report
+        .memory_info
+        .as_ref()
+        .and_then(|m| m.get("maxmemory_policy"))
+        .cloned();
+
+    let scan_type = match redis_version.as_deref().map(version_at_least_6) {
+        Some(true) => {
+            "伺服器支援，但未使用：類型由 pipeline 中的 TYPE 取得，一次 SCAN 涵蓋所有類型"
+        }
+        Some(false) => "伺服器不支援（需要 6.0 以上），類型由 pipeline 中的 TYPE 取得",
+        None => "未使用：類型由 pipeline 中的 TYPE 取得",
+    };
+    let samples = match effective.samples {
+        Some(0) => "SAMPLES 0：逐一計算所有元素".to_string(),
+        Some(n) => format!("SAMPLES {}", n),
+        None => "未指定，伺服器預設抽樣 5 個元素".to_string(),
+    };
+    let mut features = vec![
+        FeatureState {
+            name: "SCAN TYPE".into(),
+            enabled: false,
+            reason: scan_type.into(),
+        },
+        FeatureState {
+            name: "MEMORY USAGE SAMPLES".into(),
+            enabled: effective.samples.is_some(),
+            reason: samples,
+        },
+    ];
+    // (指令, 使用者要求, 實際使用, 是否受 maxmemory-policy 影響)
+    for (name, wanted, used, by_policy) in [
+        ("PTTL", requested.collect_ttl, effective.collect_ttl, false),
+        (
+            "OBJECT IDLETIME",
+            requested.collect_idle,
+            effective.collect_idle,
+            true,
+        ),
+        (
+            "OBJECT FREQ",
+            requested.collect_freq,
+            effective.collect_freq,
+            true,
+        ),
+        (
+            "OBJECT ENCODING",
+            requested.collect_encoding,
+            effective.collect_encoding,
+            false,
+        ),
+    ] {
+        let reason = if used {
+            match &policy {
+                Some(p) if by_policy => format!("已啟用（maxmemory-policy 為 {}）", p),
+                _ => "已啟用".to_string(),
+            }
+        } else if wanted {
+            // 要求了卻沒用：依 maxmemory-policy 或 ACL 權限自動關閉
+            let acl = report
+                .acl_denied
+                .iter()
+                .find(|d| d.command == name)
+                .map(|d| format!("ACL 權限不足（需要 {}）", d.acl));
+            report
+                .unavailable
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.clone())
+                .or(acl)
+                .map(|why| format!("自動關閉：{}", why))
+                .unwrap_or_else(|| "自動關閉".into())
+        } else {
+            "未要求".to_string()
+        };
+        features.push(FeatureState {
+            name: name.into(),
+            enabled: used,
+            reason,
+        });
+    }
+
+    let server = SERVER_SOURCES
+        .iter()
+        .map(|&name| {
+            let err = report.unavailable.iter().find(|(k, _)| k == name);
+            FeatureState {
+                name: name.into(),
+                enabled: err.is_none(),
+                reason: err.map(|(_, v)| v.clone()).unwrap_or_else(|| "-".into()),
+            }
+        })
+        .collect();
+
+    Capabilities {
+        target: target.to_string(),
+        redis_version,
+        redis_mode: info("redis_mode"),
+        maxmemory_policy: policy,
+        features,
+        server,
+        commands,
+    }
+}
+
+/// 掃描期間的指令數：結束時的累計減去開始時的累計（同一條連線可能跑過多次掃描）
+pub fn command_delta(
+    before: &BTreeMap<String, u64>,
+    after: &BTreeMap<String, u64>,
+) -> Vec<(String, u64)> {
+    after
+        .iter()
+        .map(|(name, &n)| (name.clone(), n - before.get(name).copied().unwrap_or(0)))
+        .filter(|(_, n)| *n > 0)
+        .collect()
+}
+
+/// "7.2.4" → true；Valkey 等相容實作也回報 redis_version
+fn version_at_least_6(version: &str) -> bool {
+    version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse::<u32>().ok())
+        .is_some_and(|major| major >= 6)
+}
@@ -0,0 +1,119 @@
+//! 多節點掃描的 checkpoint：每個節點完成就存一份結果，中斷後重跑只掃描未完成的節點
+//!
+//! 節點內的 SCAN cursor 要搭配累積中的統計才有意義，所以未完成的節點會從頭掃描；
+//! 已完成的節點直接沿用存下來的結果。
+
+use crate::output::warning;
+use crate::report::Report;
+use crate::scan::ScanConfig;
+use crate::snapshot;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = "checkpoint.json";
+
+/// 記錄建立 checkpoint 時的掃描設定，設定不同的結果不能合併
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    settings: String,
+    created_at: u64,
+}
+
+pub struct Checkpoint {
+    dir: PathBuf,
+    created_at: u64,
+}
+
+impl Checkpoint {
+    /// 目錄中已有 checkpoint 時檢查設定是否相同；不同時回傳錯誤，避免混用兩種設定的結果
+    pub fn open(dir: &Path, cfg: &ScanConfig, now: u64) -> Result<Self, Box<dyn Error>> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("無法建立 checkpoint 目錄 {}: {}", dir.display(), e))?;
+        let settings = cfg.fingerprint();
+        let path = dir.join(MANIFEST);
+
+        if let Ok(raw) = std::fs::read(&path) {
+            let m: Manifest = serde_json::from_slice(&raw)
+                .map_err(|e| format!("checkpoint 格式錯誤 {}: {}", path.display(), e))?;
+            if m.settings != settings {
+                return Err(format!(
+                    "{} 是以不同的掃描設定建立的，刪除此目錄或改用其他目錄",
+                    dir.display()
+                )
+                .into());
+            }
+            return Ok(Self {
+                dir: dir.to_path_buf(),
+                created_at: m.created_at,
+            });
+        }
+
+        let m = Manifest {
+            settings,
+            created_at: now,
+        };
+        std::fs::write(&path, serde_json::to_vec_pretty(&m)?)
+            .map_err(|e| format!("無法寫入 {}: {}", path.display(), e))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            created_at: now,
+        })
+    }
+
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    /// 已完成節點的結果；沒有或讀不到時為 None（重新掃描）
+    pub fn load(&self, node: &str) -> Option<Report> {
+        let path = self.node_path(node);
+        if !path.exists() {
+            return None;
+        }
+        match snapshot::load(&path) {
+            Ok(r) => Some(r),
+            Err(e) => {
+                warning!("checkpoint 無法使用，重新掃描 {}: {}", node, e);
+                None
+            }
+        }
+    }
+
+    /// 先寫暫存檔再改名，中途被中斷不會留下半個檔案
+    pub fn save(&self, node: &str, report: &Report) -> Result<(), Box<dyn Error>> {
+        let path = self.node_path(node);
+        let tmp = path.with_extension("json.tmp");
+        snapshot::save(report, &tmp)?;
+        std::fs::rename(&tmp, &path).map_err(|e| format!("無法寫入 {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// 全部節點完成後移除；只刪除 checkpoint 自己的檔案，目錄有其他檔案時保留目錄
+    pub fn clear(&self, nodes: &[String]) -> Result<(), Box<dyn Error>> {
+        for node in nodes {
+            let path = self.node_path(node);
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .map_err(|e| format!("無法刪除 {}: {}", path.display(), e))?;
+            }
+        }
+        std::fs::remove_file(self.dir.join(MANIFEST))?;
+        let _ = std::fs::remove_dir(&self.dir);
+        Ok(())
+    }
+
+    fn node_path(&self, node: &str) -> PathBuf {
+        let name: String = node
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("node-{}.json", name))
+    }
+}
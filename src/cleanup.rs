@@ -0,0 +1,73 @@
+use crate::report::Report;
+use crate::stats::KeyTypeCode;
+
+/// 從 snapshot 的 Top keys 挑出要清理的 key
+///
+/// 只會看到 snapshot 內的 Top N，不會另外 SCAN；回傳 (type, mem, key)。
+pub fn select_keys<'a>(
+    report: &'a Report,
+    pattern: &str,
+    type_filter: Option<KeyTypeCode>,
+    min_bytes: u64,
+) -> Vec<(KeyTypeCode, u64, &'a str)> {
+    let mut out = Vec::new();
+    for tr in &report.types {
+        if type_filter.is_some_and(|t| t != tr.type_code) {
+            continue;
+        }
+        for k in &tr.top {
+            if k.mem >= min_bytes && glob_match(pattern, &k.key) {
+                out.push((tr.type_code, k.mem, k.key.as_str()));
+            }
+        }
+    }
+    out.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.2.cmp(b.2)));
+    out
+}
+
+/// 簡易 glob 比對（`*` 任意長度、`?` 單一字元），語意與 Redis MATCH 的常用子集相同
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = s.chars().collect();
+
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            // 回到上一個 * 多吃一個字元
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+
+    while pi < p.len() && p[pi] == '*' {
+        pi += 1;
+    }
+    pi == p.len()
+}
+
+/// 以 redis-cli 可接受的方式加上雙引號，特殊字元用 \xHH 跳脫
+pub fn quote_key(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 2);
+    out.push('"');
+    for b in key.bytes() {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7e => out.push(b as char),
+            _ => out.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,1123 @@
+use crate::age::AgeRules;
+use crate::alert::EarlyAlertConfig;
+use crate::budget::parse_budget;
+use crate::conn::ConnArgs;
+use crate::dedup::DedupMode;
+use crate::diff::DEFAULT_MIN_CHANGE;
+use crate::export::{ExportFormat, ExportTarget};
+use crate::fleet::DEFAULT_PARALLEL;
+use crate::format::{parse_duration, parse_size};
+use crate::guard::GuardConfig;
+use crate::index::{DEFAULT_INDEX_PREFIX, IndexTarget};
+use crate::owners::OwnerRules;
+use crate::preview::Redact;
+use crate::report::{Column, DEFAULT_LONG_KEY_NAME, RenderOptions};
+use crate::retention::RetentionPolicy;
+use crate::role::OnRoleChange;
+use crate::scan::{DEFAULT_PREFIX_TABLES, MeasureFilter, ScanConfig};
+use crate::split::SplitBy;
+use crate::stats::KeyTypeCode;
+use crate::strata::DEFAULT_STRATUM_MIN;
+use crate::trend::CapacityTarget;
+use crate::typecache::DEFAULT_TYPE_REFRESH;
+use crate::zombie::{DEFAULT_ZOMBIE_IDLE_DAYS, ZombieFilter};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 找出 Redis 每種資料類型中佔用記憶體最多的 keys
+///
+/// 未指定子命令時等同 `scan`，例如 `redis-top-keys-analyzer 10.0.0.1:6380`。
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    /// 數字與日期格式，例如 de_DE、fr、en_US（預設取 LC_ALL / LC_NUMERIC，C 或未設定時用 1,234.56）
+    #[arg(long, global = true, value_name = "LOCALE")]
+    pub locale: Option<String>,
+
+    /// 報表顯示時間用的時區，例如 +08:00、-05:30（預設 UTC；JSON 等機器可讀輸出一律是 UTC）
+    #[arg(long, global = true, value_name = "OFFSET", value_parser = parse_timezone)]
+    pub timezone: Option<i64>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// 掃描 Redis 並輸出報表（預設）
+    Scan(Box<ScanArgs>),
+    /// 從 snapshot 檔重新輸出報表，不需連線
+    Report(ReportArgs),
+    /// 比較兩個 snapshot 的差異（任一側可以是 RDB 檔，較新的一側可以是即時掃描）
+    Diff(Box<DiffArgs>),
+    /// 合併多個實例的 snapshot 成一份全體報表（全域 Top N、各實例小計、跨實例重複的前綴）
+    Merge(MergeArgs),
+    /// 同時掃描多個獨立的實例（--url 可重複），個別實例失敗不影響其他實例，輸出各實例狀態與合併報表
+    Fleet(Box<FleetArgs>),
+    /// 從多次掃描的 snapshot 計算各前綴的成長速度與預計超過預算的時間
+    Trend(TrendArgs),
+    /// 定期重新掃描並輸出帶時間戳的報表（--listen 另提供 Prometheus /metrics 與 /status）
+    Watch(Box<WatchArgs>),
+    /// 同一次掃描比較 redis-cli --bigkeys（依元素數）與依記憶體的排名，列出兩者不一致的 key
+    Validate(Box<ValidateArgs>),
+    /// 直接解析 RDB 檔（例如備份），不連線 Redis，記憶體依序列化大小估計
+    AnalyzeRdb(AnalyzeRdbArgs),
+    /// 以短時間試跑比較不同掃描參數的吞吐量與伺服器延遲，給出建議值
+    Bench(BenchArgs),
+    /// 依 snapshot 中的 Top keys 產生清理指令（預設只輸出，不執行）
+    Cleanup(CleanupArgs),
+    /// 輸出 shell 自動補全腳本
+    ///
+    /// 例如 `completions bash > /etc/bash_completion.d/redis-top-keys-analyzer`，
+    /// 或 `completions zsh > "${fpath[1]}/_redis-top-keys-analyzer"`。
+    Completions(CompletionsArgs),
+}
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// 目標 shell（bash / zsh / fish / elvish / powershell）
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+/// 內建的掃描參數組合
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Profile {
+    /// 適合 production master：小批次、每批暫停、只收 TTL、SAMPLES 1
+    Safe,
+    /// 預設：與未指定 profile 時相同
+    Balanced,
+    /// 精確但較重：SAMPLES 0（完整計算）並收集 TTL / idle / encoding
+    Thorough,
+}
+
+impl Profile {
+    fn config(self) -> ScanConfig {
+        let base = ScanConfig::default();
+        match self {
+            Profile::Safe => ScanConfig {
+                scan_count: 1000,
+                batch_size: 200,
+                samples: Some(1),
+                throttle: Duration::from_millis(20),
+                collect_ttl: true,
+                collect_idle: false,
+                collect_encoding: false,
+                collect_freq: false,
+                collect_cardinality: false,
+                guard: GuardConfig::default(),
+                ..base
+            },
+            Profile::Balanced => base,
+            Profile::Thorough => ScanConfig {
+                batch_size: 1000,
+                samples: Some(0),
+                collect_ttl: true,
+                collect_idle: true,
+                collect_encoding: true,
+                ..base
+            },
+        }
+    }
+}
+
+/// 每個 key 額外收集的資訊（--collect），每一項多一個指令
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Collect {
+    /// 都不收集，每個 key 只送 MEMORY USAGE + TYPE
+    None,
+    /// PTTL：到期時間軸、TTL 政策建議
+    Ttl,
+    /// OBJECT IDLETIME：殭屍 key（LFU policy 下無法取得）
+    Idle,
+    /// OBJECT ENCODING
+    Encoding,
+    /// OBJECT FREQ：LFU 存取頻率（只有 LFU policy 可取得）
+    Freq,
+    /// STRLEN / LLEN / HLEN…：元素數（另一段 pipeline）
+    Cardinality,
+}
+
+/// 掃描參數調校：先套用 --profile，再以個別旗標覆寫
+#[derive(Args, Clone)]
+pub struct ScanTuning {
+    /// 參數組合（safe / balanced / thorough）
+    #[arg(long, value_enum, default_value = "balanced")]
+    pub profile: Profile,
+
+    /// 每次 SCAN 的 COUNT hint（balanced: 5000）
+    #[arg(long)]
+    pub scan_count: Option<u64>,
+
+    /// 每批 pipeline 的 key 數（balanced: 2000）
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+
+    /// 每個節點同時送出幾批 pipeline（共用一條 multiplexed 連線），SCAN 與結果累計仍依序處理
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub concurrency: usize,
+
+    /// MEMORY USAGE 的 SAMPLES（0 = 完整計算，未指定 = 依 profile）
+    #[arg(long)]
+    pub samples: Option<u64>,
+
+    /// 元素數超過此值的集合不做精確量測（--samples 0 / 大 SAMPLES 與 --deep 的 SAMPLES 0），改用 SAMPLES 5 的估計值並在報表標示
+    #[arg(long, value_name = "ELEMENTS")]
+    pub estimate_above: Option<u64>,
+
+    /// 每批 pipeline 之後暫停多久，例如 5ms
+    #[arg(long, value_parser = parse_duration)]
+    pub throttle: Option<Duration>,
+
+    /// 每秒最多檢查幾個 key（依 key 數限速，不受 --batch-size / --throttle 影響；多節點時為每個節點）
+    #[arg(long, value_name = "N")]
+    pub max_keys_per_sec: Option<u64>,
+
+    /// 進度條與 ETA 的更新間隔，例如 2s
+    #[arg(long, value_parser = parse_duration, default_value = "500ms")]
+    pub progress_interval: Duration,
+
+    /// 每個 key 額外收集的資訊，例如 ttl,idle（none = 都不收集；未指定 = 依 profile）
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub collect: Option<Vec<Collect>>,
+
+    /// instantaneous_ops_per_sec 超過此值時暫停（包含本工具自己的指令）
+    #[arg(long, value_name = "OPS")]
+    pub max_ops: Option<u64>,
+
+    /// Redis 行程 CPU 使用率超過此百分比時暫停（100 = 一顆核心滿載）
+    #[arg(long, value_name = "PERCENT")]
+    pub max_cpu: Option<f64>,
+
+    /// connected_clients 超過此值時暫停
+    #[arg(long, value_name = "N")]
+    pub max_clients: Option<u64>,
+
+    /// blocked_clients 超過此值時暫停
+    #[arg(long, value_name = "N")]
+    pub max_blocked: Option<u64>,
+
+    /// 最慢 replica 落後 master_repl_offset 超過此值（bytes）時暫停
+    #[arg(long, value_name = "BYTES")]
+    pub max_repl_lag: Option<u64>,
+
+    /// 最慢 replica 落後超過此值（bytes）時中止掃描
+    #[arg(long, value_name = "BYTES")]
+    pub abort_repl_lag: Option<u64>,
+
+    /// 前綴分組的分隔字元
+    #[arg(long, default_value_t = ':')]
+    pub prefix_sep: char,
+
+    /// 取 key 的前幾段當前綴，例如 2 = `user:profile:*`（0 = 不分組）
+    #[arg(long, default_value_t = 1)]
+    pub prefix_depth: usize,
+
+    /// 記憶體最大的幾個前綴各自列出 Top 10 key（0 = 不列）
+    #[arg(long, value_name = "K", default_value_t = DEFAULT_PREFIX_TABLES)]
+    pub prefix_tables: usize,
+
+    /// 兩段式掃描：先以 TYPE 分類，只對這些類型送 MEMORY USAGE，例如 hash,zset
+    #[arg(long, value_name = "TYPES", value_delimiter = ',', value_parser = parse_type)]
+    pub measure_types: Vec<KeyTypeCode>,
+
+    /// 兩段式掃描：只量測符合 glob 的 key，例如 'session:*'
+    #[arg(long, value_name = "PATTERN")]
+    pub measure_match: Option<String>,
+
+    /// 兩段式掃描：只量測長度（STRLEN / LLEN / HLEN…）>= 此值的 key
+    #[arg(long, value_name = "N")]
+    pub measure_min_elements: Option<u64>,
+
+    /// 排除 SCAN 在 rehash 期間重複回傳的 key（off / exact / bloom）
+    #[arg(long, value_enum, default_value = "off")]
+    pub dedup: DedupMode,
+
+    /// 去重最多使用的記憶體，例如 256MB（exact 超過時自動改用 bloom）
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "64MB")]
+    pub dedup_memory: u64,
+
+    /// 掃描中一量到超過此大小的 key 就立刻在 stderr 提醒，例如 1GB（不必等掃描結束）
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub alert_size: Option<u64>,
+
+    /// 搭配 --alert-size：同時把提醒 POST 到此 webhook（相容 Slack incoming webhook）
+    #[arg(long, value_name = "URL", requires = "alert_size")]
+    pub alert_webhook: Option<String>,
+
+    /// 掃描中把每個 key 的大小寫進另一台 Redis（ZSET + HASH），例如 redis://analytics:6379/0
+    #[arg(long, value_name = "URL")]
+    pub index_to: Option<String>,
+
+    /// --index-to 寫入的 key 前綴
+    #[arg(long, value_name = "PREFIX", default_value = DEFAULT_INDEX_PREFIX, requires = "index_to")]
+    pub index_prefix: String,
+
+    /// --index-to 寫入的資料保留多久（TTL），例如 7d；未指定時永久保留
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "index_to")]
+    pub index_ttl: Option<Duration>,
+
+    /// 掃描中把每個 key 的量測結果逐筆寫到此檔（NDJSON；副檔名為 .csv 時為 CSV），每批寫完即 flush，
+    /// 正常結束才寫完成標記；多節點掃描時每個節點一個檔案
+    #[arg(long, value_name = "FILE")]
+    pub export_keys: Option<PathBuf>,
+
+    /// --export-keys 的格式（未指定時依副檔名）
+    #[arg(long, value_enum, requires = "export_keys")]
+    pub export_format: Option<ExportFormat>,
+
+    /// --export-keys 每批 flush 之後再 fsync，主機當機也不會遺失已寫入的批次
+    #[arg(long, requires = "export_keys")]
+    pub export_fsync: bool,
+
+    /// 只量測這個比例的 key（依 key hash 抽樣），報表附上全體的估計值與 95% 信賴區間，例如 10%
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    pub sample_rate: Option<f64>,
+
+    /// 依類型分層抽樣：先對每個 key 送 TYPE 普查，每個類型的前 N 個 key 一律量測，罕見的大類型（stream 等）不會被漏掉
+    #[arg(long, requires = "sample_rate")]
+    pub stratify_types: bool,
+
+    /// --stratify-types 每個類型至少完整量測的 key 數
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_STRATUM_MIN, requires = "stratify_types")]
+    pub stratum_min: u64,
+
+    /// 掃描中伺服器角色改變（failover、replica 換 master、重啟）時的處理（warn / abort）
+    #[arg(long, value_enum, default_value = "warn")]
+    pub on_role_change: OnRoleChange,
+
+    /// 失敗 key 的比例超過此值就中止並說明原因，例如 2%
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    pub max_error_rate: Option<f64>,
+
+    /// 擁有者規則檔（每行 `session:* = team-auth`），依團隊彙總記憶體
+    #[arg(long, value_name = "FILE")]
+    pub owners: Option<PathBuf>,
+
+    /// 年齡規則檔（每行 `order:* = unix 90d`，來源為 unix / unix_ms / date / seq），
+    /// 由 key 名稱中的時間或遞增 ID 推算年齡（OBJECT IDLETIME 為下限），列出超過保留期限的 key
+    #[arg(long, value_name = "FILE")]
+    pub age_rules: Option<PathBuf>,
+
+    /// 替各類型 Top N 抓一小段值（GETRANGE / HRANDFIELD / ZRANDMEMBER…），看出大 key 存的是什麼
+    #[arg(long)]
+    pub preview: bool,
+
+    /// 值預覽的遮蔽方式（none / digits / shape）
+    #[arg(long, value_enum, default_value = "digits", requires = "preview")]
+    pub preview_redact: Redact,
+
+    /// 估算集合類型的結構開銷與資料比例（會一併收集 encoding 與 cardinality）
+    #[arg(long)]
+    pub overhead: bool,
+
+    /// 檢查短 string 是否為存成字串的整數（會一併收集 encoding）
+    #[arg(long)]
+    pub int_strings: bool,
+
+    /// 掃描後只對各類型 Top N 做第二階段分析：SAMPLES 0 精確記憶體、TTL、encoding、元素數與元素大小抽樣
+    #[arg(long)]
+    pub deep: bool,
+
+    /// 第二階段同時分析幾個 key（各用一條連線）
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "deep")]
+    pub deep_parallel: usize,
+
+    /// 第二階段每條連線分析完一個 key 後暫停多久（預設沿用 --throttle）
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "deep")]
+    pub deep_throttle: Option<Duration>,
+
+    /// 掃描前後各花這段時間量測 PING 延遲與其他 client 的指令耗時，報表附上掃描影響，例如 10s
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub impact_probe: Option<Duration>,
+
+    /// 量測時已消失的 key 再查一次（刪除後重建的 key 不會被漏算）
+    #[arg(long)]
+    pub retry_vanished: bool,
+
+    /// 殭屍 key 的大小門檻，例如 512KB（需要 --collect ttl,idle）
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "1MB")]
+    pub zombie_min_size: u64,
+
+    /// 殭屍 key 的閒置天數門檻
+    #[arg(long, value_name = "DAYS", default_value_t = DEFAULT_ZOMBIE_IDLE_DAYS)]
+    pub zombie_idle_days: u64,
+}
+
+impl ScanTuning {
+    /// 規則檔讀取失敗時回傳錯誤
+    pub fn to_config(&self) -> Result<ScanConfig, Box<dyn Error>> {
+        let mut cfg = self.profile.config();
+        if let Some(n) = self.scan_count {
+            cfg.scan_count = n;
+        }
+        if let Some(n) = self.batch_size {
+            cfg.batch_size = n.max(1);
+        }
+        cfg.concurrency = self.concurrency.max(1);
+        if self.samples.is_some() {
+            cfg.samples = self.samples;
+        }
+        cfg.estimate_above = self.estimate_above;
+        if let Some(d) = self.throttle {
+            cfg.throttle = d;
+        }
+        cfg.max_keys_per_sec = self.max_keys_per_sec;
+        if let Some(collect) = &self.collect {
+            cfg.collect_ttl = collect.contains(&Collect::Ttl);
+            cfg.collect_idle = collect.contains(&Collect::Idle);
+            cfg.collect_encoding = collect.contains(&Collect::Encoding);
+            cfg.collect_freq = collect.contains(&Collect::Freq);
+            cfg.collect_cardinality = collect.contains(&Collect::Cardinality);
+        }
+        cfg.collect_encoding |= self.int_strings || self.overhead;
+        cfg.collect_cardinality |= self.overhead;
+        cfg.int_strings = self.int_strings;
+        cfg.overhead = self.overhead;
+        cfg.deep = self.deep;
+        cfg.deep_parallel = self.deep_parallel.max(1);
+        cfg.deep_throttle = self.deep_throttle;
+        cfg.impact_probe = self.impact_probe;
+        cfg.guard = GuardConfig {
+            max_ops: self.max_ops,
+            max_cpu: self.max_cpu,
+            max_clients: self.max_clients,
+            max_blocked: self.max_blocked,
+            max_repl_lag: self.max_repl_lag,
+            abort_repl_lag: self.abort_repl_lag,
+        };
+        cfg.progress_interval = self.progress_interval;
+        cfg.prefix_sep = self.prefix_sep;
+        cfg.prefix_depth = self.prefix_depth;
+        cfg.prefix_tables = self.prefix_tables;
+        cfg.max_error_rate = self.max_error_rate;
+        cfg.on_role_change = self.on_role_change;
+        cfg.early_alert = self.alert_size.map(|min_bytes| EarlyAlertConfig {
+            min_bytes,
+            webhook: self.alert_webhook.clone(),
+        });
+        cfg.index_to = self.index_to.as_ref().map(|url| IndexTarget {
+            url: url.clone(),
+            prefix: self.index_prefix.clone(),
+            ttl: self.index_ttl,
+        });
+        cfg.export_keys = self.export_keys.as_ref().map(|path| ExportTarget {
+            path: path.clone(),
+            format: self
+                .export_format
+                .unwrap_or_else(|| ExportFormat::from_path(path)),
+            fsync: self.export_fsync,
+            per_node: false,
+        });
+        match self.sample_rate {
+            Some(r) if r <= 0.0 => return Err("--sample-rate 必須大於 0%".into()),
+            Some(r) if r < 1.0 => cfg.sample_rate = Some(r),
+            _ => {}
+        }
+        cfg.stratify = self.stratify_types.then_some(self.stratum_min);
+        cfg.dedup = self.dedup;
+        cfg.dedup_memory = self.dedup_memory;
+        cfg.retry_vanished = self.retry_vanished;
+        cfg.preview = self.preview.then_some(self.preview_redact);
+        cfg.zombie = ZombieFilter {
+            min_bytes: self.zombie_min_size,
+            idle_days: self.zombie_idle_days,
+        };
+        cfg.measure = MeasureFilter {
+            types: self.measure_types.clone(),
+            pattern: self.measure_match.clone(),
+            min_elements: self.measure_min_elements,
+        };
+        if let Some(path) = &self.owners {
+            cfg.owners = Some(Arc::new(OwnerRules::load(path)?));
+        }
+        if let Some(path) = &self.age_rules {
+            cfg.age_rules = Some(Arc::new(AgeRules::load(path)?));
+        }
+        Ok(cfg)
+    }
+}
+
+/// scan 在 stdout 輸出的報表格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// 文字表格
+    Text,
+    /// 單一 JSON 文件（格式同 snapshot，含 format_version）
+    Json,
+    /// 各類型 Top N 與彙總（同 --csv-out）
+    Csv,
+}
+
+/// 雲端託管 Redis 的參考單價（on-demand，USD；以常見規格的時價 × 730 小時 ÷ 可用記憶體換算成每 GB 每月）
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Pricing {
+    /// AWS ElastiCache cache.r7g.large（13.07 GiB，約 $0.219/hr）
+    AwsR7g,
+    /// AWS ElastiCache cache.m7g.large（6.38 GiB，約 $0.158/hr）
+    AwsM7g,
+    /// GCP Memorystore for Redis Standard tier（約 $0.064/GB/hr）
+    GcpStandard,
+    /// Azure Cache for Redis Premium P1（6 GB，約 $0.555/hr）
+    AzureP1,
+}
+
+impl Pricing {
+    pub fn cost_per_gb_month(self) -> f64 {
+        match self {
+            Pricing::AwsR7g => 0.219 * 730.0 / 13.07,
+            Pricing::AwsM7g => 0.158 * 730.0 / 6.38,
+            Pricing::GcpStandard => 0.064 * 730.0,
+            Pricing::AzureP1 => 0.555 * 730.0 / 6.0,
+        }
+    }
+}
+
+/// 文字報表的輸出選項
+#[derive(Args, Clone)]
+pub struct RenderArgs {
+    /// Top N 表格要顯示的欄位與順序，例如 rank,key,bytes,ttl（預設依收集的資訊自動決定）
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub columns: Vec<Column>,
+
+    /// 每 GB 每月的成本，用來在前綴 / 擁有者表格估算每月費用
+    #[arg(long, value_name = "COST", conflicts_with = "pricing")]
+    pub cost_per_gb_month: Option<f64>,
+
+    /// 以雲端託管 Redis 的參考單價估算成本（取代 --cost-per-gb-month）
+    #[arg(long, value_enum)]
+    pub pricing: Option<Pricing>,
+
+    /// 列出平均 key 名稱長度超過此值（bytes）的前綴
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_LONG_KEY_NAME)]
+    pub long_key_name: u64,
+}
+
+impl RenderArgs {
+    pub fn to_options(&self) -> RenderOptions {
+        RenderOptions {
+            columns: self.columns.clone(),
+            cost_per_gb_month: self
+                .cost_per_gb_month
+                .or(self.pricing.map(Pricing::cost_per_gb_month)),
+            long_key_name: self.long_key_name,
+        }
+    }
+}
+
+/// 容量預估的目標（trend，以及 scan / watch 搭配 --history-dir 時）
+#[derive(Args, Clone)]
+pub struct CapacityArgs {
+    /// 容量上限，例如 16GB（預設取最新 snapshot 的 maxmemory）
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub capacity: Option<u64>,
+
+    /// 使用率達到此百分比即視為滿
+    #[arg(long, value_name = "PERCENT", default_value_t = 100.0)]
+    pub target_util: f64,
+}
+
+impl CapacityArgs {
+    pub fn to_target(&self) -> CapacityTarget {
+        CapacityTarget {
+            capacity: self.capacity,
+            target_pct: self.target_util,
+        }
+    }
+}
+
+/// --history-dir 的保留政策（scan / watch）
+#[derive(Args, Clone)]
+pub struct RetentionArgs {
+    /// 歷史目錄只保留最新的 N 份 snapshot
+    #[arg(long, value_name = "N")]
+    pub keep_last: Option<usize>,
+
+    /// 刪除超過 N 天的 snapshot；與 --keep-last 同時指定時，符合任一條就保留
+    #[arg(long, value_name = "DAYS")]
+    pub keep_days: Option<u64>,
+
+    /// 超過 N 天的 snapshot 只留彙總（去掉每個 key 的 Top N 明細），trend 仍可使用
+    #[arg(long, value_name = "DAYS")]
+    pub compact_after: Option<u64>,
+}
+
+impl RetentionArgs {
+    pub fn to_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            keep_last: self.keep_last,
+            keep_days: self.keep_days,
+            compact_after_days: self.compact_after,
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct ScanArgs {
+    #[command(flatten)]
+    pub conn: ConnArgs,
+
+    #[command(flatten)]
+    pub tuning: ScanTuning,
+
+    #[command(flatten)]
+    pub render: RenderArgs,
+
+    /// 另存結果為 snapshot（JSON），供 report / diff / cleanup 使用
+    #[arg(long, value_name = "FILE")]
+    pub snapshot: Option<PathBuf>,
+
+    /// 以 Tera 模板輸出報表（取代預設的文字表格）
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<PathBuf>,
+
+    /// stdout 的報表格式；json 為完整結果（同 snapshot），csv 同 --csv-out；
+    /// 非 text 時過程訊息改寫到 stderr，可直接接 jq 或匯入試算表
+    #[arg(long, value_enum, default_value = "text", conflicts_with_all = ["template", "dry_run"])]
+    pub format: ReportFormat,
+
+    /// Kubernetes Job 模式：stdout 為 JSON 報表，stderr 每行一個 JSON 事件（log / warning / progress / result），
+    /// 不顯示進度條；收到 SIGTERM 時輸出已掃描的部分。exit code：0 成功、1 錯誤、3/4/5 檢查未通過、
+    /// 7 被 SIGTERM / SIGINT 中斷、8 有 key 量測失敗、143 超過 --grace-period
+    #[arg(long, conflicts_with_all = ["template", "format", "dry_run"])]
+    pub oneshot_json: bool,
+
+    /// --oneshot-json 收到 SIGTERM 後最多等多久輸出部分結果，超過就直接以 143 結束
+    /// （應小於 Pod 的 terminationGracePeriodSeconds）
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "20s")]
+    pub grace_period: Duration,
+
+    /// 寫出 JSON 報表到本機路徑或物件儲存（s3:// gs:// az://），`{ts}` 會換成掃描時間
+    #[arg(long, value_name = "PATH|URL")]
+    pub output: Option<String>,
+
+    /// 掃描完成後把 JSON 報表 POST 到此 URL
+    #[arg(long, value_name = "URL")]
+    pub post_url: Option<String>,
+
+    /// POST 時使用的 Bearer token
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        env = "REDIS_TOP_KEYS_POST_TOKEN",
+        hide_env_values = true
+    )]
+    pub post_token: Option<String>,
+
+    /// 超過告警門檻時送出 PagerDuty 事件（Events API v2 的 routing key）
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "REDIS_TOP_KEYS_PAGERDUTY_KEY",
+        hide_env_values = true
+    )]
+    pub pagerduty_routing_key: Option<String>,
+
+    /// 超過告警門檻時建立 Opsgenie 告警（API key）
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "REDIS_TOP_KEYS_OPSGENIE_KEY",
+        hide_env_values = true
+    )]
+    pub opsgenie_api_key: Option<String>,
+
+    /// 告警門檻：單一 key 超過此大小
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "5GB")]
+    pub alert_key_size: u64,
+
+    /// 告警門檻：used_memory 超過 maxmemory 的此百分比
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent, default_value = "90")]
+    pub alert_used_pct: f64,
+
+    /// 直接掃描這些後端節點並合併結果（twemproxy / codis 等 proxy 後面的 shard）
+    #[arg(
+        long,
+        value_name = "HOST:PORT,...",
+        value_delimiter = ',',
+        conflicts_with = "host"
+    )]
+    pub nodes: Vec<String>,
+
+    /// 與此 snapshot 比較，全域 Top N 出現新的大 key 時列出並以 exit code 3 結束
+    #[arg(long, value_name = "FILE")]
+    pub baseline: Option<PathBuf>,
+
+    /// 前綴預算檔（每行 `session:* = 4GB`），有前綴超出時以 exit code 4 結束
+    #[arg(long, value_name = "FILE")]
+    pub budgets: Option<PathBuf>,
+
+    /// 沒有量測到任何 key（資料庫是空的、篩選條件排除了全部 key）時以 exit code 5 結束
+    #[arg(long)]
+    pub fail_on_empty: bool,
+
+    /// 拆分報表：owner = 每個擁有者一份只含自己 key 的報表（需要 --owners 與 --output-dir）
+    #[arg(long, value_enum, value_name = "BY", requires_all = ["owners", "output_dir"])]
+    pub split_report_by: Option<SplitBy>,
+
+    /// 拆分報表的目錄：每個擁有者一份 .txt 與 .json，另有整體報表 all.txt / all.json
+    #[arg(long, value_name = "DIR", requires = "split_report_by")]
+    pub output_dir: Option<PathBuf>,
+
+    /// 另外輸出互動式 HTML 報表（圖表內嵌在單一檔案中，不需要網路）
+    #[arg(long, value_name = "FILE")]
+    pub html: Option<PathBuf>,
+
+    /// 另外輸出 CSV：各類型 Top N 每個 key 一行（type,rank,key,bytes），之後是各類型的彙總
+    #[arg(long, value_name = "FILE")]
+    pub csv_out: Option<PathBuf>,
+
+    /// 報表與 snapshot 附上的備註，例如 "release 4.2 上線後"（可重複）
+    #[arg(long = "note", value_name = "TEXT")]
+    pub notes: Vec<String>,
+
+    /// 另存一份 snapshot 到此目錄（以掃描時間命名），供 trend 計算成長趨勢；
+    /// 目錄中已有較早的紀錄時，報表最後會附上容量預估
+    #[arg(long, value_name = "DIR")]
+    pub history_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub retention: RetentionArgs,
+
+    #[command(flatten)]
+    pub capacity: CapacityArgs,
+
+    /// 同時掃描幾個節點（搭配 --nodes / --twemproxy-config / --cluster），每個節點一條進度條
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub parallel: usize,
+
+    /// 從 twemproxy (nutcracker) 設定檔的 servers 取得後端節點
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["host", "nodes"])]
+    pub twemproxy_config: Option<PathBuf>,
+
+    /// Redis Cluster：由 HOST[:PORT] 的 CLUSTER SLOTS 找出所有 master，逐一掃描後合併成一份報表
+    #[arg(long, conflicts_with_all = ["nodes", "twemproxy_config"])]
+    pub cluster: bool,
+
+    /// 只連線並做掃描前的檢查，列出會送出的指令、預估耗時與負載，不實際掃描
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// 多節點掃描時每個節點完成就存入此目錄；中斷後以相同參數重跑只掃描未完成的節點，
+    /// 全部完成後自動清除
+    #[arg(long, value_name = "DIR")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// 不結束：每隔 --interval 在同一條連線上重新掃描，輸出帶時間戳的報表（同 `watch` 子命令，
+    /// 更多選項如 --listen、--count 請用子命令）
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "snapshot", "template", "format", "oneshot_json", "grace_period", "output", "post_url",
+            "post_token", "pagerduty_routing_key", "opsgenie_api_key", "alert_key_size",
+            "alert_used_pct", "baseline", "budgets", "fail_on_empty", "split_report_by",
+            "output_dir", "html", "csv_out", "nodes", "parallel", "twemproxy_config", "cluster",
+            "dry_run", "checkpoint",
+        ]
+    )]
+    pub watch: bool,
+
+    /// --watch 兩次掃描的間隔，例如 30s、10m、1h
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        default_value = "10m",
+        requires = "watch"
+    )]
+    pub interval: Duration,
+}
+
+impl ScanArgs {
+    /// --watch：只帶入 watch 也有的選項，其餘已由 conflicts_with_all 排除
+    pub fn into_watch(self) -> WatchArgs {
+        WatchArgs {
+            conn: self.conn,
+            tuning: self.tuning,
+            render: self.render,
+            interval: self.interval,
+            config: None,
+            count: 0,
+            listen: None,
+            history_dir: self.history_dir,
+            notes: self.notes,
+            warm_types: false,
+            type_cache: None,
+            type_refresh: DEFAULT_TYPE_REFRESH,
+            retention: self.retention,
+            capacity: self.capacity,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// scan --snapshot 產生的檔案
+    pub snapshot: PathBuf,
+
+    #[command(flatten)]
+    pub render: RenderArgs,
+
+    /// 以 Tera 模板輸出報表（取代預設的文字表格）
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<PathBuf>,
+
+    /// 另外輸出互動式 HTML 報表（圖表內嵌在單一檔案中，不需要網路）
+    #[arg(long, value_name = "FILE")]
+    pub html: Option<PathBuf>,
+
+    /// 另外輸出 CSV：各類型 Top N 每個 key 一行（type,rank,key,bytes），之後是各類型的彙總
+    #[arg(long, value_name = "FILE")]
+    pub csv_out: Option<PathBuf>,
+
+    /// 與此 snapshot 比較，全域 Top N 出現新的大 key 時列出並以 exit code 3 結束
+    #[arg(long, value_name = "FILE")]
+    pub baseline: Option<PathBuf>,
+
+    /// 前綴預算檔（每行 `session:* = 4GB`），有前綴超出時以 exit code 4 結束
+    #[arg(long, value_name = "FILE")]
+    pub budgets: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// snapshot 檔案或目錄（目錄會讀取其中所有 *.json），每份視為一個實例
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub render: RenderArgs,
+
+    /// 以 Tera 模板輸出報表（取代預設的文字表格）
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<PathBuf>,
+
+    /// 另存合併結果為 snapshot（JSON），供 report / diff / cleanup 使用
+    #[arg(long, value_name = "FILE")]
+    pub snapshot: Option<PathBuf>,
+}
+
+#[derive(Args)]
+// --live 時以連線旗標指定目標；位置參數留給兩側的檔案，host / port 改為 --host / --port
+#[command(
+    mut_arg("host", |a| a.long("host")),
+    mut_arg("port", |a| a.long("port"))
+)]
+pub struct DiffArgs {
+    /// 較舊的 snapshot，或 RDB 檔（依檔頭自動辨識，例如還原用的備份）
+    pub old: PathBuf,
+
+    /// 較新的 snapshot 或 RDB 檔；指定 --live 時省略
+    #[arg(required_unless_present = "live")]
+    pub new: Option<PathBuf>,
+
+    /// 較新的一側改為即時掃描（--host / --port 或其他連線旗標），例如還原後驗證
+    #[arg(long, conflicts_with = "new")]
+    pub live: bool,
+
+    /// 前綴的記憶體增減超過此比例（%）才列出
+    #[arg(long, value_name = "PCT", default_value_t = DEFAULT_MIN_CHANGE)]
+    pub min_change: f64,
+
+    #[command(flatten)]
+    pub conn: ConnArgs,
+
+    /// 即時掃描的參數；前綴分組（--prefix-sep / --prefix-depth）也套用到 RDB 的分析
+    #[command(flatten)]
+    pub tuning: ScanTuning,
+}
+
+#[derive(Args)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub conn: ConnArgs,
+
+    #[command(flatten)]
+    pub tuning: ScanTuning,
+
+    #[command(flatten)]
+    pub render: RenderArgs,
+
+    /// 兩次掃描的間隔，例如 30s、10m、1h
+    #[arg(long, default_value = "10m", value_parser = parse_duration)]
+    pub interval: Duration,
+
+    /// 設定檔（每行 `name = value`，同命令列旗標）；等待下一輪時檢查，有變更就重新載入並列出變更，不必重啟
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// 掃描次數，0 = 不限
+    #[arg(long, default_value_t = 0)]
+    pub count: u64,
+
+    /// 在此位址提供 HTTP，例如 0.0.0.0:9465：GET /metrics（Prometheus：各類型與 Top key 的記憶體、掃描耗時）
+    /// 與 GET /status（JSON：狀態、進度、上一輪摘要與錯誤）；指定時單輪掃描失敗只記錄錯誤、照常等待下一輪
+    #[arg(long, alias = "status-listen", value_name = "ADDR")]
+    pub listen: Option<String>,
+
+    /// 每次掃描另存一份 snapshot 到此目錄，供 trend 計算成長趨勢
+    #[arg(long, value_name = "DIR")]
+    pub history_dir: Option<PathBuf>,
+
+    /// 每次掃描的報表與 snapshot 附上的備註（可重複）
+    #[arg(long = "note", value_name = "TEXT")]
+    pub notes: Vec<String>,
+
+    /// 沿用上一輪每個 key 的類型，只送 MEMORY USAGE 不送 TYPE（改變類型的 key 到下次完整掃描才更正）
+    #[arg(long)]
+    pub warm_types: bool,
+
+    /// 類型快取存到此檔案，watch 重新啟動後沿用（隱含 --warm-types）
+    #[arg(long, value_name = "FILE")]
+    pub type_cache: Option<PathBuf>,
+
+    /// 沿用快取幾輪後做一次完整的 TYPE 重建快取；0 = 不重建
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_TYPE_REFRESH)]
+    pub type_refresh: u64,
+
+    #[command(flatten)]
+    pub retention: RetentionArgs,
+
+    #[command(flatten)]
+    pub capacity: CapacityArgs,
+}
+
+#[derive(Args)]
+pub struct TrendArgs {
+    /// snapshot 檔案或目錄（目錄會讀取其中所有 *.json，例如 --history-dir 的輸出）
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// 列出成長最快的前幾個前綴
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
+
+    /// 前綴的記憶體預算，例如 'session:*=4GB'（可重複指定，依序比對第一個符合的）
+    #[arg(long = "budget", value_name = "PATTERN=SIZE", value_parser = parse_budget)]
+    pub budgets: Vec<(String, u64)>,
+
+    /// 從預算檔讀取預算（格式同 scan --budgets），接在 --budget 之後比對
+    #[arg(long = "budgets", value_name = "FILE")]
+    pub budgets_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub capacity: CapacityArgs,
+}
+
+// --concurrency 在 fleet 原本是同時掃描的實例數，保留給 --parallel 當別名；
+// 每個實例內同時在途的 pipeline 批次數在 fleet 改名為 --batch-concurrency
+#[derive(Args)]
+#[command(mut_arg("concurrency", |a| a.long("batch-concurrency")))]
+pub struct FleetArgs {
+    /// 要掃描的實例（host:port 或 redis:// / rediss:// URL，可重複）；不接受位置參數
+    #[arg(long = "url", value_name = "URL", required = true)]
+    pub urls: Vec<String>,
+
+    /// 同時掃描幾個實例（每個實例內同時在途的 pipeline 批次數為 --batch-concurrency）
+    #[arg(long, alias = "concurrency", value_name = "N", default_value_t = DEFAULT_PARALLEL)]
+    pub parallel: usize,
+
+    #[command(flatten)]
+    pub conn: ConnArgs,
+
+    #[command(flatten)]
+    pub tuning: ScanTuning,
+
+    #[command(flatten)]
+    pub render: RenderArgs,
+
+    /// 每個成功的實例另存一份 snapshot 到此目錄（檔名為 host_port）
+    #[arg(long, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// 另存合併結果為 snapshot（JSON），失敗的實例記在其中
+    #[arg(long, value_name = "FILE")]
+    pub snapshot: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    #[command(flatten)]
+    pub conn: ConnArgs,
+
+    #[command(flatten)]
+    pub tuning: ScanTuning,
+
+    /// 另存結果為 snapshot（JSON），含依元素數的 Top N
+    #[arg(long, value_name = "FILE")]
+    pub snapshot: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct AnalyzeRdbArgs {
+    /// RDB 檔（BGSAVE 或備份產生的 dump.rdb）
+    pub file: PathBuf,
+
+    /// 只分析此資料庫（預設全部）
+    #[arg(long, value_name = "N")]
+    pub db: Option<u64>,
+
+    /// 前綴分組的分隔字元
+    #[arg(long, default_value_t = ':')]
+    pub prefix_sep: char,
+
+    /// 取 key 的前幾段當前綴，例如 2 = `user:profile:*`（0 = 不分組）
+    #[arg(long, default_value_t = 1)]
+    pub prefix_depth: usize,
+
+    /// 記憶體最大的幾個前綴各自列出 Top 10 key（0 = 不列）
+    #[arg(long, value_name = "K", default_value_t = DEFAULT_PREFIX_TABLES)]
+    pub prefix_tables: usize,
+
+    #[command(flatten)]
+    pub render: RenderArgs,
+
+    /// 另存結果為 snapshot（JSON），可用 report / diff / trend 處理
+    #[arg(long, value_name = "FILE")]
+    pub snapshot: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    #[command(flatten)]
+    pub conn: ConnArgs,
+
+    /// 每組參數試跑多久
+    #[arg(long, default_value = "3s", value_parser = parse_duration)]
+    pub trial: Duration,
+
+    /// 可接受的 PING p99 延遲上限（毫秒），超過的組合不列入建議
+    #[arg(long, default_value_t = 10.0)]
+    pub max_latency_ms: f64,
+}
+
+#[derive(Args)]
+pub struct CleanupArgs {
+    /// scan --snapshot 產生的檔案
+    pub snapshot: PathBuf,
+
+    /// --execute 的連線目標，需與 snapshot 的來源相同（密碼、TLS 等同 scan）
+    #[command(flatten)]
+    pub conn: ConnArgs,
+
+    /// 只處理符合 glob pattern 的 key（支援 * 與 ?）
+    #[arg(long = "match", value_name = "PATTERN", default_value = "*")]
+    pub pattern: String,
+
+    /// 只處理指定類型（string/list/set/zset/hash/stream）
+    #[arg(long = "type", value_name = "TYPE")]
+    pub type_name: Option<String>,
+
+    /// 只處理記憶體 >= 此值的 key（bytes）
+    #[arg(long, default_value_t = 0)]
+    pub min_bytes: u64,
+
+    /// 改為對報表中的 TTL 政策建議（快取前綴裡沒有 TTL 的大 key）設定 EXPIRE，而不是 UNLINK
+    #[arg(long, conflicts_with = "type_name")]
+    pub expire_suggested: bool,
+
+    /// 實際對 snapshot 的來源執行 UNLINK / EXPIRE（否則只輸出指令）
+    #[arg(long)]
+    pub execute: bool,
+}
+
+/// 類型名稱（string/list/set/zset/hash/stream）
+fn parse_type(s: &str) -> Result<KeyTypeCode, String> {
+    KeyTypeCode::from_name(s.trim()).ok_or_else(|| format!("未知的類型: {}", s))
+}
+
+/// 解析 "2%" 或 "2"（都代表 2%），回傳 0~1 的比例
+fn parse_percent(s: &str) -> Result<f64, String> {
+    let n: f64 = s
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("無效的百分比: {}", s))?;
+    if !(0.0..=100.0).contains(&n) {
+        return Err(format!("百分比必須介於 0 到 100: {}", s));
+    }
+    Ok(n / 100.0)
+}
+
+/// 解析 "UTC"、"+08:00"、"-0530"、"UTC+8" 這類固定偏移，回傳偏移秒數
+fn parse_timezone(s: &str) -> Result<i64, String> {
+    let t = s.trim();
+    let t = t
+        .strip_prefix("UTC")
+        .or_else(|| t.strip_prefix("GMT"))
+        .unwrap_or(t);
+    if t.is_empty() || t == "Z" {
+        return Ok(0);
+    }
+    let invalid = || format!("無效的時區: {}（用 UTC、+08:00、-05:30 這類固定偏移）", s);
+    let (sign, rest) = match t.as_bytes()[0] {
+        b'+' => (1, &t[1..]),
+        b'-' => (-1, &t[1..]),
+        _ => return Err(invalid()),
+    };
+    // 先確認只有數字與冒號，split_at 才不會切在多位元組字元中間
+    if !rest.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+        return Err(invalid());
+    }
+    let (h, m) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let (h, m) = (
+        h.parse::<i64>().map_err(|_| invalid())?,
+        m.parse::<i64>().map_err(|_| invalid())?,
+    );
+    if h > 14 || m >= 60 {
+        return Err(invalid());
+    }
+    Ok(sign * (h * 3600 + m * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn parse_timezone_offsets() {
+        assert_eq!(parse_timezone("+0800"), Ok(8 * 3600));
+        assert_eq!(parse_timezone("+08:00"), Ok(8 * 3600));
+        assert_eq!(parse_timezone("-0530"), Ok(-(5 * 3600 + 30 * 60)));
+        assert_eq!(parse_timezone("UTC"), Ok(0));
+    }
+
+    #[test]
+    fn fleet_concurrency_is_an_alias_of_parallel() {
+        Cli::command().debug_assert();
+        let cli = Cli::parse_from([
+            "redis-top-keys-analyzer",
+            "fleet",
+            "--url",
+            "a:6379",
+            "--concurrency",
+            "8",
+            "--batch-concurrency",
+            "2",
+        ]);
+        let Some(Command::Fleet(args)) = cli.command else {
+            panic!("應為 fleet 子命令");
+        };
+        assert_eq!(args.parallel, 8);
+        assert_eq!(args.tuning.concurrency, 2);
+    }
+
+    #[test]
+    fn parse_timezone_rejects_non_ascii() {
+        assert!(parse_timezone("+a€").is_err());
+        assert!(parse_timezone("+08:€").is_err());
+        assert!(parse_timezone("+€").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::report::{Column, Report};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// 未指定 --columns 時的 Top N 欄位
+const DEFAULT_COLUMNS: [Column; 5] = [
+    Column::Rank,
+    Column::Key,
+    Column::Bytes,
+    Column::Ttl,
+    Column::Elements,
+];
+
+/// 試算表 / BI 用的 CSV：先是各類型 Top N（每個 key 一行），空一行後是各類型的彙總
+///
+/// Top N 的欄位與順序同文字報表的 --columns（空 = 預設欄位），第一欄固定是類型。
+/// 數字一律是未格式化的整數（bytes），不受 --locale 影響；key 依 RFC 4180 加引號。
+pub fn render_csv(report: &Report, columns: &[Column], out: &mut impl Write) -> io::Result<()> {
+    let columns = if columns.is_empty() {
+        &DEFAULT_COLUMNS[..]
+    } else {
+        columns
+    };
+    let header: Vec<&str> = columns.iter().map(|c| c.csv_header()).collect();
+    writeln!(out, "type,{}", header.join(","))?;
+    for t in report.types.iter().filter(|t| t.count > 0) {
+        let mut cum_mem: u64 = 0;
+        for (i, k) in t.top.iter().enumerate() {
+            cum_mem += k.mem;
+            let cum_pct = if t.total_mem > 0 {
+                cum_mem as f64 / t.total_mem as f64 * 100.0
+            } else {
+                0.0
+            };
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|c| c.csv_cell(i + 1, k, cum_pct))
+                .collect();
+            writeln!(out, "{},{}", t.type_code.name(), cells.join(","))?;
+        }
+    }
+
+    let total_mem = report.total_mem();
+    let share = |mem: u64| {
+        if total_mem > 0 {
+            mem as f64 / total_mem as f64 * 100.0
+        } else {
+            0.0
+        }
+    };
+    writeln!(out)?;
+    writeln!(out, "type,keys,bytes,share_pct")?;
+    for t in report.types.iter().filter(|t| t.count > 0) {
+        writeln!(
+            out,
+            "{},{},{},{:.2}",
+            t.type_code.name(),
+            t.count,
+            t.total_mem,
+            share(t.total_mem)
+        )?;
+    }
+    let keys: u64 = report.types.iter().map(|t| t.count).sum();
+    writeln!(out, "total,{},{},{:.2}", keys, total_mem, share(total_mem))?;
+    out.flush()
+}
+
+pub fn write_csv(report: &Report, columns: &[Column], path: &Path) -> Result<(), Box<dyn Error>> {
+    let write = || -> io::Result<()> {
+        render_csv(report, columns, &mut BufWriter::new(File::create(path)?))
+    };
+    write().map_err(|e| format!("無法寫入 {}: {}", path.display(), e))?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use crate::output::warning;
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+const EXACT_ENTRY_BYTES: u64 = 16; // HashSet<u64> 每筆約佔的記憶體（含 hashbrown 的空槽）
+const BLOOM_HASHES: u64 = 7; // 約 1% 誤判率時的最佳 hash 數
+
+/// SCAN 重複 key 的排除方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DedupMode {
+    /// 不排除（SCAN 在 rehash 期間可能重複回傳同一個 key）
+    #[default]
+    Off,
+    /// 記住每個 key 的 64-bit hash；超過記憶體上限時自動改用 bloom
+    Exact,
+    /// 固定大小的 bloom filter，少量 key 可能被誤判為重複而漏算
+    Bloom,
+}
+
+/// 已看過的 key
+///
+/// 只存 hash 不存 key 本身；exact 模式的誤判機率是 64-bit hash 碰撞，可忽略。
+pub struct Dedup {
+    set: Option<HashSet<u64>>,
+    bloom: Option<Vec<u64>>, // bit 陣列
+    cap_bytes: u64,
+    pub duplicates: u64,
+}
+
+impl Dedup {
+    pub fn new(mode: DedupMode, cap_bytes: u64) -> Option<Self> {
+        let mut d = Self {
+            set: None,
+            bloom: None,
+            cap_bytes: cap_bytes.max(1024),
+            duplicates: 0,
+        };
+        match mode {
+            DedupMode::Off => return None,
+            DedupMode::Exact => d.set = Some(HashSet::new()),
+            DedupMode::Bloom => d.bloom = Some(d.empty_bloom()),
+        }
+        Some(d)
+    }
+
+    /// 第一次看到此 key 時回傳 true；重複時回傳 false 並計數
+    pub fn first_seen(&mut self, key: &str) -> bool {
+        let mut h = DefaultHasher::new();
+        key.hash(&mut h);
+        let hash = h.finish();
+
+        let fresh = match (&mut self.set, &mut self.bloom) {
+            (Some(set), _) => set.insert(hash),
+            (None, Some(bits)) => bloom_insert(bits, hash),
+            (None, None) => true,
+        };
+        if !fresh {
+            self.duplicates += 1;
+        }
+
+        // exact 超過上限：把已有的 hash 搬進 bloom，之後改用 bloom
+        let overflow = self
+            .set
+            .as_ref()
+            .is_some_and(|set| set.len() as u64 * EXACT_ENTRY_BYTES > self.cap_bytes);
+        if overflow {
+            let set = self.set.take().unwrap_or_default();
+            warning!(
+                "去重用的 hash set 超過記憶體上限（{} keys），改用 bloom filter",
+                set.len()
+            );
+            let mut bits = self.empty_bloom();
+            for h in set {
+                bloom_insert(&mut bits, h);
+            }
+            self.bloom = Some(bits);
+        }
+
+        fresh
+    }
+
+    /// 是否使用 bloom（結果可能少算少量 key）
+    pub fn is_approximate(&self) -> bool {
+        self.bloom.is_some()
+    }
+
+    fn empty_bloom(&self) -> Vec<u64> {
+        vec![0; (self.cap_bytes / 8) as usize]
+    }
+}
+
+/// 以 double hashing 從一個 64-bit hash 推出 BLOOM_HASHES 個位置；全部已設定代表（可能）看過
+fn bloom_insert(bits: &mut [u64], hash: u64) -> bool {
+    let n = bits.len() as u64 * 64;
+    let h1 = hash;
+    let h2 = hash.rotate_left(32) | 1;
+    let mut fresh = false;
+    for i in 0..BLOOM_HASHES {
+        let bit = h1.wrapping_add(i.wrapping_mul(h2)) % n;
+        let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+        if bits[word] & mask == 0 {
+            bits[word] |= mask;
+            fresh = true;
+        }
+    }
+    fresh
+}
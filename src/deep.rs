@@ -0,0 +1,274 @@
+//! 第二階段：只對各類型 Top N 做昂貴但精確的分析
+//!
+//! 第一階段為了速度用 MEMORY USAGE 的抽樣估計；Top N 只有幾十個 key，
+//! 這裡改用 SAMPLES 0 完整計算，並補上 TTL、encoding、元素數與元素大小抽樣。
+
+use crate::conn::RedisConn;
+use crate::latency::{SLOW_MEASURE, SlowMeasure};
+use crate::output::warning;
+use crate::report::Report;
+use crate::scan::{parse_int, parse_string, send_pipeline};
+use crate::stats::{KeyTypeCode, TopKey};
+use redis::Value;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const DEEP_SAMPLES: usize = 32; // 每個集合類型 key 抽樣的元素數
+
+/// 抽樣元素的大小（hash 為 field + value，zset 為 member + score，stream 為整筆 entry）
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ElementSizes {
+    pub sampled: u64,
+    pub avg_bytes: u64,
+    pub max_bytes: u64,
+}
+
+/// 逐個 key 送 pipeline（SAMPLES 0 對大 key 是 O(n)，不一次送全部以免長時間佔住伺服器），
+/// 最多 parallel 條連線同時處理不同的 key，每條連線處理完一個 key 暫停 throttle。
+/// 更新後依新的記憶體重新排序，類型合計跟著修正。
+///
+/// 回傳分析的 key 數與第一個錯誤訊息；失敗的 key 保留第一階段的結果。
+/// 額外的連線開不起來時以已開的連線繼續。元素數超過 estimate_above 的集合不送 SAMPLES 0，
+/// 耗時超過 SLOW_MEASURE 的 key 記在 report.latency。
+pub fn deep_analyze(
+    con: &mut RedisConn,
+    report: &mut Report,
+    parallel: usize,
+    throttle: Duration,
+    estimate_above: Option<u64>,
+) -> (usize, Option<String>) {
+    let target = report.target.clone();
+    let jobs: Vec<Mutex<(KeyTypeCode, &mut TopKey)>> = report
+        .types
+        .iter_mut()
+        .flat_map(|tr| {
+            let t = tr.type_code;
+            tr.top.iter_mut().map(move |k| Mutex::new((t, k)))
+        })
+        .collect();
+
+    let first_error = Mutex::new(None);
+    let slow: Mutex<Vec<SlowMeasure>> = Mutex::new(Vec::new());
+    let estimated: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+    let mut extra = Vec::new();
+    for _ in 1..parallel.min(jobs.len()) {
+        match con.open_another() {
+            Ok(c) => extra.push(c),
+            Err(e) => {
+                first_error
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .get_or_insert(format!("無法開啟額外連線: {}", e));
+                break;
+            }
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let analyzed = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        for c in std::iter::once(con).chain(extra.iter_mut()) {
+            let (jobs, next, analyzed, first_error) = (&jobs, &next, &analyzed, &first_error);
+            let (slow, estimated, target) = (&slow, &estimated, &target);
+            s.spawn(move || {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(job) = jobs.get(i) else {
+                        break;
+                    };
+                    let mut job = job.lock().unwrap_or_else(|e| e.into_inner());
+                    let (t, k) = &mut *job;
+                    let was_estimated = k.estimated;
+                    let started = Instant::now();
+                    match analyze_key(c, *t, k, estimate_above) {
+                        Ok(()) => {
+                            analyzed.fetch_add(1, Ordering::Relaxed);
+                            if k.estimated && !was_estimated {
+                                lock(estimated).push(k.mem);
+                            }
+                        }
+                        Err(e) => {
+                            first_error
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .get_or_insert(e);
+                        }
+                    }
+                    let elapsed = started.elapsed();
+                    if elapsed >= SLOW_MEASURE {
+                        lock(slow).push(SlowMeasure {
+                            target: target.clone(),
+                            deep: true,
+                            ms: elapsed.as_millis() as u64,
+                            keys: 1,
+                            type_code: *t,
+                            key: k.key.clone(),
+                            mem: k.mem,
+                            elements: k.elements,
+                        });
+                    }
+                    if !throttle.is_zero() {
+                        std::thread::sleep(throttle);
+                    }
+                }
+            });
+        }
+    });
+    drop(jobs);
+
+    for m in slow.into_inner().unwrap_or_else(|e| e.into_inner()) {
+        if let Some(warning) = report.latency.add_slow(m) {
+            warning!("{}", warning);
+        }
+    }
+    for mem in estimated.into_inner().unwrap_or_else(|e| e.into_inner()) {
+        report.latency.add_estimated(mem);
+    }
+
+    for tr in &mut report.types {
+        let estimated: u64 = tr
+            .top
+            .iter()
+            .map(|k| k.estimated_mem.unwrap_or(k.mem))
+            .sum();
+        let exact: u64 = tr.top.iter().map(|k| k.mem).sum();
+        tr.total_mem = (tr.total_mem + exact).saturating_sub(estimated);
+        tr.top.sort_by(TopKey::rank_cmp);
+    }
+
+    (
+        analyzed.into_inner(),
+        first_error.into_inner().unwrap_or_else(|e| e.into_inner()),
+    )
+}
+
+fn analyze_key(
+    con: &mut RedisConn,
+    t: KeyTypeCode,
+    k: &mut TopKey,
+    estimate_above: Option<u64>,
+) -> Result<(), String> {
+    // 元素太多的集合 SAMPLES 0 會卡住伺服器，先以 O(1) 的長度指令確認
+    let exact = match estimate_above {
+        Some(limit) if t != KeyTypeCode::String && !k.estimated => {
+            let n = match k.elements {
+                Some(n) => n,
+                None => redis::cmd(t.length_command())
+                    .arg(&k.key)
+                    .query::<i64>(con)
+                    .map_err(|e| e.to_string())?
+                    .max(0) as u64,
+            };
+            n <= limit
+        }
+        Some(_) => !k.estimated,
+        None => true,
+    };
+
+    let mut pipe = redis::pipe();
+    if exact {
+        pipe.cmd("MEMORY")
+            .arg("USAGE")
+            .arg(&k.key)
+            .arg("SAMPLES")
+            .arg(0);
+    }
+    pipe.cmd("PTTL").arg(&k.key);
+    pipe.cmd("OBJECT").arg("ENCODING").arg(&k.key);
+    pipe.cmd(t.length_command()).arg(&k.key);
+    let sampled = push_sample_cmd(&mut pipe, t, &k.key);
+    let base = exact as usize;
+    let count = base + 3 + sampled as usize;
+
+    let values = send_pipeline(con, &pipe, count).map_err(|e| e.to_string())?;
+    // 抽樣指令失敗（例如 6.2 以前沒有 HRANDFIELD）只是少了元素大小
+    if let Some(Value::ServerError(e)) = values[..base + 3]
+        .iter()
+        .find(|v| matches!(v, Value::ServerError(_)))
+    {
+        return Err(redis::RedisError::from(e.clone()).to_string());
+    }
+
+    if exact {
+        // key 在兩階段之間被刪除：保留第一階段的結果
+        let Some(mem) = parse_int(&values[0]).map(|m| m.max(0) as u64) else {
+            return Err(format!("{} 已不存在", k.key));
+        };
+        k.estimated_mem = Some(k.mem);
+        k.mem = mem;
+    } else {
+        k.estimated = true;
+    }
+    k.ttl_ms = parse_int(&values[base]);
+    k.encoding = parse_string(&values[base + 1]);
+    k.elements = parse_int(&values[base + 2]).map(|n| n.max(0) as u64);
+    if sampled {
+        k.element_sizes = element_sizes(t, &values[base + 3]);
+    }
+    Ok(())
+}
+
+fn lock<T>(m: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 集合類型抽樣取回元素；string 沒有元素可抽樣，回傳 false
+fn push_sample_cmd(pipe: &mut redis::Pipeline, t: KeyTypeCode, key: &str) -> bool {
+    let n = DEEP_SAMPLES;
+    match t {
+        KeyTypeCode::String => return false,
+        KeyTypeCode::List => pipe.cmd("LRANGE").arg(key).arg(0).arg(n - 1),
+        KeyTypeCode::Set => pipe.cmd("SRANDMEMBER").arg(key).arg(n),
+        KeyTypeCode::ZSet => pipe.cmd("ZRANDMEMBER").arg(key).arg(n).arg("WITHSCORES"),
+        KeyTypeCode::Hash => pipe.cmd("HRANDFIELD").arg(key).arg(n).arg("WITHVALUES"),
+        KeyTypeCode::Stream => pipe
+            .cmd("XRANGE")
+            .arg(key)
+            .arg("-")
+            .arg("+")
+            .arg("COUNT")
+            .arg(n),
+    };
+    true
+}
+
+/// 每個元素的 bytes；hash / zset 在 RESP2 是扁平的 [f, v, f, v…]，兩兩一組
+fn element_sizes(t: KeyTypeCode, v: &Value) -> Option<ElementSizes> {
+    let (Value::Array(items) | Value::Set(items)) = v else {
+        return None;
+    };
+    let flat = matches!(t, KeyTypeCode::Hash | KeyTypeCode::ZSet)
+        && items.iter().all(|i| !matches!(i, Value::Array(_)));
+    let sizes: Vec<u64> = if flat {
+        items
+            .chunks(2)
+            .map(|p| p.iter().map(value_bytes).sum())
+            .collect()
+    } else {
+        items.iter().map(value_bytes).collect()
+    };
+    if sizes.is_empty() {
+        return None;
+    }
+    Some(ElementSizes {
+        sampled: sizes.len() as u64,
+        avg_bytes: sizes.iter().sum::<u64>() / sizes.len() as u64,
+        max_bytes: sizes.iter().copied().max().unwrap_or(0),
+    })
+}
+
+fn value_bytes(v: &Value) -> u64 {
+    match v {
+        Value::BulkString(b) => b.len() as u64,
+        Value::SimpleString(s) => s.len() as u64,
+        Value::Int(_) | Value::Double(_) => 8,
+        Value::Array(items) | Value::Set(items) => items.iter().map(value_bytes).sum(),
+        Value::Map(pairs) => pairs
+            .iter()
+            .map(|(k, v)| value_bytes(k) + value_bytes(v))
+            .sum(),
+        _ => 0,
+    }
+}
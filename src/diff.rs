@@ -0,0 +1,716 @@
+use crate::format::{Decimal, format_int, format_unix_ts, truncate_key};
+use crate::rdb;
+use crate::report::Report;
+use crate::scan::ScanConfig;
+use crate::snapshot;
+use crate::stats::{KeyTypeCode, MAX_PREFIXES, OTHER_PREFIX, TOP_N};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const DEFAULT_MIN_CHANGE: f64 = 10.0; // 前綴與 key 的記憶體增減超過幾 % 才列出
+const DIFF_PREFIXES: usize = 20; // 前綴變化最多列出幾個
+const DIFF_KEYS: usize = 20; // 大小變化的 key 最多列出幾個
+
+/// diff 的一側：RDB 檔（依檔頭辨識）以與掃描相同的前綴分組離線分析，其他當成 snapshot
+pub fn load_side(path: &Path, db: Option<u64>, cfg: &ScanConfig) -> Result<Report, Box<dyn Error>> {
+    if !rdb::is_rdb(path) {
+        return snapshot::load(path);
+    }
+    rdb::analyze(
+        path,
+        db,
+        cfg.prefix_sep,
+        cfg.prefix_depth,
+        cfg.prefix_tables,
+    )
+}
+
+/// 只有一側是 RDB 的估計值時，把舊側的記憶體換算到新側的比例（新 / 舊總記憶體）
+///
+/// 序列化大小與 MEMORY USAGE 的差距依資料而定，不換算的話完全相同的資料也會
+/// 在每個前綴都顯示大幅增減；換算後比較的是各前綴、各 key 佔總量的比例變化。
+fn mem_scale(old: &Report, new: &Report) -> Option<f64> {
+    let (om, nm) = (old.total_mem(), new.total_mem());
+    (rdb::is_rdb_report(old) != rdb::is_rdb_report(new) && om > 0 && nm > 0)
+        .then(|| nm as f64 / om as f64)
+}
+
+fn scaled(mem: u64, scale: Option<f64>) -> u64 {
+    match scale {
+        Some(s) => (mem as f64 * s).round() as u64,
+        None => mem,
+    }
+}
+
+/// 增減是否達到門檻：base 的 min_change %
+fn is_significant(delta: i64, base: u64, min_change: f64) -> bool {
+    delta != 0 && delta.unsigned_abs() as f64 >= base as f64 * min_change / 100.0
+}
+
+/// 比較兩個 snapshot：各類型 keys / 記憶體增減、前綴與 key 的明顯增減，以及 Top keys 的進出榜與成長
+///
+/// 任一側可以是 RDB 的離線分析；只有一側是 RDB 時，前綴與 key 的記憶體先換算到同一比例（mem_scale）。
+pub fn render_diff(
+    old: &Report,
+    new: &Report,
+    min_change: f64,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "Snapshot 比較: {} ({}) → {} ({})",
+        old.target,
+        format_unix_ts(old.started_at),
+        new.target,
+        format_unix_ts(new.started_at)
+    )?;
+    for (label, r) in [("舊", old), ("新", new)] {
+        for note in &r.notes {
+            writeln!(out, "📝 {}: {}", label, note)?;
+        }
+    }
+    let scale = mem_scale(old, new);
+    if let Some(s) = scale {
+        let (estimate, exact, ratio) = if rdb::is_rdb_report(old) {
+            ("舊", "新", 1.0 / s)
+        } else {
+            ("新", "舊", s)
+        };
+        writeln!(
+            out,
+            "⚠ {}側是 RDB 的估計值（序列化大小），{}側是 MEMORY USAGE 實測值，兩者不能直接相減：",
+            estimate, exact
+        )?;
+        writeln!(
+            out,
+            "  總記憶體 估計值 / 實測值 = {:.3}；前綴與 key 的比較先把舊側乘上 {:.3} 換算，key 數為精確值",
+            Decimal(ratio),
+            Decimal(s)
+        )?;
+    }
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "{:<10} {:>15} {:>15} {:>12} {:>15} {:>15} {:>15}",
+        "類型", "Keys (舊)", "Keys (新)", "Keys 增減", "MB (舊)", "MB (新)", "MB 增減"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    for t in KeyTypeCode::all() {
+        let (oc, om) = old
+            .get(*t)
+            .map(|r| (r.count, r.total_mem))
+            .unwrap_or((0, 0));
+        let (nc, nm) = new
+            .get(*t)
+            .map(|r| (r.count, r.total_mem))
+            .unwrap_or((0, 0));
+        if oc == 0 && nc == 0 {
+            continue;
+        }
+        writeln!(
+            out,
+            "{:<10} {:>15} {:>15} {:>12} {:>15.2} {:>15.2} {:>+15.2}",
+            t.name(),
+            format_int(oc),
+            format_int(nc),
+            format_signed(nc as i64 - oc as i64),
+            Decimal(om as f64 / 1024.0 / 1024.0),
+            Decimal(nm as f64 / 1024.0 / 1024.0),
+            Decimal((nm as f64 - om as f64) / 1024.0 / 1024.0)
+        )?;
+    }
+
+    let (om, nm) = (old.total_mem(), new.total_mem());
+    writeln!(
+        out,
+        "\n總計: {} → {} keys, {:.2} → {:.2} MB ({:+.2} MB)",
+        format_int(old.scanned),
+        format_int(new.scanned),
+        Decimal(om as f64 / 1024.0 / 1024.0),
+        Decimal(nm as f64 / 1024.0 / 1024.0),
+        Decimal((nm as f64 - om as f64) / 1024.0 / 1024.0)
+    )?;
+
+    render_prefix_changes(old, new, min_change, scale, out)?;
+    render_key_changes(old, new, min_change, scale, out)?;
+
+    // ------------------------------------------------------------
+    // Top keys 變化
+    // ------------------------------------------------------------
+    let old_label = if scale.is_some() {
+        "舊 (換算)"
+    } else {
+        "舊 (Bytes)"
+    };
+    for t in KeyTypeCode::all() {
+        let old_top = old.get(*t).map(|r| r.top.as_slice()).unwrap_or(&[]);
+        let new_top = new.get(*t).map(|r| r.top.as_slice()).unwrap_or(&[]);
+        if old_top.is_empty() && new_top.is_empty() {
+            continue;
+        }
+
+        writeln!(out, "\n🔸 {} - Top keys 變化", t.title())?;
+        writeln!(out, "{}", "-".repeat(120))?;
+        writeln!(
+            out,
+            "{:<6} {:>15} {:>15} {:>15} Key",
+            "狀態", old_label, "新 (Bytes)", "增減 (Bytes)"
+        )?;
+        writeln!(out, "{}", "-".repeat(120))?;
+
+        for k in new_top {
+            match old_top.iter().find(|o| o.key == k.key) {
+                Some(o) => {
+                    let om = scaled(o.mem, scale);
+                    writeln!(
+                        out,
+                        "{:<6} {:>15} {:>15} {:>15} {}",
+                        "",
+                        om,
+                        k.mem,
+                        format_signed(k.mem as i64 - om as i64),
+                        truncate_key(&k.key, 80)
+                    )?
+                }
+                None => writeln!(
+                    out,
+                    "{:<6} {:>15} {:>15} {:>15} {}",
+                    "新進",
+                    "-",
+                    k.mem,
+                    "-",
+                    truncate_key(&k.key, 80)
+                )?,
+            }
+        }
+        for o in old_top {
+            if !new_top.iter().any(|k| k.key == o.key) {
+                writeln!(
+                    out,
+                    "{:<6} {:>15} {:>15} {:>15} {}",
+                    "出榜",
+                    scaled(o.mem, scale),
+                    "-",
+                    "-",
+                    truncate_key(&o.key, 80)
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 一個前綴兩側的記憶體（舊側已換算）；None = 該側沒有這個前綴
+struct PrefixChange<'a> {
+    prefix: &'a str,
+    old: Option<u64>,
+    new: Option<u64>,
+}
+
+impl PrefixChange<'_> {
+    fn delta(&self) -> i64 {
+        self.new.unwrap_or(0) as i64 - self.old.unwrap_or(0) as i64
+    }
+}
+
+/// 該側的前綴種類達到 MAX_PREFIXES，之後出現的前綴都被歸到 OTHER_PREFIX
+fn prefixes_capped(r: &Report) -> bool {
+    r.prefixes.iter().any(|p| p.prefix == OTHER_PREFIX)
+}
+
+/// 記憶體增減超過 min_change % 的前綴，依增減絕對值 desc、同樣時依名稱
+///
+/// 兩側都有的前綴以舊側的大小為基準；只出現在一側的（新增 / 消失）以該側的總記憶體為基準，
+/// 避免零星的小前綴洗版。任一側達到前綴上限時，OTHER_PREFIX 與在該側找不到的前綴
+/// 都可能只是被歸到 OTHER_PREFIX，不列出。
+fn prefix_changes<'a>(
+    old: &'a Report,
+    new: &'a Report,
+    min_change: f64,
+    scale: Option<f64>,
+) -> Vec<PrefixChange<'a>> {
+    let mut sizes: BTreeMap<&str, PrefixChange> = BTreeMap::new();
+    for p in &old.prefixes {
+        let entry = sizes.entry(&p.prefix).or_insert(PrefixChange {
+            prefix: &p.prefix,
+            old: None,
+            new: None,
+        });
+        entry.old = Some(scaled(p.total_mem, scale));
+    }
+    for p in &new.prefixes {
+        let entry = sizes.entry(&p.prefix).or_insert(PrefixChange {
+            prefix: &p.prefix,
+            old: None,
+            new: None,
+        });
+        entry.new = Some(p.total_mem);
+    }
+    let (old_capped, new_capped) = (prefixes_capped(old), prefixes_capped(new));
+    let (old_total, new_total) = (scaled(old.total_mem(), scale), new.total_mem());
+    let mut changed: Vec<PrefixChange> = sizes
+        .into_values()
+        .filter(|c| !((old_capped || new_capped) && c.prefix == OTHER_PREFIX))
+        .filter(|c| match (c.old, c.new) {
+            (Some(o), Some(_)) => is_significant(c.delta(), o, min_change),
+            (None, _) => !old_capped && is_significant(c.delta(), new_total, min_change),
+            (_, None) => !new_capped && is_significant(c.delta(), old_total, min_change),
+        })
+        .collect();
+    changed.sort_by(|a, b| {
+        b.delta()
+            .unsigned_abs()
+            .cmp(&a.delta().unsigned_abs())
+            .then_with(|| a.prefix.cmp(b.prefix))
+    });
+    changed
+}
+
+/// 前綴變化（prefix_changes），最多 DIFF_PREFIXES 個
+///
+/// 兩側都沒有前綴統計（舊版 snapshot、--prefix-depth 0）時不輸出。
+fn render_prefix_changes(
+    old: &Report,
+    new: &Report,
+    min_change: f64,
+    scale: Option<f64>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if old.prefixes.is_empty() && new.prefixes.is_empty() {
+        return Ok(());
+    }
+    let changed = prefix_changes(old, new, min_change, scale);
+
+    writeln!(
+        out,
+        "\n🔸 前綴變化（記憶體增減 ≥ {}%，共 {} 個）",
+        min_change,
+        format_int(changed.len() as u64)
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    if prefixes_capped(old) || prefixes_capped(new) {
+        writeln!(
+            out,
+            "  ⚠ 前綴超過 {} 種，超出的歸到 {}：{} 與只出現在一側的前綴不列出",
+            format_int(MAX_PREFIXES as u64),
+            OTHER_PREFIX,
+            OTHER_PREFIX
+        )?;
+    }
+    if changed.is_empty() {
+        writeln!(out, "  沒有明顯變化的前綴")?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "{:<6} {:>15} {:>15} {:>15} {:>9} 前綴",
+        "狀態",
+        if scale.is_some() {
+            "MB (舊，換算)"
+        } else {
+            "MB (舊)"
+        },
+        "MB (新)",
+        "MB 增減",
+        "增減 %"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    let mb = |m: Option<u64>| match m {
+        Some(m) => format!("{:.2}", Decimal(m as f64 / 1024.0 / 1024.0)),
+        None => "-".to_string(),
+    };
+    for c in changed.iter().take(DIFF_PREFIXES) {
+        let (status, pct) = match (c.old, c.new) {
+            (Some(o), Some(_)) if o > 0 => (
+                "",
+                format!("{:+.1}", Decimal(c.delta() as f64 / o as f64 * 100.0)),
+            ),
+            (Some(_), Some(_)) => ("", "-".to_string()),
+            (None, _) => ("新增", "-".to_string()),
+            (_, None) => ("消失", "-".to_string()),
+        };
+        writeln!(
+            out,
+            "{:<6} {:>15} {:>15} {:>+15.2} {:>9} {}",
+            status,
+            mb(c.old),
+            mb(c.new),
+            Decimal(c.delta() as f64 / 1024.0 / 1024.0),
+            pct,
+            truncate_key(c.prefix, 80)
+        )?;
+    }
+    if changed.len() > DIFF_PREFIXES {
+        writeln!(
+            out,
+            "  …另有 {} 個前綴未列出",
+            format_int((changed.len() - DIFF_PREFIXES) as u64)
+        )?;
+    }
+    Ok(())
+}
+
+/// 報表中記錄了大小的 key：各類型的 Top N 加上前綴的 Top keys
+fn known_sizes(r: &Report) -> BTreeMap<&str, u64> {
+    let top = r
+        .types
+        .iter()
+        .flat_map(|t| &t.top)
+        .map(|k| (k.key.as_str(), k.mem));
+    let prefix_top = r
+        .prefixes
+        .iter()
+        .flat_map(|p| &p.top)
+        .map(|k| (k.key.as_str(), k.mem));
+    top.chain(prefix_top).collect()
+}
+
+/// 兩側都記錄了大小、且增減超過 min_change % 的 key：(key, 舊（已換算）, 新)，依增減絕對值 desc
+///
+/// 只出現在一側的 key 不算：可能只是掉出 Top N，不代表被刪除。
+fn key_changes<'a>(
+    old: &'a Report,
+    new: &'a Report,
+    min_change: f64,
+    scale: Option<f64>,
+) -> Vec<(&'a str, u64, u64)> {
+    let old_sizes = known_sizes(old);
+    let mut changed: Vec<(&str, u64, u64)> = known_sizes(new)
+        .into_iter()
+        .filter_map(|(key, n)| {
+            let o = scaled(*old_sizes.get(key)?, scale);
+            is_significant(n as i64 - o as i64, o, min_change).then_some((key, o, n))
+        })
+        .collect();
+    changed.sort_by(|a, b| {
+        let delta = |c: &(&str, u64, u64)| c.2.abs_diff(c.1);
+        delta(b).cmp(&delta(a)).then_with(|| a.0.cmp(b.0))
+    });
+    changed
+}
+
+/// 大小變化的 key（key_changes），最多 DIFF_KEYS 個
+fn render_key_changes(
+    old: &Report,
+    new: &Report,
+    min_change: f64,
+    scale: Option<f64>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let changed = key_changes(old, new, min_change, scale);
+    writeln!(
+        out,
+        "\n🔸 大小變化的 key（Top N 與前綴 Top keys 中兩側都有、增減 ≥ {}%，共 {} 個）",
+        min_change,
+        format_int(changed.len() as u64)
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    if changed.is_empty() {
+        writeln!(out, "  沒有明顯變化的 key")?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "{:>15} {:>15} {:>15} {:>9} Key",
+        if scale.is_some() {
+            "舊 (換算)"
+        } else {
+            "舊 (Bytes)"
+        },
+        "新 (Bytes)",
+        "增減 (Bytes)",
+        "增減 %"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for (key, o, n) in changed.iter().take(DIFF_KEYS) {
+        let delta = *n as i64 - *o as i64;
+        let pct = match o {
+            0 => "-".to_string(),
+            o => format!("{:+.1}", Decimal(delta as f64 / *o as f64 * 100.0)),
+        };
+        writeln!(
+            out,
+            "{:>15} {:>15} {:>15} {:>9} {}",
+            o,
+            n,
+            format_signed(delta),
+            pct,
+            truncate_key(key, 80)
+        )?;
+    }
+    if changed.len() > DIFF_KEYS {
+        writeln!(
+            out,
+            "  …另有 {} 個 key 未列出",
+            format_int((changed.len() - DIFF_KEYS) as u64)
+        )?;
+    }
+    Ok(())
+}
+
+/// 目前全域 Top N 中、baseline 完全沒出現過的 key，回傳筆數
+///
+/// 新出現的大 key 幾乎都是剛上線的 bug（忘了設 TTL、無限 append…），
+/// 比既有大 key 的緩慢成長更值得優先處理。
+pub fn render_new_big_keys(
+    baseline: &Report,
+    current: &Report,
+    out: &mut impl Write,
+) -> io::Result<usize> {
+    let known = |key: &str| {
+        baseline
+            .types
+            .iter()
+            .any(|t| t.top.iter().any(|k| k.key == key))
+    };
+    let fresh: Vec<_> = current
+        .global_top(TOP_N)
+        .into_iter()
+        .filter(|(_, k)| !known(&k.key))
+        .collect();
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "新出現的大 key（全域 Top {}，相較 baseline {} ({})）",
+        TOP_N,
+        baseline.target,
+        format_unix_ts(baseline.started_at)
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    if fresh.is_empty() {
+        writeln!(out, "  沒有新出現的大 key")?;
+        return Ok(0);
+    }
+
+    writeln!(
+        out,
+        "{:<8} {:>15} {:>20} {:<24} Key",
+        "類型", "記憶體 (MB)", "記憶體 (Bytes)", "首次發現"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for (t, k) in &fresh {
+        writeln!(
+            out,
+            "{:<8} {:>15.3} {:>20} {:<24} {}",
+            t.name(),
+            Decimal(k.mem as f64 / 1024.0 / 1024.0),
+            k.mem,
+            format_unix_ts(current.started_at),
+            truncate_key(&k.key, 80)
+        )?;
+    }
+
+    Ok(fresh.len())
+}
+
+/// 帶正負號的千分位
+fn format_signed(n: i64) -> String {
+    if n < 0 {
+        format!("-{}", format_int(n.unsigned_abs()))
+    } else {
+        format!("+{}", format_int(n as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{PrefixReport, SNAPSHOT_VERSION, TypeReport};
+    use crate::stats::{KeyExtra, PrefixKey, TopKey};
+    use serde_json::json;
+
+    /// 各前綴的記憶體；總記憶體為各前綴合計，top 為 string 類型的 Top keys
+    fn report(target: &str, prefixes: &[(&str, u64)], top: &[(&str, u64)]) -> Report {
+        let mut r: Report = serde_json::from_value(json!({
+            "format_version": SNAPSHOT_VERSION,
+            "target": target,
+            "started_at": 0,
+            "duration_ms": 0,
+            "scanned": prefixes.len(),
+            "errors": 0,
+            "types": [],
+            "unavailable": [],
+        }))
+        .unwrap();
+        let mut t = TypeReport::empty(KeyTypeCode::String);
+        t.total_mem = prefixes.iter().map(|(_, m)| m).sum();
+        t.top = top
+            .iter()
+            .map(|(k, m)| TopKey::new(*m, k, &KeyExtra::default()))
+            .collect();
+        r.types.push(t);
+        r.prefixes = prefixes
+            .iter()
+            .map(|(p, m)| PrefixReport {
+                prefix: p.to_string(),
+                count: 1,
+                total_mem: *m,
+                name_bytes: 0,
+                top: Vec::new(),
+                bands: Default::default(),
+            })
+            .collect();
+        r
+    }
+
+    fn changes(old: &Report, new: &Report) -> Vec<(String, Option<u64>, Option<u64>)> {
+        prefix_changes(old, new, DEFAULT_MIN_CHANGE, mem_scale(old, new))
+            .into_iter()
+            .map(|c| (c.prefix.to_string(), c.old, c.new))
+            .collect()
+    }
+
+    #[test]
+    fn prefix_change_below_threshold_is_hidden() {
+        let old = report("a", &[("a:*", 1000), ("b:*", 1000), ("c:*", 1000)], &[]);
+        let new = report("b", &[("a:*", 1090), ("b:*", 1100), ("c:*", 1000)], &[]);
+        assert_eq!(
+            changes(&old, &new),
+            vec![("b:*".to_string(), Some(1000), Some(1100))]
+        );
+    }
+
+    #[test]
+    fn one_sided_prefix_is_measured_against_the_total() {
+        let old = report(
+            "a",
+            &[("big:*", 9000), ("gone:*", 1500), ("tiny:*", 10)],
+            &[],
+        );
+        let new = report(
+            "b",
+            &[("big:*", 9000), ("new:*", 2000), ("speck:*", 10)],
+            &[],
+        );
+        assert_eq!(
+            changes(&old, &new),
+            vec![
+                ("new:*".to_string(), None, Some(2000)),
+                ("gone:*".to_string(), Some(1500), None),
+            ]
+        );
+        let mut out = Vec::new();
+        render_prefix_changes(&old, &new, DEFAULT_MIN_CHANGE, None, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.lines()
+                .any(|l| l.starts_with("新增") && l.ends_with("new:*"))
+        );
+        assert!(
+            text.lines()
+                .any(|l| l.starts_with("消失") && l.ends_with("gone:*"))
+        );
+    }
+
+    #[test]
+    fn prefixes_sort_by_absolute_change_then_name() {
+        let old = report("a", &[("a:*", 1000), ("b:*", 1000), ("c:*", 1000)], &[]);
+        let new = report("b", &[("a:*", 1200), ("b:*", 500), ("c:*", 800)], &[]);
+        let order: Vec<String> = changes(&old, &new).into_iter().map(|c| c.0).collect();
+        assert_eq!(order, ["b:*", "a:*", "c:*"]);
+    }
+
+    #[test]
+    fn prefix_list_is_truncated() {
+        let names: Vec<String> = (0..DIFF_PREFIXES + 5)
+            .map(|i| format!("p{:02}:*", i))
+            .collect();
+        let old: Vec<(&str, u64)> = names.iter().map(|n| (n.as_str(), 1000)).collect();
+        let new: Vec<(&str, u64)> = names.iter().map(|n| (n.as_str(), 2000)).collect();
+        let mut out = Vec::new();
+        let (old, new) = (report("a", &old, &[]), report("b", &new, &[]));
+        render_prefix_changes(&old, &new, DEFAULT_MIN_CHANGE, None, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().filter(|l| l.ends_with(":*")).count(),
+            DIFF_PREFIXES
+        );
+        assert!(text.contains("…另有 5 個前綴未列出"));
+    }
+
+    #[test]
+    fn capped_side_hides_overflow_and_missing_prefixes() {
+        let old = report("a", &[("a:*", 5000), ("b:*", 5000)], &[]);
+        let new = report("b", &[("a:*", 5000), (OTHER_PREFIX, 5000)], &[]);
+        // b:* 在新側可能只是被歸到 (其他)
+        assert!(changes(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn rdb_side_is_scaled_before_comparing() {
+        // 序列化大小只有實測值的一半，比例相同時不算變化
+        let old = report(
+            "rdb:/backup/dump.rdb",
+            &[("a:*", 500), ("b:*", 1500)],
+            &[("k", 100)],
+        );
+        let new = report(
+            "redis://127.0.0.1:6379/",
+            &[("a:*", 1000), ("b:*", 3000)],
+            &[("k", 200)],
+        );
+        assert_eq!(mem_scale(&old, &new), Some(2.0));
+        assert!(changes(&old, &new).is_empty());
+        assert!(key_changes(&old, &new, DEFAULT_MIN_CHANGE, Some(2.0)).is_empty());
+        // 兩側都是 RDB 時不換算
+        let both = report("rdb:/backup/new.rdb", &[("a:*", 1000)], &[]);
+        assert_eq!(mem_scale(&old, &both), None);
+    }
+
+    #[test]
+    fn keys_in_both_sides_are_compared() {
+        let mut old = report(
+            "a",
+            &[("a:*", 1000)],
+            &[("a:1", 100), ("a:2", 100), ("gone", 500)],
+        );
+        let mut new = report(
+            "b",
+            &[("a:*", 1000)],
+            &[("a:1", 105), ("a:2", 300), ("fresh", 500)],
+        );
+        // 只在前綴 Top keys 中的 key 也比較
+        let prefix_key = |mem| PrefixKey {
+            type_code: KeyTypeCode::String,
+            key: "a:3".to_string(),
+            mem,
+        };
+        old.prefixes[0].top.push(prefix_key(1000));
+        new.prefixes[0].top.push(prefix_key(500));
+        assert_eq!(
+            key_changes(&old, &new, DEFAULT_MIN_CHANGE, None),
+            vec![("a:3", 1000, 500), ("a:2", 100, 300)]
+        );
+    }
+
+    #[test]
+    fn load_side_detects_rdb_and_snapshot() {
+        let dir = std::env::temp_dir().join(format!("rtka-diff-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dump = dir.join("dump.rdb");
+        // 檔頭 + 一個 string（k = v）+ EOF 與 checksum
+        let mut rdb = b"REDIS0012".to_vec();
+        rdb.extend_from_slice(&[0, 1, b'k', 1, b'v', 0xFF]);
+        rdb.extend_from_slice(&[0; 8]);
+        std::fs::write(&dump, rdb).unwrap();
+        let snapshot = dir.join("snapshot.json");
+        let json = serde_json::to_string(&report("redis://10.0.0.1:6379/", &[], &[])).unwrap();
+        std::fs::write(&snapshot, json).unwrap();
+
+        let cfg = ScanConfig::default();
+        let from_rdb = load_side(&dump, None, &cfg).unwrap();
+        assert!(rdb::is_rdb_report(&from_rdb));
+        assert_eq!(from_rdb.scanned, 1);
+        let from_json = load_side(&snapshot, None, &cfg).unwrap();
+        assert!(!rdb::is_rdb_report(&from_json));
+        assert_eq!(from_json.target, "redis://10.0.0.1:6379/");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
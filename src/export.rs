@@ -0,0 +1,264 @@
+//! --export-keys：掃描中把每個 key 的量測結果逐筆寫成 NDJSON 或 CSV，交給其他工具分析
+//!
+//! 每批 pipeline 之後 flush 一次（--export-fsync 時再 fsync），程式中途被砍掉也只會少最後一批，
+//! 已寫入的每一行都是完整的記錄。掃描正常結束才寫完成標記：
+//! - NDJSON：最後一行為 `{"complete":true,...}`（一般記錄沒有 complete 欄位）
+//! - CSV：最後一行為 `# complete keys=N scanned=N errors=N finished_at=...`
+//!
+//! 沒有完成標記的檔案就是中斷的掃描。寫入失敗只提醒並停止寫入，不影響掃描本身。
+
+use crate::fleet::file_name;
+use crate::format::format_int;
+use crate::output::{say, warning};
+use crate::progress::ProgressBar;
+use crate::report::Report;
+use crate::stats::{KeyExtra, KeyTypeCode};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// 逐筆記錄的格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ExportFormat {
+    /// 每行一個 JSON 物件
+    Ndjson,
+    /// 第一行為欄位名稱
+    Csv,
+}
+
+impl ExportFormat {
+    /// 未指定 --export-format 時依副檔名判斷：.csv 為 CSV，其他為 NDJSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Ndjson,
+        }
+    }
+}
+
+/// 輸出位置與寫入方式
+#[derive(Clone, Debug)]
+pub struct ExportTarget {
+    pub path: PathBuf,
+    pub format: ExportFormat,
+    pub fsync: bool,    // 每批 flush 之後再 fsync
+    pub per_node: bool, // 多節點掃描：每個節點一個檔案，檔名插入節點位置
+}
+
+const CSV_HEADER: &str = "key,type,mem,ttl_ms,idle_secs,encoding,freq,elements,estimated";
+
+#[derive(Serialize)]
+struct Record<'a> {
+    key: &'a str,
+    #[serde(rename = "type")]
+    type_name: &'a str,
+    mem: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    idle_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    freq: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elements: Option<u64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    estimated: bool,
+}
+
+#[derive(Serialize)]
+struct Marker<'a> {
+    complete: bool,
+    target: &'a str,
+    keys: u64,
+    scanned: u64,
+    errors: u64,
+    finished_at: &'a str,
+}
+
+/// 單一節點掃描的寫入端
+pub struct KeyExport {
+    out: BufWriter<File>,
+    path: PathBuf,
+    format: ExportFormat,
+    fsync: bool,
+    written: u64,
+    failed: Option<String>, // 第一次寫入失敗的原因；之後不再寫入
+    warned: bool,
+}
+
+impl KeyExport {
+    /// 建立不了檔案時直接回錯誤，在開始掃描前就讓使用者知道
+    pub fn open(t: &ExportTarget, target: &str) -> redis::RedisResult<Self> {
+        let path = if t.per_node {
+            node_path(&t.path, target)
+        } else {
+            t.path.clone()
+        };
+        let open = || -> io::Result<BufWriter<File>> {
+            let mut out = BufWriter::new(File::create(&path)?);
+            if t.format == ExportFormat::Csv {
+                writeln!(out, "{}", CSV_HEADER)?;
+            }
+            Ok(out)
+        };
+        let out = open().map_err(|e| {
+            redis::RedisError::from((
+                redis::ErrorKind::ClientError,
+                "無法建立 --export-keys",
+                format!("{}: {}", path.display(), e),
+            ))
+        })?;
+        Ok(Self {
+            out,
+            path,
+            format: t.format,
+            fsync: t.fsync,
+            written: 0,
+            failed: None,
+            warned: false,
+        })
+    }
+
+    pub fn add_key(&mut self, key: &str, t: KeyTypeCode, mem: u64, extra: &KeyExtra) {
+        if self.failed.is_some() {
+            return;
+        }
+        let record = Record {
+            key,
+            type_name: t.name(),
+            mem,
+            ttl_ms: extra.ttl_ms,
+            idle_secs: extra.idle_secs,
+            encoding: extra.encoding.as_deref(),
+            freq: extra.freq,
+            elements: extra.elements,
+            estimated: extra.estimated,
+        };
+        let written = match self.format {
+            ExportFormat::Ndjson => serde_json::to_writer(&mut self.out, &record)
+                .map_err(io::Error::from)
+                .and_then(|()| writeln!(self.out)),
+            ExportFormat::Csv => write_csv(&mut self.out, &record),
+        };
+        match written {
+            Ok(()) => self.written += 1,
+            Err(e) => self.failed = Some(e.to_string()),
+        }
+    }
+
+    /// 一批寫完：flush（--export-fsync 時再 fsync），失敗時提醒一次並停止之後的寫入
+    pub fn flush(&mut self, pb: &ProgressBar) {
+        if self.warned {
+            return;
+        }
+        if self.failed.is_none() {
+            if let Err(e) = self.sync() {
+                self.failed = Some(e.to_string());
+            }
+        }
+        if let Some(e) = &self.failed {
+            pb.suspend(|| {
+                warning!(
+                    "⚠ 寫入 --export-keys {} 失敗，停止寫入: {}",
+                    self.path.display(),
+                    e
+                )
+            });
+            self.warned = true;
+        }
+    }
+
+    /// 掃描結束：寫入完成標記；回傳失敗原因（記在 report.unavailable）
+    pub fn finish(mut self, report: &Report, pb: &ProgressBar) -> Option<String> {
+        if self.failed.is_none() {
+            let marked = match self.format {
+                ExportFormat::Ndjson => serde_json::to_writer(
+                    &mut self.out,
+                    &Marker {
+                        complete: true,
+                        target: &report.target,
+                        keys: self.written,
+                        scanned: report.scanned,
+                        errors: report.errors,
+                        finished_at: &report.finished_at_utc,
+                    },
+                )
+                .map_err(io::Error::from)
+                .and_then(|()| writeln!(self.out)),
+                ExportFormat::Csv => writeln!(
+                    self.out,
+                    "# complete keys={} scanned={} errors={} finished_at={}",
+                    self.written, report.scanned, report.errors, report.finished_at_utc
+                ),
+            };
+            if let Err(e) = marked.and_then(|()| self.sync()) {
+                self.failed = Some(e.to_string());
+            }
+        }
+        match self.failed {
+            None => {
+                pb.suspend(|| {
+                    say!(
+                        "✔ 已寫入 {} 個 key 到 {}",
+                        format_int(self.written),
+                        self.path.display()
+                    )
+                });
+                None
+            }
+            Some(e) => Some(format!("{}: {}", self.path.display(), e)),
+        }
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.out.flush()?;
+        if self.fsync {
+            self.out.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+/// keys.ndjson + 10.0.0.1:6379 → keys.10.0.0.1_6379.ndjson
+fn node_path(path: &Path, target: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or_else(|| "keys".into(), |s| s.to_string_lossy());
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, file_name(target), ext.to_string_lossy()),
+        None => format!("{}.{}", stem, file_name(target)),
+    };
+    path.with_file_name(name)
+}
+
+fn write_csv(out: &mut impl Write, r: &Record) -> io::Result<()> {
+    fn opt<T: ToString>(v: Option<T>) -> String {
+        v.map(|v| v.to_string()).unwrap_or_default()
+    }
+    writeln!(
+        out,
+        "{},{},{},{},{},{},{},{},{}",
+        csv_field(r.key),
+        r.type_name,
+        r.mem,
+        opt(r.ttl_ms),
+        opt(r.idle_secs),
+        csv_field(r.encoding.unwrap_or("")),
+        opt(r.freq),
+        opt(r.elements),
+        if r.estimated { "1" } else { "" }
+    )
+}
+
+/// 含逗號、引號、換行或開頭為 # 的欄位加上引號（# 開頭的行保留給完成標記）
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) || s.starts_with('#') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
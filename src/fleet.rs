@@ -0,0 +1,258 @@
+//! fleet：同時掃描多個彼此獨立的實例（--url 可重複），輸出各實例的狀態與合併報表
+//!
+//! 與 --nodes 不同，一個實例連不上或掃描失敗不會中止整批：該實例標記為失敗，其他實例照常跑完，
+//! 合併報表只含成功的實例。連線設定（TLS、帳號密碼、等待、重連）所有實例共用。
+
+use crate::conn::ConnArgs;
+use crate::conn::{connect_quiet, without_password};
+use crate::format::{Decimal, format_int, format_secs};
+use crate::merge;
+use crate::progress::{MultiProgress, ProgressBar, ProgressStyle};
+use crate::report::Report;
+use crate::scan::{self, ScanConfig, ScanDisplay};
+use crate::snapshot;
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PARALLEL: usize = 4;
+
+/// worker 寫回的 (結果, 耗時)；None = 尚未執行
+type Slot = Mutex<Option<(Result<Report, String>, Duration)>>;
+
+/// 單一實例的結果
+pub struct Instance {
+    pub target: String,                  // --url（不含密碼）
+    pub outcome: Result<Report, String>, // 失敗時為連線或掃描的錯誤
+    pub elapsed: Duration,
+}
+
+/// 同時掃描最多 parallel 個實例：每個實例一條進度條，最上面是全部實例的合計
+///
+/// 連線也在各自的 worker 裡進行，連不上的實例不會拖住其他實例開始掃描。
+pub fn scan_fleet(
+    conn: &ConnArgs,
+    urls: &[String],
+    cfg: &ScanConfig,
+    parallel: usize,
+) -> Vec<Instance> {
+    let targets: Vec<String> = urls.iter().map(|u| without_password(u)).collect();
+    let workers = parallel.clamp(1, urls.len().max(1));
+    println!(
+        "\n掃描 {} 個實例（同時 {} 個）: SCAN + PIPELINE {}\n",
+        urls.len(),
+        workers,
+        cfg.describe_commands()
+    );
+
+    let width = targets.iter().map(|t| t.len()).max().unwrap_or(0);
+    let multi = MultiProgress::new();
+    let overall = multi.add(ProgressBar::new(0));
+    overall.set_style(
+        ProgressStyle::with_template(&format!(
+            "{{prefix:<{}}} [{{elapsed_precise}}] [{{wide_bar:.green/blue}}] {{pos}}/{{len}} keys ({{percent}}%)",
+            width
+        ))
+        .unwrap()
+        .progress_chars("=>-"),
+    );
+    overall.set_prefix("全部實例");
+    let style = ProgressStyle::with_template(&format!(
+        "{{prefix:<{}}} [{{elapsed_precise}}] [{{wide_bar:.cyan/blue}}] {{pos}}/{{len}} keys ({{percent}}%) {{msg}}",
+        width
+    ))
+    .unwrap()
+    .progress_chars("=>-");
+    let bars: Vec<ProgressBar> = targets
+        .iter()
+        .map(|t| {
+            let bar = multi.add(ProgressBar::new(0));
+            bar.set_style(style.clone());
+            bar.set_prefix(t.clone());
+            bar.set_message("等待中");
+            bar
+        })
+        .collect();
+
+    let next = AtomicUsize::new(0);
+    let results: Vec<Slot> = urls.iter().map(|_| Mutex::new(None)).collect();
+    std::thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= urls.len() {
+                        break;
+                    }
+                    let started = Instant::now();
+                    bars[i].set_message("連線中");
+                    let outcome = scan_instance(conn, &urls[i], cfg, &multi, &bars[i], &overall);
+                    if let Err(e) = &outcome {
+                        bars[i].abandon_with_message(format!("✖ {}", e));
+                    }
+                    *results[i].lock().unwrap_or_else(|e| e.into_inner()) =
+                        Some((outcome, started.elapsed()));
+                }
+            });
+        }
+    });
+    overall.finish();
+
+    targets
+        .into_iter()
+        .zip(results)
+        .map(|(target, result)| {
+            let (outcome, elapsed) = result
+                .into_inner()
+                .unwrap_or_else(|e| e.into_inner())
+                .unwrap_or_else(|| (Err("未執行".into()), Duration::ZERO));
+            Instance {
+                target,
+                outcome,
+                elapsed,
+            }
+        })
+        .collect()
+}
+
+fn scan_instance(
+    conn: &ConnArgs,
+    url: &str,
+    cfg: &ScanConfig,
+    multi: &MultiProgress,
+    bar: &ProgressBar,
+    overall: &ProgressBar,
+) -> Result<Report, String> {
+    let instance = ConnArgs {
+        host: Some(url.to_string()),
+        port: None,
+        ..conn.clone()
+    };
+    let (mut con, target) = connect_quiet(&instance).map_err(|e| e.to_string())?;
+    bar.set_message("");
+    let display = ScanDisplay::shard(multi, bar, overall);
+    scan::scan_with(&mut con, &target, cfg, &display).map_err(|e| e.to_string())
+}
+
+/// 成功實例的合併報表；失敗的實例記在 unavailable（snapshot 中保留各實例的狀態）
+///
+/// 全部失敗時回 None。
+pub fn merged_report(instances: &[Instance]) -> Option<Report> {
+    let reports: Vec<Report> = instances
+        .iter()
+        .filter_map(|i| i.outcome.as_ref().ok().cloned())
+        .collect();
+    if reports.is_empty() {
+        return None;
+    }
+    let shared = merge::shared_prefixes(&reports);
+    let mut report = Report::merge(reports);
+    report.shared_prefixes = shared;
+    for i in instances {
+        if let Err(e) = &i.outcome {
+            report
+                .unavailable
+                .push(("fleet".into(), format!("{}: {}", i.target, e)));
+        }
+    }
+    Some(report)
+}
+
+/// 各實例的狀態、keys、記憶體與耗時
+pub fn render_status(instances: &[Instance], out: &mut impl Write) -> io::Result<()> {
+    let failed = instances.iter().filter(|i| i.outcome.is_err()).count();
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "🛰 Fleet: {} 個實例，成功 {}，失敗 {}",
+        instances.len(),
+        instances.len() - failed,
+        failed
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "{:<40} {:<6} {:>15} {:>20} {:>10}  說明",
+        "實例", "狀態", "Keys 數量", "總記憶體 (MB)", "耗時"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for i in instances {
+        match &i.outcome {
+            Ok(r) => writeln!(
+                out,
+                "{:<40} {:<6} {:>15} {:>20.2} {:>10}  {}",
+                i.target,
+                "✔ 成功",
+                format_int(r.scanned),
+                Decimal(r.total_mem() as f64 / 1024.0 / 1024.0),
+                format_secs(i.elapsed.as_secs()),
+                if r.errors > 0 {
+                    format!("{} 個 key 量測失敗", format_int(r.errors))
+                } else {
+                    String::new()
+                }
+            )?,
+            Err(e) => writeln!(
+                out,
+                "{:<40} {:<6} {:>15} {:>20} {:>10}  {}",
+                i.target,
+                "✖ 失敗",
+                "-",
+                "-",
+                format_secs(i.elapsed.as_secs()),
+                e
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// --output-dir：每個成功的實例寫一份 snapshot，回傳寫入的檔案
+pub fn write_instance_snapshots(
+    instances: &[Instance],
+    dir: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("無法建立目錄 {}: {}", dir.display(), e))?;
+    let mut used = HashSet::new();
+    let mut files = Vec::new();
+    for i in instances {
+        let Ok(report) = &i.outcome else { continue };
+        let base = file_name(&i.target);
+        let mut name = base.clone();
+        let mut n = 2;
+        while !used.insert(name.clone()) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+        let path = dir.join(format!("{}.json", name));
+        snapshot::save(report, &path)?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// redis://:***@10.0.0.1:6379/2 → 10.0.0.1_6379_2
+pub fn file_name(target: &str) -> String {
+    let rest = target.split_once("://").map_or(target, |(_, r)| r);
+    let rest = rest.rsplit_once('@').map_or(rest, |(_, host)| host);
+    let name: String = rest
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "instance".to_string()
+    } else {
+        name
+    }
+}
@@ -0,0 +1,304 @@
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// 數字與日期的在地化格式（--locale 或 LC_ALL / LC_NUMERIC）
+#[derive(Clone, Copy, Debug)]
+pub struct NumberLocale {
+    thousands: &'static str,
+    decimal: char,
+    group_decimals: bool, // 預設格式不對小數分組，維持原本的輸出
+    date: DateStyle,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum DateStyle {
+    Iso,               // 2026-10-14
+    Dmy(&'static str), // 14.10.2026 / 14/10/2026
+    Mdy,               // 10/14/2026
+}
+
+const DEFAULT_LOCALE: NumberLocale = NumberLocale {
+    thousands: ",",
+    decimal: '.',
+    group_decimals: false,
+    date: DateStyle::Iso,
+};
+
+static LOCALE: OnceLock<NumberLocale> = OnceLock::new();
+static TIMEZONE: OnceLock<i64> = OnceLock::new(); // 顯示用的 UTC 偏移（秒）
+
+/// 設定整個程式使用的格式，只有第一次呼叫有效
+///
+/// 支援 `de_DE.UTF-8`、`fr`、`en-US` 這類寫法；C / POSIX / 無法辨識時維持預設。
+pub fn init_locale(name: Option<&str>) {
+    let name = name
+        .map(str::to_string)
+        .or_else(|| {
+            ["LC_ALL", "LC_NUMERIC"]
+                .iter()
+                .filter_map(|k| std::env::var(k).ok())
+                .find(|v| !v.is_empty())
+        })
+        .unwrap_or_default();
+    let _ = LOCALE.set(parse_locale(&name));
+}
+
+fn parse_locale(name: &str) -> NumberLocale {
+    let base = name
+        .split(['.', '@'])
+        .next()
+        .unwrap_or("")
+        .replace('-', "_");
+    let (lang, region) = base.split_once('_').unwrap_or((&base, ""));
+    let lang = lang.to_ascii_lowercase();
+    let region = region.to_ascii_uppercase();
+
+    let locale = |thousands, decimal, date| NumberLocale {
+        thousands,
+        decimal,
+        group_decimals: true,
+        date,
+    };
+
+    match (lang.as_str(), region.as_str()) {
+        ("" | "c" | "posix", _) => DEFAULT_LOCALE,
+        ("de" | "it", "CH") => locale("'", '.', DateStyle::Dmy(".")),
+        ("en", "US" | "") => locale(",", '.', DateStyle::Mdy),
+        ("en", _) => locale(",", '.', DateStyle::Dmy("/")),
+        ("zh" | "ja" | "ko" | "th" | "he", _) => locale(",", '.', DateStyle::Iso),
+        ("de" | "nl" | "da" | "id" | "tr" | "el" | "ro", _) => {
+            locale(".", ',', DateStyle::Dmy("."))
+        }
+        ("es" | "it" | "pt", _) => locale(".", ',', DateStyle::Dmy("/")),
+        ("fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk" | "hu" | "bg", _) => {
+            locale(" ", ',', DateStyle::Dmy("."))
+        }
+        _ => DEFAULT_LOCALE,
+    }
+}
+
+fn locale() -> &'static NumberLocale {
+    LOCALE.get().unwrap_or(&DEFAULT_LOCALE)
+}
+
+/// 整數加上千分位（分隔符號依 locale）
+pub fn format_int(n: u64) -> String {
+    group_digits(&n.to_string(), locale().thousands)
+}
+
+fn group_digits(digits: &str, sep: &str) -> String {
+    let mut out_rev = String::new();
+    let sep_rev: String = sep.chars().rev().collect();
+
+    for (i, ch) in digits.chars().rev().enumerate() {
+        if i != 0 && i % 3 == 0 {
+            out_rev.push_str(&sep_rev);
+        }
+        out_rev.push(ch);
+    }
+
+    out_rev.chars().rev().collect()
+}
+
+/// 依 locale 輸出的小數；用法與 f64 相同，例如 `{:>15.2}`、`{:+.2}`
+#[derive(Clone, Copy)]
+pub struct Decimal(pub f64);
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let loc = locale();
+        let prec = f.precision().unwrap_or(2);
+        let raw = format!("{:.*}", prec, self.0.abs());
+        let (int, frac) = raw.split_once('.').unwrap_or((&raw, ""));
+
+        let mut s = String::new();
+        if self.0.is_sign_negative() && self.0 != 0.0 {
+            s.push('-');
+        } else if f.sign_plus() {
+            s.push('+');
+        }
+        if loc.group_decimals {
+            s.push_str(&group_digits(int, loc.thousands));
+        } else {
+            s.push_str(int);
+        }
+        if !frac.is_empty() {
+            s.push(loc.decimal);
+            s.push_str(frac);
+        }
+
+        // 自己處理寬度與對齊（Formatter::pad 會把 precision 當成截斷長度）
+        let len = s.chars().count();
+        let width = f.width().unwrap_or(0);
+        if len >= width {
+            return f.write_str(&s);
+        }
+        let fill = width - len;
+        let (left, right) = match f.align() {
+            Some(fmt::Alignment::Left) => (0, fill),
+            Some(fmt::Alignment::Center) => (fill / 2, fill - fill / 2),
+            _ => (fill, 0),
+        };
+        for _ in 0..left {
+            f.write_str(" ")?;
+        }
+        f.write_str(&s)?;
+        for _ in 0..right {
+            f.write_str(" ")?;
+        }
+        Ok(())
+    }
+}
+
+/// 依百分比畫出水平長條，不足一格的部分用 1/8 格字元補上
+pub fn ascii_bar(pct: f64, width: usize) -> String {
+    const PARTIAL: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+    let eighths = ((pct.clamp(0.0, 100.0) / 100.0) * (width * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    let rest = eighths % 8;
+    if rest > 0 {
+        bar.push(PARTIAL[rest - 1]);
+    }
+    bar
+}
+
+/// 長 key 截斷
+pub fn truncate_key(key: &str, max_chars: usize) -> String {
+    if key.chars().count() <= max_chars {
+        key.to_string()
+    } else {
+        let mut s: String = key.chars().take(max_chars - 3).collect();
+        s.push_str("...");
+        s
+    }
+}
+
+/// 秒數轉成精簡的人類可讀時間（45s、12m、3h、20d）
+pub fn format_secs(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86_400 => format!("{:.1}h", secs as f64 / 3600.0),
+        _ => format!("{:.1}d", secs as f64 / 86_400.0),
+    }
+}
+
+/// PTTL 顯示：-1 = 永久，其他負值 = 已不存在
+pub fn format_ttl(ttl_ms: Option<i64>) -> String {
+    match ttl_ms {
+        None => "-".into(),
+        Some(-1) => "永久".into(),
+        Some(t) if t < 0 => "已刪除".into(),
+        Some(t) => format_secs((t / 1000) as u64),
+    }
+}
+
+/// Unix 秒數轉成 `YYYY-MM-DD HH:MM:SS UTC`（日期順序依 locale；不引入時間套件，civil-from-days 演算法）
+pub fn format_unix_ts(secs: u64) -> String {
+    let offset = TIMEZONE.get().copied().unwrap_or(0);
+    let (year, month, day, h, m, s) = civil_from_unix(secs.saturating_add_signed(offset));
+    let date = match locale().date {
+        DateStyle::Iso => format!("{:04}-{:02}-{:02}", year, month, day),
+        DateStyle::Dmy(sep) => format!("{:02}{sep}{:02}{sep}{:04}", day, month, year),
+        DateStyle::Mdy => format!("{:02}/{:02}/{:04}", month, day, year),
+    };
+    format!("{} {:02}:{:02}:{:02} {}", date, h, m, s, zone_label(offset))
+}
+
+/// 設定報表顯示時間用的時區（UTC 偏移秒數），只有第一次呼叫有效；機器可讀的輸出一律用 UTC
+pub fn init_timezone(offset_secs: i64) {
+    let _ = TIMEZONE.set(offset_secs);
+}
+
+fn zone_label(offset: i64) -> String {
+    if offset == 0 {
+        return "UTC".into();
+    }
+    let sign = if offset < 0 { '-' } else { '+' };
+    let a = offset.unsigned_abs();
+    format!("UTC{}{:02}:{:02}", sign, a / 3600, a % 3600 / 60)
+}
+
+/// Unix 秒數轉成 ISO-8601 UTC，例如 `2026-10-14T03:04:05Z`（不受 --locale / --timezone 影響）
+pub fn format_iso8601(secs: u64) -> String {
+    let (year, month, day, h, m, s) = civil_from_unix(secs);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, h, m, s
+    )
+}
+
+/// Unix 秒數轉成 `YYYYMMDDTHHMMSSZ`，適合放進檔名 / object key
+pub fn format_unix_ts_compact(secs: u64) -> String {
+    let (year, month, day, h, m, s) = civil_from_unix(secs);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year, month, day, h, m, s
+    )
+}
+
+/// (年, 月, 日, 時, 分, 秒)，UTC
+fn civil_from_unix(secs: u64) -> (i64, i64, i64, u64, u64, u64) {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// 目前時間（Unix 秒）
+pub fn now_unix() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 解析 "512MB"、"4GB"、"100k"，單位以 1024 為底，純數字視為 bytes
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: f64 = num.parse().map_err(|_| format!("無效的大小: {}", s))?;
+
+    let mult: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        "T" | "TB" => 1024 * 1024 * 1024 * 1024,
+        _ => return Err(format!("無效的大小單位: {}（支援 B/KB/MB/GB/TB）", s)),
+    };
+    Ok((n * mult as f64) as u64)
+}
+
+/// 解析 "500ms"、"30s"、"10m"、"1h"、"1d"，純數字視為秒
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num.parse().map_err(|_| format!("無效的時間: {}", s))?;
+
+    let d = match unit {
+        "ms" => Duration::from_millis(n),
+        "" | "s" => Duration::from_secs(n),
+        "m" => Duration::from_secs(n * 60),
+        "h" => Duration::from_secs(n * 3600),
+        "d" => Duration::from_secs(n * 86_400),
+        _ => return Err(format!("無效的時間單位: {}（支援 ms/s/m/h/d）", s)),
+    };
+    Ok(d)
+}
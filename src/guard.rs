@@ -0,0 +1,273 @@
+use crate::conn::RedisConn;
+use crate::format::now_unix;
+use crate::output::warning;
+use crate::progress::ProgressBar;
+use crate::server::fetch_info;
+use crate::status::StatusBoard;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const GUARD_EVERY: Duration = Duration::from_secs(1); // 掃描中檢查 INFO 的間隔
+const GUARD_RECHECK: Duration = Duration::from_secs(2); // 暫停中重新檢查的間隔
+const RATE_BURST: Duration = Duration::from_secs(1); // 落後時最多補回幾秒的額度，避免暫停後突然衝高
+
+/// 伺服器負載門檻，未指定的項目不檢查
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GuardConfig {
+    pub max_ops: Option<u64>, // instantaneous_ops_per_sec（含本工具自己送出的指令）
+    pub max_cpu: Option<f64>, // used_cpu_sys + used_cpu_user 的增量，100 = 一顆核心滿載
+    pub max_clients: Option<u64>, // connected_clients
+    pub max_blocked: Option<u64>, // blocked_clients
+    pub max_repl_lag: Option<u64>, // master_repl_offset 與最慢 replica 的差距（bytes），超過就暫停
+    pub abort_repl_lag: Option<u64>, // 差距超過此值直接中止掃描
+}
+
+impl GuardConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.max_ops.is_some()
+            || self.max_cpu.is_some()
+            || self.max_clients.is_some()
+            || self.max_blocked.is_some()
+            || self.max_repl_lag.is_some()
+            || self.abort_repl_lag.is_some()
+    }
+}
+
+/// 一次因負載過高而暫停的紀錄
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GuardPause {
+    pub started_at: u64, // Unix 秒
+    pub duration_ms: u64,
+    pub reason: String,
+}
+
+/// --max-keys-per-sec 的結果
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyRate {
+    pub limit: u64,        // 每秒最多檢查的 key 數
+    pub keys: u64,         // 實際檢查的 key 數（含錯誤與已消失的 key）
+    pub elapsed_ms: u64,   // 掃描耗時（含限速等待）
+    pub throttled_ms: u64, // 因限速而等待的時間
+}
+
+impl KeyRate {
+    pub fn achieved(&self) -> f64 {
+        self.keys as f64 / (self.elapsed_ms.max(1) as f64 / 1000.0)
+    }
+}
+
+/// 依檢查的 key 數限速，與 batch 大小、每批暫停無關
+///
+/// 每處理完一批就把「下一個 key 最早可以送出的時間」往後推 n / limit 秒，還沒到就睡。
+pub struct RateLimiter {
+    limit: u64,
+    started: Instant,
+    next: Instant,
+    keys: u64,
+    throttled: Duration,
+}
+
+impl RateLimiter {
+    /// limit = None 或 0 時不限速
+    pub fn new(limit: Option<u64>) -> Option<Self> {
+        let limit = limit.filter(|&l| l > 0)?;
+        let now = Instant::now();
+        Some(Self {
+            limit,
+            started: now,
+            next: now,
+            keys: 0,
+            throttled: Duration::ZERO,
+        })
+    }
+
+    /// 每秒額度的十分之一：pipeline 不會一次送出好幾秒份的 key
+    pub fn batch_size(&self, batch_size: usize) -> usize {
+        batch_size.min((self.limit / 10).max(1) as usize)
+    }
+
+    pub fn consume(&mut self, n: u64) {
+        self.keys += n;
+        let now = Instant::now();
+        let floor = now.checked_sub(RATE_BURST).unwrap_or(now);
+        self.next = self.next.max(floor) + Duration::from_secs_f64(n as f64 / self.limit as f64);
+        if self.next > now {
+            let wait = self.next - now;
+            std::thread::sleep(wait);
+            self.throttled += wait;
+        }
+    }
+
+    pub fn finish(self) -> KeyRate {
+        KeyRate {
+            limit: self.limit,
+            keys: self.keys,
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            throttled_ms: self.throttled.as_millis() as u64,
+        }
+    }
+}
+
+/// 掃描中定期讀 INFO，超過門檻就暫停，直到伺服器恢復才繼續
+pub struct LoadGuard {
+    cfg: GuardConfig,
+    enabled: bool,
+    last_check: Instant,
+    last_cpu: Option<(f64, Instant)>, // 上次的 CPU 累計秒數，用來算使用率
+    pauses: Vec<GuardPause>,
+    max_lag_seen: Option<u64>,
+    status: Option<Arc<StatusBoard>>, // 暫停與恢復同步回報給 watch --listen
+}
+
+impl LoadGuard {
+    pub fn new(cfg: GuardConfig) -> Self {
+        Self {
+            cfg,
+            enabled: cfg.is_enabled(),
+            last_check: Instant::now(),
+            last_cpu: None,
+            pauses: Vec::new(),
+            max_lag_seen: None,
+            status: None,
+        }
+    }
+
+    pub fn with_status(mut self, status: Option<Arc<StatusBoard>>) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// 距離上次檢查超過 GUARD_EVERY 才讀 INFO；超標時在這裡阻塞到恢復為止
+    ///
+    /// 複寫延遲超過 abort_repl_lag 時回傳錯誤，由呼叫端中止掃描。
+    pub fn check(&mut self, con: &mut RedisConn, pb: &ProgressBar) -> redis::RedisResult<()> {
+        if !self.enabled || self.last_check.elapsed() < GUARD_EVERY {
+            return Ok(());
+        }
+        let Some(reason) = self.over_threshold(con)? else {
+            return Ok(());
+        };
+
+        let started_at = now_unix();
+        let started = Instant::now();
+        let mut current = reason.clone();
+        loop {
+            pb.set_message(format!("⏸ 負載過高暫停中: {}", current));
+            if let Some(status) = &self.status {
+                status.paused(Some(current.clone()));
+            }
+            std::thread::sleep(GUARD_RECHECK);
+            match self.over_threshold(con)? {
+                Some(r) => current = r,
+                None => break,
+            }
+        }
+        pb.set_message("");
+        if let Some(status) = &self.status {
+            status.paused(None);
+        }
+
+        self.pauses.push(GuardPause {
+            started_at,
+            duration_ms: started.elapsed().as_millis() as u64,
+            reason,
+        });
+        Ok(())
+    }
+
+    /// (暫停紀錄, 觀察到的最大複寫延遲 bytes)
+    pub fn finish(self) -> (Vec<GuardPause>, Option<u64>) {
+        (self.pauses, self.max_lag_seen)
+    }
+
+    /// 回傳超標的項目說明；INFO 讀不到時停用保護（只警告，不中斷掃描）
+    fn over_threshold(&mut self, con: &mut RedisConn) -> redis::RedisResult<Option<String>> {
+        self.last_check = Instant::now();
+
+        let info = match fetch_info(con, "default") {
+            Ok(info) => info,
+            Err(e) => {
+                warning!("⚠ 無法讀取 INFO，停用負載保護: {}", e);
+                self.enabled = false;
+                return Ok(None);
+            }
+        };
+        let num = |k: &str| info.get(k).and_then(|v| v.parse::<f64>().ok());
+
+        let mut reasons = Vec::new();
+
+        if let (Some(max), Some(v)) = (self.cfg.max_ops, num("instantaneous_ops_per_sec"))
+            && v > max as f64
+        {
+            reasons.push(format!("ops/s {} > {}", v, max));
+        }
+
+        if let (Some(sys), Some(user)) = (num("used_cpu_sys"), num("used_cpu_user")) {
+            let now = Instant::now();
+            let prev = self.last_cpu.replace((sys + user, now));
+            if let (Some(max), Some((prev_cpu, prev_at))) = (self.cfg.max_cpu, prev) {
+                let wall = now.duration_since(prev_at).as_secs_f64().max(f64::EPSILON);
+                let pct = (sys + user - prev_cpu) / wall * 100.0;
+                if pct > max {
+                    reasons.push(format!("CPU {:.0}% > {:.0}%", pct, max));
+                }
+            }
+        }
+
+        if let (Some(max), Some(v)) = (self.cfg.max_clients, num("connected_clients"))
+            && v > max as f64
+        {
+            reasons.push(format!("clients {} > {}", v, max));
+        }
+
+        if let (Some(max), Some(v)) = (self.cfg.max_blocked, num("blocked_clients"))
+            && v > max as f64
+        {
+            reasons.push(format!("blocked {} > {}", v, max));
+        }
+
+        if self.cfg.max_repl_lag.is_some() || self.cfg.abort_repl_lag.is_some() {
+            if let Some(lag) = replica_lag_bytes(&info) {
+                self.max_lag_seen = Some(self.max_lag_seen.unwrap_or(0).max(lag));
+                if let Some(max) = self.cfg.abort_repl_lag
+                    && lag > max
+                {
+                    return Err(redis::RedisError::from((
+                        redis::ErrorKind::ClientError,
+                        "複寫延遲超過上限，中止掃描",
+                        format!("{} bytes > {} bytes", lag, max),
+                    )));
+                }
+                if let Some(max) = self.cfg.max_repl_lag
+                    && lag > max
+                {
+                    reasons.push(format!("repl lag {} bytes > {}", lag, max));
+                }
+            }
+        }
+
+        Ok((!reasons.is_empty()).then(|| reasons.join(", ")))
+    }
+}
+
+/// master_repl_offset 與最慢 replica offset 的差距；沒有 replica（或不是 master）時為 None
+///
+/// INFO replication 中每個 replica 一行，例如
+/// `slave0:ip=10.0.0.2,port=6379,state=online,offset=12345,lag=0`
+fn replica_lag_bytes(info: &BTreeMap<String, String>) -> Option<u64> {
+    let master: u64 = info.get("master_repl_offset")?.parse().ok()?;
+    info.iter()
+        .filter(|(k, _)| {
+            k.strip_prefix("slave")
+                .is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .filter_map(|(_, v)| {
+            v.split(',')
+                .find_map(|kv| kv.strip_prefix("offset="))
+                .and_then(|o| o.parse::<u64>().ok())
+        })
+        .map(|offset| master.saturating_sub(offset))
+        .max()
+}
@@ -0,0 +1,31 @@
+//! 固定的 key hash：抽樣與類型快取檔都依賴它，不能隨 Rust 版本或執行檔改變
+
+/// 固定的 64-bit hash（FNV-1a 再經 murmur3 的 fmix64），不隨 Rust 版本或執行檔改變
+///
+/// FNV-1a 對只差結尾數字的 key（user:1、user:2…）高位元分布不均，低抽樣率時會少抽；
+/// 加上 fmix64 讓每個位元都受整個 key 影響。
+pub fn key_hash(key: &str) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in key.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_hash_is_pinned() {
+        // 改變這些值會讓不同版本的抽樣掃描無法互相比較，--type-cache 檔也會全部失效
+        assert_eq!(key_hash("user:1"), 5540904067209686849);
+        assert_eq!(key_hash("session:42"), 12597963026816124749);
+        assert_eq!(key_hash("cache:abc"), 2538686944921982689);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::format::format_unix_ts;
+use crate::report::Report;
+use serde_json::{Value, json};
+use std::error::Error;
+use std::path::Path;
+
+const HTML_PREFIXES: usize = 300; // treemap 最多放幾個前綴，其餘併成「其他」
+
+/// 單一檔案的 HTML 報表：資料以 JSON 內嵌在頁面中，圖表由頁面內的 JS 以 SVG 繪製
+///
+/// 不引用任何外部 CSS / JS / 字型，離線或內網也能直接用瀏覽器開啟。
+pub fn write_html(report: &Report, path: &Path) -> Result<(), Box<dyn Error>> {
+    // 內嵌在 <script> 裡：key 名稱可能含有 `</script>`，把 < 改成 JSON 跳脫的 \u003c
+    let data = serde_json::to_string(&chart_data(report))?.replace('<', "\\u003c");
+    let title = escape(&format!("Redis Top Keys — {}", report.target));
+    let page = PAGE.replace("__TITLE__", &title).replace("__DATA__", &data);
+    std::fs::write(path, page).map_err(|e| format!("無法寫入 {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// 圖表需要的資料（只取 Top N、前綴與到期時間軸，不含伺服器資訊）
+fn chart_data(report: &Report) -> Value {
+    let types: Vec<Value> = report
+        .types
+        .iter()
+        .filter(|t| t.count > 0)
+        .map(|t| {
+            json!({
+                "name": t.type_code.title(),
+                "count": t.count,
+                "total_mem": t.total_mem,
+                "top": t.top.iter().map(|k| json!({ "key": k.key, "mem": k.mem })).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+
+    let mut prefixes: Vec<Value> = report
+        .prefixes
+        .iter()
+        .take(HTML_PREFIXES)
+        .map(|p| json!({ "prefix": p.prefix, "count": p.count, "mem": p.total_mem }))
+        .collect();
+    let rest = &report.prefixes[report.prefixes.len().min(HTML_PREFIXES)..];
+    if !rest.is_empty() {
+        prefixes.push(json!({
+            "prefix": format!("（其他 {} 個前綴）", rest.len()),
+            "count": rest.iter().map(|p| p.count).sum::<u64>(),
+            "mem": rest.iter().map(|p| p.total_mem).sum::<u64>(),
+        }));
+    }
+
+    let ttl = report.expiry.as_ref().map(|e| {
+        json!({
+            "hourly_mem": e.hourly_mem,
+            "hourly_count": e.hourly_count,
+            "later_mem": e.later_mem,
+            "persistent_mem": e.persistent_mem,
+            "persistent_count": e.persistent_count,
+        })
+    });
+
+    json!({
+        "target": report.target,
+        "started_at": format_unix_ts(report.started_at),
+        "notes": report.notes,
+        "scanned": report.scanned,
+        "total_mem": report.total_mem(),
+        "types": types,
+        "prefixes": prefixes,
+        "ttl": ttl,
+    })
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const PAGE: &str = r##"<!DOCTYPE html>
+<html lang="zh-Hant">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>__TITLE__</title>
+<style>
+body { font-family: -apple-system, "Segoe UI", "Noto Sans TC", sans-serif; margin: 24px; color: #222; background: #fafafa; }
+h1 { font-size: 20px; margin: 0 0 4px; }
+h2 { font-size: 16px; margin: 32px 0 8px; border-bottom: 1px solid #ddd; padding-bottom: 4px; }
+.meta { color: #666; font-size: 13px; }
+.tabs button { margin: 0 4px 8px 0; padding: 4px 10px; border: 1px solid #bbb; background: #fff; border-radius: 4px; cursor: pointer; }
+.tabs button.on { background: #2b6cb0; color: #fff; border-color: #2b6cb0; }
+svg { background: #fff; border: 1px solid #e2e2e2; border-radius: 4px; display: block; }
+svg text { font-size: 11px; }
+.bar { fill: #4a90d9; } .bar:hover { fill: #2b6cb0; }
+.cell { stroke: #fff; } .cell:hover { opacity: .8; }
+#tip { position: fixed; pointer-events: none; background: rgba(0,0,0,.8); color: #fff; font-size: 12px; padding: 4px 8px; border-radius: 4px; display: none; max-width: 520px; word-break: break-all; white-space: pre-line; }
+.empty { color: #888; font-size: 13px; }
+.notes { margin: 8px 0 0; padding-left: 20px; font-size: 13px; color: #444; }
+</style>
+</head>
+<body>
+<h1 id="title"></h1>
+<div class="meta" id="meta"></div>
+<ul class="notes" id="notes"></ul>
+
+<h2>各類型 Top N 記憶體</h2>
+<div class="tabs" id="type-tabs"></div>
+<svg id="top-chart" width="1000" height="320"></svg>
+
+<h2>前綴記憶體分布（treemap）</h2>
+<svg id="treemap" width="1000" height="480"></svg>
+
+<h2>TTL 到期時間軸</h2>
+<div class="tabs" id="ttl-tabs"></div>
+<svg id="ttl-chart" width="1000" height="280"></svg>
+
+<div id="tip"></div>
+<script type="application/json" id="data">__DATA__</script>
+<script>
+(function () {
+  var data = JSON.parse(document.getElementById("data").textContent);
+  var NS = "http://www.w3.org/2000/svg";
+  var tip = document.getElementById("tip");
+  var COLORS = ["#4a90d9", "#e07b39", "#5bb381", "#c55a9a", "#d9b84a", "#7a6fd1", "#4ab3c4", "#b5654a"];
+
+  function mb(b) { return (b / 1024 / 1024).toFixed(2) + " MB"; }
+  function commas(n) { return String(n).replace(/\B(?=(\d{3})+(?!\d))/g, ","); }
+  function el(name, attrs, parent) {
+    var e = document.createElementNS(NS, name);
+    for (var k in attrs) e.setAttribute(k, attrs[k]);
+    if (parent) parent.appendChild(e);
+    return e;
+  }
+  function clear(svg) { while (svg.firstChild) svg.removeChild(svg.firstChild); }
+  function hover(e, text) {
+    e.addEventListener("mousemove", function (ev) {
+      tip.textContent = text;
+      tip.style.display = "block";
+      tip.style.left = (ev.clientX + 12) + "px";
+      tip.style.top = (ev.clientY + 12) + "px";
+    });
+    e.addEventListener("mouseleave", function () { tip.style.display = "none"; });
+  }
+  function empty(svg, msg) {
+    clear(svg);
+    svg.setAttribute("height", 40);
+    var t = el("text", { x: 12, y: 24, "class": "empty" }, svg);
+    t.textContent = msg;
+  }
+  function tabs(id, names, onSelect) {
+    var box = document.getElementById(id);
+    var buttons = names.map(function (name, i) {
+      var b = document.createElement("button");
+      b.textContent = name;
+      b.onclick = function () {
+        buttons.forEach(function (x) { x.className = ""; });
+        b.className = "on";
+        onSelect(i);
+      };
+      box.appendChild(b);
+      return b;
+    });
+    if (buttons.length) buttons[0].onclick();
+  }
+
+  document.getElementById("title").textContent = "Redis Top Keys — " + data.target;
+  document.getElementById("meta").textContent =
+    data.started_at + "，共 " + commas(data.scanned) + " keys，" + mb(data.total_mem);
+  data.notes.forEach(function (n) {
+    var li = document.createElement("li");
+    li.textContent = "📝 " + n;
+    document.getElementById("notes").appendChild(li);
+  });
+
+  // 各類型 Top N：水平長條圖
+  var top = document.getElementById("top-chart");
+  if (!data.types.length) {
+    empty(top, "沒有資料");
+  } else {
+    tabs("type-tabs", data.types.map(function (t) { return t.name; }), function (i) {
+      var t = data.types[i];
+      clear(top);
+      var rowH = 26, left = 320, width = 1000 - left - 120;
+      top.setAttribute("height", Math.max(t.top.length, 1) * rowH + 20);
+      var max = Math.max.apply(null, t.top.map(function (k) { return k.mem; }).concat([1]));
+      t.top.forEach(function (k, r) {
+        var y = 10 + r * rowH;
+        var label = el("text", { x: left - 8, y: y + 16, "text-anchor": "end" }, top);
+        label.textContent = k.key.length > 48 ? k.key.slice(0, 47) + "…" : k.key;
+        var w = Math.max(1, k.mem / max * width);
+        var bar = el("rect", { x: left, y: y + 3, width: w, height: rowH - 8, "class": "bar" }, top);
+        hover(bar, k.key + "\n" + mb(k.mem) + "（佔 " + t.name + " 的 " +
+          (k.mem / Math.max(t.total_mem, 1) * 100).toFixed(2) + "%）");
+        var v = el("text", { x: left + w + 6, y: y + 16 }, top);
+        v.textContent = mb(k.mem);
+      });
+    });
+  }
+
+  // 前綴 treemap（squarified）
+  var tm = document.getElementById("treemap");
+  var items = data.prefixes.filter(function (p) { return p.mem > 0; });
+  if (!items.length) {
+    empty(tm, "沒有前綴統計（--prefix-depth 0）");
+  } else {
+    var total = items.reduce(function (s, p) { return s + p.mem; }, 0);
+    var W = 1000, H = 480;
+    var scale = W * H / total;
+    var nodes = items.map(function (p) { return { p: p, area: p.mem * scale }; });
+    var rects = [];
+    (function squarify(list, x, y, w, h) {
+      while (list.length) {
+        var side = Math.min(w, h), row = [], sum = 0, worst = Infinity;
+        while (list.length) {
+          var next = list[0], s = sum + next.area;
+          var rmax = Math.max.apply(null, row.map(function (n) { return n.area; }).concat([next.area]));
+          var rmin = Math.min.apply(null, row.map(function (n) { return n.area; }).concat([next.area]));
+          var ratio = Math.max(side * side * rmax / (s * s), (s * s) / (side * side * rmin));
+          if (ratio > worst) break;
+          worst = ratio; sum = s; row.push(list.shift());
+        }
+        var thick = sum / side, off = 0;
+        row.forEach(function (n) {
+          var len = n.area / thick;
+          if (w >= h) rects.push({ n: n, x: x, y: y + off, w: thick, h: len });
+          else rects.push({ n: n, x: x + off, y: y, w: len, h: thick });
+          off += len;
+        });
+        if (w >= h) { x += thick; w -= thick; } else { y += thick; h -= thick; }
+      }
+    })(nodes.slice(), 0, 0, W, H);
+    rects.forEach(function (r, i) {
+      var p = r.n.p;
+      var cell = el("rect", { x: r.x, y: r.y, width: Math.max(r.w, 0), height: Math.max(r.h, 0),
+        fill: COLORS[i % COLORS.length], "class": "cell" }, tm);
+      hover(cell, p.prefix + "\n" + mb(p.mem) + "，" + commas(p.count) + " keys（" +
+        (p.mem / total * 100).toFixed(2) + "%）");
+      if (r.w > 60 && r.h > 18) {
+        var t = el("text", { x: r.x + 4, y: r.y + 14, fill: "#fff" }, tm);
+        var chars = Math.floor((r.w - 8) / 7);
+        t.textContent = p.prefix.length > chars ? p.prefix.slice(0, chars - 1) + "…" : p.prefix;
+        t.style.pointerEvents = "none";
+      }
+    });
+  }
+
+  // TTL 到期時間軸：每小時一根，最後是超出範圍與沒有 TTL
+  var ttl = document.getElementById("ttl-chart");
+  if (!data.ttl) {
+    empty(ttl, "本次掃描未收集 TTL");
+  } else {
+    tabs("ttl-tabs", ["記憶體", "key 數"], function (mode) {
+      clear(ttl);
+      var hours = mode === 0 ? data.ttl.hourly_mem : data.ttl.hourly_count;
+      var bars = hours.map(function (v, h) { return { label: "+" + (h + 1) + "h", v: v, hour: h }; });
+      if (mode === 0) {
+        bars.push({ label: "更晚", v: data.ttl.later_mem });
+        bars.push({ label: "永久", v: data.ttl.persistent_mem });
+      } else {
+        bars.push({ label: "永久", v: data.ttl.persistent_count });
+      }
+      var left = 70, bottom = 240, height = 220, bw = (1000 - left - 10) / bars.length;
+      var max = Math.max.apply(null, bars.map(function (b) { return b.v; }).concat([1]));
+      var axis = el("text", { x: left - 6, y: bottom - height + 10, "text-anchor": "end" }, ttl);
+      axis.textContent = mode === 0 ? mb(max) : commas(max);
+      bars.forEach(function (b, i) {
+        var h = b.v / max * height;
+        var r = el("rect", { x: left + i * bw + 1, y: bottom - h, width: Math.max(bw - 2, 1), height: h,
+          "class": "bar" }, ttl);
+        if (b.hour === undefined) r.setAttribute("style", "fill:#999");
+        hover(r, b.label + "：" + (mode === 0 ? mb(b.v) : commas(b.v) + " keys"));
+        if (i % 6 === 5 || b.hour === undefined) {
+          var t = el("text", { x: left + i * bw + bw / 2, y: bottom + 14, "text-anchor": "middle" }, ttl);
+          t.textContent = b.label;
+        }
+      });
+    });
+  }
+})();
+</script>
+</body>
+</html>
+"##;
@@ -0,0 +1,152 @@
+//! 掃描前後的延遲探測（--impact-probe），量化掃描對線上流量的影響
+//!
+//! 掃描前、後各用一段時間窗連續 PING，並以 INFO commandstats 的差值算出其他 client
+//! 的指令量與伺服器端平均耗時；掃描期間連線忙著跑 pipeline，只取 commandstats。
+
+use crate::conn::RedisConn;
+use crate::server::fetch_info;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+pub const PING_EVERY: Duration = Duration::from_millis(50);
+
+/// 本工具會送出的指令；commandstats 差值扣掉這些，只留其他 client 的流量
+/// （STRLEN / LLEN 等也可能來自其他 client，這裡寧可少算）
+const OWN_COMMANDS: &[&str] = &[
+    "ping",
+    "info",
+    "scan",
+    "type",
+    "memory",
+    "pttl",
+    "object",
+    "dbsize",
+    "strlen",
+    "llen",
+    "hlen",
+    "scard",
+    "zcard",
+    "xlen",
+    "getrange",
+    "lrange",
+    "srandmember",
+    "zrandmember",
+    "hrandfield",
+    "xrange",
+    "config",
+    "client",
+    "function",
+    "select",
+    "auth",
+    "hello",
+    "readonly",
+    "asking",
+    "cluster",
+];
+
+/// 單一時間窗的量測
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProbeWindow {
+    pub window_ms: u64,
+    pub pings: u64, // 掃描期間不 PING，為 0
+    pub ping_p50_us: u64,
+    pub ping_p99_us: u64,
+    pub ping_max_us: u64,
+    pub calls: u64, // 其他 client 的指令數（commandstats 差值）
+    pub usec: u64,  // 這些指令在伺服器端的合計耗時
+}
+
+impl ProbeWindow {
+    pub fn ops_per_sec(&self) -> f64 {
+        self.calls as f64 * 1000.0 / self.window_ms.max(1) as f64
+    }
+
+    pub fn usec_per_call(&self) -> Option<f64> {
+        (self.calls > 0).then(|| self.usec as f64 / self.calls as f64)
+    }
+}
+
+/// 單一節點的掃描前 / 中 / 後
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScanImpact {
+    pub target: String,
+    pub before: ProbeWindow,
+    pub during: ProbeWindow,
+    pub after: ProbeWindow,
+}
+
+/// INFO commandstats 的 (calls, usec) 合計，不含 OWN_COMMANDS
+#[derive(Clone, Copy)]
+pub struct CommandTotals {
+    calls: u64,
+    usec: u64,
+}
+
+impl CommandTotals {
+    /// `cmdstat_get:calls=10,usec=20,usec_per_call=2.00,...`
+    pub fn fetch(con: &mut RedisConn) -> redis::RedisResult<Self> {
+        let stats = fetch_info(con, "commandstats")?;
+        let mut totals = Self { calls: 0, usec: 0 };
+        for (name, fields) in &stats {
+            let Some(cmd) = name.strip_prefix("cmdstat_") else {
+                continue;
+            };
+            // 子指令為 cmdstat_config|get
+            let base = cmd.split('|').next().unwrap_or(cmd);
+            if OWN_COMMANDS.contains(&base) {
+                continue;
+            }
+            for field in fields.split(',') {
+                match field.split_once('=') {
+                    Some(("calls", v)) => totals.calls += v.parse::<u64>().unwrap_or(0),
+                    Some(("usec", v)) => totals.usec += v.parse::<u64>().unwrap_or(0),
+                    _ => {}
+                }
+            }
+        }
+        Ok(totals)
+    }
+
+    /// CONFIG RESETSTAT 會讓計數歸零，差值用 saturating_sub 避免溢位
+    pub fn window(&self, end: &Self, window_ms: u64) -> ProbeWindow {
+        ProbeWindow {
+            window_ms,
+            pings: 0,
+            ping_p50_us: 0,
+            ping_p99_us: 0,
+            ping_max_us: 0,
+            calls: end.calls.saturating_sub(self.calls),
+            usec: end.usec.saturating_sub(self.usec),
+        }
+    }
+}
+
+/// 在 window 內每 PING_EVERY 送一次 PING
+pub fn probe(con: &mut RedisConn, window: Duration) -> redis::RedisResult<ProbeWindow> {
+    let start = CommandTotals::fetch(con)?;
+    let began = Instant::now();
+    let mut rtts: Vec<u64> = Vec::new();
+    while began.elapsed() < window {
+        let sent = Instant::now();
+        redis::cmd("PING").query::<String>(con)?;
+        let rtt = sent.elapsed();
+        rtts.push(rtt.as_micros() as u64);
+        std::thread::sleep(PING_EVERY.saturating_sub(rtt));
+    }
+    let window_ms = began.elapsed().as_millis() as u64;
+    let end = CommandTotals::fetch(con)?;
+
+    rtts.sort_unstable();
+    let pct = |p: f64| {
+        rtts.get(((rtts.len() as f64 * p).ceil() as usize).saturating_sub(1))
+            .copied()
+            .unwrap_or(0)
+    };
+    Ok(ProbeWindow {
+        pings: rtts.len() as u64,
+        ping_p50_us: pct(0.50),
+        ping_p99_us: pct(0.99),
+        ping_max_us: rtts.last().copied().unwrap_or(0),
+        ..start.window(&end, window_ms)
+    })
+}
@@ -0,0 +1,210 @@
+//! --index-to：掃描中把每個 key 的大小寫進另一台分析用 Redis，之後直接用
+//! ZREVRANGEBYSCORE / HGETALL 查詢，不必另存檔案
+//!
+//! 每次掃描以 `{prefix}:run:{開始時間}:{目標}` 為命名空間：
+//! - `{ns}:sizes`：ZSET，member 為 key、score 為記憶體 bytes
+//! - `{ns}:type:{類型}`：同上，只含單一類型
+//! - `{ns}:k:{key}`：HASH，type / mem 與有收集的 ttl_ms、idle_secs、encoding、freq、elements
+//! - `{ns}:meta`：HASH，掃描的目標、時間與合計
+//!
+//! `{prefix}:runs`（ZSET，score 為開始時間）列出所有掃描，`{prefix}:latest:{目標}` 指向最近一次。
+//! 寫入失敗只提醒並停止寫入，不影響掃描本身。
+
+use crate::conn::without_password;
+use crate::format::format_int;
+use crate::output::{say, warning};
+use crate::progress::ProgressBar;
+use crate::report::Report;
+use crate::stats::{KeyExtra, KeyTypeCode};
+use std::time::Duration;
+
+pub const DEFAULT_INDEX_PREFIX: &str = "redis-top-keys";
+
+/// 分析用 Redis 的位置與資料保留時間
+#[derive(Clone, Debug)]
+pub struct IndexTarget {
+    pub url: String,
+    pub prefix: String,
+    pub ttl: Option<Duration>, // 寫入的 key 都設定此 TTL；None = 永久保留
+}
+
+/// 單一節點掃描的寫入端；每批 key 累積成一個 pipeline，由 flush 送出
+pub struct IndexWriter {
+    con: redis::Connection,
+    url: String, // 顯示用，不含密碼
+    prefix: String,
+    ns: String,
+    ttl_secs: Option<u64>,
+    pipe: redis::Pipeline,
+    written: u64,
+    failed: Option<String>, // 第一次寫入失敗的原因；之後不再寫入
+}
+
+impl IndexWriter {
+    /// 連不上分析用 Redis 時直接回錯誤，在開始掃描前就讓使用者知道
+    pub fn open(t: &IndexTarget, target: &str, started_at: u64) -> redis::RedisResult<Self> {
+        let url = without_password(&t.url);
+        let con = redis::Client::open(t.url.as_str())
+            .and_then(|c| c.get_connection())
+            .map_err(|e| {
+                redis::RedisError::from((
+                    e.kind(),
+                    "無法連線 --index-to",
+                    format!("{}: {}", url, e),
+                ))
+            })?;
+        Ok(Self {
+            con,
+            url,
+            prefix: t.prefix.clone(),
+            ns: format!("{}:run:{}:{}", t.prefix, started_at, target),
+            ttl_secs: t.ttl.map(|d| d.as_secs().max(1)),
+            pipe: redis::pipe(),
+            written: 0,
+            failed: None,
+        })
+    }
+
+    pub fn add_key(&mut self, key: &str, t: KeyTypeCode, mem: u64, extra: &KeyExtra) {
+        if self.failed.is_some() {
+            return;
+        }
+        let hash = format!("{}:k:{}", self.ns, key);
+        let pipe = &mut self.pipe;
+        pipe.cmd("ZADD")
+            .arg(format!("{}:sizes", self.ns))
+            .arg(mem)
+            .arg(key)
+            .ignore();
+        pipe.cmd("ZADD")
+            .arg(format!("{}:type:{}", self.ns, t.name()))
+            .arg(mem)
+            .arg(key)
+            .ignore();
+
+        let mut fields: Vec<(&str, String)> =
+            vec![("type", t.name().to_string()), ("mem", mem.to_string())];
+        if let Some(v) = extra.ttl_ms {
+            fields.push(("ttl_ms", v.to_string()));
+        }
+        if let Some(v) = extra.idle_secs {
+            fields.push(("idle_secs", v.to_string()));
+        }
+        if let Some(v) = &extra.encoding {
+            fields.push(("encoding", v.clone()));
+        }
+        if let Some(v) = extra.freq {
+            fields.push(("freq", v.to_string()));
+        }
+        if let Some(v) = extra.elements {
+            fields.push(("elements", v.to_string()));
+        }
+        if extra.estimated {
+            fields.push(("estimated", "1".into()));
+        }
+        pipe.cmd("HSET").arg(&hash).arg(fields).ignore();
+        if let Some(ttl) = self.ttl_secs {
+            pipe.cmd("EXPIRE").arg(&hash).arg(ttl).ignore();
+        }
+        self.written += 1;
+    }
+
+    /// 送出累積的指令；失敗時提醒一次並停止之後的寫入
+    pub fn flush(&mut self, pb: &ProgressBar) {
+        if self.failed.is_some() || self.pipe.is_empty() {
+            return;
+        }
+        // 每批都重設彙總 key 的 TTL，掃描中斷時也不會留下永久的資料
+        if let Some(ttl) = self.ttl_secs {
+            for k in self.summary_keys() {
+                self.pipe.cmd("EXPIRE").arg(k).arg(ttl).ignore();
+            }
+        }
+        let pipe = std::mem::replace(&mut self.pipe, redis::pipe());
+        if let Err(e) = pipe.query::<()>(&mut self.con) {
+            pb.suspend(|| warning!("⚠ 寫入 --index-to {} 失敗，停止寫入: {}", self.url, e));
+            self.failed = Some(e.to_string());
+        }
+    }
+
+    /// 掃描結束：寫入 meta、更新 runs / latest，--deep 修正過的 Top key 以最終的記憶體覆蓋
+    ///
+    /// 回傳失敗原因（記在 report.unavailable）。
+    pub fn finish(mut self, report: &Report, pb: &ProgressBar) -> Option<String> {
+        for tr in &report.types {
+            for k in tr.top.iter().filter(|k| k.estimated_mem.is_some()) {
+                self.pipe
+                    .cmd("ZADD")
+                    .arg(format!("{}:sizes", self.ns))
+                    .arg(k.mem)
+                    .arg(&k.key)
+                    .ignore();
+                self.pipe
+                    .cmd("ZADD")
+                    .arg(format!("{}:type:{}", self.ns, tr.type_code.name()))
+                    .arg(k.mem)
+                    .arg(&k.key)
+                    .ignore();
+                self.pipe
+                    .cmd("HSET")
+                    .arg(format!("{}:k:{}", self.ns, k.key))
+                    .arg("mem")
+                    .arg(k.mem)
+                    .ignore();
+            }
+        }
+
+        let meta = format!("{}:meta", self.ns);
+        self.pipe
+            .cmd("HSET")
+            .arg(&meta)
+            .arg(&[
+                ("target", report.target.clone()),
+                ("started_at", report.started_at_utc.clone()),
+                ("finished_at", report.finished_at_utc.clone()),
+                ("scanned", report.scanned.to_string()),
+                ("errors", report.errors.to_string()),
+                ("total_mem", report.total_mem().to_string()),
+                ("indexed", self.written.to_string()),
+            ])
+            .ignore();
+        if let Some(ttl) = self.ttl_secs {
+            self.pipe.cmd("EXPIRE").arg(&meta).arg(ttl).ignore();
+        }
+        self.pipe
+            .cmd("ZADD")
+            .arg(format!("{}:runs", self.prefix))
+            .arg(report.started_at)
+            .arg(&self.ns)
+            .ignore();
+        self.pipe
+            .cmd("SET")
+            .arg(format!("{}:latest:{}", self.prefix, report.target))
+            .arg(&self.ns)
+            .ignore();
+        self.flush(pb);
+
+        if self.failed.is_none() {
+            pb.suspend(|| {
+                say!(
+                    "✔ 已寫入 {} 個 key 到 {}（ZREVRANGEBYSCORE {}:sizes +inf -inf LIMIT 0 10 查詢最大的 key）",
+                    format_int(self.written),
+                    self.url,
+                    self.ns
+                )
+            });
+        }
+        self.failed
+    }
+
+    /// 每次掃描都有的彙總 key（不含每個 key 的 HASH）
+    fn summary_keys(&self) -> Vec<String> {
+        let mut keys = vec![format!("{}:sizes", self.ns)];
+        keys.extend(
+            KeyTypeCode::all()
+                .iter()
+                .map(|t| format!("{}:type:{}", self.ns, t.name())),
+        );
+        keys
+    }
+}
@@ -0,0 +1,112 @@
+use crate::alloc::{embstr_alloc, size_class};
+use crate::report::IntStringReport;
+use crate::stats::{MAX_PREFIXES, NO_PREFIX, prefix_of};
+use std::collections::HashMap;
+
+pub const MAX_INT_LEN: usize = 20; // Redis 只會把 20 字元以內的整數字串轉成 int 編碼
+const SHARED_INTEGERS: i64 = 10_000; // 0..9999 可共用 shared integer 物件
+pub const ROBJ_BYTES: u64 = 16; // redisObject 本身
+
+/// 單一前綴中 string 值的整數使用情況
+#[derive(Default)]
+struct Counts {
+    strings: u64,
+    int_encoded: u64,
+    numeric: u64, // 標準整數字串卻不是 int 編碼（APPEND / SETRANGE 寫入等）
+    padded: u64,  // 補零、正負號或空白的數字字串，無法轉 int 編碼
+    savings: u64, // 改成 int 編碼可省下的 bytes（不含 shared integer）
+    shared: u64,  // 數字字串中值落在 0..9999、可共用 shared integer 的數量
+}
+
+/// 依前綴統計「存成字串的小整數」（計數器存成補零字串是常見的浪費）
+pub struct IntStringStats {
+    sep: char,
+    depth: usize,
+    map: HashMap<String, Counts>,
+}
+
+impl IntStringStats {
+    pub fn new(sep: char, depth: usize) -> Self {
+        Self {
+            sep,
+            depth,
+            map: HashMap::new(),
+        }
+    }
+
+    fn counts(&mut self, key: &str) -> Option<&mut Counts> {
+        let prefix = if self.depth == 0 {
+            NO_PREFIX
+        } else {
+            prefix_of(key, self.sep, self.depth).unwrap_or(NO_PREFIX)
+        };
+        if !self.map.contains_key(prefix) {
+            if self.map.len() >= MAX_PREFIXES {
+                return None;
+            }
+            self.map.insert(prefix.to_owned(), Counts::default());
+        }
+        self.map.get_mut(prefix)
+    }
+
+    /// 每個 string key 都要呼叫一次；int 編碼的 key 直接計入
+    pub fn add_string(&mut self, key: &str, int_encoded: bool) {
+        if let Some(c) = self.counts(key) {
+            c.strings += 1;
+            c.int_encoded += int_encoded as u64;
+        }
+    }
+
+    /// 短的 embstr / raw 值（GETRANGE 取回的完整內容），判斷是否為數字字串
+    pub fn add_value(&mut self, key: &str, value: &[u8]) {
+        let Ok(text) = std::str::from_utf8(value) else {
+            return;
+        };
+        let Ok(n) = text.trim().parse::<i64>() else {
+            return;
+        };
+        let Some(c) = self.counts(key) else {
+            return;
+        };
+
+        // 標準寫法（無補零、無 +、無空白）才能被 Redis 轉成 int 編碼
+        if n.to_string() == text {
+            c.numeric += 1;
+        } else {
+            c.padded += 1;
+        }
+        c.savings += embstr_alloc(value.len() as u64) - size_class(ROBJ_BYTES);
+        if (0..SHARED_INTEGERS).contains(&n) {
+            c.shared += 1;
+        }
+    }
+
+    /// 有數字字串的前綴，依可省記憶體 desc
+    pub fn into_reports(self) -> Vec<IntStringReport> {
+        let sep = self.sep;
+        let mut v: Vec<IntStringReport> = self
+            .map
+            .into_iter()
+            .filter(|(_, c)| c.numeric + c.padded > 0)
+            .map(|(p, c)| IntStringReport {
+                prefix: if p == NO_PREFIX {
+                    p
+                } else {
+                    format!("{}{}*", p, sep)
+                },
+                strings: c.strings,
+                int_encoded: c.int_encoded,
+                numeric: c.numeric,
+                padded: c.padded,
+                savings: c.savings,
+                shared: c.shared,
+            })
+            .collect();
+        v.sort_by(|a, b| {
+            b.savings
+                .cmp(&a.savings)
+                .then_with(|| a.prefix.cmp(&b.prefix))
+        });
+        v
+    }
+}
@@ -0,0 +1,78 @@
+//! --oneshot-json：給 Kubernetes Job / CronJob 用的單次執行模式
+//!
+//! - stdout 只有 JSON 報表（同 --format json），stderr 每行一個 JSON 事件，不顯示進度條
+//! - 收到 SIGTERM / SIGINT 時不再送出新的 SCAN，略過深度分析、值預覽與掃描後的延遲探測，
+//!   盡快輸出已掃描部分的報表（report.interrupted 記錄原因）並以 EXIT_INTERRUPTED 結束
+//! - 超過 --grace-period 仍沒輸出完就直接以 EXIT_GRACE_EXPIRED 結束，趕在 kubelet 的 SIGKILL 之前
+//!   留下明確的結束原因
+
+use crate::output;
+use serde_json::json;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::Duration;
+
+pub const EXIT_INTERRUPTED: i32 = 7; // 收到 SIGTERM / SIGINT，報表只含已掃描的部分
+pub const EXIT_SCAN_ERRORS: i32 = 8; // --oneshot-json：掃描完成但有 key 量測失敗
+pub const EXIT_GRACE_EXPIRED: i32 = 143; // grace period 內沒能輸出報表（同被 SIGTERM 結束的慣例 128 + 15）
+
+const WATCH_EVERY: Duration = Duration::from_millis(100); // 背景執行緒檢查是否收到信號的間隔
+
+static SIGNAL: AtomicI32 = AtomicI32::new(0); // 收到的信號編號；0 = 沒有
+
+/// 安裝 SIGTERM / SIGINT 的處理，並啟動 grace period 的計時執行緒
+pub fn install(grace: Duration) {
+    install_handlers();
+    std::thread::spawn(move || {
+        while SIGNAL.load(Ordering::Relaxed) == 0 {
+            std::thread::sleep(WATCH_EVERY);
+        }
+        output::event(
+            "signal",
+            json!({
+                "signal": stop_requested().unwrap_or_default(),
+                "grace_secs": grace.as_secs_f64(),
+            }),
+        );
+        std::thread::sleep(grace);
+        output::event(
+            "error",
+            json!({
+                "message": format!("{:?} 內未能輸出報表，直接結束", grace),
+                "exit_code": EXIT_GRACE_EXPIRED,
+            }),
+        );
+        std::process::exit(EXIT_GRACE_EXPIRED);
+    });
+}
+
+/// 已收到停止信號時回傳信號名稱；掃描迴圈每次 SCAN 前檢查
+pub fn stop_requested() -> Option<&'static str> {
+    match SIGNAL.load(Ordering::Relaxed) {
+        0 => None,
+        SIGTERM => Some("SIGTERM"),
+        _ => Some("SIGINT"),
+    }
+}
+
+#[cfg(unix)]
+const SIGTERM: i32 = libc::SIGTERM;
+#[cfg(not(unix))]
+const SIGTERM: i32 = 15;
+
+#[cfg(unix)]
+fn install_handlers() {
+    // 信號處理函式裡只能做 async-signal-safe 的事：記下信號編號，其餘交給掃描迴圈與計時執行緒
+    extern "C" fn on_signal(sig: libc::c_int) {
+        SIGNAL.store(sig, Ordering::Relaxed);
+    }
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: on_signal 只寫入一個 atomic，符合信號處理函式的限制
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+}
+
+/// 非 unix 平台沒有 SIGTERM，維持預設行為（Ctrl-C 直接結束）
+#[cfg(not(unix))]
+fn install_handlers() {}
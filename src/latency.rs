@@ -0,0 +1,258 @@
+//! MEMORY USAGE 的耗時：偵測拖慢伺服器的量測，並記錄 --estimate-above 改用估計值的大 key
+//!
+//! pipeline 中的指令由伺服器依序執行，無法個別計時；整批耗時超過 SLOW_MEASURE 時，
+//! 批次中最大的 key 最可能是原因（MEMORY USAGE 的成本隨抽樣數與元素大小增加）。
+//!
+//! 另外記錄 SCAN 與量測 pipeline 的往返時間分布（CommandRtt）：量測批次突然變慢而前一個 SCAN
+//! 正常，多半是批次中的某個 key；兩者一起變慢則比較像網路或伺服器整體的延遲。
+
+use crate::format::{Decimal, format_int};
+use crate::stats::KeyTypeCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const SLOW_MEASURE: Duration = Duration::from_millis(250); // 單次量測（一批 pipeline 或第二階段一個 key）超過此值視為過慢
+pub const ESTIMATE_SAMPLES: u64 = 5; // 大 key 改用的 SAMPLES（與伺服器預設相同，成本與元素數無關）
+const SLOW_KEEP: usize = 10; // 報表保留最慢的幾筆
+const SLOW_WARN_LINES: u64 = 5; // 掃描中最多印出幾行過慢提醒
+pub const SPIKE_FACTOR: u64 = 5; // 往返時間超過目前 p50 的幾倍視為尖峰
+const SPIKE_MIN: Duration = Duration::from_millis(20); // 低於此值不算尖峰（本機連線的 p50 很小，十倍仍然很快）
+const SPIKE_MIN_SAMPLES: u64 = 20; // 至少有這麼多筆才判斷尖峰，p50 才有意義
+const SPIKE_KEEP: usize = 10; // 報表保留最慢的幾個尖峰
+const RTT_SUB_BUCKETS: u32 = 4; // 每個 2 的次方再細分幾桶（誤差約 25%）
+
+/// 一次過慢的量測
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SlowMeasure {
+    pub target: String,
+    pub deep: bool, // 第二階段（SAMPLES 0）
+    pub ms: u64,
+    pub keys: u64, // 這次量測包含的 key 數
+    #[serde(rename = "type")]
+    pub type_code: KeyTypeCode,
+    pub key: String, // 其中最大的 key
+    pub mem: u64,
+    pub elements: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MeasureLatency {
+    pub slow_ms: u64,                // 判定為過慢的門檻
+    pub estimate_above: Option<u64>, // --estimate-above；None = 全部精確量測
+    pub slow_count: u64,
+    pub slow: Vec<SlowMeasure>, // 依耗時 desc，最多 SLOW_KEEP 筆
+    pub estimated: u64,         // 改用估計值的 key 數
+    pub estimated_mem: u64,     // 這些 key 的估計記憶體合計
+}
+
+impl Default for MeasureLatency {
+    fn default() -> Self {
+        Self {
+            slow_ms: SLOW_MEASURE.as_millis() as u64,
+            estimate_above: None,
+            slow_count: 0,
+            slow: Vec::new(),
+            estimated: 0,
+            estimated_mem: 0,
+        }
+    }
+}
+
+impl MeasureLatency {
+    pub fn new(estimate_above: Option<u64>) -> Self {
+        Self {
+            estimate_above,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slow_count == 0 && self.estimated == 0
+    }
+
+    /// 記錄一次過慢的量測；前幾次回傳要印出的提醒
+    pub fn add_slow(&mut self, m: SlowMeasure) -> Option<String> {
+        self.slow_count += 1;
+        let warning = (self.slow_count <= SLOW_WARN_LINES).then(|| {
+            format!(
+                "⚠ {}量測耗時 {} ms（{} keys），最大的 key: {} {}（{:.2} MB{}）{}",
+                if m.deep { "第二階段" } else { "" },
+                m.ms,
+                m.keys,
+                m.type_code.name(),
+                m.key,
+                Decimal(m.mem as f64 / 1024.0 / 1024.0),
+                m.elements
+                    .map(|n| format!("，{} 個元素", format_int(n)))
+                    .unwrap_or_default(),
+                if self.estimate_above.is_none() {
+                    "，可用 --estimate-above 略過大集合的精確量測"
+                } else {
+                    ""
+                }
+            )
+        });
+        self.keep(m);
+        warning
+    }
+
+    pub fn add_estimated(&mut self, mem: u64) {
+        self.estimated += 1;
+        self.estimated_mem += mem;
+    }
+
+    /// 合併另一個節點（或第二階段）的結果
+    pub fn absorb(&mut self, other: MeasureLatency) {
+        self.estimate_above = self.estimate_above.or(other.estimate_above);
+        self.slow_count += other.slow_count;
+        self.estimated += other.estimated;
+        self.estimated_mem += other.estimated_mem;
+        for m in other.slow {
+            self.keep(m);
+        }
+    }
+
+    fn keep(&mut self, m: SlowMeasure) {
+        self.slow.push(m);
+        self.slow.sort_by_key(|m| std::cmp::Reverse(m.ms));
+        self.slow.truncate(SLOW_KEEP);
+    }
+}
+
+/// 往返時間的分布（微秒）：小於 8 µs 各自一桶，其餘每個 2 的次方分成 RTT_SUB_BUCKETS 桶
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RttHistogram {
+    pub counts: Vec<u64>, // 只配置到用到的最大桶
+    pub count: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+}
+
+impl RttHistogram {
+    pub fn add(&mut self, d: Duration) {
+        let us = d.as_micros() as u64;
+        let b = rtt_bucket(us);
+        if self.counts.len() <= b {
+            self.counts.resize(b + 1, 0);
+        }
+        self.counts[b] += 1;
+        self.count += 1;
+        self.total_us += us;
+        self.max_us = self.max_us.max(us);
+    }
+
+    pub fn merge(&mut self, other: &RttHistogram) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (a, b) in self.counts.iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+        self.count += other.count;
+        self.total_us += other.total_us;
+        self.max_us = self.max_us.max(other.max_us);
+    }
+
+    /// p（0~1）分位數的微秒數，取所在桶的上限（不超過實際最大值）
+    pub fn percentile(&self, p: f64) -> u64 {
+        let want = ((self.count as f64 * p).ceil() as u64).max(1);
+        let mut cum = 0;
+        for (b, &c) in self.counts.iter().enumerate() {
+            cum += c;
+            if cum >= want {
+                return rtt_bucket_upper(b).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+}
+
+fn rtt_bucket(us: u64) -> usize {
+    if us < 8 {
+        return us as usize;
+    }
+    let exp = u64::BITS - 1 - us.leading_zeros(); // >= 3
+    let sub = (us >> (exp - 2)) & (RTT_SUB_BUCKETS as u64 - 1);
+    (8 + (exp - 3) * RTT_SUB_BUCKETS) as usize + sub as usize
+}
+
+fn rtt_bucket_upper(b: usize) -> u64 {
+    if b < 8 {
+        return b as u64;
+    }
+    let exp = ((b - 8) / RTT_SUB_BUCKETS as usize) as u32 + 3;
+    let sub = ((b - 8) % RTT_SUB_BUCKETS as usize) as u64;
+    ((RTT_SUB_BUCKETS as u64 + sub + 1) << (exp - 2)).saturating_sub(1)
+}
+
+/// 往返時間的尖峰：超過當時 p50 的 SPIKE_FACTOR 倍
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RttSpike {
+    pub target: String,
+    pub at_secs: u64, // 掃描開始後幾秒
+    pub scan: bool,   // true = SCAN，false = 量測 pipeline
+    pub ms: u64,
+    pub p50_us: u64, // 當時的 p50
+    pub keys: u64,
+    #[serde(rename = "type")]
+    pub type_code: Option<KeyTypeCode>, // 量測 pipeline：批次中最大的 key
+    pub key: Option<String>,
+    pub mem: Option<u64>,
+    pub scan_slow: bool, // 量測 pipeline：同一輪的 SCAN 也是尖峰（較像網路或整體延遲）
+}
+
+/// 本工具自己送出的 SCAN 與量測 pipeline 的往返時間
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct CommandRtt {
+    pub scan: RttHistogram,
+    pub pipeline: RttHistogram,
+    pub spike_count: u64,
+    pub spikes: Vec<RttSpike>, // 依耗時 desc，最多 SPIKE_KEEP 筆
+}
+
+impl CommandRtt {
+    pub fn is_empty(&self) -> bool {
+        self.scan.count == 0 && self.pipeline.count == 0
+    }
+
+    /// 記錄一次 SCAN；是尖峰時回傳當時（加入這一筆之前）的 p50 微秒數
+    pub fn add_scan(&mut self, d: Duration) -> Option<u64> {
+        let spike = spike_p50(&self.scan, d);
+        self.scan.add(d);
+        spike
+    }
+
+    pub fn add_pipeline(&mut self, d: Duration) -> Option<u64> {
+        let spike = spike_p50(&self.pipeline, d);
+        self.pipeline.add(d);
+        spike
+    }
+
+    pub fn add_spike(&mut self, s: RttSpike) {
+        self.spike_count += 1;
+        self.keep(s);
+    }
+
+    pub fn absorb(&mut self, other: CommandRtt) {
+        self.scan.merge(&other.scan);
+        self.pipeline.merge(&other.pipeline);
+        self.spike_count += other.spike_count;
+        for s in other.spikes {
+            self.keep(s);
+        }
+    }
+
+    fn keep(&mut self, s: RttSpike) {
+        self.spikes.push(s);
+        self.spikes.sort_by_key(|s| std::cmp::Reverse(s.ms));
+        self.spikes.truncate(SPIKE_KEEP);
+    }
+}
+
+fn spike_p50(h: &RttHistogram, d: Duration) -> Option<u64> {
+    if h.count < SPIKE_MIN_SAMPLES || d < SPIKE_MIN {
+        return None;
+    }
+    let p50 = h.percentile(0.5).max(1);
+    (d.as_micros() as u64 >= p50 * SPIKE_FACTOR).then_some(p50)
+}
@@ -0,0 +1,77 @@
+//! 找出 Redis 每種資料類型中佔用記憶體最多的 keys
+//!
+//! 連線（[`conn`]）、掃描（[`scan`]）、統計（[`stats`]）與報表模型（[`report`]）可以直接當 lib 使用；
+//! 命令列介面在 `cli` feature 後面，`--no-default-features` 時不需要 clap。
+
+pub mod acl;
+pub mod age;
+pub mod alert;
+pub mod alloc;
+pub mod bench;
+pub mod binwaste;
+pub mod budget;
+pub mod capabilities;
+pub mod checkpoint;
+pub mod cleanup;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod conn;
+#[cfg(feature = "formats-csv")]
+pub mod csv;
+pub mod dedup;
+pub mod deep;
+pub mod diff;
+pub mod export;
+pub mod fleet;
+pub mod format;
+pub mod guard;
+pub mod hash;
+#[cfg(feature = "formats-html")]
+pub mod html;
+pub mod impact;
+pub mod index;
+pub mod intstr;
+pub mod job;
+pub mod latency;
+pub mod merge;
+pub mod metrics;
+pub mod output;
+pub mod overhead;
+pub mod owners;
+pub mod plan;
+pub mod preview;
+pub mod progress;
+pub mod rdb;
+#[cfg(feature = "cli")]
+pub mod reload;
+pub mod report;
+pub mod retention;
+pub mod role;
+pub mod scan;
+pub mod sentinel;
+pub mod server;
+pub mod shards;
+pub mod snapshot;
+pub mod split;
+pub mod stats;
+pub mod status;
+pub mod strata;
+#[cfg(feature = "template")]
+pub mod template;
+pub mod trend;
+pub mod ttl_policy;
+pub mod typecache;
+pub mod upload;
+pub mod validate;
+pub mod zombie;
+
+use std::error::Error;
+
+/// 精簡版編譯時沒有包含的功能
+pub fn feature_disabled(feature: &str, flag: &str) -> Box<dyn Error> {
+    format!(
+        "此版本編譯時未啟用 `{}` feature，無法使用 {}（請以 --features {} 重新編譯）",
+        feature, flag, feature
+    )
+    .into()
+}
@@ -1,468 +1,1071 @@
-use indicatif::{ProgressBar, ProgressStyle};
-use redis::{self, Connection, Value};
-use std::env;
+use clap::{CommandFactory, Parser};
+use cli::{
+    AnalyzeRdbArgs, BenchArgs, CleanupArgs, Cli, Command, CompletionsArgs, DiffArgs, FleetArgs,
+    MergeArgs, ReportArgs, ReportFormat, ScanArgs, TrendArgs, ValidateArgs, WatchArgs,
+};
+use conn::connect;
+use format::{Decimal, format_int, format_unix_ts, now_unix};
+#[cfg(feature = "formats-csv")]
+use redis_top_keys_analyzer::csv;
+#[cfg(feature = "formats-html")]
+use redis_top_keys_analyzer::html;
+#[cfg(feature = "template")]
+use redis_top_keys_analyzer::template;
+use redis_top_keys_analyzer::{
+    alert, bench, budget, checkpoint, cleanup, cli, conn, diff, feature_disabled, fleet, format,
+    job, merge, output, plan, rdb, reload, report, retention, say, scan, shards, snapshot, split,
+    stats, status, trend, typecache, upload, validate,
+};
+use serde_json::json;
+use split::SplitBy;
+use stats::KeyTypeCode;
+use status::StatusBoard;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use typecache::TypeCache;
+
+const EXIT_NEW_BIG_KEYS: i32 = 3; // 全域 Top N 出現 baseline 沒有的 key
+const EXIT_BUDGET_EXCEEDED: i32 = 4; // 有前綴超出 --budgets 的預算
+const EXIT_EMPTY: i32 = 5; // --fail-on-empty：沒有量測到任何 key
+const EXIT_FLEET_PARTIAL: i32 = 6; // fleet：部分實例失敗，報表只含成功的實例
+const RELOAD_POLL: Duration = Duration::from_secs(1); // watch --config：等待下一輪時多久檢查一次設定檔
+
+/// 報表已正常輸出，但檢查沒有通過，以專屬的 exit code 結束（方便 CI / cron 判斷）
+#[derive(Debug)]
+struct CheckFailed {
+    code: i32,
+    message: String,
+    violations: u64,        // 新出現的大 key 與超出預算的前綴數合計
+    result: Option<String>, // scan 的 RESULT 行，在失敗訊息之後輸出，維持為最後一行
+}
+
+impl CheckFailed {
+    fn new(code: i32, message: String) -> Self {
+        Self {
+            code,
+            message,
+            violations: 0,
+            result: None,
+        }
+    }
+}
 
-const SCAN_COUNT: u64 = 5000; // 每次 SCAN 的 count hint
-const BATCH_SIZE: usize = 2000; // 每批 pipeline key 數
-const PROGRESS_EVERY: u64 = 50_000; // 每掃描多少 keys 更新一次進度條
-const TOP_N: usize = 10; // 每類型 Top N
+impl std::fmt::Display for CheckFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for CheckFailed {}
 
 fn main() {
     if let Err(err) = run() {
-        eprintln!("發生錯誤: {}", err);
+        if let Some(failed) = err.downcast_ref::<CheckFailed>() {
+            if output::is_json_lines() {
+                output::event(
+                    "failed",
+                    json!({ "message": failed.message, "exit_code": failed.code }),
+                );
+            } else {
+                eprintln!("✖ {}", failed.message);
+            }
+            if let Some(line) = &failed.result {
+                eprintln!("{}", line);
+            }
+            std::process::exit(failed.code);
+        }
+        if output::is_json_lines() {
+            output::event(
+                "error",
+                json!({ "message": err.to_string(), "exit_code": 1 }),
+            );
+        } else {
+            eprintln!("發生錯誤: {}", err);
+        }
         std::process::exit(1);
     }
 }
 
-/// Key 類型（只處理常見的六種）
-#[derive(Copy, Clone, Debug)]
-enum KeyTypeCode {
-    String = 0,
-    List = 1,
-    Set = 2,
-    ZSet = 3,
-    Hash = 4,
-    Stream = 5,
+fn run() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    format::init_locale(cli.locale.as_deref());
+    format::init_timezone(cli.timezone.unwrap_or(0));
+
+    // 沒有子命令時維持原本行為：直接掃描
+    match cli.command.unwrap_or(Command::Scan(Box::new(cli.scan))) {
+        Command::Scan(args) => cmd_scan(*args),
+        Command::Report(args) => cmd_report(args),
+        Command::Diff(args) => cmd_diff(*args),
+        Command::Merge(args) => cmd_merge(args),
+        Command::Fleet(args) => cmd_fleet(*args),
+        Command::Trend(args) => cmd_trend(args),
+        Command::Watch(args) => cmd_watch(*args),
+        Command::Validate(args) => cmd_validate(*args),
+        Command::AnalyzeRdb(args) => cmd_analyze_rdb(args),
+        Command::Bench(args) => cmd_bench(args),
+        Command::Cleanup(args) => cmd_cleanup(args),
+        Command::Completions(args) => cmd_completions(args),
+    }
 }
 
-impl KeyTypeCode {
-    fn all() -> &'static [KeyTypeCode] {
-        use KeyTypeCode::*;
-        &[String, List, Set, ZSet, Hash, Stream]
+fn cmd_scan(args: ScanArgs) -> Result<(), Box<dyn Error>> {
+    if args.watch {
+        return cmd_watch(args.into_watch());
     }
-
-    fn name(self) -> &'static str {
-        match self {
-            KeyTypeCode::String => "string",
-            KeyTypeCode::List => "list",
-            KeyTypeCode::Set => "set",
-            KeyTypeCode::ZSet => "zset",
-            KeyTypeCode::Hash => "hash",
-            KeyTypeCode::Stream => "stream",
+    if args.oneshot_json {
+        output::json_lines();
+        job::install(args.grace_period);
+    } else if args.format != ReportFormat::Text {
+        output::reserve_stdout();
+    }
+    let format = if args.oneshot_json {
+        ReportFormat::Json
+    } else {
+        args.format
+    };
+    let mut cfg = args.tuning.to_config()?;
+    cfg.split_owners = args.split_report_by == Some(SplitBy::Owner);
+    check_features(&args)?;
+    // 先讀 baseline，路徑錯誤時不必白跑一次掃描
+    let baseline = args.baseline.as_deref().map(snapshot::load).transpose()?;
+    let budgets = match &args.budgets {
+        Some(path) => budget::load_budgets(path)?,
+        None => Vec::new(),
+    };
+    let nodes = match &args.twemproxy_config {
+        Some(path) => shards::twemproxy_servers(path)?,
+        None if args.cluster => shards::cluster_masters(&args.conn)?,
+        None => args.nodes.clone(),
+    };
+    if args.dry_run {
+        return dry_run(&args, &nodes, &cfg);
+    }
+    if !nodes.is_empty() && (args.conn.socket.is_some() || !args.conn.sentinel.is_empty()) {
+        return Err(
+            "--socket / --sentinel 只適用於單一實例，不能搭配 --nodes、--twemproxy-config 或 --cluster"
+                .into(),
+        );
+    }
+    if nodes.is_empty() && args.checkpoint.is_some() {
+        return Err("--checkpoint 需要搭配 --nodes、--twemproxy-config 或 --cluster".into());
+    }
+    if let Some(export) = cfg.export_keys.as_mut() {
+        export.per_node = !nodes.is_empty();
+    }
+    let mut report = if nodes.is_empty() {
+        let (mut con, url) = connect(&args.conn)?;
+        scan::scan(&mut con, &url, &cfg)?
+    } else {
+        let checkpoint = args
+            .checkpoint
+            .as_deref()
+            .map(|dir| checkpoint::Checkpoint::open(dir, &cfg, now_unix()))
+            .transpose()?;
+        shards::scan_nodes(&args.conn, &nodes, &cfg, args.parallel, checkpoint.as_ref())?
+    };
+    report.notes = args.notes.clone();
+
+    match (&args.template, format) {
+        (Some(path), _) => render_template(&report, path)?,
+        (None, ReportFormat::Json) => snapshot::write(&report, &mut io::stdout().lock())?,
+        (None, ReportFormat::Csv) => {
+            render_csv(&report, &args.render.columns, &mut io::stdout().lock())?
+        }
+        (None, ReportFormat::Text) => {
+            report::render_text(&report, &args.render.to_options(), &mut io::stdout().lock())?
         }
     }
 
-    fn title(self) -> &'static str {
-        // 顯示用（大寫）
-        match self {
-            KeyTypeCode::String => "STRING",
-            KeyTypeCode::List => "LIST",
-            KeyTypeCode::Set => "SET",
-            KeyTypeCode::ZSet => "ZSET",
-            KeyTypeCode::Hash => "HASH",
-            KeyTypeCode::Stream => "STREAM",
+    if let Some(path) = &args.snapshot {
+        snapshot::save(&report, path)?;
+        say!("\n✔ Snapshot 已寫入 {}", path.display());
+    }
+    if let Some(path) = &args.html {
+        write_html(&report, path)?;
+        say!("\n✔ HTML 報表已寫入 {}", path.display());
+    }
+    if let Some(path) = &args.csv_out {
+        write_csv(&report, &args.render.columns, path)?;
+        say!("\n✔ CSV 已寫入 {}", path.display());
+    }
+    if let (Some(dir), Some(rules)) = (&args.output_dir, &cfg.owners) {
+        let files = split::write_owner_reports(&report, rules, dir, &args.render.to_options())?;
+        print_split_files(dir, &files);
+    }
+    if let Some(dir) = &args.history_dir {
+        save_and_project(
+            &report,
+            dir,
+            args.retention.to_policy(),
+            args.capacity.to_target(),
+        )?;
+    }
+    if let Some(dest) = &args.output {
+        let written = upload::write_report(&report, dest)?;
+        say!("\n✔ 報表已寫入 {}", written);
+    }
+    if let Some(url) = &args.post_url {
+        upload::post_report(&report, url, args.post_token.as_deref())?;
+        say!("\n✔ 報表已送出至 {}", url);
+    }
+    send_alerts(&report, &args)?;
+
+    // 中斷的報表不做檢查（只含部分 key，預算與 baseline 的比較沒有意義）
+    let checked = match &report.interrupted {
+        Some(why) => Err(CheckFailed::new(job::EXIT_INTERRUPTED, why.clone()).into()),
+        None => run_checks(
+            &report,
+            baseline.as_ref(),
+            &budgets,
+            args.fail_on_empty,
+            args.template.is_some() || format != ReportFormat::Text,
+        )
+        .and_then(|()| {
+            if args.oneshot_json && report.errors > 0 {
+                let message = format!("{} 個 key 量測失敗", format_int(report.errors));
+                return Err(CheckFailed::new(job::EXIT_SCAN_ERRORS, message).into());
+            }
+            Ok(())
+        }),
+    };
+    match checked {
+        Ok(()) => {
+            eprintln!("{}", result_line(&report, 0, 0));
+            Ok(())
+        }
+        Err(mut e) => {
+            if let Some(failed) = e.downcast_mut::<CheckFailed>() {
+                failed.result = Some(result_line(&report, failed.violations, failed.code));
+            }
+            Err(e)
         }
     }
 }
 
-/// 將 Redis 回傳的 TYPE 結果(Value)轉成 KeyTypeCode（不分配 String）
+/// --dry-run：每個節點做掃描前的檢查與試量測，列出計畫後結束
+fn dry_run(
+    args: &ScanArgs,
+    nodes: &[String],
+    cfg: &scan::ScanConfig,
+) -> Result<(), Box<dyn Error>> {
+    let mut plans = Vec::new();
+    if nodes.is_empty() {
+        let (mut con, url) = connect(&args.conn)?;
+        plans.push(plan::plan(&mut con, &url, cfg)?);
+    }
+    for node in nodes {
+        let (mut con, _) = shards::connect_node(&args.conn, node)?;
+        plans.push(plan::plan(&mut con, node, cfg)?);
+    }
+    plan::render_plans(&plans, cfg, args.parallel, &mut io::stdout().lock())?;
+    Ok(())
+}
+
+/// 給 log 監控用的單行結果（固定寫到 stderr，與報表格式無關），
+/// 例如 `RESULT total_keys=1200 total_mem=52428800 errors=0 duration=3.412 violations=0 role_changes=0 started=2026-10-14T03:04:05Z finished=2026-10-14T03:04:08Z`
 ///
-/// redis 1.x / RESP3 會用 `BulkString(Vec<u8>)` 或 `SimpleString(String)` 表示 "string"/"hash" 等。
-fn parse_type_code(v: &Value) -> Option<KeyTypeCode> {
-    match v {
-        Value::BulkString(b) => match b.as_slice() {
-            b"string" => Some(KeyTypeCode::String),
-            b"list" => Some(KeyTypeCode::List),
-            b"set" => Some(KeyTypeCode::Set),
-            b"zset" => Some(KeyTypeCode::ZSet),
-            b"hash" => Some(KeyTypeCode::Hash),
-            b"stream" => Some(KeyTypeCode::Stream),
-            _ => None,
-        },
-        Value::SimpleString(s) => match s.as_str() {
-            "string" => Some(KeyTypeCode::String),
-            "list" => Some(KeyTypeCode::List),
-            "set" => Some(KeyTypeCode::Set),
-            "zset" => Some(KeyTypeCode::ZSet),
-            "hash" => Some(KeyTypeCode::Hash),
-            "stream" => Some(KeyTypeCode::Stream),
-            _ => None,
-        },
-        _ => None,
+/// total_mem 為 bytes，duration 為秒（monotonic），started / finished 為 ISO-8601 UTC。
+/// --oneshot-json 時改為同樣欄位的 result 事件，另附 exit_code 與 interrupted。
+fn result_line(report: &report::Report, violations: u64, exit_code: i32) -> String {
+    if output::is_json_lines() {
+        return output::event_line(
+            "result",
+            json!({
+                "total_keys": report.scanned,
+                "total_mem": report.total_mem(),
+                "errors": report.errors,
+                "duration": report.duration_ms as f64 / 1000.0,
+                "violations": violations,
+                "role_changes": report.role_changes.len(),
+                "started": report.started_at_utc,
+                "finished": report.finished_at_utc,
+                "interrupted": report.interrupted.is_some(),
+                "exit_code": exit_code,
+            }),
+        );
     }
+    format!(
+        "RESULT total_keys={} total_mem={} errors={} duration={:.3} violations={} role_changes={} started={} finished={}",
+        report.scanned,
+        report.total_mem(),
+        report.errors,
+        report.duration_ms as f64 / 1000.0,
+        violations,
+        report.role_changes.len(),
+        report.started_at_utc,
+        report.finished_at_utc
+    )
 }
 
-/// 單一類型的統計
-#[derive(Clone, Default)]
-struct TypeStats {
-    top: Vec<(u64, String)>, // (mem_bytes, key)
-    total_mem: u64,
-    count: u64,
+/// 掃描 / 報表之後的檢查：新出現的大 key（--baseline）、前綴預算（--budgets）與空結果（--fail-on-empty）
+///
+/// 各項都會先完整輸出再決定 exit code；有多項沒通過時用第一項的 code。
+/// 使用模板輸出時改寫到 stderr，避免混進模板產生的內容。
+fn run_checks(
+    report: &report::Report,
+    baseline: Option<&report::Report>,
+    budgets: &[(String, u64)],
+    fail_on_empty: bool,
+    to_stderr: bool,
+) -> Result<(), Box<dyn Error>> {
+    // --oneshot-json 的 stderr 只有 JSON 事件；檢查結果由 failed 事件與 exit code 表示
+    let mut out: Box<dyn Write> = if output::is_json_lines() {
+        Box::new(io::sink())
+    } else if to_stderr {
+        Box::new(io::stderr().lock())
+    } else {
+        Box::new(io::stdout().lock())
+    };
+
+    let mut failed: Vec<(i32, String)> = Vec::new();
+    let mut violations = 0;
+    if let Some(baseline) = baseline {
+        let fresh = diff::render_new_big_keys(baseline, report, &mut out)?;
+        violations += fresh as u64;
+        if fresh > 0 {
+            failed.push((
+                EXIT_NEW_BIG_KEYS,
+                format!("全域 Top {} 中有 {} 個新出現的大 key", stats::TOP_N, fresh),
+            ));
+        }
+    }
+    if !budgets.is_empty() {
+        let exceeded = budget::render_budgets(report, budgets, &mut out)?;
+        violations += exceeded as u64;
+        if exceeded > 0 {
+            failed.push((
+                EXIT_BUDGET_EXCEEDED,
+                format!("{} 個前綴超出記憶體預算", exceeded),
+            ));
+        }
+    }
+    if let (true, Some(empty)) = (fail_on_empty, &report.empty) {
+        failed.push((EXIT_EMPTY, format!("空結果：{}", empty.describe())));
+    }
+    out.flush()?;
+
+    match failed.first() {
+        Some((code, _)) => Err(CheckFailed {
+            code: *code,
+            message: failed
+                .iter()
+                .map(|(_, m)| m.as_str())
+                .collect::<Vec<_>>()
+                .join("；"),
+            violations,
+            result: None,
+        }
+        .into()),
+        None => Ok(()),
+    }
 }
 
-impl TypeStats {
-    fn new() -> Self {
-        Self::default()
+/// 超過硬門檻時通知 PagerDuty / Opsgenie；沒有設定任何一個時不做事
+fn send_alerts(report: &report::Report, args: &ScanArgs) -> Result<(), Box<dyn Error>> {
+    if args.pagerduty_routing_key.is_none() && args.opsgenie_api_key.is_none() {
+        return Ok(());
+    }
+    let findings = alert::critical_findings(
+        report,
+        alert::AlertThresholds {
+            max_key_bytes: args.alert_key_size,
+            max_used: args.alert_used_pct,
+        },
+    );
+    if findings.is_empty() {
+        return Ok(());
+    }
+    if let Some(key) = &args.pagerduty_routing_key {
+        alert::send_pagerduty(report, &findings, key)?;
+        say!("\n✔ 已送出 PagerDuty 事件: {}", findings.join("；"));
     }
+    if let Some(key) = &args.opsgenie_api_key {
+        alert::send_opsgenie(report, &findings, key)?;
+        say!("\n✔ 已建立 Opsgenie 告警: {}", findings.join("；"));
+    }
+    Ok(())
+}
 
-    /// 新增一個 key 的統計，只在進入 Top N 時才 clone key
-    fn add_key(&mut self, mem: u64, key: &str) {
-        self.count += 1;
-        self.total_mem += mem;
+/// 需要的功能沒有編譯進來時在掃描前就回報，不必掃完才失敗
+fn check_features(args: &ScanArgs) -> Result<(), Box<dyn Error>> {
+    if args.template.is_some() && !cfg!(feature = "template") {
+        return Err(feature_disabled("template", "--template"));
+    }
+    if args.output.as_deref().is_some_and(upload::is_remote) && !cfg!(feature = "cloud") {
+        return Err(feature_disabled("cloud", "--output s3:// / gs:// / az://"));
+    }
+    if args.post_url.is_some() && !cfg!(feature = "post") {
+        return Err(feature_disabled("post", "--post-url"));
+    }
+    if args.pagerduty_routing_key.is_some() && !cfg!(feature = "post") {
+        return Err(feature_disabled("post", "--pagerduty-routing-key"));
+    }
+    if args.opsgenie_api_key.is_some() && !cfg!(feature = "post") {
+        return Err(feature_disabled("post", "--opsgenie-api-key"));
+    }
+    if args.tuning.alert_webhook.is_some() && !cfg!(feature = "post") {
+        return Err(feature_disabled("post", "--alert-webhook"));
+    }
+    if args.tuning.concurrency > 1 && !cfg!(feature = "async") {
+        return Err(feature_disabled("async", "--concurrency"));
+    }
+    if args.html.is_some() && !cfg!(feature = "formats-html") {
+        return Err(feature_disabled("formats-html", "--html"));
+    }
+    if args.csv_out.is_some() && !cfg!(feature = "formats-csv") {
+        return Err(feature_disabled("formats-csv", "--csv-out"));
+    }
+    if args.format == ReportFormat::Csv && !cfg!(feature = "formats-csv") {
+        return Err(feature_disabled("formats-csv", "--format csv"));
+    }
+    Ok(())
+}
 
-        // Top N 還沒滿，直接塞
-        if self.top.len() < TOP_N {
-            self.top.push((mem, key.to_owned()));
-            return;
-        }
+#[cfg(feature = "template")]
+fn render_template(report: &report::Report, path: &Path) -> Result<(), Box<dyn Error>> {
+    template::render_template(report, path, &mut io::stdout().lock())
+}
 
-        // 找目前 Top 中 mem 最小的一筆
-        let mut min_idx = 0;
-        let mut min_mem = self.top[0].0;
-        for (i, (m, _)) in self.top.iter().enumerate().skip(1) {
-            if *m < min_mem {
-                min_mem = *m;
-                min_idx = i;
-            }
-        }
+#[cfg(not(feature = "template"))]
+fn render_template(_: &report::Report, _: &Path) -> Result<(), Box<dyn Error>> {
+    Err(feature_disabled("template", "--template"))
+}
 
-        // 只有新的 mem 比最小的大才換掉
-        if mem > min_mem {
-            self.top[min_idx] = (mem, key.to_owned());
-        }
+#[cfg(feature = "formats-html")]
+fn write_html(report: &report::Report, path: &Path) -> Result<(), Box<dyn Error>> {
+    html::write_html(report, path)
+}
+
+#[cfg(not(feature = "formats-html"))]
+fn write_html(_: &report::Report, _: &Path) -> Result<(), Box<dyn Error>> {
+    Err(feature_disabled("formats-html", "--html"))
+}
+
+#[cfg(feature = "formats-csv")]
+fn write_csv(
+    report: &report::Report,
+    columns: &[report::Column],
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    csv::write_csv(report, columns, path)
+}
+
+#[cfg(not(feature = "formats-csv"))]
+fn write_csv(_: &report::Report, _: &[report::Column], _: &Path) -> Result<(), Box<dyn Error>> {
+    Err(feature_disabled("formats-csv", "--csv-out"))
+}
+
+#[cfg(feature = "formats-csv")]
+fn render_csv(
+    report: &report::Report,
+    columns: &[report::Column],
+    out: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    Ok(csv::render_csv(report, columns, out)?)
+}
+
+#[cfg(not(feature = "formats-csv"))]
+fn render_csv(
+    _: &report::Report,
+    _: &[report::Column],
+    _: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    Err(feature_disabled("formats-csv", "--format csv"))
+}
+
+fn cmd_report(args: ReportArgs) -> Result<(), Box<dyn Error>> {
+    let report = snapshot::load(&args.snapshot)?;
+    let baseline = args.baseline.as_deref().map(snapshot::load).transpose()?;
+    let budgets = match &args.budgets {
+        Some(path) => budget::load_budgets(path)?,
+        None => Vec::new(),
+    };
+    // 訊息寫到 stderr：報表本身（文字或模板）可能被導到檔案
+    if let Some(path) = &args.html {
+        write_html(&report, path)?;
+        eprintln!("✔ HTML 報表已寫入 {}", path.display());
+    }
+    if let Some(path) = &args.csv_out {
+        write_csv(&report, &args.render.columns, path)?;
+        eprintln!("✔ CSV 已寫入 {}", path.display());
     }
 
-    /// 回傳依 mem desc 排序後的 Top N
-    fn sorted_top_desc(&self) -> Vec<(u64, String)> {
-        let mut v = self.top.clone();
-        v.sort_by(|a, b| b.0.cmp(&a.0));
-        v
+    if let Some(path) = &args.template {
+        render_template(&report, path)?;
+        return run_checks(&report, baseline.as_ref(), &budgets, false, true);
     }
-}
 
-/// 所有類型的統計，固定 6 個 slot，避免 HashMap + String type key
-struct AllStats {
-    inner: [TypeStats; 6],
+    println!(
+        "Snapshot: {} ({}，耗時 {:.1}s)\n",
+        report.target,
+        format_unix_ts(report.started_at),
+        report.duration_ms as f64 / 1000.0
+    );
+    report::render_text(&report, &args.render.to_options(), &mut io::stdout().lock())?;
+
+    run_checks(&report, baseline.as_ref(), &budgets, false, false)
 }
 
-impl AllStats {
-    fn new() -> Self {
-        Self {
-            inner: [
-                TypeStats::new(),
-                TypeStats::new(),
-                TypeStats::new(),
-                TypeStats::new(),
-                TypeStats::new(),
-                TypeStats::new(),
-            ],
+fn cmd_merge(args: MergeArgs) -> Result<(), Box<dyn Error>> {
+    let files = trend::collect_snapshots(&args.inputs)?;
+    if files.is_empty() {
+        return Err("沒有可合併的 snapshot".into());
+    }
+    let reports = files
+        .iter()
+        .map(|path| snapshot::load(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let shared = merge::shared_prefixes(&reports);
+    let mut report = report::Report::merge(reports);
+    report.shared_prefixes = shared;
+
+    match &args.template {
+        Some(path) => render_template(&report, path)?,
+        None => {
+            println!("合併 {} 份 snapshot\n", files.len());
+            report::render_text(&report, &args.render.to_options(), &mut io::stdout().lock())?;
         }
     }
+    if let Some(path) = &args.snapshot {
+        snapshot::save(&report, path)?;
+        println!("\n✔ Snapshot 已寫入 {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn cmd_fleet(args: FleetArgs) -> Result<(), Box<dyn Error>> {
+    if args.conn.host.is_some() {
+        return Err("fleet 以 --url 指定實例（可重複），不接受位置參數".into());
+    }
+    if args.conn.socket.is_some() {
+        return Err("fleet 不支援 --socket，請改用 --url redis+unix:///path".into());
+    }
+    if !args.conn.sentinel.is_empty() {
+        return Err("fleet 不支援 --sentinel，請以 --url 指定各實例".into());
+    }
+    let mut cfg = args.tuning.to_config()?;
+    if let Some(export) = cfg.export_keys.as_mut() {
+        export.per_node = true;
+    }
+    let instances = fleet::scan_fleet(&args.conn, &args.urls, &cfg, args.parallel);
 
-    fn get_mut(&mut self, t: KeyTypeCode) -> &mut TypeStats {
-        &mut self.inner[t as usize]
+    let report = fleet::merged_report(&instances);
+    if let Some(report) = &report {
+        println!();
+        report::render_text(report, &args.render.to_options(), &mut io::stdout().lock())?;
     }
+    fleet::render_status(&instances, &mut io::stdout().lock())?;
 
-    fn get(&self, t: KeyTypeCode) -> &TypeStats {
-        &self.inner[t as usize]
+    if let Some(dir) = &args.output_dir {
+        let files = fleet::write_instance_snapshots(&instances, dir)?;
+        println!(
+            "\n✔ 已寫入 {} 份實例 snapshot 到 {}",
+            files.len(),
+            dir.display()
+        );
+    }
+    if let (Some(path), Some(report)) = (&args.snapshot, &report) {
+        snapshot::save(report, path)?;
+        println!("\n✔ Snapshot 已寫入 {}", path.display());
     }
 
-    fn total_mem(&self) -> u64 {
-        self.inner.iter().map(|s| s.total_mem).sum()
+    let failed = instances.iter().filter(|i| i.outcome.is_err()).count();
+    if report.is_none() {
+        return Err(format!("全部 {} 個實例都掃描失敗", instances.len()).into());
     }
+    if failed > 0 {
+        return Err(Box::new(CheckFailed {
+            code: EXIT_FLEET_PARTIAL,
+            message: format!(
+                "{}/{} 個實例掃描失敗，報表只含成功的實例",
+                failed,
+                instances.len()
+            ),
+            violations: 0,
+            result: None,
+        }));
+    }
+    Ok(())
 }
 
-fn run() -> redis::RedisResult<()> {
-    // ------------------------------------------------------------
-    // CLI 參數處理：支援 host, host:port, host port
-    // ------------------------------------------------------------
-    let (host, port) = parse_host_port();
-    let redis_url = format!("redis://{}:{}/", host, port);
+fn cmd_validate(args: ValidateArgs) -> Result<(), Box<dyn Error>> {
+    let mut cfg = args.tuning.to_config()?;
+    // --bigkeys 的排名依據就是 STRLEN / LLEN / HLEN…
+    cfg.collect_cardinality = true;
+    cfg.rank_by_elements = true;
+    let (mut con, url) = connect(&args.conn)?;
+    let report = scan::scan(&mut con, &url, &cfg)?;
+
+    println!();
+    validate::render_validation(&report, &mut io::stdout().lock())?;
+    if let Some(path) = &args.snapshot {
+        snapshot::save(&report, path)?;
+        println!("\n✔ Snapshot 已寫入 {}", path.display());
+    }
+    Ok(())
+}
 
-    println!("嘗試連線 Redis: {}", redis_url);
+fn cmd_analyze_rdb(args: AnalyzeRdbArgs) -> Result<(), Box<dyn Error>> {
+    let report = rdb::analyze(
+        &args.file,
+        args.db,
+        args.prefix_sep,
+        args.prefix_depth,
+        args.prefix_tables,
+    )?;
+    println!(
+        "完成！共 {} keys（存檔時已過期: {}，耗時 {:.1}s）\n",
+        format_int(report.scanned),
+        format_int(report.vanished),
+        report.duration_ms as f64 / 1000.0
+    );
+    report::render_text(&report, &args.render.to_options(), &mut io::stdout().lock())?;
+    if let Some(path) = &args.snapshot {
+        snapshot::save(&report, path)?;
+        println!("\n✔ Snapshot 已寫入 {}", path.display());
+    }
+    Ok(())
+}
 
-    // ------------------------------------------------------------
-    // 建立連線
-    // ------------------------------------------------------------
-    let client = redis::Client::open(redis_url)?;
-    let mut con = client.get_connection()?;
+fn cmd_diff(args: DiffArgs) -> Result<(), Box<dyn Error>> {
+    let cfg = args.tuning.to_config()?;
+    let (old, new) = match &args.new {
+        Some(path) => (
+            diff::load_side(&args.old, None, &cfg)?,
+            diff::load_side(path, None, &cfg)?,
+        ),
+        // --live：先連線，RDB 只取同一個資料庫，最後才掃描
+        None => {
+            let (mut con, url) = connect(&args.conn)?;
+            let old = diff::load_side(&args.old, Some(con.db() as u64), &cfg)?;
+            let new = scan::scan(&mut con, &url, &cfg)?;
+            println!();
+            (old, new)
+        }
+    };
 
-    println!("✔ Redis 連線成功\n");
+    diff::render_diff(&old, &new, args.min_change, &mut io::stdout().lock())?;
 
-    // ------------------------------------------------------------
-    // 取得 key 總量（DBSIZE）
-    // ------------------------------------------------------------
-    let total_keys: u64 = redis::cmd("DBSIZE").query(&mut con)?;
-    println!("資料庫共 {} keys\n", format_with_commas(total_keys));
+    Ok(())
+}
 
-    // ------------------------------------------------------------
-    // 建立進度條
-    // ------------------------------------------------------------
-    let pb = ProgressBar::new(total_keys);
-    pb.set_style(
-        ProgressStyle::with_template(
-            "[{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} keys ({percent}%) {msg}",
+fn cmd_trend(args: TrendArgs) -> Result<(), Box<dyn Error>> {
+    let files = trend::collect_snapshots(&args.inputs)?;
+    let history = trend::load_history(&files)?;
+    if history.len() < 2 {
+        return Err(format!(
+            "至少需要 2 份含前綴統計的 snapshot，目前只有 {} 份",
+            history.len()
         )
-        .unwrap()
-        .progress_chars("=>-"),
-    );
+        .into());
+    }
+    if history[0].started_at == history[history.len() - 1].started_at {
+        return Err("所有 snapshot 的掃描時間相同，無法計算成長速度".into());
+    }
 
-    println!("開始 SCAN + PIPELINE MEMORY USAGE + TYPE...\n");
+    let mut budgets = args.budgets.clone();
+    if let Some(path) = &args.budgets_file {
+        budgets.extend(budget::load_budgets(path)?);
+    }
 
-    // ------------------------------------------------------------
-    // SCAN 全庫，搭配 pipeline 一次抓 MEMORY USAGE + TYPE
-    // ------------------------------------------------------------
-    let mut stats = AllStats::new();
+    let mut out = io::stdout().lock();
+    trend::render_trend(&history, &budgets, args.top, &mut out)?;
+    trend::render_capacity(&history, args.capacity.to_target(), &mut out)?;
 
-    let mut cursor: u64 = 0;
-    let mut scanned: u64 = 0;
-    let mut errors: u64 = 0;
+    Ok(())
+}
 
-    loop {
-        let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
-            .arg(cursor)
-            .arg("COUNT")
-            .arg(SCAN_COUNT)
-            .query(&mut con)?;
+/// 寫入歷史目錄並套用保留政策；已有較早的紀錄時接著輸出容量預估
+fn save_and_project(
+    report: &report::Report,
+    dir: &std::path::Path,
+    retention: retention::RetentionPolicy,
+    target: trend::CapacityTarget,
+) -> Result<(), Box<dyn Error>> {
+    let path = trend::save_history(report, dir)?;
+    say!("\n✔ 歷史紀錄已寫入 {}", path.display());
+
+    let pruned = retention::prune(dir, retention, report.started_at)?;
+    if !pruned.removed.is_empty() || !pruned.compacted.is_empty() {
+        say!(
+            "✔ 保留政策：刪除 {} 份、壓縮 {} 份，釋放 {:.2} MB",
+            pruned.removed.len(),
+            pruned.compacted.len(),
+            Decimal(pruned.freed_bytes as f64 / 1024.0 / 1024.0)
+        );
+    }
 
-        cursor = next_cursor;
+    let history = trend::load_history(&trend::collect_snapshots(&[dir.to_path_buf()])?)?;
+    if history.len() >= 2 && history[0].started_at < report.started_at {
+        trend::render_capacity(&history, target, &mut output::messages())?;
+    }
+    Ok(())
+}
 
-        if keys.is_empty() {
-            if cursor == 0 {
-                break;
-            }
-            continue;
-        }
+/// 定期重新掃描，沿用同一條連線
+fn cmd_watch(args: WatchArgs) -> Result<(), Box<dyn Error>> {
+    let mut config = args
+        .config
+        .as_deref()
+        .map(reload::ConfigFile::open)
+        .transpose()?;
+    let mut args = match &config {
+        Some(file) => file.watch_args()?,
+        None => args,
+    };
+    let (mut con, url) = connect(&args.conn)?;
+    let mut cfg = args.tuning.to_config()?;
+    if args.warm_types || args.type_cache.is_some() {
+        let cache = match &args.type_cache {
+            Some(path) => TypeCache::load(path, args.type_refresh)?,
+            None => TypeCache::new(args.type_refresh),
+        };
+        cfg.type_cache = Some(Arc::new(cache));
+    }
+    if let Some(addr) = &args.listen {
+        let board = Arc::new(StatusBoard::new());
+        status::serve(addr, board.clone())?;
+        cfg.status = Some(board);
+    }
 
-        // 每個 chunk 做一次 pipeline
-        for chunk in keys.chunks(BATCH_SIZE) {
-            match fetch_mem_and_type_batch(&mut con, chunk) {
-                Ok(batch_results) => {
-                    for (key, (mem_opt, type_opt)) in chunk.iter().zip(batch_results.into_iter()) {
-                        match (mem_opt, type_opt) {
-                            (Some(mem), Some(type_code)) => {
-                                stats.get_mut(type_code).add_key(mem, key);
-                                scanned += 1;
-                            }
-                            _ => {
-                                errors += 1;
-                            }
-                        }
-
-                        if scanned >= total_keys {
-                            pb.set_position(total_keys);
-                        } else if scanned.is_multiple_of(PROGRESS_EVERY) {
-                            pb.set_position(scanned);
-                        }
-                    }
+    let mut round: u64 = 0;
+    loop {
+        round += 1;
+        println!("{}", "#".repeat(120));
+        println!("# [{}] 第 {} 次掃描", format_unix_ts(now_unix()), round);
+        println!("{}\n", "#".repeat(120));
+
+        if let Some(cache) = &cfg.type_cache {
+            cache.begin_round();
+        }
+        if let Some(status) = &cfg.status {
+            status.begin_round(round);
+        }
+        let mut report = match scan::scan(&mut con, &url, &cfg) {
+            Ok(report) => report,
+            // 有狀態端點時由端點回報錯誤，watch 繼續等下一輪
+            Err(e) if cfg.status.is_some() => {
+                eprintln!("⚠ 第 {} 次掃描失敗: {}", round, e);
+                if let Some(status) = &cfg.status {
+                    status.fail_round(&e.to_string());
                 }
-                Err(e) => {
-                    eprintln!("Pipeline 批次錯誤: {}", e);
-                    errors += chunk.len() as u64;
+                if args.count > 0 && round >= args.count {
+                    break;
                 }
+                wait_next_round(Instant::now(), &mut config, &mut args, &mut cfg);
+                continue;
             }
+            Err(e) => return Err(e.into()),
+        };
+        let finished = Instant::now();
+        if let Some(cache) = &cfg.type_cache {
+            print_type_cache(cache, args.type_refresh);
+            if let Some(path) = &args.type_cache {
+                if let Err(e) = cache.save(path) {
+                    eprintln!("⚠ 無法寫入類型快取 {}: {}", path.display(), e);
+                }
+            }
+        }
+        report.notes = args.notes.clone();
+        report::render_text(&report, &args.render.to_options(), &mut io::stdout().lock())?;
+        if let Some(dir) = &args.history_dir {
+            save_and_project(
+                &report,
+                dir,
+                args.retention.to_policy(),
+                args.capacity.to_target(),
+            )?;
         }
 
-        if cursor == 0 {
+        // 最後一輪也要更新 /status 與 /metrics
+        if let Some(status) = &cfg.status {
+            status.finish_round(&report);
+        }
+        if args.count > 0 && round >= args.count {
             break;
         }
+        wait_next_round(finished, &mut config, &mut args, &mut cfg);
     }
 
-    pb.set_position(scanned.min(total_keys));
-    pb.finish_with_message("掃描完成");
+    Ok(())
+}
 
-    println!(
-        "\n完成！共掃描 {} keys (錯誤: {})\n",
-        format_with_commas(scanned),
-        errors
-    );
-    println!("{}", "=".repeat(120));
-
-    // ------------------------------------------------------------
-    // 類型 Top N
-    // ------------------------------------------------------------
-    for t in KeyTypeCode::all() {
-        let st = stats.get(*t);
-        if st.count == 0 || st.top.is_empty() {
-            continue;
+/// 等到本輪結束後 args.interval；等待中定期檢查設定檔，間隔改變時以本輪結束的時間重新計算
+fn wait_next_round(
+    finished: Instant,
+    config: &mut Option<reload::ConfigFile>,
+    args: &mut WatchArgs,
+    cfg: &mut scan::ScanConfig,
+) {
+    println!("\n下次掃描: {:?} 後\n", args.interval);
+    loop {
+        let wake = finished + args.interval;
+        if let Some(status) = &cfg.status {
+            status
+                .idle_until(now_unix() + wake.saturating_duration_since(Instant::now()).as_secs());
         }
+        let now = Instant::now();
+        if now >= wake {
+            break;
+        }
+        std::thread::sleep(RELOAD_POLL.min(wake - now));
+        if let Some(file) = config.as_mut().filter(|f| f.changed()) {
+            if reload_watch(file, args, cfg) {
+                println!("下次掃描: 本輪結束後 {:?}\n", args.interval);
+            }
+        }
+    }
+}
 
-        let top = st.sorted_top_desc();
-
-        println!("\n🔸 {} - Top {}", t.title(), TOP_N);
-        println!("{}", "-".repeat(120));
+/// 重新載入設定檔，成功時取代 args / cfg；連線與類型快取的設定沿用啟動時的值
+///
+/// 回傳是否套用了新的設定。
+fn reload_watch(
+    file: &mut reload::ConfigFile,
+    args: &mut WatchArgs,
+    cfg: &mut scan::ScanConfig,
+) -> bool {
+    let (mut next, changes) = match file.reload() {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!(
+                "⚠ 設定檔 {} 有誤，沿用目前的設定: {}",
+                file.path().display(),
+                e
+            );
+            return false;
+        }
+    };
+    let mut next_cfg = match next.tuning.to_config() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!(
+                "⚠ 設定檔 {} 有誤，沿用目前的設定: {}",
+                file.path().display(),
+                e
+            );
+            return false;
+        }
+    };
+    if next.conn.redis_url() != args.conn.redis_url() {
+        eprintln!("⚠ 連線目標的變更需要重新啟動 watch，本次不套用");
+    }
+    if next.warm_types != args.warm_types
+        || next.type_cache != args.type_cache
+        || next.type_refresh != args.type_refresh
+    {
+        eprintln!("⚠ 類型快取的設定變更需要重新啟動 watch，本次不套用");
+    }
+    if next.listen != args.listen {
+        eprintln!("⚠ --listen 的變更需要重新啟動 watch，本次不套用");
+    }
+    next.conn = args.conn.clone();
+    next.warm_types = args.warm_types;
+    next.type_cache = args.type_cache.take();
+    next.type_refresh = args.type_refresh;
+    next.listen = args.listen.take();
+    next_cfg.type_cache = cfg.type_cache.take();
+    next_cfg.status = cfg.status.take();
+
+    if changes.is_empty() {
+        println!("設定檔 {} 已重新載入，內容沒有變更", file.path().display());
+    } else {
         println!(
-            "{:>6} {:>15} {:>20} Key",
-            "排名", "記憶體 (MB)", "記憶體 (Bytes)"
+            "✔ 已重新載入設定檔 {}，下一輪起套用:",
+            file.path().display()
         );
-        println!("{}", "-".repeat(120));
-
-        for (idx, (mem, key)) in top.iter().enumerate() {
-            let mem_mb = *mem as f64 / 1024.0 / 1024.0;
-            println!(
-                "{:>6} {:>15.3} {:>20} {}",
-                idx + 1,
-                mem_mb,
-                mem,
-                truncate_key(key, 80)
-            );
+        for c in &changes {
+            println!("  {}", c);
         }
+    }
+    *args = next;
+    *cfg = next_cfg;
+    true
+}
 
-        let total_type_mem = st.total_mem;
-        let top_mem: u64 = top.iter().map(|(m, _)| *m).sum();
-        let top_pct = if total_type_mem > 0 {
-            (top_mem as f64 / total_type_mem as f64) * 100.0
-        } else {
-            0.0
-        };
+fn print_split_files(dir: &Path, files: &[split::SplitFile]) {
+    say!(
+        "\n✔ 已依擁有者拆分為 {} 份報表，整體報表為 {}",
+        files.len(),
+        dir.join(format!("{}.txt", split::ALL_NAME)).display()
+    );
+    for f in files {
+        say!(
+            "  {:<30} {:>12} keys {:>12.2} MB  {}",
+            f.owner,
+            format_int(f.keys),
+            Decimal(f.total_mem as f64 / 1024.0 / 1024.0),
+            f.path.display()
+        );
+    }
+}
 
+/// 本輪沿用了多少類型；完整 TYPE 的輪次說明快取已重建
+fn print_type_cache(cache: &TypeCache, refresh_every: u64) {
+    let st = cache.round_stats();
+    if st.warm {
         println!(
-            "\n  統計: 此類型共 {} keys, 總記憶體 {:.2} MB",
-            format_with_commas(st.count),
-            total_type_mem as f64 / 1024.0 / 1024.0
+            "類型快取：{} 個 key 沿用上一輪的類型、不送 TYPE；{} 個 key 仍送 TYPE",
+            format_int(st.hits),
+            format_int(st.misses)
         );
+    } else if refresh_every > 0 {
         println!(
-            "  Top {} 佔比: {:.2}% ({:.2} MB)",
-            TOP_N,
-            top_pct,
-            top_mem as f64 / 1024.0 / 1024.0
+            "類型快取：本輪完整送 TYPE，已記住 {} 個 key；之後 {} 輪只送 MEMORY USAGE",
+            format_int(st.entries as u64),
+            refresh_every
         );
-    }
-
-    // ------------------------------------------------------------
-    // 總體摘要
-    // ------------------------------------------------------------
-    println!("\n{}", "=".repeat(120));
-    println!("總體摘要");
-    println!("{}", "=".repeat(120));
-    println!(
-        "{:<15} {:>15} {:>20} 佔比",
-        "類型", "Keys 數量", "總記憶體 (MB)"
-    );
-    println!("{}", "-".repeat(120));
-
-    let total_mem = stats.total_mem();
-
-    for t in KeyTypeCode::all() {
-        let st = stats.get(*t);
-        if st.count == 0 {
-            continue;
-        }
-
-        let pct = if total_mem > 0 {
-            (st.total_mem as f64 / total_mem as f64) * 100.0
-        } else {
-            0.0
-        };
-
+    } else {
         println!(
-            "{:<15} {:>15} {:>20.2} {:>6.2}%",
-            t.name(),
-            format_with_commas(st.count),
-            st.total_mem as f64 / 1024.0 / 1024.0,
-            pct
+            "類型快取：本輪完整送 TYPE，已記住 {} 個 key",
+            format_int(st.entries as u64)
         );
     }
+}
 
-    println!(
-        "\n總計: {} keys, {:.2} MB",
-        format_with_commas(scanned),
-        total_mem as f64 / 1024.0 / 1024.0
-    );
-
+/// 補全內容由 clap 的定義產生：子命令、旗標，以及 --profile、--columns 等列舉值
+fn cmd_completions(args: CompletionsArgs) -> Result<(), Box<dyn Error>> {
+    let mut cmd = Cli::command();
+    let name = cmd.get_name().to_string();
+    clap_complete::generate(args.shell, &mut cmd, name, &mut io::stdout());
     Ok(())
 }
 
-/// 針對一批 keys，用 pipeline 一次取得 (MEMORY USAGE, TYPE)
-/// 回傳 Vec<(Option<mem_bytes>, Option<KeyTypeCode>)>
-fn fetch_mem_and_type_batch(
-    con: &mut Connection,
-    keys: &[String],
-) -> redis::RedisResult<Vec<(Option<u64>, Option<KeyTypeCode>)>> {
-    let mut pipe = redis::pipe();
+fn cmd_bench(args: BenchArgs) -> Result<(), Box<dyn Error>> {
+    let (mut con, _) = connect(&args.conn)?;
 
-    for key in keys {
-        // MEMORY USAGE key
-        pipe.cmd("MEMORY").arg("USAGE").arg(key);
-        // TYPE key
-        pipe.cmd("TYPE").arg(key);
-    }
+    bench::run(&mut con, args.trial, args.max_latency_ms)?;
 
-    // Vec<Value> 長度 = 2 * keys.len()
-    let values: Vec<Value> = pipe.query(con)?;
+    Ok(())
+}
 
-    if values.len() != keys.len() * 2 {
-        return Err(redis::RedisError::from((
-            redis::ErrorKind::TypeError,
-            "Pipeline 回傳長度不匹配",
-        )));
+/// 依 snapshot 產生 UNLINK 指令；加上 --execute 才會真的刪除
+fn cmd_cleanup(args: CleanupArgs) -> Result<(), Box<dyn Error>> {
+    let report = snapshot::load(&args.snapshot)?;
+    if args.expire_suggested {
+        return expire_suggested(&report, &args);
     }
 
-    let mut result = Vec::with_capacity(keys.len());
+    let type_filter = match &args.type_name {
+        Some(name) => {
+            Some(KeyTypeCode::from_name(name).ok_or_else(|| format!("未知的類型: {}", name))?)
+        }
+        None => None,
+    };
+
+    let keys = cleanup::select_keys(&report, &args.pattern, type_filter, args.min_bytes);
+    let total: u64 = keys.iter().map(|(_, mem, _)| *mem).sum();
+
+    if !args.execute {
+        // dry-run：輸出可直接餵給 redis-cli 的指令，說明行用 # 開頭
+        let mut out = io::stdout().lock();
+        writeln!(
+            out,
+            "# 來源 {} ({})，共 {} keys，約 {:.2} MB",
+            report.target,
+            format_unix_ts(report.started_at),
+            format_int(keys.len() as u64),
+            Decimal(total as f64 / 1024.0 / 1024.0)
+        )?;
+        for (t, mem, key) in &keys {
+            writeln!(out, "# {} {} bytes", t.name(), mem)?;
+            writeln!(out, "UNLINK {}", cleanup::quote_key(key))?;
+        }
+        return Ok(());
+    }
 
-    for idx in 0..keys.len() {
-        let mem_val = &values[2 * idx];
-        let type_val = &values[2 * idx + 1];
+    let mut con = connect_cleanup_target(&report, &args)?;
+    let mut pipe = redis::pipe();
+    for (_, _, key) in &keys {
+        pipe.cmd("UNLINK").arg(*key);
+    }
+    let removed: Vec<u64> = pipe.query(&mut con)?;
 
-        // MEMORY USAGE，一般是 Int；保守多支援 BulkString / SimpleString
-        let mem_opt = match mem_val {
-            Value::Nil => None,
-            Value::Int(i) => Some(*i as u64),
-            Value::BulkString(b) => {
-                let s = String::from_utf8_lossy(b);
-                s.parse::<u64>().ok()
-            }
-            Value::SimpleString(s) => s.parse::<u64>().ok(),
-            _ => None,
-        };
+    println!(
+        "✔ 已對 {} 執行 UNLINK: {} / {} keys 存在並已刪除（snapshot 估計約 {:.2} MB）",
+        report.target,
+        format_int(removed.iter().sum()),
+        format_int(keys.len() as u64),
+        Decimal(total as f64 / 1024.0 / 1024.0)
+    );
 
-        let type_opt = parse_type_code(type_val);
+    Ok(())
+}
 
-        result.push((mem_opt, type_opt));
+/// cleanup --execute 的連線：report.target 不含密碼，不能拿來重新連線；
+/// 改用命令列的連線參數，並確認與 snapshot 的來源是同一個目標，避免刪到別的實例
+fn connect_cleanup_target(
+    report: &report::Report,
+    args: &CleanupArgs,
+) -> Result<conn::RedisConn, Box<dyn Error>> {
+    let (con, url) = connect(&args.conn)?;
+    if url != report.target {
+        return Err(format!(
+            "snapshot 的來源是 {}，但連線目標是 {}；請指定與 snapshot 相同的目標",
+            report.target, url
+        )
+        .into());
     }
-
-    Ok(result)
+    Ok(con)
 }
 
-/// 解析 CLI host / port
-///
-/// 無參數: 127.0.0.1:6379
-/// 1 參數: "host" 或 "host:port"
-/// 2+ 參數: host port
-fn parse_host_port() -> (String, u16) {
-    let args: Vec<String> = env::args().collect();
-
-    if args.len() <= 1 {
-        return ("127.0.0.1".to_string(), 6379);
+/// cleanup --expire-suggested：依 TTL 政策建議設定 EXPIRE
+fn expire_suggested(report: &report::Report, args: &CleanupArgs) -> Result<(), Box<dyn Error>> {
+    if report.ttl_suggestions.is_empty() {
+        println!(
+            "# snapshot 中沒有 TTL 政策建議（未收集 TTL、--prefix-depth 0，或沒有發現漏設 TTL 的 key）"
+        );
+        return Ok(());
     }
-
-    if args.len() == 2 {
-        let arg = &args[1];
-        if let Some((h, p)) = arg.split_once(':') {
-            let port = p.parse::<u16>().unwrap_or(6379);
-            (h.to_string(), port)
-        } else {
-            (arg.to_string(), 6379)
+    let keys: Vec<_> = report
+        .ttl_suggestions
+        .iter()
+        .filter(|s| s.mem >= args.min_bytes && cleanup::glob_match(&args.pattern, &s.key))
+        .collect();
+    let total: u64 = keys.iter().map(|s| s.mem).sum();
+
+    if !args.execute {
+        let mut out = io::stdout().lock();
+        writeln!(
+            out,
+            "# 來源 {} ({})，共 {} keys，約 {:.2} MB",
+            report.target,
+            format_unix_ts(report.started_at),
+            format_int(keys.len() as u64),
+            Decimal(total as f64 / 1024.0 / 1024.0)
+        )?;
+        for s in &keys {
+            writeln!(
+                out,
+                "# {} bytes，{} 中 {:.1}% 有 TTL",
+                s.mem,
+                s.prefix,
+                Decimal(s.volatile_pct)
+            )?;
+            writeln!(out, "EXPIRE {} {}", cleanup::quote_key(&s.key), s.ttl_secs)?;
         }
-    } else {
-        let host = args[1].clone();
-        let port = args[2].parse::<u16>().unwrap_or(6379);
-        (host, port)
+        return Ok(());
     }
-}
-
-/// 千分位格式
-fn format_with_commas(n: u64) -> String {
-    let s = n.to_string();
-    let mut out_rev = String::new();
 
-    for (i, ch) in s.chars().rev().enumerate() {
-        if i != 0 && i % 3 == 0 {
-            out_rev.push(',');
-        }
-        out_rev.push(ch);
+    let mut con = connect_cleanup_target(report, args)?;
+    let mut pipe = redis::pipe();
+    for s in &keys {
+        pipe.cmd("EXPIRE").arg(&s.key).arg(s.ttl_secs);
     }
+    let updated: Vec<u64> = pipe.query(&mut con)?;
 
-    out_rev.chars().rev().collect()
-}
+    println!(
+        "✔ 已對 {} 執行 EXPIRE: {} / {} keys 存在並已設定 TTL（snapshot 估計約 {:.2} MB）",
+        report.target,
+        format_int(updated.iter().sum()),
+        format_int(keys.len() as u64),
+        Decimal(total as f64 / 1024.0 / 1024.0)
+    );
 
-/// 長 key 截斷
-fn truncate_key(key: &str, max_chars: usize) -> String {
-    if key.chars().count() <= max_chars {
-        key.to_string()
-    } else {
-        let mut s: String = key.chars().take(max_chars - 3).collect();
-        s.push_str("...");
-        s
-    }
+    Ok(())
 }
@@ -0,0 +1,793 @@
+use crate::age::merge_age_groups;
+use crate::guard::KeyRate;
+use crate::latency::{CommandRtt, MeasureLatency};
+use crate::overhead::sort_reports;
+use crate::report::{
+    BinWasteReport, DedupSummary, EmptyReason, EmptyResult, IntStringReport, NodeReport,
+    OverheadReport, OwnerDetail, OwnerReport, PrefixReport, Report, SNAPSHOT_VERSION, Sampling,
+    SharedPrefix, TtlSuggestion, TypeReport, ZombieReport,
+};
+use crate::server::{ClientBufferSummary, FunctionLibrary, KeyspaceActivity};
+use crate::stats::{
+    ExpiryTimeline, KeyTypeCode, LONGEST_KEYS, NO_PREFIX, OTHER_PREFIX, SizeHistogram, TOP_N,
+    TopKey, longest_cmp, merge_bands, merge_top, prefix_key_cmp, push_prefix_key,
+};
+use crate::ttl_policy::sort_suggestions;
+use crate::validate::{ElementTop, elements_cmp};
+use crate::zombie::ZOMBIE_TOP;
+use std::collections::{BTreeMap, HashMap};
+
+impl TypeReport {
+    pub fn empty(type_code: KeyTypeCode) -> Self {
+        Self {
+            type_code,
+            count: 0,
+            total_mem: 0,
+            top: Vec::new(),
+            sizes: SizeHistogram::default(),
+            unmeasured: 0,
+            mem_sq: 0.0,
+        }
+    }
+
+    /// 加上另一份同類型的結果：數量、記憶體與大小分布加總，Top keys 重新取前 N
+    pub fn merge(&mut self, other: &TypeReport) {
+        self.count += other.count;
+        self.total_mem += other.total_mem;
+        self.unmeasured += other.unmeasured;
+        self.mem_sq += other.mem_sq;
+        self.sizes.merge(&other.sizes);
+        merge_top(&mut self.top, &other.top);
+    }
+}
+
+impl Report {
+    /// 合併分開掃描的結果（cluster / proxy 後的各節點、不同 db、或在不同機器上跑的 snapshot）
+    ///
+    /// 數量與記憶體加總、Top keys 重新取前 N；輸入本身是合併結果時會展開其節點小計，
+    /// 所以分批合併與一次合併的結果相同。
+    /// MEMORY STATS / INFO memory 是各節點自己的比例與配置，加總沒有意義，合併後不保留。
+    pub fn merge(reports: Vec<Report>) -> Report {
+        merge_reports(reports)
+    }
+}
+
+/// 各輸入（每個實例一份 snapshot）都有的前綴，依總記憶體 desc
+///
+/// cluster 各 shard 本來就共用前綴，所以只在 merge 子命令合併不同實例時計算。
+pub fn shared_prefixes(reports: &[Report]) -> Vec<SharedPrefix> {
+    let mut map: BTreeMap<&str, SharedPrefix> = BTreeMap::new();
+    for r in reports {
+        for p in &r.prefixes {
+            let e = map
+                .entry(p.prefix.as_str())
+                .or_insert_with(|| SharedPrefix {
+                    prefix: p.prefix.clone(),
+                    instances: 0,
+                    count: 0,
+                    total_mem: 0,
+                    largest: String::new(),
+                    largest_mem: 0,
+                });
+            e.instances += 1;
+            e.count += p.count;
+            e.total_mem += p.total_mem;
+            if p.total_mem >= e.largest_mem {
+                e.largest = r.target.clone();
+                e.largest_mem = p.total_mem;
+            }
+        }
+    }
+    let mut v: Vec<SharedPrefix> = map
+        .into_values()
+        .filter(|p| p.instances > 1 && p.prefix != NO_PREFIX && p.prefix != OTHER_PREFIX)
+        .collect();
+    v.sort_by(|a, b| {
+        b.total_mem
+            .cmp(&a.total_mem)
+            .then_with(|| a.prefix.cmp(&b.prefix))
+    });
+    v
+}
+
+fn merge_reports(reports: Vec<Report>) -> Report {
+    let nodes: Vec<NodeReport> = reports
+        .iter()
+        .flat_map(|r| {
+            if r.nodes.is_empty() {
+                vec![NodeReport {
+                    target: r.target.clone(),
+                    scanned: r.scanned,
+                    total_mem: r.total_mem(),
+                }]
+            } else {
+                r.nodes.clone()
+            }
+        })
+        .collect();
+
+    let types = KeyTypeCode::all()
+        .iter()
+        .map(|t| {
+            let mut merged = TypeReport::empty(*t);
+            for tr in reports.iter().filter_map(|r| r.get(*t)) {
+                merged.merge(tr);
+            }
+            merged
+        })
+        .collect();
+
+    // 每個節點都有收 TTL 才合併時間軸
+    let expiry = if reports.iter().all(|r| r.expiry.is_some()) {
+        let mut timelines = reports.iter().filter_map(|r| r.expiry.as_ref());
+        let mut all = timelines
+            .next()
+            .cloned()
+            .unwrap_or_else(ExpiryTimeline::new);
+        for e in timelines {
+            all.merge(e);
+        }
+        Some(all)
+    } else {
+        None
+    };
+
+    let clients = if reports.iter().all(|r| r.clients.is_some()) {
+        let mut all = ClientBufferSummary::default();
+        for r in &reports {
+            let c = r.clients.as_ref().expect("已檢查");
+            all.clients += c.clients;
+            all.replicas += c.replicas;
+            all.total_mem += c.total_mem;
+            all.output_mem += c.output_mem;
+            all.query_buf += c.query_buf;
+            all.top_omem.extend(
+                c.top_omem
+                    .iter()
+                    .map(|(omem, desc)| (*omem, format!("{} @ {}", desc, r.target))),
+            );
+        }
+        all.top_omem
+            .sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        all.top_omem.truncate(3);
+        Some(all)
+    } else {
+        None
+    };
+
+    // MEMORY STATS / INFO memory 一律不合併，各節點自己的失敗原因也不需要
+    const NOT_MERGED: [&str; 2] = ["MEMORY STATS", "INFO memory"];
+    let mut unavailable: Vec<(String, String)> = reports
+        .iter()
+        .flat_map(|r| {
+            r.unavailable
+                .iter()
+                .filter(|(what, _)| !NOT_MERGED.contains(&what.as_str()))
+                .map(move |(what, why)| (what.clone(), format!("{}: {}", r.target, why)))
+        })
+        .collect();
+    for what in NOT_MERGED {
+        unavailable.push((what.into(), "多節點合併時不彙總，請分別掃描各節點".into()));
+    }
+
+    Report {
+        format_version: SNAPSHOT_VERSION,
+        target: nodes
+            .iter()
+            .map(|n| n.target.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+        started_at: reports.iter().map(|r| r.started_at).min().unwrap_or(0),
+        // ISO-8601 UTC 可以直接比較字串大小
+        started_at_utc: reports
+            .iter()
+            .map(|r| r.started_at_utc.clone())
+            .min()
+            .unwrap_or_default(),
+        finished_at_utc: reports
+            .iter()
+            .map(|r| r.finished_at_utc.clone())
+            .max()
+            .unwrap_or_default(),
+        // 各節點同時掃描（--parallel / --cluster / fleet），耗時取最慢的節點而不是加總
+        duration_ms: reports.iter().map(|r| r.duration_ms).max().unwrap_or(0),
+        scanned: reports.iter().map(|r| r.scanned).sum(),
+        errors: reports.iter().map(|r| r.errors).sum(),
+        vanished: reports.iter().map(|r| r.vanished).sum(),
+        types,
+        expiry,
+        memory_stats: None,
+        clients,
+        memory_info: None,
+        unavailable,
+        pauses: reports.iter().flat_map(|r| r.pauses.clone()).collect(),
+        role_changes: reports
+            .iter()
+            .flat_map(|r| r.role_changes.iter().cloned())
+            .collect(),
+        max_repl_lag: reports.iter().filter_map(|r| r.max_repl_lag).max(),
+        key_rate: merge_key_rate(&reports),
+        nodes,
+        duplicates: merge_duplicates(&reports),
+        sampling: merge_sampling(&reports),
+        prefixes: merge_prefixes(&reports),
+        owners: merge_owners(&reports),
+        zombies: merge_zombies(&reports),
+        key_names: merge_key_names(&reports),
+        longest_keys: merge_longest_keys(&reports),
+        ttl_suggestions: merge_ttl_suggestions(&reports),
+        int_strings: merge_int_strings(&reports),
+        bin_waste: merge_bin_waste(&reports),
+        shared_prefixes: Vec::new(),
+        overhead: merge_overhead(&reports),
+        activity: merge_activity(&reports),
+        functions: merge_functions(&reports),
+        notes: merge_notes(&reports),
+        keyspace_checks: reports
+            .iter()
+            .flat_map(|r| r.keyspace_checks.iter().cloned())
+            .collect(),
+        impact: reports
+            .iter()
+            .flat_map(|r| r.impact.iter().cloned())
+            .collect(),
+        latency: reports
+            .iter()
+            .fold(MeasureLatency::default(), |mut all, r| {
+                all.absorb(r.latency.clone());
+                all
+            }),
+        rtt: reports.iter().fold(CommandRtt::default(), |mut all, r| {
+            all.absorb(r.rtt.clone());
+            all
+        }),
+        capabilities: reports
+            .iter()
+            .flat_map(|r| r.capabilities.iter().cloned())
+            .collect(),
+        ages: merge_age_groups(reports.iter().flat_map(|r| r.ages.iter().cloned())),
+        // 壓縮過的 snapshot 沒有 Top N，合併結果的 Top N 也不完整
+        owner_details: merge_owner_details(&reports),
+        scope: None,
+        empty: merge_empty(&reports),
+        element_tops: merge_element_tops(&reports),
+        compacted: reports.iter().any(|r| r.compacted),
+        interrupted: reports.iter().find_map(|r| r.interrupted.clone()),
+        acl_denied: reports
+            .iter()
+            .flat_map(|r| r.acl_denied.iter().cloned())
+            .collect(),
+    }
+}
+
+/// 每個節點都有殭屍統計才合併，門檻取第一個節點的設定（同一次掃描都一樣）
+fn merge_zombies(reports: &[Report]) -> Option<ZombieReport> {
+    if !reports.iter().all(|r| r.zombies.is_some()) {
+        return None;
+    }
+    let mut zombies = reports.iter().filter_map(|r| r.zombies.as_ref());
+    let mut all = zombies.next()?.clone();
+    for z in zombies {
+        all.count += z.count;
+        all.total_mem += z.total_mem;
+        all.top.extend(z.top.iter().cloned());
+    }
+    all.top.sort_by(TopKey::rank_cmp);
+    all.top.truncate(ZOMBIE_TOP);
+    Some(all)
+}
+
+fn merge_key_names(reports: &[Report]) -> SizeHistogram {
+    let mut all = SizeHistogram::default();
+    for r in reports {
+        all.merge(&r.key_names);
+    }
+    all
+}
+
+fn merge_longest_keys(reports: &[Report]) -> Vec<TopKey> {
+    let mut all: Vec<TopKey> = reports
+        .iter()
+        .flat_map(|r| r.longest_keys.iter().cloned())
+        .collect();
+    all.sort_by(longest_cmp);
+    all.truncate(LONGEST_KEYS);
+    all
+}
+
+/// 依前綴加總，依可省記憶體 desc
+fn merge_int_strings(reports: &[Report]) -> Vec<IntStringReport> {
+    let mut map: BTreeMap<&str, IntStringReport> = BTreeMap::new();
+    for r in reports.iter().flat_map(|r| &r.int_strings) {
+        match map.get_mut(r.prefix.as_str()) {
+            Some(e) => {
+                e.strings += r.strings;
+                e.int_encoded += r.int_encoded;
+                e.numeric += r.numeric;
+                e.padded += r.padded;
+                e.savings += r.savings;
+                e.shared += r.shared;
+            }
+            None => {
+                map.insert(&r.prefix, r.clone());
+            }
+        }
+    }
+    let mut v: Vec<IntStringReport> = map.into_values().collect();
+    v.sort_by(|a, b| {
+        b.savings
+            .cmp(&a.savings)
+            .then_with(|| a.prefix.cmp(&b.prefix))
+    });
+    v
+}
+
+/// cluster 中 function 會複製到每個節點，同名 library 只留一份
+fn merge_functions(reports: &[Report]) -> Option<Vec<FunctionLibrary>> {
+    let mut all: Vec<FunctionLibrary> = Vec::new();
+    let mut any = false;
+    for libs in reports.iter().filter_map(|r| r.functions.as_ref()) {
+        any = true;
+        for l in libs {
+            if !all.iter().any(|a| a.name == l.name) {
+                all.push(l.clone());
+            }
+        }
+    }
+    all.sort_by(|a, b| {
+        b.code_bytes
+            .cmp(&a.code_bytes)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    any.then_some(all)
+}
+
+/// 每個節點都有才合併：計數加總，期間取最長的一個
+fn merge_activity(reports: &[Report]) -> Option<KeyspaceActivity> {
+    let mut all = KeyspaceActivity::default();
+    for r in reports {
+        let a = r.activity.as_ref()?;
+        all.window_ms = all.window_ms.max(a.window_ms);
+        all.expired_keys += a.expired_keys;
+        all.evicted_keys += a.evicted_keys;
+        all.hits += a.hits;
+        all.misses += a.misses;
+        all.keys_start += a.keys_start;
+        all.keys_end += a.keys_end;
+        all.expires_start += a.expires_start;
+        all.expires_end += a.expires_end;
+    }
+    Some(all)
+}
+
+/// 依 (類型, encoding) 加總
+fn merge_overhead(reports: &[Report]) -> Vec<OverheadReport> {
+    let mut map: BTreeMap<(u8, &str), OverheadReport> = BTreeMap::new();
+    for r in reports.iter().flat_map(|r| &r.overhead) {
+        match map.get_mut(&(r.type_code as u8, r.encoding.as_str())) {
+            Some(e) => {
+                e.keys += r.keys;
+                e.elements += r.elements;
+                e.total_mem += r.total_mem;
+                e.overhead += r.overhead;
+            }
+            None => {
+                map.insert((r.type_code as u8, &r.encoding), r.clone());
+            }
+        }
+    }
+    let mut v: Vec<OverheadReport> = map.into_values().collect();
+    sort_reports(&mut v);
+    v
+}
+
+/// 依前綴加總，依可回收 bytes desc
+fn merge_bin_waste(reports: &[Report]) -> Vec<BinWasteReport> {
+    let mut map: BTreeMap<&str, BinWasteReport> = BTreeMap::new();
+    for r in reports.iter().flat_map(|r| &r.bin_waste) {
+        match map.get_mut(r.prefix.as_str()) {
+            Some(e) => {
+                e.count += r.count;
+                e.total_mem += r.total_mem;
+                e.waste += r.waste;
+                e.near += r.near;
+                e.reclaimable += r.reclaimable;
+            }
+            None => {
+                map.insert(&r.prefix, r.clone());
+            }
+        }
+    }
+    let mut v: Vec<BinWasteReport> = map.into_values().collect();
+    v.sort_by(|a, b| {
+        b.reclaimable
+            .cmp(&a.reclaimable)
+            .then_with(|| b.waste.cmp(&a.waste))
+            .then_with(|| a.prefix.cmp(&b.prefix))
+    });
+    v
+}
+
+/// 各節點的建議合在一起取最大的幾個（同一個 key 只會在一個節點上）
+fn merge_ttl_suggestions(reports: &[Report]) -> Vec<TtlSuggestion> {
+    let mut all: Vec<TtlSuggestion> = reports
+        .iter()
+        .flat_map(|r| r.ttl_suggestions.iter().cloned())
+        .collect();
+    sort_suggestions(&mut all);
+    all
+}
+
+/// 同名擁有者跨節點加總
+fn merge_owners(reports: &[Report]) -> Vec<OwnerReport> {
+    let mut sum: HashMap<&str, (u64, u64)> = HashMap::new();
+    for o in reports.iter().flat_map(|r| &r.owners) {
+        let e = sum.entry(o.owner.as_str()).or_default();
+        e.0 += o.count;
+        e.1 += o.total_mem;
+    }
+    let mut v: Vec<OwnerReport> = sum
+        .into_iter()
+        .map(|(owner, (count, total_mem))| OwnerReport {
+            owner: owner.to_string(),
+            count,
+            total_mem,
+        })
+        .collect();
+    v.sort_by(|a, b| {
+        b.total_mem
+            .cmp(&a.total_mem)
+            .then_with(|| a.owner.cmp(&b.owner))
+    });
+    v
+}
+
+/// 各節點以相同比例抽樣時才能合併估計；比例不同或有節點沒抽樣時不提供估計
+///
+/// 分層抽樣的各類型普查數與估計值直接相加（節點之間互相獨立）；有的節點分層、有的沒有時不提供估計。
+fn merge_sampling(reports: &[Report]) -> Option<Sampling> {
+    let first = reports.first()?.sampling.as_ref()?;
+    let stratified = !first.strata.is_empty();
+    let mut skipped = 0;
+    let mut strata = first.strata.clone();
+    for st in &mut strata {
+        (st.keys, st.sampled, st.est_mem, st.mem_var) = (0, 0, 0.0, 0.0);
+    }
+    for r in reports {
+        match &r.sampling {
+            Some(s) if s.rate == first.rate && s.strata.is_empty() != stratified => {
+                skipped += s.skipped;
+                for (acc, st) in strata.iter_mut().zip(&s.strata) {
+                    acc.keys += st.keys;
+                    acc.sampled += st.sampled;
+                    acc.est_mem += st.est_mem;
+                    acc.mem_var += st.mem_var;
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(Sampling {
+        rate: first.rate,
+        skipped,
+        strata,
+    })
+}
+
+/// 依元素數的 Top N 跨節點重新取前 N；沒有節點收集時為空
+fn merge_element_tops(reports: &[Report]) -> Vec<ElementTop> {
+    if reports.iter().all(|r| r.element_tops.is_empty()) {
+        return Vec::new();
+    }
+    KeyTypeCode::all()
+        .iter()
+        .map(|&t| {
+            let mut top: Vec<TopKey> = reports
+                .iter()
+                .flat_map(|r| &r.element_tops)
+                .filter(|e| e.type_code == t)
+                .flat_map(|e| e.top.iter().cloned())
+                .collect();
+            top.sort_by(elements_cmp);
+            top.truncate(TOP_N);
+            ElementTop { type_code: t, top }
+        })
+        .collect()
+}
+
+/// 同一擁有者跨節點合併；有節點沒收集 TTL 時沒有到期時間軸
+fn merge_owner_details(reports: &[Report]) -> Vec<OwnerDetail> {
+    let mut by_owner: BTreeMap<&str, Vec<&OwnerDetail>> = BTreeMap::new();
+    for d in reports.iter().flat_map(|r| &r.owner_details) {
+        by_owner.entry(d.owner.as_str()).or_default().push(d);
+    }
+    let mut v: Vec<OwnerDetail> = by_owner
+        .into_iter()
+        .map(|(owner, details)| {
+            let types = KeyTypeCode::all()
+                .iter()
+                .map(|t| {
+                    let mut merged = TypeReport::empty(*t);
+                    for tr in details.iter().flat_map(|d| &d.types) {
+                        if tr.type_code == *t {
+                            merged.merge(tr);
+                        }
+                    }
+                    merged
+                })
+                .collect();
+            let expiry = details
+                .iter()
+                .map(|d| d.expiry.as_ref())
+                .collect::<Option<Vec<_>>>()
+                .map(|all| {
+                    let mut merged = ExpiryTimeline::new();
+                    for e in all {
+                        merged.merge(e);
+                    }
+                    merged
+                });
+            let mut key_names = SizeHistogram::default();
+            for d in &details {
+                key_names.merge(&d.key_names);
+            }
+            OwnerDetail {
+                owner: owner.to_string(),
+                types,
+                prefixes: merge_prefix_list(details.iter().flat_map(|d| &d.prefixes)),
+                expiry,
+                key_names,
+            }
+        })
+        .collect();
+    v.sort_by(|a, b| {
+        b.total_mem()
+            .cmp(&a.total_mem())
+            .then_with(|| a.owner.cmp(&b.owner))
+    });
+    v
+}
+
+/// 每個節點都沒有量測到 key 才是空結果；有節點是篩選掉的就算篩選，全部 DBSIZE 為 0 才算空資料庫
+fn merge_empty(reports: &[Report]) -> Option<EmptyResult> {
+    let all: Vec<&EmptyResult> = reports.iter().filter_map(|r| r.empty.as_ref()).collect();
+    if all.is_empty() || all.len() != reports.len() {
+        return None;
+    }
+    let reason = if all.iter().all(|e| e.reason == EmptyReason::Database) {
+        EmptyReason::Database
+    } else if all.iter().any(|e| e.reason == EmptyReason::Filtered) {
+        EmptyReason::Filtered
+    } else {
+        EmptyReason::Vanished
+    };
+    Some(EmptyResult {
+        reason,
+        dbsize: all.iter().map(|e| e.dbsize).sum(),
+        filters: all
+            .iter()
+            .find(|e| !e.filters.is_empty())
+            .map(|e| e.filters.clone())
+            .unwrap_or_default(),
+    })
+}
+
+/// 每個節點都有去重才合併
+fn merge_duplicates(reports: &[Report]) -> Option<DedupSummary> {
+    let all: Vec<&DedupSummary> = reports
+        .iter()
+        .filter_map(|r| r.duplicates.as_ref())
+        .collect();
+    (all.len() == reports.len()).then(|| DedupSummary {
+        suppressed: all.iter().map(|d| d.suppressed).sum(),
+        approximate: all.iter().any(|d| d.approximate),
+    })
+}
+
+fn merge_prefixes(reports: &[Report]) -> Vec<PrefixReport> {
+    merge_prefix_list(reports.iter().flat_map(|r| &r.prefixes))
+}
+
+/// 同名前綴跨節點加總；各節點的前綴 Top key 合併後取前 PREFIX_TOP_KEYS 個
+fn merge_prefix_list<'a>(prefixes: impl Iterator<Item = &'a PrefixReport>) -> Vec<PrefixReport> {
+    let mut sum: HashMap<&str, PrefixReport> = HashMap::new();
+    for p in prefixes {
+        let Some(e) = sum.get_mut(p.prefix.as_str()) else {
+            // 第一次出現的前綴直接沿用，Top key 之後重新排序
+            sum.insert(p.prefix.as_str(), p.clone());
+            continue;
+        };
+        e.count += p.count;
+        e.total_mem += p.total_mem;
+        e.name_bytes += p.name_bytes;
+        merge_bands(&mut e.bands, &p.bands);
+        for k in &p.top {
+            push_prefix_key(&mut e.top, k.type_code, &k.key, k.mem);
+        }
+    }
+    let mut v: Vec<PrefixReport> = sum
+        .into_values()
+        .map(|mut p| {
+            p.top.sort_by(prefix_key_cmp);
+            p
+        })
+        .collect();
+    v.sort_by(|a, b| {
+        b.total_mem
+            .cmp(&a.total_mem)
+            .then_with(|| a.prefix.cmp(&b.prefix))
+    });
+    v
+}
+
+/// 各節點各自限速：檢查數與耗時分別加總，得到的是每個節點的平均速率
+fn merge_key_rate(reports: &[Report]) -> Option<KeyRate> {
+    reports
+        .iter()
+        .filter_map(|r| r.key_rate.clone())
+        .reduce(|a, b| KeyRate {
+            limit: a.limit.max(b.limit),
+            keys: a.keys + b.keys,
+            elapsed_ms: a.elapsed_ms + b.elapsed_ms,
+            throttled_ms: a.throttled_ms + b.throttled_ms,
+        })
+}
+
+/// 各份報表的備註，重複的只留一份（同一次 --nodes 掃描的節點帶著相同備註）
+fn merge_notes(reports: &[Report]) -> Vec<String> {
+    let mut notes: Vec<String> = Vec::new();
+    for note in reports.iter().flat_map(|r| &r.notes) {
+        if !notes.contains(note) {
+            notes.push(note.clone());
+        }
+    }
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Stratum;
+    use crate::stats::KeyExtra;
+    use serde_json::json;
+
+    /// 只有 string 類型 Top keys 的報表
+    fn report(target: &str, top: &[(&str, u64)]) -> Report {
+        let mut r: Report = serde_json::from_value(json!({
+            "target": target,
+            "started_at": 0,
+            "duration_ms": 0,
+            "scanned": top.len(),
+            "errors": 0,
+            "types": [],
+            "unavailable": [],
+        }))
+        .unwrap();
+        let mut t = TypeReport::empty(KeyTypeCode::String);
+        for (key, mem) in top {
+            t.count += 1;
+            t.total_mem += mem;
+            t.top.push(TopKey::new(*mem, key, &KeyExtra::default()));
+        }
+        t.top.sort_by(TopKey::rank_cmp);
+        r.types.push(t);
+        r
+    }
+
+    fn sampled(target: &str, rate: f64, strata: Vec<Stratum>) -> Report {
+        let mut r = report(target, &[]);
+        r.sampling = Some(Sampling {
+            rate,
+            skipped: 10,
+            strata,
+        });
+        r
+    }
+
+    fn stratum(type_code: KeyTypeCode, keys: u64, sampled: u64) -> Stratum {
+        Stratum {
+            type_code,
+            keys,
+            sampled,
+            est_mem: keys as f64 * 100.0,
+            mem_var: 1.0,
+        }
+    }
+
+    fn top_keys(r: &Report) -> Vec<(String, u64)> {
+        r.get(KeyTypeCode::String)
+            .unwrap()
+            .top
+            .iter()
+            .map(|k| (k.key.clone(), k.mem))
+            .collect()
+    }
+
+    #[test]
+    fn duration_is_the_slowest_node() {
+        let mut a = report("a", &[]);
+        a.duration_ms = 3_000;
+        let mut b = report("b", &[]);
+        b.duration_ms = 5_000;
+        let mut c = report("c", &[]);
+        c.duration_ms = 1_000;
+        assert_eq!(Report::merge(vec![a, b, c]).duration_ms, 5_000);
+    }
+
+    #[test]
+    fn top_n_ties_at_cutoff_break_by_key_name() {
+        // 兩個節點各自的 Top N 在截斷處同樣大小，合併後依 key 名稱決定誰留下
+        let big: Vec<(String, u64)> = (0..TOP_N - 1)
+            .map(|i| (format!("big:{}", i), 200))
+            .collect();
+        let mut a: Vec<(&str, u64)> = big.iter().map(|(k, m)| (k.as_str(), *m)).collect();
+        a.push(("tie:b", 100));
+        let b = [("tie:a", 100), ("tie:c", 100), ("small", 50)];
+        let merged = Report::merge(vec![report("a", &a), report("b", &b)]);
+        let top = top_keys(&merged);
+        assert_eq!(top.len(), TOP_N);
+        assert_eq!(top.last().unwrap(), &("tie:a".to_string(), 100));
+        assert!(top.iter().all(|(k, _)| k != "tie:b" && k != "tie:c"));
+        assert_eq!(
+            merged.get(KeyTypeCode::String).unwrap().count,
+            TOP_N as u64 + 3
+        );
+
+        // 輸入順序與分批合併都不影響結果
+        let reversed = Report::merge(vec![report("b", &b), report("a", &a)]);
+        assert_eq!(top_keys(&reversed), top);
+        let staged = Report::merge(vec![Report::merge(vec![report("a", &a)]), report("b", &b)]);
+        assert_eq!(top_keys(&staged), top);
+    }
+
+    #[test]
+    fn sampling_needs_every_node_at_the_same_rate() {
+        let merged = Report::merge(vec![
+            sampled("a", 0.1, Vec::new()),
+            sampled("b", 0.1, Vec::new()),
+        ]);
+        let s = merged.sampling.expect("同比例抽樣可以合併");
+        assert_eq!((s.rate, s.skipped), (0.1, 20));
+
+        let mixed = Report::merge(vec![sampled("a", 0.1, Vec::new()), report("b", &[])]);
+        assert!(mixed.sampling.is_none());
+        let mixed = Report::merge(vec![report("a", &[]), sampled("b", 0.1, Vec::new())]);
+        assert!(mixed.sampling.is_none());
+        let rates = Report::merge(vec![
+            sampled("a", 0.1, Vec::new()),
+            sampled("b", 0.2, Vec::new()),
+        ]);
+        assert!(rates.sampling.is_none());
+    }
+
+    #[test]
+    fn strata_merge_only_when_every_node_is_stratified() {
+        let strata = || {
+            vec![
+                stratum(KeyTypeCode::String, 1000, 10),
+                stratum(KeyTypeCode::Hash, 50, 50),
+            ]
+        };
+        let merged = Report::merge(vec![
+            sampled("a", 0.1, strata()),
+            sampled("b", 0.1, strata()),
+        ]);
+        let s = merged.sampling.expect("都有分層可以合併");
+        assert_eq!(s.strata.len(), 2);
+        assert_eq!((s.strata[0].keys, s.strata[0].sampled), (2000, 20));
+        assert_eq!((s.strata[1].keys, s.strata[1].sampled), (100, 100));
+        assert_eq!(s.strata[0].est_mem, 200_000.0);
+        assert_eq!(s.strata[1].mem_var, 2.0);
+
+        let mixed = Report::merge(vec![
+            sampled("a", 0.1, strata()),
+            sampled("b", 0.1, Vec::new()),
+        ]);
+        assert!(mixed.sampling.is_none());
+        let mixed = Report::merge(vec![
+            sampled("a", 0.1, Vec::new()),
+            sampled("b", 0.1, strata()),
+        ]);
+        assert!(mixed.sampling.is_none());
+    }
+}
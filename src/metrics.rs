@@ -0,0 +1,148 @@
+//! watch --listen 的 GET /metrics：Prometheus text exposition format（0.0.4）
+//!
+//! 掃描結果的指標在每輪結束時產生一次，之後的 scrape 直接回傳同一份內容；
+//! 掃描狀態與累計次數則在每次 scrape 時由 StatusBoard 補上。key 指標只含各類型的 Top N，序列數有上限。
+
+use crate::report::Report;
+use std::fmt::Write;
+
+/// 一輪掃描結果的指標
+pub fn report_metrics(report: &Report) -> String {
+    let target = label(&report.target);
+    let mut out = String::new();
+
+    family(
+        &mut out,
+        "redis_topkeys_type_bytes",
+        "gauge",
+        "各類型 key 的記憶體合計（MEMORY USAGE，bytes）",
+    );
+    for t in &report.types {
+        let _ = writeln!(
+            out,
+            "redis_topkeys_type_bytes{{target=\"{}\",type=\"{}\"}} {}",
+            target,
+            t.type_code.name(),
+            t.total_mem
+        );
+    }
+    family(
+        &mut out,
+        "redis_topkeys_type_keys",
+        "gauge",
+        "各類型的 key 數",
+    );
+    for t in &report.types {
+        let _ = writeln!(
+            out,
+            "redis_topkeys_type_keys{{target=\"{}\",type=\"{}\"}} {}",
+            target,
+            t.type_code.name(),
+            t.count
+        );
+    }
+    family(
+        &mut out,
+        "redis_topkeys_key_bytes",
+        "gauge",
+        "各類型 Top N 的 key 記憶體（bytes）",
+    );
+    for t in &report.types {
+        for k in &t.top {
+            let _ = writeln!(
+                out,
+                "redis_topkeys_key_bytes{{target=\"{}\",type=\"{}\",key=\"{}\"}} {}",
+                target,
+                t.type_code.name(),
+                label(&k.key),
+                k.mem
+            );
+        }
+    }
+
+    let scalars: [(&str, &str, String); 6] = [
+        (
+            "redis_topkeys_scan_duration_seconds",
+            "最近一輪掃描的耗時",
+            format!("{:.3}", report.duration_ms as f64 / 1000.0),
+        ),
+        (
+            "redis_topkeys_scanned_keys",
+            "最近一輪量測的 key 數",
+            report.scanned.to_string(),
+        ),
+        (
+            "redis_topkeys_scan_errors",
+            "最近一輪量測失敗的 key 數",
+            report.errors.to_string(),
+        ),
+        (
+            "redis_topkeys_vanished_keys",
+            "最近一輪掃描期間消失的 key 數",
+            report.vanished.to_string(),
+        ),
+        (
+            "redis_topkeys_total_bytes",
+            "最近一輪所有 key 的記憶體合計（bytes）",
+            report.total_mem().to_string(),
+        ),
+        (
+            "redis_topkeys_last_scan_timestamp_seconds",
+            "最近一輪掃描的開始時間（Unix 秒）",
+            report.started_at.to_string(),
+        ),
+    ];
+    for (name, help, value) in scalars {
+        family(&mut out, name, "gauge", help);
+        let _ = writeln!(out, "{}{{target=\"{}\"}} {}", name, target, value);
+    }
+    out
+}
+
+/// 掃描狀態與累計次數（每次 scrape 產生）
+pub fn state_metrics(scanning: bool, up: bool, scans: u64, failures: u64) -> String {
+    let mut out = String::new();
+    let lines: [(&str, &str, &str, u64); 4] = [
+        (
+            "redis_topkeys_up",
+            "gauge",
+            "最近一輪掃描是否成功（尚未跑完第一輪時為 1）",
+            up as u64,
+        ),
+        (
+            "redis_topkeys_scanning",
+            "gauge",
+            "目前是否正在掃描（含負載保護暫停）",
+            scanning as u64,
+        ),
+        (
+            "redis_topkeys_scans_total",
+            "counter",
+            "成功完成的掃描次數",
+            scans,
+        ),
+        (
+            "redis_topkeys_scan_failures_total",
+            "counter",
+            "失敗的掃描次數",
+            failures,
+        ),
+    ];
+    for (name, kind, help, value) in lines {
+        family(&mut out, name, kind, help);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+    out
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// label 值的跳脫：反斜線、雙引號與換行
+fn label(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,104 @@
+//! 過程訊息（連線、掃描階段、檔案已寫入、警告…）的輸出位置
+//!
+//! 平常過程訊息與報表一樣寫到 stdout、警告寫到 stderr；--format json 等機器可讀格式佔用 stdout 時
+//! 過程訊息改寫到 stderr，讓 stdout 只有報表本身，可以直接接 jq。
+//! --oneshot-json 時 stderr 也是機器可讀的：每行一個 JSON 事件（log / warning / progress …）。
+
+use crate::format::{format_iso8601, now_unix};
+use serde_json::Value;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+const STDOUT: u8 = 0;
+const STDERR: u8 = 1;
+const JSON_LINES: u8 = 2;
+
+static MODE: AtomicU8 = AtomicU8::new(STDOUT);
+
+/// stdout 保留給報表，之後的過程訊息都寫到 stderr
+pub fn reserve_stdout() {
+    MODE.fetch_max(STDERR, Ordering::Relaxed);
+}
+
+/// stdout 保留給報表，過程訊息與警告都以 JSON 事件寫到 stderr
+pub fn json_lines() {
+    MODE.store(JSON_LINES, Ordering::Relaxed);
+}
+
+pub fn is_json_lines() -> bool {
+    MODE.load(Ordering::Relaxed) == JSON_LINES
+}
+
+/// 過程中輸出的表格（容量預估等）寫到這裡；JSON 事件模式下不輸出
+pub fn messages() -> Box<dyn Write> {
+    match MODE.load(Ordering::Relaxed) {
+        STDOUT => Box::new(io::stdout().lock()),
+        STDERR => Box::new(io::stderr().lock()),
+        _ => Box::new(io::sink()),
+    }
+}
+
+/// stderr 的一行 JSON 事件：`{"event":"progress","at":"2026-10-14T03:04:05Z",...}`
+pub fn event(kind: &str, fields: Value) {
+    eprintln!("{}", event_line(kind, fields));
+}
+
+/// event 與 at 固定放在最前面（serde_json 的物件依欄位名稱排序），其餘欄位接在後面
+pub fn event_line(kind: &str, fields: Value) -> String {
+    let head = format!(
+        "{{\"event\":{},\"at\":{}",
+        Value::from(kind),
+        Value::from(format_iso8601(now_unix()))
+    );
+    match fields {
+        Value::Object(fields) if !fields.is_empty() => {
+            let rest = Value::Object(fields).to_string();
+            format!("{},{}", head, &rest[1..])
+        }
+        _ => format!("{}}}", head),
+    }
+}
+
+pub fn print_message(args: fmt::Arguments) {
+    match MODE.load(Ordering::Relaxed) {
+        STDOUT => println!("{}", args),
+        STDERR => eprintln!("{}", args),
+        _ => log_event("log", args),
+    }
+}
+
+pub fn print_warning(args: fmt::Arguments) {
+    if is_json_lines() {
+        log_event("warning", args);
+    } else {
+        eprintln!("{}", args);
+    }
+}
+
+/// 訊息前後的空行只是排版用，事件中去掉；多行訊息保留在同一個 message 裡
+fn log_event(kind: &str, args: fmt::Arguments) {
+    let text = args.to_string();
+    let text = text.trim();
+    if !text.is_empty() {
+        event(kind, serde_json::json!({ "message": text }));
+    }
+}
+
+/// 同 println!，stdout 保留給報表時改寫到 stderr
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::output::print_message(format_args!($($arg)*))
+    };
+}
+
+/// 同 eprintln!，JSON 事件模式下改為 warning 事件
+#[macro_export]
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::output::print_warning(format_args!($($arg)*))
+    };
+}
+
+pub use crate::{say, warning};
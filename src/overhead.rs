@@ -0,0 +1,94 @@
+//! 依 encoding 估算集合類型的結構開銷（Redis 7，64-bit）
+//!
+//! MEMORY USAGE 只給總數；這裡用各 encoding 的資料結構大小推估其中有多少是
+//! dictEntry、bucket、skiplist node、robj 等結構，剩下的視為使用者資料。
+//! 元素長度未知，sds 一律以 sdshdr8 計，結果是近似值，適合比較不同 schema 的差異。
+
+use crate::report::OverheadReport;
+use crate::stats::KeyTypeCode;
+use std::collections::BTreeMap;
+
+const KEY_OVERHEAD: u64 = 16 + 24 + SDS_HDR; // robj + 主字典的 dictEntry + key 的 sds header
+const SDS_HDR: u64 = 4; // sdshdr8 3 bytes + 結尾 \0
+const DICT: u64 = 56; // dict 結構本身
+const DICT_ENTRY: u64 = 24;
+const BUCKET: u64 = 8; // bucket 陣列每格一個指標，大小為 2 的次方
+const LISTPACK_HDR: u64 = 7; // total bytes 4 + 元素數 2 + 結尾 1
+const LISTPACK_ENTRY: u64 = 2; // 短元素的 encoding byte + backlen
+const INTSET_HDR: u64 = 8;
+const QUICKLIST: u64 = 40;
+const QUICKLIST_NODE: u64 = 32;
+const QUICKLIST_NODE_BYTES: u64 = 8 * 1024; // list-max-listpack-size -2
+const ZSKIPLIST: u64 = 32 + 24 + 32 * 16; // zskiplist + 32 層的 header node
+const SKIPLIST_NODE: u64 = 8 + 8 + 21; // ele 指標 + backward + 平均 1.33 層 × 16（score 算資料）
+
+/// 單一 key 的結構開銷（bytes）；不認得的 encoding（stream 等）回傳 None
+pub fn structure_bytes(t: KeyTypeCode, encoding: &str, elements: u64, mem: u64) -> Option<u64> {
+    let n = elements;
+    let buckets = BUCKET * n.max(1).next_power_of_two();
+    let bytes = match (t, encoding) {
+        (KeyTypeCode::Hash | KeyTypeCode::ZSet, "listpack" | "ziplist") => {
+            LISTPACK_HDR + n * 2 * LISTPACK_ENTRY // field + value / member + score
+        }
+        (KeyTypeCode::List | KeyTypeCode::Set, "listpack" | "ziplist") => {
+            LISTPACK_HDR + n * LISTPACK_ENTRY
+        }
+        (KeyTypeCode::Set, "intset") => INTSET_HDR,
+        (KeyTypeCode::List, "quicklist") => {
+            let nodes = mem.div_ceil(QUICKLIST_NODE_BYTES).max(1);
+            QUICKLIST + nodes * (QUICKLIST_NODE + LISTPACK_HDR) + n * LISTPACK_ENTRY
+        }
+        (KeyTypeCode::Set, "hashtable") => DICT + buckets + n * (DICT_ENTRY + SDS_HDR),
+        (KeyTypeCode::Hash, "hashtable") => DICT + buckets + n * (DICT_ENTRY + 2 * SDS_HDR),
+        (KeyTypeCode::ZSet, "skiplist") => {
+            DICT + buckets + ZSKIPLIST + n * (DICT_ENTRY + SKIPLIST_NODE + SDS_HDR)
+        }
+        _ => return None,
+    };
+    Some((KEY_OVERHEAD + bytes).min(mem))
+}
+
+/// 依 (類型, encoding) 彙總
+#[derive(Default)]
+pub struct OverheadStats {
+    map: BTreeMap<(u8, String), OverheadReport>,
+}
+
+impl OverheadStats {
+    pub fn add_key(&mut self, t: KeyTypeCode, encoding: &str, elements: u64, mem: u64) {
+        let Some(overhead) = structure_bytes(t, encoding, elements, mem) else {
+            return;
+        };
+        let e = self
+            .map
+            .entry((t as u8, encoding.to_owned()))
+            .or_insert_with(|| OverheadReport {
+                type_code: t,
+                encoding: encoding.to_owned(),
+                keys: 0,
+                elements: 0,
+                total_mem: 0,
+                overhead: 0,
+            });
+        e.keys += 1;
+        e.elements += elements;
+        e.total_mem += mem;
+        e.overhead += overhead;
+    }
+
+    /// 依類型順序，同類型內依記憶體 desc
+    pub fn into_reports(self) -> Vec<OverheadReport> {
+        let mut v: Vec<OverheadReport> = self.map.into_values().collect();
+        sort_reports(&mut v);
+        v
+    }
+}
+
+pub fn sort_reports(v: &mut [OverheadReport]) {
+    v.sort_by(|a, b| {
+        (a.type_code as u8)
+            .cmp(&(b.type_code as u8))
+            .then_with(|| b.total_mem.cmp(&a.total_mem))
+            .then_with(|| a.encoding.cmp(&b.encoding))
+    });
+}
@@ -0,0 +1,148 @@
+use crate::cleanup::glob_match;
+use crate::report::OwnerDetail;
+use crate::scan::{ScanConfig, prefix_reports, type_reports};
+use crate::stats::{AllStats, ExpiryTimeline, KeyExtra, KeyTypeCode, PrefixStats, SizeHistogram};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+pub const UNOWNED: &str = "(未歸屬)";
+
+/// key pattern → 負責團隊，依檔案順序比對，第一個符合的生效
+#[derive(Debug, Default)]
+pub struct OwnerRules {
+    rules: Vec<(String, String)>, // (pattern, owner)
+}
+
+impl OwnerRules {
+    /// 讀取規則檔：每行 `pattern = owner`，例如 `session:* = team-auth`，`#` 之後為註解
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| format!("無法讀取擁有者規則 {}: {}", path.display(), e))?;
+
+        let mut rules = Vec::new();
+        for (no, line) in raw.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (pattern, owner) = line
+                .rsplit_once('=')
+                .map(|(p, o)| (p.trim(), o.trim()))
+                .filter(|(p, o)| !p.is_empty() && !o.is_empty())
+                .ok_or_else(|| {
+                    format!(
+                        "{} 第 {} 行: 格式應為 PATTERN = OWNER",
+                        path.display(),
+                        no + 1
+                    )
+                })?;
+            rules.push((pattern.to_string(), owner.to_string()));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn owner_of(&self, key: &str) -> &str {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, key))
+            .map(|(_, owner)| owner.as_str())
+            .unwrap_or(UNOWNED)
+    }
+}
+
+/// 依擁有者彙總的筆數與記憶體
+#[derive(Default)]
+pub struct OwnerStats {
+    map: HashMap<String, (u64, u64)>, // owner -> (count, total_mem)
+}
+
+impl OwnerStats {
+    pub fn add_key(&mut self, owner: &str, mem: u64) {
+        if let Some(e) = self.map.get_mut(owner) {
+            e.0 += 1;
+            e.1 += mem;
+            return;
+        }
+        self.map.insert(owner.to_owned(), (1, mem));
+    }
+
+    /// (owner, count, total_mem)，依記憶體由大到小
+    pub fn into_sorted(self) -> Vec<(String, u64, u64)> {
+        let mut v: Vec<(String, u64, u64)> = self
+            .map
+            .into_iter()
+            .map(|(owner, (count, mem))| (owner, count, mem))
+            .collect();
+        v.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        v
+    }
+}
+
+/// --split-report-by owner：每個擁有者各自的類型 Top N、前綴、到期時間軸與 key 名稱長度
+pub struct OwnerDetails {
+    sep: char,
+    depth: usize,
+    keep_top: bool,
+    collect_ttl: bool,
+    map: HashMap<String, OwnerAcc>,
+}
+
+struct OwnerAcc {
+    stats: AllStats,
+    prefixes: PrefixStats,
+    expiry: Option<ExpiryTimeline>,
+    key_names: SizeHistogram,
+}
+
+impl OwnerDetails {
+    pub fn new(cfg: &ScanConfig) -> Self {
+        Self {
+            sep: cfg.prefix_sep,
+            depth: cfg.prefix_depth,
+            keep_top: cfg.prefix_tables > 0,
+            collect_ttl: cfg.collect_ttl,
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn add_key(&mut self, owner: &str, key: &str, t: KeyTypeCode, mem: u64, extra: &KeyExtra) {
+        if !self.map.contains_key(owner) {
+            let acc = OwnerAcc {
+                stats: AllStats::new(),
+                prefixes: PrefixStats::new(self.sep, self.depth, self.keep_top),
+                expiry: self.collect_ttl.then(ExpiryTimeline::new),
+                key_names: SizeHistogram::default(),
+            };
+            self.map.insert(owner.to_owned(), acc);
+        }
+        let acc = self.map.get_mut(owner).expect("已建立");
+        acc.stats.get_mut(t).add_key(mem, key, extra);
+        acc.prefixes.add_key(key, mem, t);
+        acc.key_names.add(key.len() as u64);
+        if let (Some(expiry), Some(pttl)) = (&mut acc.expiry, extra.ttl_ms) {
+            expiry.add_key(pttl, mem);
+        }
+    }
+
+    /// 依記憶體由大到小
+    pub fn into_reports(self, prefix_tables: usize) -> Vec<OwnerDetail> {
+        let mut v: Vec<OwnerDetail> = self
+            .map
+            .into_iter()
+            .map(|(owner, acc)| OwnerDetail {
+                owner,
+                types: type_reports(&acc.stats),
+                prefixes: prefix_reports(acc.prefixes, prefix_tables),
+                expiry: acc.expiry,
+                key_names: acc.key_names,
+            })
+            .collect();
+        v.sort_by(|a, b| {
+            b.total_mem()
+                .cmp(&a.total_mem())
+                .then_with(|| a.owner.cmp(&b.owner))
+        });
+        v
+    }
+}
@@ -0,0 +1,438 @@
+//! --dry-run：連線並做掃描前的檢查，列出這次掃描會送出的指令、預估的耗時與負載，不實際掃描
+//!
+//! 指令數以 DBSIZE 乘上每個 key 的指令推算；耗時以一小批 key 的試量測外推，
+//! 實際掃描會因 key 大小分布、網路與當下負載而不同。
+
+use crate::conn::RedisConn;
+use crate::format::{Decimal, format_int, format_secs};
+use crate::guard::{GUARD_EVERY, RateLimiter};
+use crate::impact::PING_EVERY;
+use crate::intstr::MAX_INT_LEN;
+use crate::latency::ESTIMATE_SAMPLES;
+use crate::role::ROLE_EVERY;
+use crate::scan::{ScanConfig, fetch_key_meta_batch, match_eviction_policy};
+use crate::server::fetch_info;
+use crate::stats::{KeyTypeCode, TOP_N};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+const TRIAL_KEYS: u64 = 200; // 試量測的 key 數，用來估計每個 key 的耗時
+const DEEP_CMDS_PER_KEY: u64 = 5; // MEMORY USAGE SAMPLES 0 + PTTL + OBJECT ENCODING + 長度 + 元素抽樣
+const SERVER_INFO_CMDS: u64 = 6; // 掃描後的 MEMORY STATS / CLIENT LIST / FUNCTION LIST / INFO memory / stats / keyspace
+
+/// 預計送出的一種指令
+pub struct PlannedCommand {
+    pub command: String,
+    pub per_key: Option<&'static str>, // 每個 key 送幾次；None = 與 key 數無關
+    pub calls: u64,
+    pub at_most: bool, // 實際次數取決於資料（例如只有短 string 才送），calls 為上限
+}
+
+/// 單一節點的掃描計畫
+pub struct Plan {
+    pub target: String,
+    pub redis_version: Option<String>,
+    pub total_keys: u64,
+    pub measured_keys: u64,             // 扣掉 --sample-rate 沒抽中的 key
+    pub skipped: Vec<(String, String)>, // 依 maxmemory-policy 自動關閉的指令
+    pub describe: String,               // 每個 key 的指令，例如 "MEMORY USAGE + TYPE + PTTL"
+    pub commands: Vec<PlannedCommand>,
+    pub round_trips: u64,           // pipeline 往返次數（不含 SCAN）
+    pub batch_size: usize,          // 考慮 --max-keys-per-sec 之後的每批 key 數
+    pub trial_keys: u64,            // 實際試量測的 key 數；0 = 資料庫是空的或試量測失敗
+    pub key_cost: Option<Duration>, // 試量測得到的每個 key 耗時（含 SCAN）
+    pub work: Duration,             // 送指令與等待回覆的時間
+    pub throttle: Duration,         // --throttle 的暫停合計
+    pub duration: Duration,         // 預估的總耗時
+    pub ops_now: Option<u64>,       // 目前的 instantaneous_ops_per_sec
+}
+
+impl Plan {
+    pub fn total_commands(&self) -> u64 {
+        self.commands.iter().map(|c| c.calls).sum()
+    }
+
+    /// 掃描期間本工具平均每秒送出的指令數
+    pub fn commands_per_sec(&self) -> f64 {
+        self.total_commands() as f64 / self.duration.as_secs_f64().max(1.0)
+    }
+}
+
+/// 掃描前的檢查（INFO、maxmemory-policy、DBSIZE）加上一小批 key 的試量測
+pub fn plan(con: &mut RedisConn, target: &str, cfg: &ScanConfig) -> redis::RedisResult<Plan> {
+    let (cfg, skipped) = match_eviction_policy(con, cfg);
+    let redis_version = fetch_info(con, "server")
+        .ok()
+        .and_then(|m| m.get("redis_version").cloned());
+    let ops_now = fetch_info(con, "stats")
+        .ok()
+        .and_then(|m| m.get("instantaneous_ops_per_sec")?.parse().ok());
+    let total_keys: u64 = redis::cmd("DBSIZE").query(con)?;
+    let measured_keys = match cfg.sample_rate {
+        Some(rate) => (total_keys as f64 * rate).round() as u64,
+        None => total_keys,
+    };
+    let batch_size = RateLimiter::new(cfg.max_keys_per_sec)
+        .map_or(cfg.batch_size, |l| l.batch_size(cfg.batch_size))
+        .max(1);
+
+    let (trial_keys, key_cost) = trial(con, &cfg)?;
+    let batches = total_keys.div_ceil(batch_size as u64);
+    let measured_batches = measured_keys.div_ceil(batch_size as u64);
+    // 只有設定的 SAMPLES 比估計用的貴時，--estimate-above 才需要先查元素數
+    let estimate_probe =
+        cfg.estimate_above.is_some() && cfg.samples.is_some_and(|n| n == 0 || n > ESTIMATE_SAMPLES);
+
+    let mut commands = Vec::new();
+    let mut round_trips = 0;
+    let mut push = |command: String, per_key: Option<&'static str>, calls: u64, at_most: bool| {
+        commands.push(PlannedCommand {
+            command,
+            per_key,
+            calls,
+            at_most,
+        })
+    };
+    push(
+        format!("SCAN <cursor> COUNT {}", cfg.scan_count),
+        None,
+        total_keys.div_ceil(cfg.scan_count.max(1)).max(1),
+        false,
+    );
+    if cfg.stratify.is_some() && cfg.sample_rate.is_some() {
+        push(
+            "TYPE <key>（分層抽樣普查）".into(),
+            Some("1"),
+            total_keys,
+            false,
+        );
+        round_trips += total_keys.div_ceil(cfg.scan_count.max(1)).max(1);
+    }
+    let filtered = cfg.measure.is_active();
+    if filtered {
+        push("TYPE <key>（篩選）".into(), Some("1"), measured_keys, false);
+        round_trips += measured_batches;
+        if cfg.measure.min_elements.is_some() {
+            push(
+                "STRLEN / LLEN / HLEN… <key>（篩選）".into(),
+                Some("≤1"),
+                measured_keys,
+                true,
+            );
+            round_trips += measured_batches;
+        }
+    }
+    if estimate_probe {
+        push(
+            "TYPE <key>（--estimate-above）".into(),
+            Some("1"),
+            measured_keys,
+            filtered,
+        );
+        push(
+            "LLEN / HLEN / SCARD… <key>（--estimate-above）".into(),
+            Some("≤1"),
+            measured_keys,
+            true,
+        );
+        round_trips += measured_batches * 2;
+    }
+    let memory_usage = match cfg.samples {
+        Some(n) => format!("MEMORY USAGE <key> SAMPLES {}", n),
+        None => "MEMORY USAGE <key>".to_string(),
+    };
+    let mut per_key = vec![(memory_usage, true), ("TYPE <key>".to_string(), true)];
+    per_key.push(("PTTL <key>".into(), cfg.collect_ttl));
+    per_key.push(("OBJECT IDLETIME <key>".into(), cfg.collect_idle));
+    per_key.push(("OBJECT ENCODING <key>".into(), cfg.collect_encoding));
+    per_key.push(("OBJECT FREQ <key>".into(), cfg.collect_freq));
+    for (command, enabled) in per_key {
+        if enabled {
+            push(command, Some("1"), measured_keys, filtered);
+        }
+    }
+    round_trips += measured_batches;
+    if cfg.collect_cardinality {
+        push(
+            "STRLEN / LLEN / HLEN… <key>".into(),
+            Some("1"),
+            measured_keys,
+            filtered,
+        );
+        round_trips += measured_batches;
+    }
+    if cfg.int_strings && cfg.collect_encoding {
+        push(
+            format!("GETRANGE <key> 0 {}（短 string）", MAX_INT_LEN),
+            Some("≤1"),
+            measured_keys,
+            true,
+        );
+        round_trips += measured_batches;
+    }
+
+    // 耗時：試量測的每 key 成本外推，加上 throttle；有 --max-keys-per-sec 時不會比限速更快
+    let work = key_cost.map_or(Duration::ZERO, |c| {
+        c * measured_keys.min(u32::MAX as u64) as u32
+    });
+    let throttle = cfg.throttle * batches.min(u32::MAX as u64) as u32;
+    let limited = cfg
+        .max_keys_per_sec
+        .filter(|&l| l > 0)
+        .map_or(Duration::ZERO, |l| {
+            Duration::from_secs_f64(measured_keys as f64 / l as f64)
+        });
+    let probes = cfg.impact_probe.map_or(Duration::ZERO, |w| w * 2);
+    let duration = (work + throttle).max(limited) + probes;
+
+    if cfg.guard.is_enabled() {
+        let calls = (duration.as_secs_f64() / GUARD_EVERY.as_secs_f64()).ceil() as u64;
+        push(
+            format!("INFO（負載保護，每 {:?}）", GUARD_EVERY),
+            None,
+            calls,
+            false,
+        );
+    }
+    // 開始、結束各一次，掃描中每 ROLE_EVERY 一次
+    push(
+        format!("INFO（角色檢查，每 {:?}）", ROLE_EVERY),
+        None,
+        (duration.as_secs_f64() / ROLE_EVERY.as_secs_f64()).ceil() as u64 + 2,
+        false,
+    );
+    if let Some(window) = cfg.impact_probe {
+        let pings = (window.as_millis() / PING_EVERY.as_millis()) as u64 * 2;
+        push(
+            format!("PING（--impact-probe {:?} × 2）", window),
+            None,
+            pings,
+            false,
+        );
+    }
+    let top_keys = (TOP_N * KeyTypeCode::all().len()) as u64;
+    if cfg.deep {
+        push(
+            format!(
+                "第二階段 MEMORY USAGE SAMPLES 0 / PTTL / 元素抽樣（最多 {} 個 key）",
+                top_keys
+            ),
+            None,
+            top_keys * DEEP_CMDS_PER_KEY,
+            true,
+        );
+    }
+    if cfg.preview.is_some() {
+        push(
+            "值預覽 GETRANGE / LRANGE / HRANDFIELD…".into(),
+            None,
+            top_keys,
+            true,
+        );
+    }
+    push(
+        "MEMORY STATS / CLIENT LIST / FUNCTION LIST / INFO".into(),
+        None,
+        SERVER_INFO_CMDS,
+        false,
+    );
+
+    Ok(Plan {
+        target: target.to_string(),
+        redis_version,
+        total_keys,
+        measured_keys,
+        skipped,
+        describe: cfg.describe_commands(),
+        commands,
+        round_trips,
+        batch_size,
+        trial_keys,
+        key_cost,
+        work,
+        throttle,
+        duration,
+        ops_now,
+    })
+}
+
+/// 以一次 SCAN 取回的 key 試跑第一階段的 pipeline，回傳（試量測 key 數, 每 key 耗時）
+///
+/// 只送掃描本身也會送的唯讀指令；小批次的往返比例較高，估出來的耗時偏保守。
+fn trial(con: &mut RedisConn, cfg: &ScanConfig) -> redis::RedisResult<(u64, Option<Duration>)> {
+    let started = Instant::now();
+    let (_, mut keys): (u64, Vec<String>) = redis::cmd("SCAN")
+        .arg(0)
+        .arg("COUNT")
+        .arg(TRIAL_KEYS)
+        .query(con)?;
+    keys.truncate(TRIAL_KEYS as usize);
+    if keys.is_empty() {
+        return Ok((0, None));
+    }
+    if fetch_key_meta_batch(con, &keys, cfg).is_err() {
+        return Ok((0, None));
+    }
+    Ok((
+        keys.len() as u64,
+        Some(started.elapsed() / keys.len() as u32),
+    ))
+}
+
+pub fn render_plans(
+    plans: &[Plan],
+    cfg: &ScanConfig,
+    parallel: usize,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for p in plans {
+        render_plan(p, cfg, out)?;
+    }
+    if plans.len() > 1 {
+        let commands: u64 = plans.iter().map(Plan::total_commands).sum();
+        let sequential: Duration = plans.iter().map(|p| p.duration).sum();
+        let longest = plans.iter().map(|p| p.duration).max().unwrap_or_default();
+        let wall = if parallel > 1 {
+            longest.max(sequential / parallel as u32)
+        } else {
+            sequential
+        };
+        writeln!(out, "{}", "=".repeat(120))?;
+        writeln!(
+            out,
+            "📋 合計 {} 個節點: {} keys，約 {} 個指令，預估耗時 {}（--parallel {}）",
+            plans.len(),
+            format_int(plans.iter().map(|p| p.total_keys).sum()),
+            format_int(commands),
+            format_duration(wall),
+            parallel
+        )?;
+        writeln!(out, "{}\n", "=".repeat(120))?;
+    }
+    writeln!(
+        out,
+        "（--dry-run：未實際掃描；拿掉 --dry-run 即以相同參數執行）"
+    )?;
+    Ok(())
+}
+
+fn render_plan(p: &Plan, cfg: &ScanConfig, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "🧪 Dry run: {}{}",
+        p.target,
+        p.redis_version
+            .as_ref()
+            .map(|v| format!("（Redis {}）", v))
+            .unwrap_or_default()
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(out, "DBSIZE: {} keys", format_int(p.total_keys))?;
+    if let Some(rate) = cfg.sample_rate {
+        writeln!(
+            out,
+            "--sample-rate {:.1}%：預計量測 {} keys{}",
+            Decimal(rate * 100.0),
+            format_int(p.measured_keys),
+            cfg.stratify
+                .map(|n| format!("（依類型分層，每個類型另外至少量測 {} 個）", format_int(n)))
+                .unwrap_or_default()
+        )?;
+    }
+    for (what, why) in &p.skipped {
+        writeln!(out, "不收集 {}: {}", what, why)?;
+    }
+    writeln!(out, "每個 key: {}", p.describe)?;
+
+    writeln!(out, "\n{:<64} {:>8} {:>16}", "指令", "每 key", "預估次數")?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for c in &p.commands {
+        writeln!(
+            out,
+            "{:<64} {:>8} {:>16}",
+            c.command,
+            c.per_key.unwrap_or("-"),
+            if c.at_most {
+                format!("最多 {}", format_int(c.calls))
+            } else {
+                format_int(c.calls)
+            }
+        )?;
+    }
+    writeln!(out, "{}", "-".repeat(120))?;
+    writeln!(
+        out,
+        "{:<64} {:>8} {:>16}",
+        "合計",
+        "",
+        format_int(p.total_commands())
+    )?;
+    writeln!(
+        out,
+        "pipeline: 每批 {} keys，約 {} 次往返{}",
+        format_int(p.batch_size as u64),
+        format_int(p.round_trips),
+        if cfg.concurrency > 1 {
+            format!("（同時 {} 批，共用一條 multiplexed 連線）", cfg.concurrency)
+        } else {
+            String::new()
+        }
+    )?;
+    if let Some(index) = &cfg.index_to {
+        writeln!(
+            out,
+            "另外寫入 --index-to（不在上表）: 每 key ZADD × 2 + HSET{}，約 {} 個指令",
+            if index.ttl.is_some() { " + EXPIRE" } else { "" },
+            format_int(p.measured_keys * (3 + index.ttl.is_some() as u64))
+        )?;
+    }
+
+    match p.key_cost {
+        Some(cost) => writeln!(
+            out,
+            "\n預估耗時: {}（試量測 {} keys：每 key {} µs；送指令 {} + throttle {}）",
+            format_duration(p.duration),
+            p.trial_keys,
+            cost.as_micros(),
+            format_duration(p.work),
+            format_duration(p.throttle)
+        )?,
+        None => writeln!(
+            out,
+            "\n預估耗時: {}（資料庫是空的或試量測失敗，只計入 throttle / 限速）",
+            format_duration(p.duration)
+        )?,
+    }
+    if let Some(limit) = cfg.max_keys_per_sec {
+        writeln!(out, "限速: --max-keys-per-sec {}", format_int(limit))?;
+    }
+    writeln!(
+        out,
+        "預估負載: 單一連線，平均約 {} 指令/s{}",
+        format_int(p.commands_per_sec().round() as u64),
+        p.ops_now
+            .map(|ops| format!("（伺服器目前 {} ops/s）", format_int(ops)))
+            .unwrap_or_default()
+    )?;
+    writeln!(
+        out,
+        "負載保護: {}",
+        if cfg.guard.is_enabled() {
+            "已啟用，超過門檻時暫停（暫停時間不計入預估）"
+        } else {
+            "未設定（可用 --max-ops / --max-cpu 等在負載過高時暫停）"
+        }
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// 一分鐘以內顯示到小數一位（小資料庫的預估多半不到 1 秒）
+fn format_duration(d: Duration) -> String {
+    if d < Duration::from_secs(60) {
+        format!("{:.1}s", Decimal(d.as_secs_f64()))
+    } else {
+        format_secs(d.as_secs())
+    }
+}
@@ -0,0 +1,296 @@
+use crate::conn::RedisConn;
+use crate::format::truncate_key;
+use crate::report::Report;
+use crate::scan::send_pipeline;
+use crate::stats::KeyTypeCode;
+use redis::Value;
+use serde::{Deserialize, Serialize};
+
+const PREVIEW_BYTES: isize = 128; // string 取 GETRANGE 0..128
+const PREVIEW_ITEMS: usize = 3; // 集合類型取幾個元素
+const PREVIEW_CHARS: usize = 120; // 整段預覽最多幾個字元
+const ITEM_CHARS: usize = 40; // 單一欄位 / 元素最多幾個字元
+const MIN_SCORE_BYTES: usize = 16; // 樣本太少時熵沒有參考價值
+const HIGH_ENTROPY: f64 = 0.9; // 相對熵超過此值：已壓縮或加密
+const LOW_ENTROPY: f64 = 0.75; // 文字且相對熵低於此值：壓縮效益高
+
+/// 依樣本判斷值的可壓縮程度
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    Gzip,
+    Zlib,
+    Zstd,
+    Lz4,
+    Snappy,
+    HighEntropy, // 已壓縮或加密，再壓縮沒有效益
+    Json,        // JSON，相對熵低
+    Text,        // 一般文字，相對熵低
+    Mixed,       // 介於中間（protobuf / msgpack 等二進位，或熵偏高的文字）
+}
+
+impl ValueKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ValueKind::Gzip => "已壓縮 (gzip)",
+            ValueKind::Zlib => "已壓縮 (zlib)",
+            ValueKind::Zstd => "已壓縮 (zstd)",
+            ValueKind::Lz4 => "已壓縮 (lz4)",
+            ValueKind::Snappy => "已壓縮 (snappy)",
+            ValueKind::HighEntropy => "已壓縮或加密，不必再壓縮",
+            ValueKind::Json => "JSON，壓縮效益高",
+            ValueKind::Text => "文字，壓縮效益高",
+            ValueKind::Mixed => "壓縮效益中等",
+        }
+    }
+}
+
+/// 值樣本的 Shannon 熵與分類
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ValueScore {
+    pub entropy: f64, // bits / byte
+    pub kind: ValueKind,
+}
+
+/// 值預覽的遮蔽方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Redact {
+    /// 不遮蔽（只在確定資料不含個資時使用）
+    None,
+    /// 數字換成 #，保留欄位名稱與文字（遮住 id、電話、卡號）
+    #[default]
+    Digits,
+    /// 只保留結構：英文字母換成 a / A、數字換成 9，其餘符號不變
+    Shape,
+}
+
+impl Redact {
+    fn apply(self, s: &str) -> String {
+        match self {
+            Redact::None => s.to_string(),
+            Redact::Digits => s
+                .chars()
+                .map(|c| if c.is_ascii_digit() { '#' } else { c })
+                .collect(),
+            Redact::Shape => s
+                .chars()
+                .map(|c| match c {
+                    'a'..='z' => 'a',
+                    'A'..='Z' => 'A',
+                    '0'..='9' => '9',
+                    c if c.is_alphanumeric() => '*', // 非 ASCII 文字
+                    c => c,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// 替每個類型 Top N 的 key 抓一小段值，寫入 TopKey.preview
+///
+/// 只讀取少量資料（GETRANGE / HRANDFIELD / ZRANDMEMBER / SRANDMEMBER / LRANGE / XRANGE COUNT 1），
+/// 不會把整個大 key 傳回來。個別指令失敗（例如 6.2 以前沒有 HRANDFIELD）時略過該 key，
+/// 回傳第一個錯誤訊息。
+pub fn fetch_previews(con: &mut RedisConn, report: &mut Report, redact: Redact) -> Option<String> {
+    let mut pipe = redis::pipe();
+    let mut count = 0;
+    for tr in &report.types {
+        for k in &tr.top {
+            push_preview_cmd(&mut pipe, tr.type_code, &k.key);
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return None;
+    }
+
+    let values = match send_pipeline(con, &pipe, count) {
+        Ok(v) => v,
+        Err(e) => return Some(e.to_string()),
+    };
+
+    let mut first_error = None;
+    let mut values = values.into_iter();
+    for tr in &mut report.types {
+        for k in &mut tr.top {
+            let Some(v) = values.next() else {
+                break;
+            };
+            if let Value::ServerError(e) = &v {
+                first_error.get_or_insert_with(|| format!("{:?}", e));
+                continue;
+            }
+            k.preview = format_preview(tr.type_code, &v).map(|p| redact.apply(&p));
+            let mut raw = Vec::new();
+            collect_bytes(&v, &mut raw);
+            k.score = score(&raw);
+        }
+    }
+    first_error
+}
+
+fn push_preview_cmd(pipe: &mut redis::Pipeline, t: KeyTypeCode, key: &str) {
+    let n = PREVIEW_ITEMS;
+    match t {
+        KeyTypeCode::String => pipe.cmd("GETRANGE").arg(key).arg(0).arg(PREVIEW_BYTES - 1),
+        KeyTypeCode::List => pipe.cmd("LRANGE").arg(key).arg(0).arg(n - 1),
+        KeyTypeCode::Set => pipe.cmd("SRANDMEMBER").arg(key).arg(n),
+        KeyTypeCode::ZSet => pipe.cmd("ZRANDMEMBER").arg(key).arg(n).arg("WITHSCORES"),
+        KeyTypeCode::Hash => pipe.cmd("HRANDFIELD").arg(key).arg(n).arg("WITHVALUES"),
+        KeyTypeCode::Stream => pipe
+            .cmd("XRANGE")
+            .arg(key)
+            .arg("-")
+            .arg("+")
+            .arg("COUNT")
+            .arg(1),
+    };
+}
+
+/// 依類型排成一行：hash `f=v, …`、zset `member (score), …`、stream `id: f=v, …`
+fn format_preview(t: KeyTypeCode, v: &Value) -> Option<String> {
+    // string 只有一段，可以顯示較長
+    let max = if t == KeyTypeCode::String {
+        PREVIEW_CHARS
+    } else {
+        ITEM_CHARS
+    };
+    let mut leaves = Vec::new();
+    collect_leaves(v, max, &mut leaves);
+    if leaves.is_empty() {
+        return None;
+    }
+
+    let text = match t {
+        KeyTypeCode::String | KeyTypeCode::List | KeyTypeCode::Set => leaves.join(", "),
+        KeyTypeCode::Hash => pairs(&leaves, |f, v| format!("{}={}", f, v)),
+        KeyTypeCode::ZSet => pairs(&leaves, |m, s| format!("{} ({})", m, s)),
+        KeyTypeCode::Stream => format!(
+            "{}: {}",
+            leaves[0],
+            pairs(&leaves[1..], |f, v| format!("{}={}", f, v))
+        ),
+    };
+    Some(truncate_key(&text, PREVIEW_CHARS))
+}
+
+fn pairs(leaves: &[String], f: impl Fn(&str, &str) -> String) -> String {
+    leaves
+        .chunks(2)
+        .map(|p| f(&p[0], p.get(1).map(String::as_str).unwrap_or("")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 回覆中所有 BulkString 的原始 bytes（遮蔽前），用來計算熵
+fn collect_bytes(v: &Value, out: &mut Vec<u8>) {
+    match v {
+        Value::BulkString(b) => out.extend_from_slice(b),
+        Value::Array(items) | Value::Set(items) => {
+            for item in items {
+                collect_bytes(item, out);
+            }
+        }
+        Value::Map(pairs) => {
+            for (k, v) in pairs {
+                collect_bytes(k, out);
+                collect_bytes(v, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 先看壓縮格式的 magic bytes，再看相對熵（熵 ÷ 樣本長度可達到的最大熵）
+///
+/// 樣本只有一百多 bytes，隨機資料的熵也到不了 8 bits/byte，所以用相對值判斷。
+fn score(b: &[u8]) -> Option<ValueScore> {
+    if b.len() < MIN_SCORE_BYTES {
+        return None;
+    }
+
+    let mut freq = [0u64; 256];
+    for &x in b {
+        freq[x as usize] += 1;
+    }
+    let n = b.len() as f64;
+    let entropy: f64 = freq
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / n;
+            -p * p.log2()
+        })
+        .sum();
+    let relative = entropy / n.min(256.0).log2();
+
+    let kind = if b.starts_with(&[0x1f, 0x8b]) {
+        ValueKind::Gzip
+    } else if b.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        ValueKind::Zstd
+    } else if b.starts_with(&[0x04, 0x22, 0x4d, 0x18]) {
+        ValueKind::Lz4
+    } else if b.starts_with(b"\xff\x06\x00\x00sNaPpY") {
+        ValueKind::Snappy
+    } else if b[0] == 0x78 && matches!(b[1], 0x01 | 0x5e | 0x9c | 0xda) {
+        ValueKind::Zlib
+    } else if relative >= HIGH_ENTROPY {
+        ValueKind::HighEntropy
+    } else if std::str::from_utf8(b).is_ok()
+        && matches!(b.trim_ascii_start().first(), Some(b'{' | b'['))
+    {
+        // JSON 的欄位名稱重複出現，樣本短時熵看起來偏高，實際壓縮效益仍然很好
+        ValueKind::Json
+    } else if relative < LOW_ENTROPY && std::str::from_utf8(b).is_ok() {
+        ValueKind::Text
+    } else {
+        ValueKind::Mixed
+    };
+    Some(ValueScore { entropy, kind })
+}
+
+/// 攤平巢狀回覆（RESP2 的扁平陣列與 RESP3 的巢狀陣列都一樣處理）
+fn collect_leaves(v: &Value, max: usize, out: &mut Vec<String>) {
+    match v {
+        Value::BulkString(b) => out.push(printable(b, max)),
+        Value::SimpleString(s) => out.push(truncate_key(s, max)),
+        Value::Int(i) => out.push(i.to_string()),
+        Value::Double(d) => out.push(d.to_string()),
+        Value::Array(items) | Value::Set(items) => {
+            for item in items {
+                collect_leaves(item, max, out);
+            }
+        }
+        Value::Map(pairs) => {
+            for (k, v) in pairs {
+                collect_leaves(k, max, out);
+                collect_leaves(v, max, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 文字直接顯示（控制字元換成 .），二進位資料只顯示長度與開頭幾個 bytes（可辨識 gzip 等格式）
+///
+/// GETRANGE 可能切在多 byte 字元中間，結尾不完整的 UTF-8 仍視為文字。
+fn printable(b: &[u8], max: usize) -> String {
+    let text = match std::str::from_utf8(b) {
+        Ok(s) => Some(s),
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&b[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    };
+    match text {
+        Some(s) => truncate_key(
+            &s.chars()
+                .map(|c| if c.is_control() { '.' } else { c })
+                .collect::<String>(),
+            max,
+        ),
+        None => {
+            let head: Vec<String> = b.iter().take(8).map(|x| format!("{:02x}", x)).collect();
+            format!("<binary {}B {}>", b.len(), head.join(" "))
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! 進度條：啟用 `progress` feature 時就是 indicatif，否則是同介面的空實作
+//!
+//! 掃描流程不必處處判斷 feature，精簡版只是不顯示進度。
+
+#[cfg(feature = "progress")]
+pub use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+#[cfg(not(feature = "progress"))]
+pub use noop::{MultiProgress, ProgressBar, ProgressStyle};
+
+use crate::format::format_secs;
+use crate::output;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+const ETA_ALPHA: f64 = 0.2; // 速率的指數移動平均權重，越小越平滑
+const EVENT_EVERY: Duration = Duration::from_secs(5); // --oneshot-json 的 progress 事件間隔，避免洗版 log
+
+/// 掃描用的 MultiProgress；--oneshot-json 時不畫進度條（進度改為 stderr 的 JSON 事件）
+#[cfg(feature = "progress")]
+pub fn multi() -> MultiProgress {
+    if output::is_json_lines() {
+        MultiProgress::with_draw_target(indicatif::ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    }
+}
+
+#[cfg(not(feature = "progress"))]
+pub fn multi() -> MultiProgress {
+    MultiProgress::new()
+}
+
+/// 依時間（而非 key 數）更新進度條，並以平滑後的速率估算剩餘時間
+///
+/// 小資料庫也能看到進度在動；大資料庫不會因為每個 batch 都重畫而卡頓。
+pub struct Ticker {
+    interval: Duration,
+    last: Instant,
+    last_pos: u64,
+    rate: Option<f64>,                 // keys/s
+    overall: Option<ProgressBar>,      // 平行掃描多個節點時的合計進度條，跟著累加
+    events: Option<(String, Instant)>, // --oneshot-json：(節點, 上次輸出 progress 事件的時間)
+}
+
+impl Ticker {
+    pub fn new(interval: Duration, overall: Option<ProgressBar>) -> Self {
+        Self {
+            interval,
+            last: Instant::now(),
+            last_pos: 0,
+            rate: None,
+            overall,
+            events: None,
+        }
+    }
+
+    /// 另外以 progress 事件輸出進度；target = None 時不輸出
+    pub fn with_events(mut self, target: Option<String>) -> Self {
+        self.events = target.map(|t| (t, Instant::now()));
+        self
+    }
+
+    /// 掃描結束：補上最後一段進度
+    pub fn finish(&mut self, pb: &ProgressBar, pos: u64, total: u64) {
+        if let Some(overall) = &self.overall {
+            overall.inc(pos.saturating_sub(self.last_pos));
+        }
+        self.last_pos = pos;
+        pb.set_position(pos.min(total));
+        pb.finish_with_message("掃描完成");
+        if let Some((target, _)) = &self.events {
+            progress_event(target, pos, total, None);
+        }
+    }
+
+    pub fn tick(&mut self, pb: &ProgressBar, pos: u64, total: u64) {
+        let dt = self.last.elapsed();
+        if dt < self.interval {
+            return;
+        }
+        let current = pos.saturating_sub(self.last_pos) as f64 / dt.as_secs_f64().max(1e-3);
+        let rate = match self.rate {
+            Some(r) => r + ETA_ALPHA * (current - r),
+            None => current,
+        };
+        self.rate = Some(rate);
+        if let Some(overall) = &self.overall {
+            overall.inc(pos.saturating_sub(self.last_pos));
+        }
+        self.last = Instant::now();
+        self.last_pos = pos;
+
+        pb.set_position(pos.min(total));
+        let eta = (rate > 0.0).then(|| total.saturating_sub(pos) as f64 / rate);
+        if let Some(remaining) = eta {
+            pb.set_message(format!("ETA {}", format_secs(remaining as u64)));
+        }
+        if let Some((target, last)) = &mut self.events
+            && last.elapsed() >= EVENT_EVERY
+        {
+            *last = Instant::now();
+            progress_event(target, pos, total, Some((rate, eta)));
+        }
+    }
+}
+
+/// `{"event":"progress","target":...,"scanned":...,"total":...,"percent":...,"keys_per_sec":...,"eta_secs":...,"final":false}`
+///
+/// total 為掃描開始時的 DBSIZE，掃描中新增的 key 可能讓 scanned 超過 total；rate = None 為掃描結束（含中斷）。
+fn progress_event(target: &str, pos: u64, total: u64, rate: Option<(f64, Option<f64>)>) {
+    let percent = if total == 0 {
+        100.0
+    } else {
+        (pos as f64 / total as f64 * 100.0).min(100.0)
+    };
+    let (keys_per_sec, eta_secs) = match rate {
+        Some((rate, eta)) => (Some(rate.round() as u64), eta.map(|e| e.round() as u64)),
+        None => (None, None),
+    };
+    output::event(
+        "progress",
+        json!({
+            "target": target,
+            "scanned": pos,
+            "total": total,
+            "percent": (percent * 10.0).round() / 10.0,
+            "keys_per_sec": keys_per_sec,
+            "eta_secs": eta_secs,
+            "final": rate.is_none(),
+        }),
+    );
+}
+
+#[cfg(not(feature = "progress"))]
+mod noop {
+    use std::borrow::Cow;
+    use std::convert::Infallible;
+
+    #[derive(Clone, Default)]
+    pub struct MultiProgress;
+
+    impl MultiProgress {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn add(&self, bar: ProgressBar) -> ProgressBar {
+            bar
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct ProgressStyle;
+
+    impl ProgressStyle {
+        pub fn with_template(_: &str) -> Result<Self, Infallible> {
+            Ok(Self)
+        }
+
+        pub fn progress_chars(self, _: &str) -> Self {
+            self
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct ProgressBar;
+
+    impl ProgressBar {
+        pub fn new(_: u64) -> Self {
+            Self
+        }
+
+        pub fn new_spinner() -> Self {
+            Self
+        }
+
+        pub fn set_style(&self, _: ProgressStyle) {}
+
+        pub fn set_position(&self, _: u64) {}
+
+        pub fn set_length(&self, _: u64) {}
+
+        pub fn inc(&self, _: u64) {}
+
+        pub fn inc_length(&self, _: u64) {}
+
+        pub fn set_prefix(&self, _: impl Into<Cow<'static, str>>) {}
+
+        pub fn set_message(&self, _: impl Into<Cow<'static, str>>) {}
+
+        pub fn finish_with_message(&self, _: impl Into<Cow<'static, str>>) {}
+
+        pub fn finish(&self) {}
+
+        pub fn finish_and_clear(&self) {}
+
+        pub fn abandon_with_message(&self, _: impl Into<Cow<'static, str>>) {}
+
+        pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+            f()
+        }
+    }
+}
@@ -0,0 +1,1057 @@
+//! analyze-rdb：直接解析 RDB 檔（例如備份），不連線 Redis 也能產生各類型的 Top N 與記憶體彙總
+//!
+//! 記憶體是依序列化大小推算的估計值：值的 bytes（LZF 壓縮的以解壓後計）加上 key 名稱，
+//! 再加上每個 key 與展開編碼（hashtable / skiplist / linkedlist）每個元素的固定開銷。
+//! listpack / ziplist / intset 在記憶體中的格式與 RDB 相同，估計值最接近 MEMORY USAGE；
+//! 展開編碼的實際用量會因 allocator 與 rehash 狀態而有出入。
+//!
+//! TTL 以 RDB 的 ctime（存檔時間）為基準換算，dump 時已過期的 key 算在「消失」。
+//! module 類型的 key 無法判斷類型，只計數不納入統計。
+
+use crate::binwaste::BinWasteStats;
+use crate::format::{format_int, format_iso8601};
+use crate::report::{Report, SNAPSHOT_VERSION};
+use crate::scan::{prefix_reports, type_reports};
+use crate::stats::{
+    AllStats, ExpiryTimeline, KeyExtra, KeyTypeCode, LongestKeys, PrefixStats, SizeHistogram,
+};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::time::Instant;
+
+const TARGET_PREFIX: &str = "rdb:"; // 報表的 target，例如 rdb:/backup/dump.rdb
+const KEY_OVERHEAD: u64 = 56; // dictEntry + redisObject + key 的 sds 標頭
+const HASHTABLE_ENTRY: u64 = 40; // set / hash 每個元素：dictEntry + sds 標頭（hash 的 field 與 value 各一個）
+const SKIPLIST_ENTRY: u64 = 72; // zset 每個元素：skiplist 節點 + dict entry + score
+const LINKEDLIST_ENTRY: u64 = 40; // 舊版 list 每個元素：節點 + redisObject
+const QUICKLIST_NODE: u64 = 32; // quicklist 每個節點
+
+// RDB opcode
+const OP_SLOT_INFO: u8 = 0xF4;
+const OP_FUNCTION_PRE_GA: u8 = 0xF5;
+const OP_FUNCTION2: u8 = 0xF6;
+const OP_MODULE_AUX: u8 = 0xF7;
+const OP_FREQ: u8 = 0xF8;
+const OP_IDLE: u8 = 0xF9;
+const OP_AUX: u8 = 0xFA;
+const OP_RESIZEDB: u8 = 0xFB;
+const OP_EXPIRETIME_MS: u8 = 0xFC;
+const OP_EXPIRETIME: u8 = 0xFD;
+const OP_SELECTDB: u8 = 0xFE;
+const OP_EOF: u8 = 0xFF;
+
+/// RDB 中單一 key 的序列化資訊
+pub struct RdbKey {
+    pub db: u64,
+    pub key: Vec<u8>,
+    pub type_code: Option<KeyTypeCode>, // module 類型為 None
+    pub encoding: &'static str,
+    pub elements: u64,    // string 為長度，其他類型為元素數（stream 為 entries 數）
+    pub value_bytes: u64, // 值序列化後（解壓縮）的 bytes
+    pub expire_ms: Option<i64>, // 絕對到期時間（Unix ms）
+    pub idle_secs: Option<u64>,
+    pub freq: Option<u64>,
+}
+
+impl RdbKey {
+    /// 依序列化大小推算的記憶體用量
+    pub fn estimated_mem(&self) -> u64 {
+        let per_element = match self.encoding {
+            "hashtable" if self.type_code == Some(KeyTypeCode::Hash) => HASHTABLE_ENTRY * 2,
+            "hashtable" => HASHTABLE_ENTRY,
+            "skiplist" => SKIPLIST_ENTRY,
+            "linkedlist" => LINKEDLIST_ENTRY,
+            _ => 0,
+        };
+        KEY_OVERHEAD + self.key.len() as u64 + self.value_bytes + per_element * self.elements
+    }
+}
+
+/// RDB 的檔頭與輔助欄位
+pub struct RdbInfo {
+    pub version: u32,
+    pub ctime: Option<u64>, // 存檔時間（Unix 秒），AUX ctime
+    pub redis_ver: Option<String>,
+    pub modules: u64, // module 類型的 key 數
+}
+
+/// 逐個 key 解析 RDB；值不整個載入記憶體，只在需要讀 listpack / ziplist 檔頭時解壓
+///
+/// on_key 同時拿到目前為止的 RdbInfo；AUX 欄位都在第一個 key 之前。
+pub fn parse(
+    path: &Path,
+    mut on_key: impl FnMut(RdbKey, &RdbInfo),
+) -> Result<RdbInfo, Box<dyn Error>> {
+    let file = File::open(path).map_err(|e| format!("無法開啟 {}: {}", path.display(), e))?;
+    let size = file.metadata()?.len();
+    let mut r = Reader::new(BufReader::with_capacity(1 << 20, file), size);
+    parse_from(&mut r, &mut on_key)
+        .map_err(|e| format!("{}（位置 {}）: {}", path.display(), r.pos, e).into())
+}
+
+fn parse_from(
+    r: &mut Reader<impl Read>,
+    on_key: &mut impl FnMut(RdbKey, &RdbInfo),
+) -> io::Result<RdbInfo> {
+    let mut magic = [0u8; 9];
+    if r.read_exact(&mut magic).is_err() || &magic[..5] != b"REDIS" {
+        return Err(invalid("不是 RDB 檔（缺少 REDIS 檔頭）"));
+    }
+    let version: u32 = std::str::from_utf8(&magic[5..])
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| invalid("RDB 版本無法解析"))?;
+
+    let mut info = RdbInfo {
+        version,
+        ctime: None,
+        redis_ver: None,
+        modules: 0,
+    };
+    let mut db = 0;
+    let mut expire_ms = None;
+    let mut idle_secs = None;
+    let mut freq = None;
+    loop {
+        let op = r.u8()?;
+        match op {
+            OP_EOF => break,
+            OP_SELECTDB => db = r.len()?,
+            OP_RESIZEDB => {
+                r.len()?;
+                r.len()?;
+            }
+            OP_AUX => {
+                let name = r.string()?;
+                let value = r.string()?;
+                match name.as_slice() {
+                    b"ctime" => info.ctime = String::from_utf8_lossy(&value).parse().ok(),
+                    b"redis-ver" => info.redis_ver = Some(String::from_utf8_lossy(&value).into()),
+                    _ => {}
+                }
+            }
+            OP_EXPIRETIME_MS => expire_ms = Some(r.u64_le()? as i64),
+            OP_EXPIRETIME => expire_ms = Some(r.u32_le()? as i64 * 1000),
+            OP_IDLE => idle_secs = Some(r.len()?),
+            OP_FREQ => freq = Some(r.u8()? as u64),
+            OP_SLOT_INFO => {
+                for _ in 0..3 {
+                    r.len()?;
+                }
+            }
+            OP_FUNCTION2 => r.skip_string()?,
+            OP_MODULE_AUX => {
+                r.len()?; // module id
+                r.len()?; // when_opcode
+                r.len()?; // when
+                r.skip_module_values()?;
+            }
+            OP_FUNCTION_PRE_GA => return Err(invalid("不支援 Redis 7.0 RC 版的 function 格式")),
+            t => {
+                let key = r.string()?;
+                let (type_code, encoding, elements, value_bytes) = read_value(r, t, version)?;
+                if type_code.is_none() {
+                    info.modules += 1;
+                }
+                let k = RdbKey {
+                    db,
+                    key,
+                    type_code,
+                    encoding,
+                    elements,
+                    value_bytes,
+                    expire_ms: expire_ms.take(),
+                    idle_secs: idle_secs.take(),
+                    freq: freq.take(),
+                };
+                on_key(k, &info);
+            }
+        }
+    }
+    Ok(info)
+}
+
+type Value = (Option<KeyTypeCode>, &'static str, u64, u64); // (類型, 編碼, 元素數, bytes)
+
+fn read_value(r: &mut Reader<impl Read>, t: u8, version: u32) -> io::Result<Value> {
+    use KeyTypeCode::*;
+    Ok(match t {
+        0 => {
+            let (len, int) = r.string_len()?;
+            let encoding = match (int, len) {
+                (true, _) => "int",
+                (false, 0..=44) => "embstr",
+                _ => "raw",
+            };
+            (Some(String), encoding, len, if int { 0 } else { len })
+        }
+        1 | 2 => {
+            let n = r.len()?;
+            let mut bytes = 0;
+            for _ in 0..n {
+                bytes += r.string_len()?.0;
+            }
+            if t == 1 {
+                (Some(List), "linkedlist", n, bytes)
+            } else {
+                (Some(Set), "hashtable", n, bytes)
+            }
+        }
+        3 | 5 => {
+            let n = r.len()?;
+            let mut bytes = 0;
+            for _ in 0..n {
+                bytes += r.string_len()?.0 + 8;
+                if t == 3 {
+                    // 舊格式的 score 以字串存：長度 1 byte（253~255 為 nan / ±inf）
+                    let len = r.u8()?;
+                    if len < 253 {
+                        r.skip(len as u64)?;
+                    }
+                } else {
+                    r.skip(8)?;
+                }
+            }
+            (Some(ZSet), "skiplist", n, bytes)
+        }
+        4 => {
+            let n = r.len()?;
+            let mut bytes = 0;
+            for _ in 0..n {
+                bytes += r.string_len()?.0 + r.string_len()?.0;
+            }
+            (Some(Hash), "hashtable", n, bytes)
+        }
+        24 => {
+            r.skip(8)?; // minExpire
+            let n = r.len()?;
+            let mut bytes = 0;
+            for _ in 0..n {
+                r.len()?; // field 的 TTL
+                bytes += r.string_len()?.0 + r.string_len()?.0 + 8;
+            }
+            (Some(Hash), "hashtable", n, bytes)
+        }
+        9 => {
+            let blob = r.string()?;
+            let n = blob
+                .first()
+                .map_or(0, |&n| if n < 254 { n as u64 } else { 0 });
+            (Some(Hash), "zipmap", n, blob.len() as u64)
+        }
+        10 => blob(r, Some(List), "ziplist", 1, ziplist_len)?,
+        11 => blob(r, Some(Set), "intset", 1, intset_len)?,
+        12 => blob(r, Some(ZSet), "ziplist", 2, ziplist_len)?,
+        13 => blob(r, Some(Hash), "ziplist", 2, ziplist_len)?,
+        16 => blob(r, Some(Hash), "listpack", 2, listpack_len)?,
+        17 => blob(r, Some(ZSet), "listpack", 2, listpack_len)?,
+        20 => blob(r, Some(Set), "listpack", 1, listpack_len)?,
+        25 => {
+            r.skip(8)?; // minExpire
+            blob(r, Some(Hash), "listpackex", 3, listpack_len)?
+        }
+        14 | 18 => {
+            let nodes = r.len()?;
+            let (mut n, mut bytes) = (0, 0);
+            for _ in 0..nodes {
+                let plain = t == 18 && r.len()? == 1;
+                let node = r.string()?;
+                n += match (t, plain) {
+                    (_, true) => 1,
+                    (14, _) => ziplist_len(&node)?,
+                    _ => listpack_len(&node)?,
+                };
+                bytes += node.len() as u64 + QUICKLIST_NODE;
+            }
+            (Some(List), "quicklist", n, bytes)
+        }
+        15 | 19 | 21 => {
+            let (n, bytes) = read_stream(r, t)?;
+            (Some(Stream), "stream", n, bytes)
+        }
+        7 => {
+            r.len()?; // module id
+            let bytes = r.skip_module_values()?;
+            (None, "module", 0, bytes)
+        }
+        _ => {
+            return Err(invalid(&format!(
+                "不支援的值類型 {}（RDB 版本 {}）",
+                t, version
+            )));
+        }
+    })
+}
+
+/// listpack / ziplist / intset 一整塊：元素數由檔頭取得；per = 每個元素佔幾個 entry（hash 為 field + value）
+fn blob(
+    r: &mut Reader<impl Read>,
+    t: Option<KeyTypeCode>,
+    encoding: &'static str,
+    per: u64,
+    count: fn(&[u8]) -> io::Result<u64>,
+) -> io::Result<Value> {
+    let data = r.string()?;
+    Ok((t, encoding, count(&data)? / per, data.len() as u64))
+}
+
+/// stream：listpack 節點、metadata、consumer group 與 PEL；回傳 (entries 數, bytes)
+fn read_stream(r: &mut Reader<impl Read>, t: u8) -> io::Result<(u64, u64)> {
+    let mut bytes = 0;
+    for _ in 0..r.len()? {
+        bytes += r.string_len()?.0; // node key（master ID）
+        bytes += r.string_len()?.0; // listpack
+    }
+    let entries = r.len()?;
+    r.len()?; // last id ms
+    r.len()?; // last id seq
+    if t >= 19 {
+        for _ in 0..5 {
+            r.len()?; // first id、max deleted id、entries_added
+        }
+    }
+    for _ in 0..r.len()? {
+        bytes += r.string_len()?.0; // group name
+        r.len()?;
+        r.len()?; // last delivered id
+        if t >= 19 {
+            r.len()?; // entries_read
+        }
+        let pel = r.len()?;
+        for _ in 0..pel {
+            r.skip(16 + 8)?; // raw id + delivery time
+            r.len()?; // delivery count
+        }
+        bytes = bytes.saturating_add(pel.saturating_mul(40));
+        for _ in 0..r.len()? {
+            bytes += r.string_len()?.0; // consumer name
+            r.skip(if t >= 21 { 16 } else { 8 })?; // seen time（與 active time）
+            let owned = r.len()?;
+            r.skip(
+                owned
+                    .checked_mul(16)
+                    .ok_or_else(|| invalid("consumer 的 PEL 長度錯誤"))?,
+            )?;
+        }
+    }
+    Ok((entries, bytes))
+}
+
+fn ziplist_len(z: &[u8]) -> io::Result<u64> {
+    let header = z.get(8..10).ok_or_else(|| invalid("ziplist 太短"))?;
+    let n = u16::from_le_bytes([header[0], header[1]]);
+    if n < u16::MAX {
+        return Ok(n as u64);
+    }
+    // 元素數超過 65534 時檔頭記 65535，需要逐一走過
+    let mut i = 10;
+    let mut n = 0;
+    while let Some(&b) = z.get(i) {
+        if b == 0xFF {
+            break;
+        }
+        i += if b == 0xFE { 5 } else { 1 }; // prevlen
+        let enc = *z.get(i).ok_or_else(|| invalid("ziplist 截斷"))?;
+        i += match enc >> 6 {
+            0 => 1 + (enc & 0x3F) as usize,
+            1 => 2 + ((((enc & 0x3F) as usize) << 8) | *z.get(i + 1).unwrap_or(&0) as usize),
+            2 => {
+                let b = z.get(i + 1..i + 5).ok_or_else(|| invalid("ziplist 截斷"))?;
+                5 + u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize
+            }
+            _ => match enc {
+                0xC0 => 3,
+                0xD0 => 5,
+                0xE0 => 9,
+                0xF0 => 4,
+                0xFE => 2,
+                _ => 1, // 0xF1~0xFD：4 bit 立即值
+            },
+        };
+        n += 1;
+    }
+    Ok(n)
+}
+
+fn listpack_len(lp: &[u8]) -> io::Result<u64> {
+    let header = lp.get(4..6).ok_or_else(|| invalid("listpack 太短"))?;
+    let n = u16::from_le_bytes([header[0], header[1]]);
+    if n < u16::MAX {
+        return Ok(n as u64);
+    }
+    let mut i = 6;
+    let mut n = 0;
+    while let Some(&b) = lp.get(i) {
+        if b == 0xFF {
+            break;
+        }
+        let byte = |k: usize| *lp.get(i + k).unwrap_or(&0) as usize;
+        let len = if b & 0x80 == 0 {
+            1
+        } else if b & 0xC0 == 0x80 {
+            1 + (b & 0x3F) as usize
+        } else if b & 0xE0 == 0xC0 {
+            2
+        } else if b & 0xF0 == 0xE0 {
+            2 + ((((b & 0x0F) as usize) << 8) | byte(1))
+        } else {
+            match b {
+                0xF0 => 5 + (byte(1) | byte(2) << 8 | byte(3) << 16 | byte(4) << 24),
+                0xF1 => 3,
+                0xF2 => 4,
+                0xF3 => 5,
+                0xF4 => 9,
+                _ => return Err(invalid("listpack 編碼錯誤")),
+            }
+        };
+        let backlen = match len {
+            0..=127 => 1,
+            128..=16382 => 2,
+            16383..=2097150 => 3,
+            2097151..=268435454 => 4,
+            _ => 5,
+        };
+        i += len + backlen;
+        n += 1;
+    }
+    Ok(n)
+}
+
+fn intset_len(s: &[u8]) -> io::Result<u64> {
+    let b = s.get(4..8).ok_or_else(|| invalid("intset 太短"))?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as u64)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+struct Reader<R> {
+    inner: BufReader<R>,
+    pos: u64,  // 已讀取的 bytes，錯誤訊息用
+    size: u64, // 檔案大小：長度欄位不可能超過剩下的 bytes
+}
+
+/// 長度編碼的前兩個 bit 為 11 時是特殊編碼
+enum Length {
+    Plain(u64),
+    Int(u8), // 0 = int8、1 = int16、2 = int32
+    Lzf,     // LZF 壓縮的字串
+}
+
+impl<R: Read> Reader<R> {
+    fn new(inner: BufReader<R>, size: u64) -> Self {
+        Self {
+            inner,
+            pos: 0,
+            size,
+        }
+    }
+
+    /// 依檔案中的長度配置緩衝區前先確認檔案還有這麼多 bytes，損毀的長度不會配置數 GB 記憶體
+    fn checked_len(&self, n: u64) -> io::Result<usize> {
+        if n > self.size.saturating_sub(self.pos) {
+            return Err(invalid(&format!(
+                "長度 {} 超過檔案剩下的 {} bytes（RDB 損毀？）",
+                n,
+                self.size.saturating_sub(self.pos)
+            )));
+        }
+        usize::try_from(n).map_err(|_| invalid("長度超出範圍"))
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(buf).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                invalid("檔案在預期之前結束（RDB 不完整？）")
+            } else {
+                e
+            }
+        })?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        let mut b = [0];
+        self.read_exact(&mut b)?;
+        Ok(b[0])
+    }
+
+    fn u32_le(&mut self) -> io::Result<u32> {
+        let mut b = [0; 4];
+        self.read_exact(&mut b)?;
+        Ok(u32::from_le_bytes(b))
+    }
+
+    fn u64_le(&mut self) -> io::Result<u64> {
+        let mut b = [0; 8];
+        self.read_exact(&mut b)?;
+        Ok(u64::from_le_bytes(b))
+    }
+
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        let copied = io::copy(&mut (&mut self.inner).take(n), &mut io::sink())?;
+        self.pos += copied;
+        if copied < n {
+            return Err(invalid("檔案在預期之前結束（RDB 不完整？）"));
+        }
+        Ok(())
+    }
+
+    fn raw_len(&mut self) -> io::Result<Length> {
+        let b = self.u8()?;
+        Ok(match b >> 6 {
+            0 => Length::Plain((b & 0x3F) as u64),
+            1 => Length::Plain((((b & 0x3F) as u64) << 8) | self.u8()? as u64),
+            2 => match b {
+                0x80 => {
+                    let mut buf = [0; 4];
+                    self.read_exact(&mut buf)?;
+                    Length::Plain(u32::from_be_bytes(buf) as u64)
+                }
+                0x81 => {
+                    let mut buf = [0; 8];
+                    self.read_exact(&mut buf)?;
+                    Length::Plain(u64::from_be_bytes(buf))
+                }
+                _ => return Err(invalid("長度編碼錯誤")),
+            },
+            _ => match b & 0x3F {
+                0..=2 => Length::Int(b & 0x3F),
+                3 => Length::Lzf,
+                _ => return Err(invalid("字串編碼錯誤")),
+            },
+        })
+    }
+
+    fn len(&mut self) -> io::Result<u64> {
+        match self.raw_len()? {
+            Length::Plain(n) => Ok(n),
+            _ => Err(invalid("預期長度卻是字串編碼")),
+        }
+    }
+
+    /// 讀取整個字串（整數編碼轉成十進位文字，LZF 解壓）
+    fn string(&mut self) -> io::Result<Vec<u8>> {
+        match self.raw_len()? {
+            Length::Plain(n) => {
+                let mut buf = vec![0; self.checked_len(n)?];
+                self.read_exact(&mut buf)?;
+                Ok(buf)
+            }
+            Length::Int(w) => Ok(self.int(w)?.to_string().into_bytes()),
+            Length::Lzf => {
+                let clen = self.len()?;
+                let ulen = self.len()?;
+                let mut buf = vec![0; self.checked_len(clen)?];
+                self.read_exact(&mut buf)?;
+                let ulen = usize::try_from(ulen).map_err(|_| invalid("LZF 解壓後長度錯誤"))?;
+                lzf_decompress(&buf, ulen)
+            }
+        }
+    }
+
+    /// 跳過字串，回傳 (解壓後的長度, 是否為整數編碼)；不配置緩衝區
+    fn string_len(&mut self) -> io::Result<(u64, bool)> {
+        match self.raw_len()? {
+            Length::Plain(n) => {
+                self.skip(n)?;
+                Ok((n, false))
+            }
+            Length::Int(w) => Ok((self.int(w)?.to_string().len() as u64, true)),
+            Length::Lzf => {
+                let clen = self.len()?;
+                let ulen = self.len()?;
+                self.skip(clen)?;
+                Ok((ulen, false))
+            }
+        }
+    }
+
+    fn skip_string(&mut self) -> io::Result<()> {
+        self.string_len().map(|_| ())
+    }
+
+    fn int(&mut self, width: u8) -> io::Result<i64> {
+        Ok(match width {
+            0 => self.u8()? as i8 as i64,
+            1 => {
+                let mut b = [0; 2];
+                self.read_exact(&mut b)?;
+                i16::from_le_bytes(b) as i64
+            }
+            _ => self.u32_le()? as i32 as i64,
+        })
+    }
+
+    /// module 序列化的值：opcode + 值，直到 EOF opcode；回傳字串值的 bytes 合計
+    fn skip_module_values(&mut self) -> io::Result<u64> {
+        let mut bytes = 0;
+        loop {
+            match self.len()? {
+                0 => return Ok(bytes),
+                1 | 2 => {
+                    self.len()?;
+                    bytes += 8;
+                }
+                3 => {
+                    self.skip(4)?;
+                    bytes += 4;
+                }
+                4 => {
+                    self.skip(8)?;
+                    bytes += 8;
+                }
+                5 => bytes += self.string_len()?.0,
+                _ => return Err(invalid("module 值的 opcode 錯誤")),
+            }
+        }
+    }
+}
+
+/// LZF 每個 3 bytes 的反向參照最多展開成 264 bytes
+const LZF_MAX_RATIO: usize = 88;
+
+fn lzf_decompress(input: &[u8], out_len: usize) -> io::Result<Vec<u8>> {
+    // 解壓後長度來自檔案：超過壓縮資料可能展開的上限就是損毀，不依它配置記憶體
+    if out_len > input.len().saturating_mul(LZF_MAX_RATIO) {
+        return Err(invalid("LZF 解壓後長度不符"));
+    }
+    let mut out = Vec::with_capacity(out_len);
+    let mut i = 0;
+    while i < input.len() {
+        let ctrl = input[i] as usize;
+        i += 1;
+        if ctrl < 32 {
+            let lit = input
+                .get(i..i + ctrl + 1)
+                .ok_or_else(|| invalid("LZF 資料截斷"))?;
+            out.extend_from_slice(lit);
+            i += ctrl + 1;
+            continue;
+        }
+        let mut len = ctrl >> 5;
+        if len == 7 {
+            len += *input.get(i).ok_or_else(|| invalid("LZF 資料截斷"))? as usize;
+            i += 1;
+        }
+        let back = ((ctrl & 0x1F) << 8)
+            + *input.get(i).ok_or_else(|| invalid("LZF 資料截斷"))? as usize
+            + 1;
+        i += 1;
+        let start = out
+            .len()
+            .checked_sub(back)
+            .ok_or_else(|| invalid("LZF 參照超出範圍"))?;
+        for k in 0..len + 2 {
+            let b = out[start + k];
+            out.push(b);
+        }
+    }
+    if out.len() != out_len {
+        return Err(invalid("LZF 解壓後長度不符"));
+    }
+    Ok(out)
+}
+
+/// 報表是 RDB 的離線分析（記憶體為估計值，不能直接和 MEMORY USAGE 比較）
+pub fn is_rdb_report(report: &Report) -> bool {
+    report.target.starts_with(TARGET_PREFIX)
+}
+
+/// 檔案開頭是 RDB 的 REDIS 檔頭（diff 用來分辨 RDB 與 snapshot）
+pub fn is_rdb(path: &Path) -> bool {
+    let mut magic = [0u8; 5];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|_| &magic == b"REDIS")
+}
+
+/// 依 RDB 產生與 scan 相同結構的報表（記憶體為估計值，沒有伺服器端資訊）
+pub fn analyze(
+    path: &Path,
+    db: Option<u64>,
+    prefix_sep: char,
+    prefix_depth: usize,
+    prefix_tables: usize,
+) -> Result<Report, Box<dyn Error>> {
+    let started = Instant::now();
+    let file_mtime = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+
+    let mut stats = AllStats::new();
+    let mut expiry = ExpiryTimeline::new();
+    let mut prefixes = PrefixStats::new(prefix_sep, prefix_depth, prefix_tables > 0);
+    let mut key_names = SizeHistogram::default();
+    let mut longest = LongestKeys::default();
+    let mut bin_waste = BinWasteStats::new(prefix_sep, prefix_depth);
+    let mut scanned = 0;
+    let mut vanished = 0;
+    let mut other_db = 0;
+
+    println!("解析 {} …", path.display());
+    let info = parse(path, |k, info| {
+        if db.is_some_and(|d| d != k.db) {
+            other_db += 1;
+            return;
+        }
+        let Some(t) = k.type_code else { return };
+        // TTL 以存檔時間為基準；舊版 RDB 沒有 ctime 時用檔案的修改時間
+        let dumped_ms = info.ctime.or(file_mtime).map(|s| s as i64 * 1000);
+        let ttl_ms = match (k.expire_ms, dumped_ms) {
+            (Some(at), Some(now)) if at <= now => {
+                vanished += 1;
+                return;
+            }
+            (Some(at), Some(now)) => Some(at - now),
+            (Some(_), None) => None,
+            (None, _) => Some(-1),
+        };
+        let mem = k.estimated_mem();
+        let name = String::from_utf8_lossy(&k.key);
+        let extra = KeyExtra {
+            ttl_ms,
+            idle_secs: k.idle_secs,
+            encoding: Some(k.encoding.to_string()),
+            freq: k.freq,
+            elements: Some(k.elements),
+            estimated: false,
+        };
+        stats.get_mut(t).add_key(mem, &name, &extra);
+        prefixes.add_key(&name, mem, t);
+        key_names.add(k.key.len() as u64);
+        longest.add_key(mem, &name, &extra);
+        if t == KeyTypeCode::String {
+            bin_waste.add_key(&name, mem);
+        }
+        if let Some(pttl) = ttl_ms {
+            expiry.add_key(pttl, mem);
+        }
+        scanned += 1;
+    })?;
+
+    let dumped_at = info.ctime.or(file_mtime).unwrap_or(0);
+    let types = type_reports(&stats);
+    let mut report = Report {
+        format_version: SNAPSHOT_VERSION,
+        target: format!("{}{}", TARGET_PREFIX, path.display()),
+        started_at: dumped_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        started_at_utc: format_iso8601(dumped_at),
+        finished_at_utc: format_iso8601(dumped_at),
+        notes: vec![format!(
+            "離線分析 RDB（版本 {}{}）：記憶體為依序列化大小推算的估計值，不是 MEMORY USAGE",
+            info.version,
+            info.redis_ver
+                .as_ref()
+                .map(|v| format!("，Redis {}", v))
+                .unwrap_or_default()
+        )],
+        scanned,
+        errors: 0,
+        vanished,
+        types,
+        expiry: Some(expiry),
+        memory_stats: None,
+        clients: None,
+        memory_info: None,
+        unavailable: Vec::new(),
+        pauses: Vec::new(),
+        max_repl_lag: None,
+        role_changes: Vec::new(),
+        key_rate: None,
+        nodes: Vec::new(),
+        duplicates: None,
+        sampling: None,
+        prefixes: prefix_reports(prefixes, prefix_tables),
+        owners: Vec::new(),
+        zombies: None,
+        key_names,
+        bin_waste: bin_waste.into_reports(),
+        shared_prefixes: Vec::new(),
+        overhead: Vec::new(),
+        activity: None,
+        functions: None,
+        keyspace_checks: Vec::new(),
+        compacted: false,
+        interrupted: None,
+        acl_denied: Vec::new(),
+        impact: Vec::new(),
+        latency: Default::default(),
+        rtt: Default::default(),
+        capabilities: Vec::new(),
+        ages: Vec::new(),
+        owner_details: Vec::new(),
+        scope: None,
+        empty: None,
+        element_tops: Vec::new(),
+        int_strings: Vec::new(),
+        longest_keys: longest.into_vec(),
+        ttl_suggestions: Vec::new(),
+    };
+    if info.modules > 0 {
+        report.notes.push(format!(
+            "{} 個 module 類型的 key 無法分類，未列入統計",
+            format_int(info.modules)
+        ));
+    }
+    if other_db > 0 {
+        report
+            .notes
+            .push(format!("略過其他資料庫的 {} 個 key", format_int(other_db)));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// 長度編碼（只用到 6 bit 與 32 bit 兩種）
+    fn len(n: usize) -> Vec<u8> {
+        if n < 64 {
+            vec![n as u8]
+        } else {
+            let mut b = vec![0x80];
+            b.extend_from_slice(&(n as u32).to_be_bytes());
+            b
+        }
+    }
+
+    fn string(s: &[u8]) -> Vec<u8> {
+        let mut b = len(s.len());
+        b.extend_from_slice(s);
+        b
+    }
+
+    fn listpack(entries: &[&[u8]]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for e in entries {
+            body.push(0x80 | e.len() as u8);
+            body.extend_from_slice(e);
+            body.push(1 + e.len() as u8); // backlen
+        }
+        let mut lp = ((6 + body.len() + 1) as u32).to_le_bytes().to_vec();
+        lp.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        lp.extend(body);
+        lp.push(0xFF);
+        lp
+    }
+
+    fn ziplist(entries: &[&[u8]]) -> Vec<u8> {
+        let mut body = Vec::new();
+        let mut prev = 0;
+        for e in entries {
+            body.push(prev as u8);
+            body.push(e.len() as u8); // 00pppppp
+            body.extend_from_slice(e);
+            prev = 2 + e.len();
+        }
+        let mut z = ((10 + body.len() + 1) as u32).to_le_bytes().to_vec();
+        z.extend_from_slice(&[0; 4]); // zltail
+        z.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        z.extend(body);
+        z.push(0xFF);
+        z
+    }
+
+    fn intset(values: &[i16]) -> Vec<u8> {
+        let mut s = 2u32.to_le_bytes().to_vec();
+        s.extend_from_slice(&(values.len() as u32).to_le_bytes());
+        for v in values {
+            s.extend_from_slice(&v.to_le_bytes());
+        }
+        s
+    }
+
+    /// 檔頭 + 一個值 + EOF 與（不檢查的）checksum
+    fn rdb(t: u8, key: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut b = b"REDIS0012".to_vec();
+        b.push(t);
+        b.extend(string(key));
+        b.extend_from_slice(value);
+        b.push(OP_EOF);
+        b.extend_from_slice(&[0; 8]);
+        b
+    }
+
+    fn parse_bytes(data: &[u8]) -> io::Result<Vec<RdbKey>> {
+        let mut keys = Vec::new();
+        let mut r = Reader::new(BufReader::new(Cursor::new(data)), data.len() as u64);
+        parse_from(&mut r, &mut |k, _| keys.push(k))?;
+        Ok(keys)
+    }
+
+    fn parse_one(data: &[u8]) -> RdbKey {
+        let mut keys = parse_bytes(data).unwrap();
+        assert_eq!(keys.len(), 1);
+        keys.pop().unwrap()
+    }
+
+    #[test]
+    fn listpack_hash() {
+        let lp = listpack(&[b"f1", b"v1", b"f2", b"value2"]);
+        let k = parse_one(&rdb(16, b"h", &string(&lp)));
+        assert_eq!(k.key, b"h");
+        assert_eq!(k.type_code, Some(KeyTypeCode::Hash));
+        assert_eq!(k.encoding, "listpack");
+        assert_eq!(k.elements, 2);
+        assert_eq!(k.value_bytes, lp.len() as u64);
+    }
+
+    #[test]
+    fn listpack_over_u16_header_is_walked() {
+        let mut lp = listpack(&[b"a", b"b", b"c"]);
+        lp[4..6].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(listpack_len(&lp).unwrap(), 3);
+    }
+
+    #[test]
+    fn ziplist_zset() {
+        let z = ziplist(&[b"m1", b"1", b"m2", b"2"]);
+        let k = parse_one(&rdb(12, b"z", &string(&z)));
+        assert_eq!(k.type_code, Some(KeyTypeCode::ZSet));
+        assert_eq!(k.encoding, "ziplist");
+        assert_eq!(k.elements, 2);
+
+        let mut z = ziplist(&[b"a", b"b", b"c", b"d", b"e"]);
+        z[8..10].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(ziplist_len(&z).unwrap(), 5);
+    }
+
+    #[test]
+    fn intset_set() {
+        let s = intset(&[1, 2, 300]);
+        let k = parse_one(&rdb(11, b"s", &string(&s)));
+        assert_eq!(k.type_code, Some(KeyTypeCode::Set));
+        assert_eq!(k.encoding, "intset");
+        assert_eq!(k.elements, 3);
+        assert_eq!(k.value_bytes, 14);
+    }
+
+    #[test]
+    fn lzf_string() {
+        // 一個字面值 'a'，再一個長度 49、往回 1 byte 的參照（重疊複製）
+        let compressed = [0x00, b'a', 0xE0, 40, 0x00];
+        let mut v = vec![0xC3];
+        v.extend(len(compressed.len()));
+        v.extend(len(50));
+        v.extend_from_slice(&compressed);
+        let k = parse_one(&rdb(0, b"s", &v));
+        assert_eq!(k.type_code, Some(KeyTypeCode::String));
+        assert_eq!(k.encoding, "raw");
+        assert_eq!(k.value_bytes, 50);
+
+        assert_eq!(lzf_decompress(&compressed, 50).unwrap(), vec![b'a'; 50]);
+        assert!(lzf_decompress(&compressed, 51).is_err());
+    }
+
+    #[test]
+    fn lzf_key_name() {
+        // key 名稱本身也可能 LZF 壓縮：string() 要解壓
+        let mut b = b"REDIS0012".to_vec();
+        b.push(0);
+        b.extend_from_slice(&[0xC3, 5, 20, 0x00, b'k', 0xE0, 10, 0x00]);
+        b.extend(string(b"v"));
+        b.push(OP_EOF);
+        let k = parse_one(&b);
+        assert_eq!(k.key, vec![b'k'; 20]);
+    }
+
+    /// 一個節點、一個 consumer group（一筆 PEL）、一個 consumer 的 stream（RDB_TYPE_STREAM_LISTPACKS_3）
+    fn stream(owned: &[u8]) -> (Vec<u8>, u64) {
+        let lp = listpack(&[b"1", b"0", b"field", b"value"]);
+        let mut v = len(1);
+        v.extend(string(&[0; 16])); // master ID
+        v.extend(string(&lp));
+        v.extend(len(3)); // entries
+        v.extend(len(5)); // last id ms
+        v.extend(len(0)); // last id seq
+        v.extend([len(1), len(0), len(0), len(0), len(3)].concat());
+        v.extend(len(1)); // groups
+        v.extend(string(b"grp"));
+        v.extend([len(5), len(0), len(3)].concat());
+        v.extend(len(1)); // PEL
+        v.extend_from_slice(&[0; 24]);
+        v.extend(len(1)); // delivery count
+        v.extend(len(1)); // consumers
+        v.extend(string(b"alice"));
+        v.extend_from_slice(&[0; 16]);
+        v.extend_from_slice(owned);
+        let bytes = 16 + lp.len() as u64 + 3 + 40 + 5;
+        (v, bytes)
+    }
+
+    #[test]
+    fn stream_with_group() {
+        let mut owned = len(1);
+        owned.extend_from_slice(&[0; 16]);
+        let (v, bytes) = stream(&owned);
+        let k = parse_one(&rdb(21, b"x", &v));
+        assert_eq!(k.type_code, Some(KeyTypeCode::Stream));
+        assert_eq!(k.elements, 3);
+        assert_eq!(k.value_bytes, bytes);
+    }
+
+    #[test]
+    fn stream_owned_overflow_is_invalid() {
+        let mut owned = vec![0x81];
+        owned.extend_from_slice(&u64::MAX.to_be_bytes());
+        let (v, _) = stream(&owned);
+        let err = parse_bytes(&rdb(21, b"x", &v)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn corrupt_lengths_do_not_allocate() {
+        // 字串長度 4 GB，檔案只有幾十 bytes
+        let mut v = vec![0x80];
+        v.extend_from_slice(&u32::MAX.to_be_bytes());
+        let err = parse_bytes(&rdb(16, b"h", &v)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("超過檔案剩下"), "{}", err);
+
+        // LZF 壓縮的 key 名稱，解壓後長度遠超過壓縮資料可能展開的大小
+        let mut b = b"REDIS0012".to_vec();
+        b.extend_from_slice(&[0, 0xC3, 2, 0x80]);
+        b.extend_from_slice(&u32::MAX.to_be_bytes());
+        b.extend_from_slice(&[0x00, b'k']);
+        b.extend(string(b"v"));
+        b.push(OP_EOF);
+        let err = parse_bytes(&b).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn truncated_file_is_invalid() {
+        let lp = listpack(&[b"f", b"v"]);
+        let data = rdb(16, b"h", &string(&lp));
+        let err = parse_bytes(&data[..data.len() - 12]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn detects_rdb_by_header() {
+        let dir = std::env::temp_dir().join(format!("rtka-rdb-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dump = dir.join("dump.rdb");
+        let snapshot = dir.join("snapshot.json");
+        let short = dir.join("short");
+        std::fs::write(&dump, rdb(0, b"k", &string(b"v"))).unwrap();
+        std::fs::write(&snapshot, b"{\"target\": \"redis://\"}").unwrap();
+        std::fs::write(&short, b"RED").unwrap();
+
+        assert!(is_rdb(&dump));
+        assert!(!is_rdb(&snapshot));
+        assert!(!is_rdb(&short));
+        assert!(!is_rdb(&dir.join("missing")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
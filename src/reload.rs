@@ -0,0 +1,151 @@
+//! watch --config：設定檔熱重載
+//!
+//! 設定檔每行一個旗標，`name = value` 或只有 `name`（布林旗標），`#` 開頭為註解，例如：
+//!
+//! ```text
+//! interval = 5m
+//! max-ops = 20000
+//! measure-types = hash,zset
+//! ```
+//!
+//! 設定檔的值接在命令列之後解析，同一個旗標以設定檔為準；可重複的旗標（--note 等）則是追加。
+//! watch 每次等待下一輪時檢查檔案的修改時間，有變更就重新解析，下一輪起套用，
+//! 不必重啟（重啟會失去類型快取，也會中斷進行中的掃描）。解析失敗時沿用原本的設定。
+
+use crate::cli::{Cli, Command, WatchArgs};
+use clap::{CommandFactory, FromArgMatches};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub struct ConfigFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    entries: BTreeMap<String, String>, // 旗標名稱（不含 --）→ 值；布林旗標為空字串
+}
+
+impl ConfigFile {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut file = Self {
+            path: path.to_path_buf(),
+            modified: None,
+            entries: BTreeMap::new(),
+        };
+        file.modified = file.mtime();
+        file.entries = file.read()?;
+        Ok(file)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 修改時間與上次讀取時不同（檔案被刪除時不算）
+    pub fn changed(&self) -> bool {
+        self.mtime().is_some_and(|m| Some(m) != self.modified)
+    }
+
+    /// 重新讀取並解析；成功才取代目前的內容，回傳新的參數與變更說明
+    pub fn reload(&mut self) -> Result<(WatchArgs, Vec<String>), Box<dyn Error>> {
+        self.modified = self.mtime();
+        let entries = self.read()?;
+        let args = watch_args(&entries)?;
+        let changes = describe_changes(&self.entries, &entries);
+        self.entries = entries;
+        Ok((args, changes))
+    }
+
+    /// 命令列加上設定檔的參數
+    pub fn watch_args(&self) -> Result<WatchArgs, Box<dyn Error>> {
+        watch_args(&self.entries)
+    }
+
+    fn mtime(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok()
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+        let text = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("無法讀取設定檔 {}: {}", self.path.display(), e))?;
+        parse(&text).map_err(|e| format!("{}: {}", self.path.display(), e).into())
+    }
+}
+
+fn parse(text: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut entries = BTreeMap::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (line, ""),
+        };
+        let name = name.trim_start_matches("--");
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("第 {} 行無法解析: {}", n + 1, line));
+        }
+        entries.insert(name.to_string(), value.to_string());
+    }
+    Ok(entries)
+}
+
+/// 以命令列 + 設定檔重新解析 watch 的參數；後出現的旗標覆寫前面的
+fn watch_args(entries: &BTreeMap<String, String>) -> Result<WatchArgs, Box<dyn Error>> {
+    let mut argv: Vec<String> = std::env::args().collect();
+    for (name, value) in entries {
+        match value.as_str() {
+            "false" => continue,
+            "" | "true" => argv.push(format!("--{}", name)),
+            v => {
+                argv.push(format!("--{}", name));
+                argv.push(v.to_string());
+            }
+        }
+    }
+    let matches = Cli::command()
+        .args_override_self(true)
+        .try_get_matches_from(argv)
+        .map_err(|e| first_line(&e.render().to_string()))?;
+    match Cli::from_arg_matches(&matches)?.command {
+        Some(Command::Watch(args)) => Ok(*args),
+        _ => Err("--config 只適用於 watch".into()),
+    }
+}
+
+fn describe_changes(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (name, value) in new {
+        match old.get(name) {
+            Some(before) if before == value => {}
+            Some(before) => {
+                changes.push(format!("--{}: {} → {}", name, shown(before), shown(value)))
+            }
+            None => changes.push(format!("--{}: 新增 {}", name, shown(value))),
+        }
+    }
+    for (name, before) in old {
+        if !new.contains_key(name) {
+            changes.push(format!(
+                "--{}: 移除 {}（改回命令列或預設值）",
+                name,
+                shown(before)
+            ));
+        }
+    }
+    changes
+}
+
+fn shown(value: &str) -> &str {
+    if value.is_empty() { "(開啟)" } else { value }
+}
+
+/// clap 的錯誤訊息附有用法說明，熱重載時只留第一行
+fn first_line(message: &str) -> String {
+    let line = message.lines().next().unwrap_or_default();
+    line.trim_start_matches("error: ").to_string()
+}
@@ -0,0 +1,3006 @@
+use crate::acl::AclDenial;
+use crate::age::{AGE_BUCKETS, AgeGroup, AgeSource, IDLE_ONLY};
+use crate::capabilities::Capabilities;
+use crate::export::csv_field;
+use crate::format::{
+    Decimal, ascii_bar, format_int, format_secs, format_ttl, format_unix_ts, truncate_key,
+};
+use crate::guard::{GuardPause, KeyRate};
+use crate::impact::ScanImpact;
+use crate::intstr::ROBJ_BYTES;
+use crate::latency::{CommandRtt, ESTIMATE_SAMPLES, MeasureLatency, SPIKE_FACTOR};
+use crate::role::RoleChange;
+use crate::server::{ClientBufferSummary, FunctionLibrary, KeyspaceActivity, KeyspaceCheck};
+use crate::stats::{
+    EXPIRING_MS, EXPIRY_HOURS, ExpiryTimeline, KeyTypeCode, PREFIX_TOP_KEYS, PrefixKey, SIZE_BANDS,
+    SizeBands, SizeHistogram, TOP_N, TopKey,
+};
+use crate::validate::ElementTop;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+pub const SNAPSHOT_VERSION: u32 = 2; // 目前的 snapshot 格式版本（1 = 沒有版本號的舊格式）
+const CHART_HEIGHT: usize = 8; // ASCII 直條圖高度（行）
+const BAR_WIDTH: usize = 40; // 摘要佔比長條寬度（字元）
+const FRAG_RATIO_WARN: f64 = 1.5; // mem_fragmentation_ratio 超過此值視為碎片過高
+const PREFIX_TOP: usize = 20; // 摘要中列出的前綴數
+const BAND_DOMINANT: f64 = 0.8; // 前綴記憶體有這個比例在同一側（>= 1MB 或以下）時給出說明
+const BAND_MANY_KEYS: u64 = 1000; // 「分散在大量 key」至少要有這麼多 key
+const PARETO_TARGET: f64 = 80.0; // 「幾個 key 就佔了 80%」的門檻
+const WHALE_SHARE: f64 = 50.0; // 前 1% key 佔超過一半記憶體 → 少數巨型 key
+const GINI_EVEN: f64 = 0.5; // Gini 低於此值 → 大小差不多，屬於整體膨脹
+const STALE_PERC_WARN: f64 = 10.0; // Redis active expire 的可接受 stale 比例（ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE）
+const KEYSPACE_CHURN_WARN: f64 = 1.0; // 掃描期間 key 數變動超過此百分比時提醒
+const IMPACT_WARN: f64 = 50.0; // 掃描期間其他 client 的平均指令耗時上升超過此百分比時提醒
+const DECAY_TOP: usize = 20; // 衰減預測追蹤的全域 Top N
+const DECAY_HOURS: [u64; 6] = [0, 1, 3, 6, 12, 24]; // 衰減預測的時間點
+const DECAY_PERSIST_WARN: f64 = 50.0; // Top N 記憶體有超過此百分比 24 小時後仍在時提醒
+const Z_95: f64 = 1.96; // 常態分布 95% 信賴區間
+const SCRIPT_CACHE_WARN: u64 = 1000; // EVAL 快取的 script 數超過此值多半是動態產生的 script
+const TOP_LIBRARIES: usize = 10; // 列出的 function library 數
+const OVERHEAD_WARN: f64 = 50.0; // 結構開銷超過一半
+const TINY_COLLECTION: f64 = 16.0; // 平均元素數低於此值視為小集合
+pub const DEFAULT_LONG_KEY_NAME: u64 = 64; // 前綴平均名稱長度超過此值（bytes）時列出
+const FRAG_BYTES_WARN: u64 = 64 * 1024 * 1024; // 且浪費超過 64MB 才警告（小實例 ratio 容易虛高）
+
+/// 一次掃描的完整結果，也是 snapshot 檔的內容
+///
+/// 掃描時把伺服器端資訊一起收進來，之後 `report` / `diff` 不需要再連線。
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Report {
+    #[serde(default)]
+    pub format_version: u32, // snapshot 格式版本，讀取時由 snapshot::load 轉換成 SNAPSHOT_VERSION
+    pub target: String,
+    pub started_at: u64,  // Unix 秒
+    pub duration_ms: u64, // 以 monotonic clock 量測，不受系統時間調整影響
+    #[serde(default)]
+    pub started_at_utc: String, // ISO-8601，例如 2026-10-14T03:04:05Z
+    #[serde(default)]
+    pub finished_at_utc: String,
+    #[serde(default)]
+    pub notes: Vec<String>, // --note 的備註，例如「release 4.2 上線後」
+    pub scanned: u64,
+    pub errors: u64,
+    #[serde(default)]
+    pub vanished: u64, // SCAN 回傳、量測時已被刪除或過期的 key（不算錯誤）
+    pub types: Vec<TypeReport>,         // 依 KeyTypeCode::all() 順序
+    pub expiry: Option<ExpiryTimeline>, // 未收集 TTL 時為 None
+    pub memory_stats: Option<Vec<(String, f64)>>,
+    pub clients: Option<ClientBufferSummary>,
+    pub memory_info: Option<BTreeMap<String, String>>,
+    pub unavailable: Vec<(String, String)>, // (來源, 無法取得的原因)
+    #[serde(default)]
+    pub pauses: Vec<GuardPause>, // 負載保護造成的暫停
+    #[serde(default)]
+    pub key_rate: Option<KeyRate>, // --max-keys-per-sec 的限速結果
+    #[serde(default)]
+    pub role_changes: Vec<RoleChange>, // 掃描期間偵測到的角色改變；非空代表報表跨越了 failover
+    #[serde(default)]
+    pub max_repl_lag: Option<u64>, // 掃描期間觀察到的最大複寫延遲（bytes），未監控時為 None
+    #[serde(default)]
+    pub nodes: Vec<NodeReport>, // 多節點合併時各節點的小計；單一節點時為空
+    #[serde(default)]
+    pub duplicates: Option<DedupSummary>, // 未啟用 --dedup 時為 None
+    #[serde(default)]
+    pub sampling: Option<Sampling>, // 未啟用 --sample-rate 時為 None
+    #[serde(default)]
+    pub prefixes: Vec<PrefixReport>, // 依前綴彙總，依記憶體 desc 排序
+    #[serde(default)]
+    pub owners: Vec<OwnerReport>, // 依 --owners 規則彙總；未指定規則時為空
+    #[serde(default)]
+    pub key_names: SizeHistogram, // key 名稱長度分布（bytes），mems 為名稱合計 bytes
+    #[serde(default)]
+    pub longest_keys: Vec<TopKey>, // 名稱最長的幾個 key，依長度 desc
+    #[serde(default)]
+    pub zombies: Option<ZombieReport>, // 未同時收集 TTL 與 idle 時為 None
+    #[serde(default)]
+    pub ttl_suggestions: Vec<TtlSuggestion>, // 未收集 TTL 或 --prefix-depth 0 時為空
+    #[serde(default)]
+    pub int_strings: Vec<IntStringReport>, // 未啟用 --int-strings 時為空
+    #[serde(default)]
+    pub bin_waste: Vec<BinWasteReport>, // 依前綴的 string 配置浪費，依可回收 bytes desc
+    #[serde(default)]
+    pub shared_prefixes: Vec<SharedPrefix>, // merge 子命令：出現在多個實例的前綴
+    #[serde(default)]
+    pub overhead: Vec<OverheadReport>, // 未啟用 --overhead 時為空
+    #[serde(default)]
+    pub activity: Option<KeyspaceActivity>, // 掃描期間 INFO stats / keyspace 的變化；無法取得時為 None
+    #[serde(default)]
+    pub functions: Option<Vec<FunctionLibrary>>, // FUNCTION LIST；Redis 7.0 以前或被擋時為 None
+    #[serde(default)]
+    pub keyspace_checks: Vec<KeyspaceCheck>, // 每個節點一筆
+    #[serde(default)]
+    pub impact: Vec<ScanImpact>, // --impact-probe：每個節點一筆
+    #[serde(default)]
+    pub latency: MeasureLatency, // 過慢的量測與 --estimate-above 改用估計值的 key
+    #[serde(default)]
+    pub rtt: CommandRtt, // SCAN 與量測 pipeline 的往返時間分布與尖峰
+    #[serde(default)]
+    pub capabilities: Vec<Capabilities>, // 實際送出的指令與偵測到的功能，每個節點一筆
+    #[serde(default)]
+    pub ages: Vec<AgeGroup>, // --age-rules：依規則推算的 key 年齡分布；未指定規則時為空
+    #[serde(default)]
+    pub owner_details: Vec<OwnerDetail>, // --split-report-by owner：各擁有者的明細；未拆分時為空
+    #[serde(default)]
+    pub scope: Option<String>, // 拆分後的報表只含此擁有者的 key；None = 整個實例
+    #[serde(default)]
+    pub empty: Option<EmptyResult>, // 沒有量測到任何 key 時說明原因；有量測到時為 None
+    #[serde(default)]
+    pub element_tops: Vec<ElementTop>, // validate：各類型依元素數的 Top N（同 redis-cli --bigkeys）；其他命令為空
+    #[serde(default)]
+    pub compacted: bool, // 保留政策已去掉每個 key 的明細，只剩彙總
+    #[serde(default)]
+    pub interrupted: Option<String>, // 掃描被中止（--oneshot-json 收到 SIGTERM 等）的原因，報表只含已掃描的部分
+    #[serde(default)]
+    pub acl_denied: Vec<AclDenial>, // 因 ACL 權限不足而關掉的收集項目，每個節點各自記錄
+}
+
+/// 沒有量測到任何 key 的原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyReason {
+    Database, // DBSIZE 為 0
+    Filtered, // 有 key，但全被 --measure-* / --sample-rate 排除
+    Vanished, // SCAN 回傳的 key 在量測前都已消失
+}
+
+/// 空結果：報表仍有伺服器資訊，但沒有任何 key 的統計
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EmptyResult {
+    pub reason: EmptyReason,
+    pub dbsize: u64,          // 掃描開始時的 DBSIZE（多節點為合計）
+    pub filters: Vec<String>, // 排除 key 的條件，例如 "--measure-match session:*"
+}
+
+/// 單一 (類型, encoding) 的結構開銷估算
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OverheadReport {
+    #[serde(rename = "type")]
+    pub type_code: KeyTypeCode,
+    pub encoding: String,
+    pub keys: u64,
+    pub elements: u64,
+    pub total_mem: u64,
+    pub overhead: u64, // 估計的結構 bytes（dictEntry、bucket、skiplist node、robj…）
+}
+
+/// 出現在多個實例（snapshot）中的前綴
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SharedPrefix {
+    pub prefix: String,
+    pub instances: u64,
+    pub count: u64,
+    pub total_mem: u64,
+    pub largest: String, // 佔用最多的實例
+    pub largest_mem: u64,
+}
+
+/// 單一前綴的 jemalloc size class 浪費（只計 string）
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BinWasteReport {
+    pub prefix: String,
+    pub count: u64,
+    pub total_mem: u64,
+    pub waste: u64,       // 配置大小 - MEMORY USAGE
+    pub near: u64,        // 剛好超過某個 size class 的 key 數
+    pub reclaimable: u64, // 這些 key 縮小到下一級 class 可省下的 bytes
+}
+
+/// 單一前綴中「存成字串的整數」
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IntStringReport {
+    pub prefix: String,
+    pub strings: u64,     // 此前綴的 string key 數
+    pub int_encoded: u64, // 已是 int 編碼
+    pub numeric: u64,     // 標準整數字串但不是 int 編碼
+    pub padded: u64,      // 補零 / 正負號 / 空白，寫入端改成標準寫法才能轉 int 編碼
+    pub savings: u64,     // 全部改成 int 編碼可省下的 bytes
+    pub shared: u64,      // 值在 0..9999，可共用 shared integer（再省每筆 robj）
+}
+
+/// 大、久未存取、又沒有 TTL 的 key
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ZombieReport {
+    pub min_bytes: u64,
+    pub idle_days: u64,
+    pub count: u64, // 符合條件的全部 key（top 只保留最大的幾筆）
+    pub total_mem: u64,
+    pub top: Vec<TopKey>,
+}
+
+/// 大多有 TTL 的前綴中，沒有 TTL 的大 key
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TtlSuggestion {
+    pub key: String,
+    pub mem: u64,
+    pub prefix: String,
+    pub prefix_count: u64,
+    pub volatile_pct: f64, // 前綴中有 TTL 的 keys 比例
+    pub ttl_secs: u64,     // 建議的 TTL：同前綴觀察到的最長剩餘 TTL
+}
+
+/// 單一擁有者（團隊）的小計
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OwnerReport {
+    pub owner: String,
+    pub count: u64,
+    pub total_mem: u64,
+}
+
+impl EmptyResult {
+    pub fn describe(&self) -> String {
+        match self.reason {
+            EmptyReason::Database => "資料庫沒有任何 key（DBSIZE = 0）".to_string(),
+            EmptyReason::Filtered => format!(
+                "共 {} keys，但全被篩選條件排除（{}）",
+                format_int(self.dbsize),
+                self.filters.join("、")
+            ),
+            EmptyReason::Vanished => format!(
+                "DBSIZE 為 {}，但 SCAN 回傳的 key 在量測前都已刪除或過期",
+                format_int(self.dbsize)
+            ),
+        }
+    }
+}
+
+/// 單一擁有者的明細，用來產生只含該擁有者 key 的報表
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OwnerDetail {
+    pub owner: String,
+    pub types: Vec<TypeReport>,         // 依 KeyTypeCode::all() 順序
+    pub prefixes: Vec<PrefixReport>,    // 依記憶體 desc
+    pub expiry: Option<ExpiryTimeline>, // 未收集 TTL 時為 None
+    pub key_names: SizeHistogram,
+}
+
+impl OwnerDetail {
+    pub fn total_mem(&self) -> u64 {
+        self.types.iter().map(|t| t.total_mem).sum()
+    }
+}
+
+/// --sample-rate 的抽樣結果：只量測依 key hash 選中的比例
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sampling {
+    pub rate: f64,    // 0~1
+    pub skipped: u64, // SCAN 回傳但未被抽中的 key
+    #[serde(default)]
+    pub strata: Vec<Stratum>, // --stratify-types 各類型的抽樣；未分層時為空
+}
+
+/// 分層抽樣中單一類型的普查數量與 Horvitz-Thompson 估計
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Stratum {
+    pub type_code: KeyTypeCode,
+    pub keys: u64,    // TYPE 普查得到的 key 數（含未抽中的）
+    pub sampled: u64, // 實際量測的 key 數
+    pub est_mem: f64, // Σ mem / p
+    pub mem_var: f64, // Σ (1 - p) / p² · mem²
+}
+
+impl Sampling {
+    /// Horvitz-Thompson 估計：總和 / rate，變異數 (1 - p) / p² · Σy²（Poisson 抽樣）
+    ///
+    /// 回傳 (估計值, 95% 信賴區間半寬)；算 key 數時 sum = sum_sq = 樣本數。
+    pub fn estimate(&self, sum: f64, sum_sq: f64) -> (f64, f64) {
+        let p = self.rate;
+        let var = (1.0 - p) / (p * p) * sum_sq;
+        (sum / p, Z_95 * var.sqrt())
+    }
+}
+
+/// SCAN 重複 key 的排除結果
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DedupSummary {
+    pub suppressed: u64,
+    pub approximate: bool, // 使用 bloom filter，可能有少量 key 被誤判為重複
+}
+
+/// 單一前綴（namespace）的小計
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PrefixReport {
+    pub prefix: String, // 例如 "user:*"
+    pub count: u64,
+    pub total_mem: u64,
+    #[serde(default)]
+    pub name_bytes: u64, // key 名稱合計長度（MEMORY USAGE 已包含名稱本身）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top: Vec<PrefixKey>, // 前綴內最大的 key；只有記憶體最大的幾個前綴才有
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bands: SizeBands, // 依 key 大小區間（SIZE_BANDS）的 key 數與記憶體
+}
+
+/// 多節點掃描時單一節點的小計
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NodeReport {
+    pub target: String,
+    pub scanned: u64,
+    pub total_mem: u64,
+}
+
+/// 單一類型的結果，top 已依 mem desc 排序
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TypeReport {
+    #[serde(rename = "type")]
+    pub type_code: KeyTypeCode,
+    pub count: u64,
+    pub total_mem: u64,
+    pub top: Vec<TopKey>,
+    #[serde(default)]
+    pub sizes: SizeHistogram, // key 大小分布，用來算偏態指標
+    #[serde(default)]
+    pub unmeasured: u64, // 兩段式掃描中只分類、未量測記憶體的 key（不含在 count）
+    #[serde(default)]
+    pub mem_sq: f64, // 記憶體的平方和（bytes²），抽樣模式估計信賴區間用
+}
+
+impl Report {
+    pub fn total_mem(&self) -> u64 {
+        self.types.iter().map(|t| t.total_mem).sum()
+    }
+
+    pub fn get(&self, t: KeyTypeCode) -> Option<&TypeReport> {
+        self.types.iter().find(|r| r.type_code == t)
+    }
+
+    /// 不分類型的 Top n（由各類型 Top N 合併，依 mem desc）
+    pub fn global_top(&self, n: usize) -> Vec<(KeyTypeCode, &TopKey)> {
+        let mut all: Vec<(KeyTypeCode, &TopKey)> = self
+            .types
+            .iter()
+            .flat_map(|t| t.top.iter().map(move |k| (t.type_code, k)))
+            .collect();
+        // 同樣大小、同樣 key 名稱（不同 db / 節點）時依類型順序
+        all.sort_by(|a, b| TopKey::rank_cmp(a.1, b.1).then_with(|| (a.0 as u8).cmp(&(b.0 as u8))));
+        all.truncate(n);
+        all
+    }
+
+    fn unavailable_reason(&self, what: &str) -> &str {
+        self.unavailable
+            .iter()
+            .find(|(k, _)| k == what)
+            .map(|(_, v)| v.as_str())
+            .unwrap_or("掃描時未收集")
+    }
+}
+
+/// Top N 表格的欄位（--columns），依指定順序輸出
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Column {
+    Rank,
+    Mb,
+    Bytes,
+    Cum,
+    Ttl,
+    Idle,
+    Encoding,
+    Freq,
+    Elements,
+    Key,
+}
+
+impl Column {
+    /// last = 最後一欄不補空白（key 放最後時不會被截成固定寬度）
+    fn header(self, last: bool) -> String {
+        match self {
+            Column::Rank => format!("{:>6}", "排名"),
+            Column::Mb => format!("{:>15}", "記憶體 (MB)"),
+            Column::Bytes => format!("{:>20}", "記憶體 (Bytes)"),
+            Column::Cum => format!("{:>10}", "累計佔比"),
+            Column::Ttl => format!("{:>12}", "TTL"),
+            Column::Idle => format!("{:>12}", "Idle"),
+            Column::Encoding => format!("{:>12}", "Encoding"),
+            Column::Freq => format!("{:>6}", "Freq"),
+            Column::Elements => format!("{:>12}", "元素數"),
+            Column::Key if last => "Key".into(),
+            Column::Key => format!("{:<80}", "Key"),
+        }
+    }
+
+    /// cum_pct = 排名 1..=rank 的 key 合計佔此類型記憶體的百分比
+    fn cell(self, rank: usize, k: &TopKey, cum_pct: f64, last: bool) -> String {
+        match self {
+            Column::Rank => format!("{:>6}", rank),
+            Column::Mb => format!("{:>15.3}", Decimal(k.mem as f64 / 1024.0 / 1024.0)),
+            Column::Bytes => format!("{:>20}", k.mem),
+            Column::Cum => format!("{:>9.2}%", Decimal(cum_pct)),
+            Column::Ttl => format!("{:>12}", format_ttl(k.ttl_ms)),
+            Column::Idle => format!(
+                "{:>12}",
+                k.idle_secs.map(format_secs).unwrap_or_else(|| "-".into())
+            ),
+            Column::Encoding => format!("{:>12}", k.encoding.as_deref().unwrap_or("-")),
+            Column::Freq => format!(
+                "{:>6}",
+                k.freq.map(|f| f.to_string()).unwrap_or_else(|| "-".into())
+            ),
+            Column::Elements => format!(
+                "{:>12}",
+                k.elements.map(format_int).unwrap_or_else(|| "-".into())
+            ),
+            Column::Key if last => truncate_key(&k.key, 80),
+            Column::Key => format!("{:<80}", truncate_key(&k.key, 80)),
+        }
+    }
+
+    /// CSV 的欄位名稱（機器可讀，不受語系影響）
+    pub fn csv_header(self) -> &'static str {
+        match self {
+            Column::Rank => "rank",
+            Column::Mb => "mb",
+            Column::Bytes => "bytes",
+            Column::Cum => "cum_pct",
+            Column::Ttl => "ttl_ms",
+            Column::Idle => "idle_secs",
+            Column::Encoding => "encoding",
+            Column::Freq => "freq",
+            Column::Elements => "elements",
+            Column::Key => "key",
+        }
+    }
+
+    /// CSV 的欄位值：未格式化的數字，缺少的資訊留空；key 依 RFC 4180 加引號
+    pub fn csv_cell(self, rank: usize, k: &TopKey, cum_pct: f64) -> String {
+        match self {
+            Column::Rank => rank.to_string(),
+            Column::Mb => format!("{:.3}", k.mem as f64 / 1024.0 / 1024.0),
+            Column::Bytes => k.mem.to_string(),
+            Column::Cum => format!("{:.2}", cum_pct),
+            Column::Ttl => k.ttl_ms.map(|v| v.to_string()).unwrap_or_default(),
+            Column::Idle => k.idle_secs.map(|v| v.to_string()).unwrap_or_default(),
+            Column::Encoding => k.encoding.as_deref().map(csv_field).unwrap_or_default(),
+            Column::Freq => k.freq.map(|v| v.to_string()).unwrap_or_default(),
+            Column::Elements => k.elements.map(|v| v.to_string()).unwrap_or_default(),
+            Column::Key => csv_field(&k.key),
+        }
+    }
+}
+
+/// 文字報表的輸出選項
+#[derive(Clone, Default)]
+pub struct RenderOptions {
+    pub columns: Vec<Column>,           // 空 = 預設欄位
+    pub cost_per_gb_month: Option<f64>, // 有設定時在前綴 / 擁有者表格加上每月成本
+    pub long_key_name: u64,             // 平均 key 名稱長度超過此值的前綴會被列出
+}
+
+impl RenderOptions {
+    /// 依每 GB 每月單價換算的成本
+    fn monthly_cost(&self, mem: u64) -> Option<f64> {
+        self.cost_per_gb_month
+            .map(|c| mem as f64 / 1024.0 / 1024.0 / 1024.0 * c)
+    }
+
+    /// 成本欄位（未設定單價時為空字串）
+    fn cost_cell(&self, mem: u64) -> String {
+        self.monthly_cost(mem)
+            .map(|c| format!(" {:>12.2}", Decimal(c)))
+            .unwrap_or_default()
+    }
+
+    fn cost_header(&self) -> String {
+        if self.cost_per_gb_month.is_some() {
+            format!(" {:>12}", "每月成本")
+        } else {
+            String::new()
+        }
+    }
+}
+
+/// --deep 的結果：精確記憶體與第一階段估計的差距，以及元素大小抽樣；兩者都沒有時為 None
+fn deep_note(k: &TopKey, estimated: u64) -> Option<String> {
+    let mut notes = Vec::new();
+    if estimated != k.mem && estimated > 0 {
+        notes.push(format!(
+            "抽樣估計 {} bytes，SAMPLES 0 精確值 {:+.1}%",
+            format_int(estimated),
+            Decimal((k.mem as f64 - estimated as f64) / estimated as f64 * 100.0)
+        ));
+    }
+    if let Some(s) = k.element_sizes {
+        notes.push(format!(
+            "抽樣 {} 個元素，平均 {} bytes，最大 {} bytes",
+            s.sampled,
+            format_int(s.avg_bytes),
+            format_int(s.max_bytes)
+        ));
+    }
+    (!notes.is_empty()).then(|| notes.join("；"))
+}
+
+/// 未指定 --columns 時：排名、MB、Bytes、累計佔比，有收集才顯示 TTL / idle / encoding / freq / 元素數，最後是 key
+fn top_columns(opts: &RenderOptions, top: &[TopKey]) -> Vec<Column> {
+    if !opts.columns.is_empty() {
+        return opts.columns.clone();
+    }
+
+    let mut cols = vec![Column::Rank, Column::Mb, Column::Bytes, Column::Cum];
+    if top.iter().any(|k| k.ttl_ms.is_some()) {
+        cols.push(Column::Ttl);
+    }
+    if top.iter().any(|k| k.idle_secs.is_some()) {
+        cols.push(Column::Idle);
+    }
+    if top.iter().any(|k| k.encoding.is_some()) {
+        cols.push(Column::Encoding);
+    }
+    if top.iter().any(|k| k.freq.is_some()) {
+        cols.push(Column::Freq);
+    }
+    if top.iter().any(|k| k.elements.is_some()) {
+        cols.push(Column::Elements);
+    }
+    cols.push(Column::Key);
+    cols
+}
+
+/// 以文字表格輸出完整報表
+pub fn render_text(report: &Report, opts: &RenderOptions, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "{}", "=".repeat(120))?;
+    for note in &report.notes {
+        writeln!(out, "📝 {}", note)?;
+    }
+    if let Some(owner) = &report.scope {
+        writeln!(
+            out,
+            "📌 只含擁有者 {} 的 key（依 --owners 規則）；實例層級的章節請看整體報表",
+            owner
+        )?;
+    }
+    if let Some(why) = &report.interrupted {
+        writeln!(out, "⚠ {}：報表只含已掃描的部分", why)?;
+    }
+    if report.compacted {
+        writeln!(
+            out,
+            "⚠ 此 snapshot 已依保留政策壓縮，只剩彙總，沒有各 key 的明細"
+        )?;
+    }
+    for c in &report.role_changes {
+        writeln!(
+            out,
+            "⚠ 掃描期間角色改變（{}，{} 偵測到）: {} → {}；報表混有 failover 前後的資料，建議重新掃描",
+            c.target,
+            format_unix_ts(c.detected_at),
+            c.before,
+            c.after
+        )?;
+    }
+    // 同一個指令在各節點都被擋時合併成一行
+    let mut denied: Vec<(&AclDenial, Vec<&str>)> = Vec::new();
+    for d in &report.acl_denied {
+        match denied.iter_mut().find(|(x, _)| x.command == d.command) {
+            Some((_, targets)) => targets.push(&d.target),
+            None => denied.push((d, vec![&d.target])),
+        }
+    }
+    for (d, targets) in denied {
+        writeln!(
+            out,
+            "⚠ ACL 權限不足，未收集 {}（{}）：報表缺少 {}；需要 {}",
+            d.command,
+            targets.join(", "),
+            d.sections.join("、"),
+            d.acl
+        )?;
+    }
+    for what in ["OBJECT IDLETIME", "OBJECT FREQ"] {
+        if report.unavailable.iter().any(|(k, _)| k == what) {
+            writeln!(
+                out,
+                "⚠ 未收集 {}: {}",
+                what,
+                report.unavailable_reason(what)
+            )?;
+        }
+    }
+
+    // 空結果只列原因與伺服器資訊，key 相關的章節全是 0
+    if let Some(empty) = &report.empty {
+        return render_empty_result(report, empty, out);
+    }
+
+    // ------------------------------------------------------------
+    // 類型 Top N
+    // ------------------------------------------------------------
+    for st in &report.types {
+        if st.count == 0 || st.top.is_empty() {
+            continue;
+        }
+
+        let t = st.type_code;
+        let top = &st.top;
+
+        writeln!(out, "\n🔸 {} - Top {}", t.title(), TOP_N)?;
+        writeln!(out, "{}", "-".repeat(120))?;
+
+        let columns = top_columns(opts, top);
+        let header: Vec<String> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c.header(i + 1 == columns.len()))
+            .collect();
+        writeln!(out, "{}", header.join(" "))?;
+        writeln!(out, "{}", "-".repeat(120))?;
+
+        let total_type_mem = st.total_mem;
+        let pct_of_type = |mem: u64| {
+            if total_type_mem > 0 {
+                (mem as f64 / total_type_mem as f64) * 100.0
+            } else {
+                0.0
+            }
+        };
+
+        // 累計佔比：前 K 個 key 佔此類型總記憶體多少
+        let mut cum_mem: u64 = 0;
+        let mut pareto_k = None;
+        for (idx, k) in top.iter().enumerate() {
+            cum_mem += k.mem;
+            let cum_pct = pct_of_type(cum_mem);
+            if pareto_k.is_none() && cum_pct >= PARETO_TARGET {
+                pareto_k = Some(idx + 1);
+            }
+
+            let line: Vec<String> = columns
+                .iter()
+                .enumerate()
+                .map(|(i, c)| c.cell(idx + 1, k, cum_pct, i + 1 == columns.len()))
+                .collect();
+            writeln!(out, "{}", line.join(" "))?;
+            if let Some(preview) = &k.preview {
+                let score = k
+                    .score
+                    .map(|sc| {
+                        format!(
+                            "  [熵 {:.1} bits/B，{}]",
+                            Decimal(sc.entropy),
+                            sc.kind.label()
+                        )
+                    })
+                    .unwrap_or_default();
+                writeln!(out, "{:>8} {}{}", "↳", preview, score)?;
+            }
+            if let Some(note) = k.estimated_mem.and_then(|est| deep_note(k, est)) {
+                writeln!(out, "{:>8} {}", "↳", note)?;
+            }
+            if k.estimated {
+                writeln!(
+                    out,
+                    "{:>8} 元素數超過 --estimate-above，記憶體為 SAMPLES {} 的估計值",
+                    "↳", ESTIMATE_SAMPLES
+                )?;
+            }
+        }
+
+        let top_mem = cum_mem;
+        let top_pct = pct_of_type(top_mem);
+
+        writeln!(
+            out,
+            "\n  統計: 此類型共 {} keys, 總記憶體 {:.2} MB",
+            format_int(st.count),
+            Decimal(total_type_mem as f64 / 1024.0 / 1024.0)
+        )?;
+        writeln!(
+            out,
+            "  Top {} 佔比: {:.2}% ({:.2} MB)",
+            TOP_N,
+            Decimal(top_pct),
+            Decimal(top_mem as f64 / 1024.0 / 1024.0)
+        )?;
+        let estimated: Option<u64> = top.iter().map(|k| k.estimated_mem).sum();
+        if let Some(est) = estimated.filter(|&e| e > 0) {
+            writeln!(
+                out,
+                "  第二階段 SAMPLES 0 精確量測: Top {} 合計與抽樣估計相差 {:+.2}%",
+                top.len(),
+                Decimal((top_mem as f64 - est as f64) / est as f64 * 100.0)
+            )?;
+        }
+        match pareto_k {
+            Some(k) => writeln!(
+                out,
+                "  前 {} 個 key 即佔此類型記憶體的 {:.0}% 以上，清理這幾個 key 效果明顯",
+                k,
+                Decimal(PARETO_TARGET)
+            )?,
+            None => writeln!(
+                out,
+                "  需要超過 {} 個 key 才能涵蓋 {:.0}% 的記憶體，記憶體分散，單獨清理大 key 效果有限",
+                top.len(),
+                Decimal(PARETO_TARGET)
+            )?,
+        }
+    }
+
+    // ------------------------------------------------------------
+    // 總體摘要
+    // ------------------------------------------------------------
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "總體摘要")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "{:<15} {:>15} {:>20} 佔比",
+        "類型", "Keys 數量", "總記憶體 (MB)"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    let total_mem = report.total_mem();
+
+    for st in &report.types {
+        if st.count == 0 {
+            continue;
+        }
+
+        let pct = if total_mem > 0 {
+            (st.total_mem as f64 / total_mem as f64) * 100.0
+        } else {
+            0.0
+        };
+
+        writeln!(
+            out,
+            "{:<15} {:>15} {:>20.2} {:>6.2}% {}",
+            st.type_code.name(),
+            format_int(st.count),
+            Decimal(st.total_mem as f64 / 1024.0 / 1024.0),
+            Decimal(pct),
+            ascii_bar(pct, BAR_WIDTH)
+        )?;
+    }
+
+    writeln!(
+        out,
+        "\n總計: {} keys, {:.2} MB",
+        format_int(report.scanned),
+        Decimal(total_mem as f64 / 1024.0 / 1024.0)
+    )?;
+    if report.vanished > 0 {
+        writeln!(
+            out,
+            "掃描期間消失 {} keys（SCAN 之後被刪除或過期，不計入錯誤）",
+            format_int(report.vanished)
+        )?;
+    }
+    if let Some(d) = &report.duplicates {
+        writeln!(
+            out,
+            "SCAN 重複回傳 {} 次，已排除{}",
+            format_int(d.suppressed),
+            if d.approximate {
+                "（bloom filter，可能有少量 key 被誤判為重複而漏算）"
+            } else {
+                ""
+            }
+        )?;
+    }
+    if let Some(s) = &report.sampling {
+        render_sampling(report, s, out)?;
+    }
+    let unmeasured: Vec<String> = report
+        .types
+        .iter()
+        .filter(|t| t.unmeasured > 0)
+        .map(|t| format!("{} {}", t.type_code.name(), format_int(t.unmeasured)))
+        .collect();
+    if !unmeasured.is_empty() {
+        writeln!(
+            out,
+            "兩段式掃描: 另有 {} keys 只分類、未量測記憶體（{}），不含在上表",
+            format_int(report.types.iter().map(|t| t.unmeasured).sum()),
+            unmeasured.join(", ")
+        )?;
+    }
+    if let (Some(rate), Some(cost)) = (opts.cost_per_gb_month, opts.monthly_cost(total_mem)) {
+        writeln!(
+            out,
+            "估計每月成本: {:.2}（以每 GB 每月 {:.2} 計，只含資料集，不含 overhead 與保留空間）",
+            Decimal(cost),
+            Decimal(rate)
+        )?;
+    }
+
+    if !report.nodes.is_empty() {
+        writeln!(
+            out,
+            "\n{:<40} {:>15} {:>20} 佔比",
+            "節點", "Keys 數量", "總記憶體 (MB)"
+        )?;
+        writeln!(out, "{}", "-".repeat(120))?;
+        for n in &report.nodes {
+            let pct = if total_mem > 0 {
+                (n.total_mem as f64 / total_mem as f64) * 100.0
+            } else {
+                0.0
+            };
+            writeln!(
+                out,
+                "{:<40} {:>15} {:>20.2} {:>6.2}% {}",
+                n.target,
+                format_int(n.scanned),
+                Decimal(n.total_mem as f64 / 1024.0 / 1024.0),
+                Decimal(pct),
+                ascii_bar(pct, BAR_WIDTH)
+            )?;
+        }
+    }
+    render_shared_prefixes(report, out)?;
+
+    if !report.pauses.is_empty() {
+        let paused_ms: u64 = report.pauses.iter().map(|p| p.duration_ms).sum();
+        writeln!(
+            out,
+            "⏸ 負載保護暫停 {} 次，共 {:.1}s",
+            report.pauses.len(),
+            paused_ms as f64 / 1000.0
+        )?;
+        for p in &report.pauses {
+            writeln!(
+                out,
+                "   {}  {:>8.1}s  {}",
+                format_unix_ts(p.started_at),
+                p.duration_ms as f64 / 1000.0,
+                p.reason
+            )?;
+        }
+    }
+    if let Some(r) = &report.key_rate {
+        writeln!(
+            out,
+            "⏱ 限速 {} keys/s{}: 實際 {} keys/s，限速等待共 {:.1}s",
+            format_int(r.limit),
+            if report.nodes.is_empty() {
+                ""
+            } else {
+                "（每個節點）"
+            },
+            format_int(r.achieved() as u64),
+            r.throttled_ms as f64 / 1000.0
+        )?;
+    }
+    if let Some(lag) = report.max_repl_lag {
+        writeln!(
+            out,
+            "複寫延遲: 掃描期間最大 {} bytes ({:.2} MB)",
+            format_int(lag),
+            Decimal(lag as f64 / 1024.0 / 1024.0)
+        )?;
+    }
+
+    // ------------------------------------------------------------
+    // 前綴（namespace）
+    // ------------------------------------------------------------
+    if !report.prefixes.is_empty() {
+        writeln!(out, "\n{}", "=".repeat(120))?;
+        writeln!(
+            out,
+            "前綴 Top {}（共 {} 個前綴）",
+            PREFIX_TOP.min(report.prefixes.len()),
+            format_int(report.prefixes.len() as u64)
+        )?;
+        writeln!(out, "{}", "=".repeat(120))?;
+        writeln!(
+            out,
+            "{:<40} {:>15} {:>20}{} 佔比",
+            "前綴",
+            "Keys 數量",
+            "總記憶體 (MB)",
+            opts.cost_header()
+        )?;
+        writeln!(out, "{}", "-".repeat(120))?;
+        for p in report.prefixes.iter().take(PREFIX_TOP) {
+            let pct = if total_mem > 0 {
+                (p.total_mem as f64 / total_mem as f64) * 100.0
+            } else {
+                0.0
+            };
+            writeln!(
+                out,
+                "{:<40} {:>15} {:>20.2}{} {:>6.2}% {}",
+                truncate_key(&p.prefix, 40),
+                format_int(p.count),
+                Decimal(p.total_mem as f64 / 1024.0 / 1024.0),
+                opts.cost_cell(p.total_mem),
+                Decimal(pct),
+                ascii_bar(pct, BAR_WIDTH)
+            )?;
+        }
+        render_prefix_bands(report, out)?;
+        render_prefix_tables(report, out)?;
+    }
+
+    // ------------------------------------------------------------
+    // 擁有者（團隊）
+    // ------------------------------------------------------------
+    if !report.owners.is_empty() && report.scope.is_none() {
+        writeln!(out, "\n{}", "=".repeat(120))?;
+        writeln!(out, "各擁有者用量")?;
+        writeln!(out, "{}", "=".repeat(120))?;
+        writeln!(
+            out,
+            "{:<40} {:>15} {:>20}{} 佔比",
+            "擁有者",
+            "Keys 數量",
+            "總記憶體 (MB)",
+            opts.cost_header()
+        )?;
+        writeln!(out, "{}", "-".repeat(120))?;
+        for o in &report.owners {
+            let pct = if total_mem > 0 {
+                (o.total_mem as f64 / total_mem as f64) * 100.0
+            } else {
+                0.0
+            };
+            writeln!(
+                out,
+                "{:<40} {:>15} {:>20.2}{} {:>6.2}% {}",
+                truncate_key(&o.owner, 40),
+                format_int(o.count),
+                Decimal(o.total_mem as f64 / 1024.0 / 1024.0),
+                opts.cost_cell(o.total_mem),
+                Decimal(pct),
+                ascii_bar(pct, BAR_WIDTH)
+            )?;
+        }
+    }
+
+    // ------------------------------------------------------------
+    // 記憶體分布偏態
+    // ------------------------------------------------------------
+    render_skew_section(report, out)?;
+
+    // ------------------------------------------------------------
+    // key 名稱長度
+    // ------------------------------------------------------------
+    render_key_name_section(report, opts.long_key_name, out)?;
+    render_longest_keys(report, out)?;
+    render_int_strings(report, out)?;
+    render_bin_waste(report, out)?;
+    render_overhead(report, out)?;
+
+    // ------------------------------------------------------------
+    // 到期時間軸
+    // ------------------------------------------------------------
+    match &report.expiry {
+        Some(expiry) => {
+            render_expiry_timeline(expiry, out)?;
+            // 已過期未回收是依整個實例的 expired_stale_perc 推估
+            if report.scope.is_none() {
+                render_stale_section(report, expiry, out)?;
+            }
+            render_top_decay(report, out)?;
+            render_ttl_suggestions(report, out)?;
+        }
+        None => {
+            writeln!(out, "\n{}", "=".repeat(120))?;
+            writeln!(out, "到期時間軸: 本次掃描未收集 TTL")?;
+        }
+    }
+    // 拆分報表到此為止，之後都是實例層級的章節
+    if report.scope.is_some() {
+        return Ok(());
+    }
+
+    // ------------------------------------------------------------
+    // 掃描期間的 keyspace 活動
+    // ------------------------------------------------------------
+    render_activity_section(report, out)?;
+    render_keyspace_checks(report, out)?;
+    render_measure_latency(report, out)?;
+    render_command_rtt(report, out)?;
+
+    // ------------------------------------------------------------
+    // 殭屍 key
+    // ------------------------------------------------------------
+    render_zombie_section(report, out)?;
+    render_age_section(report, out)?;
+
+    // ------------------------------------------------------------
+    // 非資料集記憶體（client buffer / replication / Lua…）
+    // ------------------------------------------------------------
+    render_non_dataset_section(report, out)?;
+    render_script_section(report, out)?;
+
+    // ------------------------------------------------------------
+    // 記憶體碎片分析
+    // ------------------------------------------------------------
+    render_fragmentation_section(report, out)?;
+    render_impact(report, out)?;
+    render_capabilities(report, out)?;
+
+    Ok(())
+}
+
+/// 前綴 × key 大小區間：前綴的記憶體來自少數大 key 還是大量中等大小的 key，處理方式不同
+fn render_prefix_bands(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let rows: Vec<&PrefixReport> = report
+        .prefixes
+        .iter()
+        .filter(|p| !p.bands.is_empty())
+        .take(PREFIX_TOP)
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\n各前綴依 key 大小區間的記憶體（MB / 佔該前綴 %）")?;
+    write!(out, "{:<28} {:>12}", "前綴", "總記憶體 (MB)")?;
+    for (_, label) in SIZE_BANDS {
+        write!(out, " {:>15}", label)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for p in rows {
+        write!(
+            out,
+            "{:<28} {:>12.2}",
+            truncate_key(&p.prefix, 28),
+            Decimal(p.total_mem as f64 / 1024.0 / 1024.0)
+        )?;
+        for &(count, mem) in &p.bands {
+            if count == 0 {
+                write!(out, " {:>15}", "-")?;
+                continue;
+            }
+            let pct = if p.total_mem > 0 {
+                mem as f64 / p.total_mem as f64 * 100.0
+            } else {
+                0.0
+            };
+            write!(
+                out,
+                " {:>8.2} {:>5.1}%",
+                Decimal(mem as f64 / 1024.0 / 1024.0),
+                Decimal(pct)
+            )?;
+        }
+        writeln!(out)?;
+        if let Some(hint) = band_hint(&p.bands) {
+            writeln!(out, "{:<28}   └ {}", "", hint)?;
+        }
+    }
+    Ok(())
+}
+
+/// 記憶體集中在 1MB 以上的少數 key，或分散在大量中小型 key
+fn band_hint(bands: &SizeBands) -> Option<String> {
+    let total: u64 = bands.iter().map(|&(_, m)| m).sum();
+    let keys: u64 = bands.iter().map(|&(c, _)| c).sum();
+    let &(big_keys, big_mem) = bands.last()?;
+    if total == 0 {
+        return None;
+    }
+    let big_share = big_mem as f64 / total as f64;
+    if big_share >= BAND_DOMINANT {
+        return Some(format!(
+            "{:.0}% 的記憶體在 {} 個 >= 1MB 的 key：處理這幾個大 key 即可",
+            Decimal(big_share * 100.0),
+            format_int(big_keys)
+        ));
+    }
+    let small_mem: u64 = bands[..bands.len() - 1].iter().map(|&(_, m)| m).sum();
+    if small_mem as f64 / total as f64 >= BAND_DOMINANT && keys >= BAND_MANY_KEYS {
+        return Some(format!(
+            "{:.0}% 的記憶體分散在 1MB 以下的 {} 個 key：需要從資料模型或 TTL 整體處理",
+            Decimal(small_mem as f64 / total as f64 * 100.0),
+            format_int(keys - big_keys)
+        ));
+    }
+    None
+}
+
+/// 記憶體最大的幾個前綴各自的 Top key：從「這個 namespace 很大」直接看到是哪些 key
+fn render_prefix_tables(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    for p in report.prefixes.iter().filter(|p| !p.top.is_empty()) {
+        writeln!(
+            out,
+            "\n🔹 前綴 {} - Top {}（共 {} keys，{:.2} MB）",
+            p.prefix,
+            PREFIX_TOP_KEYS,
+            format_int(p.count),
+            Decimal(p.total_mem as f64 / 1024.0 / 1024.0)
+        )?;
+        writeln!(out, "{}", "-".repeat(120))?;
+        writeln!(
+            out,
+            "{:>6} {:<8} {:>14} {:>14} {:>10}  Key",
+            "排名", "類型", "記憶體 (MB)", "Bytes", "佔前綴"
+        )?;
+        writeln!(out, "{}", "-".repeat(120))?;
+        for (i, k) in p.top.iter().enumerate() {
+            let pct = if p.total_mem > 0 {
+                k.mem as f64 / p.total_mem as f64 * 100.0
+            } else {
+                0.0
+            };
+            writeln!(
+                out,
+                "{:>6} {:<8} {:>14.3} {:>14} {:>9.2}%  {}",
+                i + 1,
+                k.type_code.name(),
+                Decimal(k.mem as f64 / 1024.0 / 1024.0),
+                format_int(k.mem),
+                Decimal(pct),
+                k.key
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// 抽樣模式：上表只是樣本，這裡以樣本的變異數推估全體的 key 數與記憶體與 95% 信賴區間
+fn render_sampling(report: &Report, s: &Sampling, out: &mut impl Write) -> io::Result<()> {
+    if !s.strata.is_empty() {
+        return render_strata(report, s, out);
+    }
+    writeln!(
+        out,
+        "\n抽樣估計（--sample-rate {:.1}%，抽中 {} keys、略過 {} keys，95% 信賴區間）",
+        Decimal(s.rate * 100.0),
+        format_int(report.scanned),
+        format_int(s.skipped)
+    )?;
+    writeln!(
+        out,
+        "{:<15} {:>15} {:>15} {:>18} {:>15} {:>10}",
+        "類型", "估計 Keys", "± Keys", "估計記憶體 (MB)", "± MB", "相對誤差"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    let mb = |b: f64| b / 1024.0 / 1024.0;
+    let (mut keys, mut keys_var, mut mem, mut mem_var) = (0.0, 0.0, 0.0, 0.0);
+    for st in report.types.iter().filter(|t| t.count > 0) {
+        let n = st.count as f64;
+        let (k, k_ci) = s.estimate(n, n);
+        let (m, m_ci) = s.estimate(st.total_mem as f64, st.mem_sq);
+        writeln!(
+            out,
+            "{:<15} {:>15} {:>15} {:>18.2} {:>15.2} {:>9.1}%",
+            st.type_code.name(),
+            format_int(k.round() as u64),
+            format_int(k_ci.round() as u64),
+            Decimal(mb(m)),
+            Decimal(mb(m_ci)),
+            Decimal(m_ci / m.max(1.0) * 100.0)
+        )?;
+        // 各類型互相獨立，變異數可以相加
+        keys += k;
+        keys_var += (k_ci / Z_95).powi(2);
+        mem += m;
+        mem_var += (m_ci / Z_95).powi(2);
+    }
+    let (keys_ci, mem_ci) = (Z_95 * keys_var.sqrt(), Z_95 * mem_var.sqrt());
+    writeln!(
+        out,
+        "{:<15} {:>15} {:>15} {:>18.2} {:>15.2} {:>9.1}%",
+        "全部",
+        format_int(keys.round() as u64),
+        format_int(keys_ci.round() as u64),
+        Decimal(mb(mem)),
+        Decimal(mb(mem_ci)),
+        Decimal(mem_ci / mem.max(1.0) * 100.0)
+    )?;
+    writeln!(
+        out,
+        "  ⚠ 其他區塊（Top N、前綴、分布）只根據樣本，未放大；罕見的大 key 可能沒有被抽中"
+    )
+}
+
+/// 分層抽樣：key 數是普查的實數，只有記憶體是估計值；列出各類型的樣本數與抽樣比例
+fn render_strata(report: &Report, s: &Sampling, out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "\n分層抽樣估計（--sample-rate {:.1}% 依類型分層，抽中 {} keys、略過 {} keys，95% 信賴區間）",
+        Decimal(s.rate * 100.0),
+        format_int(report.scanned),
+        format_int(s.skipped)
+    )?;
+    writeln!(
+        out,
+        "{:<15} {:>15} {:>12} {:>10} {:>18} {:>15} {:>10}",
+        "類型", "Keys（普查）", "樣本數", "抽樣比例", "估計記憶體 (MB)", "± MB", "相對誤差"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    let mb = |b: f64| b / 1024.0 / 1024.0;
+    let (mut keys, mut sampled, mut mem, mut mem_var) = (0, 0, 0.0, 0.0);
+    for st in s.strata.iter().filter(|st| st.keys > 0) {
+        let ci = Z_95 * st.mem_var.sqrt();
+        writeln!(
+            out,
+            "{:<15} {:>15} {:>12} {:>9.1}% {:>18.2} {:>15.2} {:>9.1}%",
+            st.type_code.name(),
+            format_int(st.keys),
+            format_int(st.sampled),
+            Decimal(st.sampled as f64 / st.keys as f64 * 100.0),
+            Decimal(mb(st.est_mem)),
+            Decimal(mb(ci)),
+            Decimal(ci / st.est_mem.max(1.0) * 100.0)
+        )?;
+        keys += st.keys;
+        sampled += st.sampled;
+        mem += st.est_mem;
+        mem_var += st.mem_var;
+    }
+    let ci = Z_95 * mem_var.sqrt();
+    writeln!(
+        out,
+        "{:<15} {:>15} {:>12} {:>9.1}% {:>18.2} {:>15.2} {:>9.1}%",
+        "全部",
+        format_int(keys),
+        format_int(sampled),
+        Decimal(sampled as f64 / keys.max(1) as f64 * 100.0),
+        Decimal(mb(mem)),
+        Decimal(mb(ci)),
+        Decimal(ci / mem.max(1.0) * 100.0)
+    )?;
+    writeln!(
+        out,
+        "  ⚠ 其他區塊（Top N、前綴、分布）只根據樣本，未放大；抽樣比例 100% 的類型為完整量測"
+    )
+}
+
+/// 掃描期間的過期、驅逐與命中率，用來判斷掃描結果是否受到淘汰活動影響
+fn render_activity_section(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "掃描期間的 keyspace 活動")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    let Some(a) = &report.activity else {
+        writeln!(
+            out,
+            "  無法取得: {}",
+            report.unavailable_reason("INFO stats")
+        )?;
+        return Ok(());
+    };
+
+    let secs = (a.window_ms as f64 / 1000.0).max(0.001);
+    writeln!(out, "  期間: {:.1}s", Decimal(secs))?;
+    writeln!(
+        out,
+        "  expired_keys: +{}（{:.1}/s）",
+        format_int(a.expired_keys),
+        Decimal(a.expired_keys as f64 / secs)
+    )?;
+    writeln!(
+        out,
+        "  evicted_keys: +{}（{:.1}/s）",
+        format_int(a.evicted_keys),
+        Decimal(a.evicted_keys as f64 / secs)
+    )?;
+    let lookups = a.hits + a.misses;
+    if lookups > 0 {
+        writeln!(
+            out,
+            "  命中率: {:.2}%（hits +{}，misses +{}）",
+            Decimal(a.hits as f64 / lookups as f64 * 100.0),
+            format_int(a.hits),
+            format_int(a.misses)
+        )?;
+    }
+    writeln!(
+        out,
+        "  keys: {} → {}（有 TTL: {} → {}）",
+        format_int(a.keys_start),
+        format_int(a.keys_end),
+        format_int(a.expires_start),
+        format_int(a.expires_end)
+    )?;
+
+    if a.evicted_keys > 0 {
+        writeln!(
+            out,
+            "\n  ⚠ 掃描期間有 key 被驅逐：記憶體已達 maxmemory，Top N 與總量會低於實際寫入量，被驅逐的 key 不會出現在報表中"
+        )?;
+    }
+
+    Ok(())
+}
+
+/// SCAN 看到的 key 數與 DBSIZE / INFO keyspace 的差距：差距越大，快照越不能代表某一時刻的狀態
+///
+/// SCAN 只保證整段期間都存在的 key 一定會回傳，期間新增或刪除的 key 可能有也可能沒有。
+fn render_keyspace_checks(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    if report.keyspace_checks.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\nkey 數比對（SCAN 回傳 vs DBSIZE / INFO keyspace）")?;
+    writeln!(
+        out,
+        "{:<40} {:>4} {:>12} {:>12} {:>12} {:>12} {:>12} {:>9}",
+        "節點", "db", "DBSIZE 開始", "DBSIZE 結束", "INFO 結束", "SCAN 回傳", "差異", "變動率"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    let mut unstable = 0;
+    for c in &report.keyspace_checks {
+        let diff = c.seen as i64 - c.dbsize_end as i64;
+        let base = c.dbsize_start.max(c.dbsize_end).max(1) as f64;
+        // 開始與結束的 key 數差距，以及 SCAN 回傳數與結束時 DBSIZE 的差距，取大的
+        let churn = (c.dbsize_end.abs_diff(c.dbsize_start) as f64).max(diff.unsigned_abs() as f64)
+            / base
+            * 100.0;
+        if churn >= KEYSPACE_CHURN_WARN {
+            unstable += 1;
+        }
+        writeln!(
+            out,
+            "{:<40} {:>4} {:>12} {:>12} {:>12} {:>12} {:>+12} {:>8.2}%",
+            truncate_key(&c.target, 40),
+            c.db,
+            format_int(c.dbsize_start),
+            format_int(c.dbsize_end),
+            c.info_end.map(format_int).unwrap_or_else(|| "-".into()),
+            format_int(c.seen),
+            diff,
+            Decimal(churn)
+        )?;
+    }
+
+    if unstable > 0 {
+        writeln!(
+            out,
+            "\n  ⚠ {} 個節點在掃描期間 key 數變動超過 {:.0}%：快速變動的 cache，報表只代表掃描期間的近似狀態",
+            unstable,
+            Decimal(KEYSPACE_CHURN_WARN)
+        )?;
+    } else {
+        writeln!(
+            out,
+            "\n  ✔ 掃描期間 key 數變動低於 {:.0}%，快照可代表目前的 keyspace",
+            Decimal(KEYSPACE_CHURN_WARN)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 過慢的 MEMORY USAGE 與改用估計值的大 key；沒有時不輸出
+fn render_measure_latency(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let l = &report.latency;
+    if l.is_empty() {
+        return Ok(());
+    }
+
+    if l.estimated > 0 {
+        writeln!(
+            out,
+            "\n≈ {} 個 key 的元素數超過 --estimate-above {}，未做精確量測，改用 SAMPLES {} 的估計值（合計 {:.2} MB）",
+            format_int(l.estimated),
+            format_int(l.estimate_above.unwrap_or(0)),
+            ESTIMATE_SAMPLES,
+            Decimal(l.estimated_mem as f64 / 1024.0 / 1024.0)
+        )?;
+    }
+    if l.slow_count == 0 {
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "\n過慢的量測（單次超過 {} ms，共 {} 次；列出最慢的 {} 次與其中最大的 key）",
+        l.slow_ms,
+        format_int(l.slow_count),
+        l.slow.len()
+    )?;
+    writeln!(
+        out,
+        "{:<30} {:<8} {:>9} {:>7} {:<7} {:>10} {:>12}  Key",
+        "節點", "階段", "耗時 (ms)", "keys", "類型", "MB", "元素數"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for m in &l.slow {
+        writeln!(
+            out,
+            "{:<30} {:<8} {:>9} {:>7} {:<7} {:>10.2} {:>12}  {}",
+            truncate_key(&m.target, 30),
+            if m.deep { "第二階段" } else { "掃描" },
+            format_int(m.ms),
+            format_int(m.keys),
+            m.type_code.name(),
+            Decimal(m.mem as f64 / 1024.0 / 1024.0),
+            m.elements.map(format_int).unwrap_or_else(|| "-".into()),
+            truncate_key(&m.key, 40)
+        )?;
+    }
+    if l.estimate_above.is_none() {
+        writeln!(
+            out,
+            "\n  ⚠ 大集合的精確量測會阻塞伺服器，可用 --estimate-above 讓元素數超過門檻的 key 改用估計值"
+        )?;
+    }
+    Ok(())
+}
+
+/// SCAN 與量測 pipeline 的往返時間分布；有尖峰時列出並判斷比較像網路還是特定 key
+fn render_command_rtt(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let r = &report.rtt;
+    if r.is_empty() {
+        return Ok(());
+    }
+    let ms = |us: u64| Decimal(us as f64 / 1000.0);
+
+    writeln!(out, "\n往返時間（本工具送出的指令，含網路）")?;
+    writeln!(
+        out,
+        "{:<16} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "", "次數", "平均 (ms)", "p50", "p95", "p99", "max"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for (name, h) in [("SCAN", &r.scan), ("量測 pipeline", &r.pipeline)] {
+        if h.count == 0 {
+            continue;
+        }
+        writeln!(
+            out,
+            "{:<16} {:>10} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+            name,
+            format_int(h.count),
+            ms(h.total_us / h.count),
+            ms(h.percentile(0.5)),
+            ms(h.percentile(0.95)),
+            ms(h.percentile(0.99)),
+            ms(h.max_us)
+        )?;
+    }
+    if r.spike_count == 0 {
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "\n往返時間尖峰（超過當時 p50 的 {} 倍，共 {} 次；列出最慢的 {} 次）",
+        SPIKE_FACTOR,
+        format_int(r.spike_count),
+        r.spikes.len()
+    )?;
+    writeln!(
+        out,
+        "{:<30} {:>8} {:<14} {:>10} {:>10} {:>7} {:<10}  最大的 key",
+        "節點", "時間點", "指令", "耗時 (ms)", "p50 (ms)", "keys", "判斷"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    let mut by_key = 0;
+    for s in &r.spikes {
+        let verdict = if s.scan || s.scan_slow {
+            "網路 / 整體"
+        } else {
+            by_key += 1;
+            "批次中的 key"
+        };
+        let key = match (&s.key, s.type_code, s.mem) {
+            (Some(k), Some(t), Some(mem)) => format!(
+                "{} {}（{:.2} MB）",
+                t.name(),
+                truncate_key(k, 40),
+                Decimal(mem as f64 / 1024.0 / 1024.0)
+            ),
+            _ => "-".into(),
+        };
+        writeln!(
+            out,
+            "{:<30} {:>7}s {:<14} {:>10} {:>10.2} {:>7} {:<10}  {}",
+            truncate_key(&s.target, 30),
+            s.at_secs,
+            if s.scan { "SCAN" } else { "量測 pipeline" },
+            format_int(s.ms),
+            ms(s.p50_us),
+            format_int(s.keys),
+            verdict,
+            key
+        )?;
+    }
+    if by_key > 0 {
+        writeln!(
+            out,
+            "\n  量測 pipeline 變慢而同一輪的 SCAN 正常：多半是批次中的大 key 讓 MEMORY USAGE 變慢（可搭配 --estimate-above）"
+        )?;
+    }
+    if by_key < r.spikes.len() {
+        writeln!(
+            out,
+            "  SCAN 也變慢：比較像網路或伺服器整體的延遲，與個別 key 無關"
+        )?;
+    }
+    Ok(())
+}
+
+/// 附錄：--impact-probe 的掃描前 / 中 / 後比較，供變更審核使用
+///
+/// 掃描中的連線忙著跑 pipeline，沒有 PING 延遲，只比較其他 client 的指令耗時。
+fn render_impact(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    if report.impact.is_empty() {
+        if report.unavailable.iter().any(|(k, _)| k == "延遲探測") {
+            writeln!(
+                out,
+                "\n掃描影響: 無法取得（{}）",
+                report.unavailable_reason("延遲探測")
+            )?;
+        }
+        return Ok(());
+    }
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "附錄：掃描影響（PING 延遲與其他 client 的 INFO commandstats）"
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "{:<36} {:<6} {:>9} {:>8} {:>10} {:>10} {:>10} {:>10} {:>12}",
+        "節點",
+        "期間",
+        "時間 (s)",
+        "PING",
+        "p50 (ms)",
+        "p99 (ms)",
+        "max (ms)",
+        "ops/s",
+        "usec/call"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    let ms = |us: u64| format!("{:.2}", Decimal(us as f64 / 1000.0));
+    for imp in &report.impact {
+        for (i, (label, w)) in [
+            ("掃描前", &imp.before),
+            ("掃描中", &imp.during),
+            ("掃描後", &imp.after),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let pinged = w.pings > 0;
+            writeln!(
+                out,
+                "{:<36} {:<6} {:>9.1} {:>8} {:>10} {:>10} {:>10} {:>10.1} {:>12}",
+                if i == 0 {
+                    truncate_key(&imp.target, 36)
+                } else {
+                    String::new()
+                },
+                label,
+                Decimal(w.window_ms as f64 / 1000.0),
+                if pinged {
+                    format_int(w.pings)
+                } else {
+                    "-".into()
+                },
+                if pinged {
+                    ms(w.ping_p50_us)
+                } else {
+                    "-".into()
+                },
+                if pinged {
+                    ms(w.ping_p99_us)
+                } else {
+                    "-".into()
+                },
+                if pinged {
+                    ms(w.ping_max_us)
+                } else {
+                    "-".into()
+                },
+                Decimal(w.ops_per_sec()),
+                w.usec_per_call()
+                    .map(|u| format!("{:.2}", Decimal(u)))
+                    .unwrap_or_else(|| "-".into())
+            )?;
+        }
+    }
+
+    writeln!(out)?;
+    for imp in &report.impact {
+        render_impact_verdict(imp, out)?;
+    }
+    Ok(())
+}
+
+/// 附錄：實際送出的指令與偵測到的功能，讓不同 Redis 版本的報表可以對照掃描方式
+///
+/// 多節點時版本與功能相同的節點合成一組，指令次數合計。
+fn render_empty_result(
+    report: &Report,
+    empty: &EmptyResult,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "空結果：沒有量測到任何 key")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(out, "  原因: {}", empty.describe())?;
+    writeln!(
+        out,
+        "  掃描: {} ~ {}，耗時 {:.1}s",
+        report.started_at_utc,
+        report.finished_at_utc,
+        report.duration_ms as f64 / 1000.0
+    )?;
+    for caps in &report.capabilities {
+        writeln!(
+            out,
+            "  {} — Redis {}（{}），maxmemory-policy {}",
+            caps.target,
+            caps.redis_version.as_deref().unwrap_or("版本不明"),
+            caps.redis_mode.as_deref().unwrap_or("-"),
+            caps.maxmemory_policy.as_deref().unwrap_or("-")
+        )?;
+    }
+    if let Some(used) = report
+        .memory_info
+        .as_ref()
+        .and_then(|m| m.get("used_memory"))
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        writeln!(
+            out,
+            "  used_memory: {:.2} MB",
+            Decimal(used as f64 / 1024.0 / 1024.0)
+        )?;
+    }
+
+    render_activity_section(report, out)?;
+    render_keyspace_checks(report, out)?;
+    render_non_dataset_section(report, out)?;
+    render_fragmentation_section(report, out)?;
+    render_impact(report, out)?;
+    render_capabilities(report, out)
+}
+
+fn render_capabilities(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    if report.capabilities.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "附錄：送出的指令與偵測到的伺服器功能")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    let mut groups: Vec<(&Capabilities, Vec<&str>)> = Vec::new();
+    let same = |a: &Capabilities, b: &Capabilities| {
+        a.redis_version == b.redis_version
+            && a.redis_mode == b.redis_mode
+            && a.maxmemory_policy == b.maxmemory_policy
+            && a.features == b.features
+            && a.server == b.server
+    };
+    for caps in &report.capabilities {
+        match groups.iter_mut().find(|(g, _)| same(g, caps)) {
+            Some((_, targets)) => targets.push(&caps.target),
+            None => groups.push((caps, vec![&caps.target])),
+        }
+    }
+
+    for (caps, targets) in &groups {
+        let nodes = if targets.len() == 1 {
+            targets[0].to_string()
+        } else {
+            format!("{} 等 {} 個節點", targets[0], targets.len())
+        };
+        writeln!(
+            out,
+            "\n{} — Redis {}（{}），maxmemory-policy {}",
+            nodes,
+            caps.redis_version.as_deref().unwrap_or("版本不明"),
+            caps.redis_mode.as_deref().unwrap_or("-"),
+            caps.maxmemory_policy.as_deref().unwrap_or("-")
+        )?;
+        writeln!(out, "{:<24} {:<6} 說明", "功能", "使用")?;
+        writeln!(out, "{}", "-".repeat(120))?;
+        for f in &caps.features {
+            writeln!(
+                out,
+                "{:<24} {:<6} {}",
+                f.name,
+                if f.enabled { "是" } else { "否" },
+                f.reason
+            )?;
+        }
+        for f in &caps.server {
+            writeln!(
+                out,
+                "{:<24} {:<6} {}",
+                f.name,
+                if f.enabled { "可用" } else { "無法取得" },
+                f.reason
+            )?;
+        }
+    }
+
+    let mut totals: BTreeMap<&str, u64> = BTreeMap::new();
+    for caps in &report.capabilities {
+        for (name, n) in &caps.commands {
+            *totals.entry(name).or_default() += n;
+        }
+    }
+    if totals.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n{:<24} {:>14}", "送出的指令", "次數")?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for (name, n) in &totals {
+        writeln!(out, "{:<24} {:>14}", name, format_int(*n))?;
+    }
+    writeln!(
+        out,
+        "{:<24} {:>14}",
+        "合計",
+        format_int(totals.values().sum())
+    )
+}
+
+fn render_impact_verdict(imp: &ScanImpact, out: &mut impl Write) -> io::Result<()> {
+    let change = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (Some(a), Some(b)) if a > 0.0 => Some((b - a) / a * 100.0),
+        _ => None,
+    };
+    match change(imp.before.usec_per_call(), imp.during.usec_per_call()) {
+        Some(pct) if pct > IMPACT_WARN => writeln!(
+            out,
+            "  ⚠ {}: 掃描期間其他 client 的平均指令耗時上升 {:.1}%（門檻 {:.0}%），考慮 --throttle 或 --max-keys-per-sec",
+            imp.target,
+            Decimal(pct),
+            Decimal(IMPACT_WARN)
+        )?,
+        Some(pct) => writeln!(
+            out,
+            "  ✔ {}: 掃描期間其他 client 的平均指令耗時變化 {:+.1}%",
+            imp.target,
+            Decimal(pct)
+        )?,
+        None => writeln!(
+            out,
+            "  {}: 探測期間沒有其他 client 的流量，無法比較指令耗時",
+            imp.target
+        )?,
+    }
+    writeln!(
+        out,
+        "    PING p99 掃描前 {:.2} ms → 掃描後 {:.2} ms",
+        Decimal(imp.before.ping_p99_us as f64 / 1000.0),
+        Decimal(imp.after.ping_p99_us as f64 / 1000.0)
+    )
+}
+
+/// 各類型 key 記憶體的集中程度：Gini 係數與前 1% / 0.1% key 的佔比
+///
+/// 用來判斷是「少數巨型 key」（清理個別 key 即可）還是「每個 key 都偏大」
+/// （要從資料結構、序列化格式著手）。由 log2 分桶近似計算，不是精確值。
+fn render_skew_section(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "記憶體分布偏態（依 key 大小分桶近似計算）")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    if report
+        .types
+        .iter()
+        .all(|t| t.count == 0 || t.sizes.is_empty())
+    {
+        writeln!(out, "  snapshot 沒有 key 大小分布資料（舊版格式）")?;
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "{:<15} {:>8} {:>12} {:>12}  判讀",
+        "類型", "Gini", "前 1% 佔比", "前 0.1% 佔比"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    for st in &report.types {
+        let h = &st.sizes;
+        let (Some(gini), Some(top1), Some(top01)) =
+            (h.gini(), h.top_share(0.01), h.top_share(0.001))
+        else {
+            continue;
+        };
+
+        let verdict = if top1 >= WHALE_SHARE {
+            "少數巨型 key，優先處理個別 key"
+        } else if gini < GINI_EVEN {
+            "大小平均，需從資料結構 / schema 調整"
+        } else {
+            "中度集中，大 key 與整體結構都值得檢查"
+        };
+        writeln!(
+            out,
+            "{:<15} {:>8.3} {:>11.2}% {:>11.2}%  {}",
+            st.type_code.name(),
+            Decimal(gini),
+            Decimal(top1),
+            Decimal(top01),
+            verdict
+        )?;
+    }
+
+    Ok(())
+}
+
+/// key 名稱長度分布，以及平均名稱過長的前綴（縮短名稱可省下的記憶體）
+fn render_key_name_section(
+    report: &Report,
+    long_name: u64,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "Key 名稱長度")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    let h = &report.key_names;
+    if h.is_empty() {
+        writeln!(out, "  snapshot 沒有 key 名稱長度資料（舊版格式）")?;
+        return Ok(());
+    }
+
+    let count: u64 = h.counts.iter().sum();
+    let name_bytes: u64 = h.mems.iter().sum();
+    let total_mem = report.total_mem();
+    writeln!(
+        out,
+        "  共 {} keys，名稱合計 {:.2} MB（佔 keys 記憶體 {:.2}%），平均 {:.1} bytes",
+        format_int(count),
+        Decimal(name_bytes as f64 / 1024.0 / 1024.0),
+        Decimal(if total_mem > 0 {
+            name_bytes as f64 / total_mem as f64 * 100.0
+        } else {
+            0.0
+        }),
+        Decimal(name_bytes as f64 / count as f64)
+    )?;
+
+    writeln!(
+        out,
+        "\n{:<20} {:>15} {:>8}",
+        "長度 (bytes)", "Keys 數量", "佔比"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for (b, &c) in h.counts.iter().enumerate() {
+        if c == 0 {
+            continue;
+        }
+        let range = if b == 0 {
+            "0".to_string()
+        } else {
+            format!("{} ~ {}", 1u64 << (b - 1), (1u64 << (b - 1)) * 2 - 1)
+        };
+        let pct = c as f64 / count as f64 * 100.0;
+        writeln!(
+            out,
+            "{:<20} {:>15} {:>7.2}% {}",
+            range,
+            format_int(c),
+            Decimal(pct),
+            ascii_bar(pct, BAR_WIDTH)
+        )?;
+    }
+
+    // 舊 snapshot 的前綴沒有名稱長度
+    if report.prefixes.iter().all(|p| p.name_bytes == 0) {
+        return Ok(());
+    }
+    let long: Vec<&PrefixReport> = report
+        .prefixes
+        .iter()
+        .filter(|p| p.count > 0 && p.name_bytes / p.count > long_name)
+        .collect();
+    writeln!(
+        out,
+        "\n平均名稱超過 {} bytes 的前綴（可省 = 名稱縮短到 {} bytes 時省下的記憶體）",
+        long_name, long_name
+    )?;
+    if long.is_empty() {
+        writeln!(out, "  沒有")?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "{:<40} {:>15} {:>10} {:>12} {:>12} {:>12}",
+        "前綴", "Keys 數量", "平均長度", "名稱 (MB)", "佔前綴", "可省 (MB)"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for p in long {
+        let saving = p.name_bytes - long_name * p.count;
+        writeln!(
+            out,
+            "{:<40} {:>15} {:>10.1} {:>12.2} {:>11.2}% {:>12.2}",
+            truncate_key(&p.prefix, 40),
+            format_int(p.count),
+            Decimal(p.name_bytes as f64 / p.count as f64),
+            Decimal(p.name_bytes as f64 / 1024.0 / 1024.0),
+            Decimal(if p.total_mem > 0 {
+                p.name_bytes as f64 / p.total_mem as f64 * 100.0
+            } else {
+                0.0
+            }),
+            Decimal(saving as f64 / 1024.0 / 1024.0)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 名稱最長的 key，名稱太長通常代表不小心把資料序列化進 key 名稱
+fn render_longest_keys(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    if report.longest_keys.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\n名稱最長的 {} 個 key", report.longest_keys.len())?;
+    writeln!(
+        out,
+        "{:<6} {:>12} {:>15}  Key",
+        "排名", "長度 (bytes)", "記憶體 (bytes)"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for (i, k) in report.longest_keys.iter().enumerate() {
+        writeln!(
+            out,
+            "{:<6} {:>12} {:>15}  {}",
+            i + 1,
+            format_int(k.key.len() as u64),
+            format_int(k.mem),
+            truncate_key(&k.key, 80)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 多個實例都有的前綴：可能是同一份資料被多個服務重複快取，或應該集中到同一個實例
+fn render_shared_prefixes(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    if report.shared_prefixes.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "\n跨實例重複的前綴（{} 個，列出記憶體最多的 {} 個）",
+        format_int(report.shared_prefixes.len() as u64),
+        report.shared_prefixes.len().min(PREFIX_TOP)
+    )?;
+    writeln!(
+        out,
+        "{:<40} {:>8} {:>15} {:>15}  最大的實例",
+        "前綴", "實例數", "Keys 數量", "總記憶體 (MB)"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for p in report.shared_prefixes.iter().take(PREFIX_TOP) {
+        writeln!(
+            out,
+            "{:<40} {:>8} {:>15} {:>15.2}  {}（{:.2} MB）",
+            truncate_key(&p.prefix, 40),
+            format_int(p.instances),
+            format_int(p.count),
+            Decimal(p.total_mem as f64 / 1024.0 / 1024.0),
+            p.largest,
+            Decimal(p.largest_mem as f64 / 1024.0 / 1024.0)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 集合類型的結構開銷：開銷比例高又元素很少時，把許多小集合合併成一個 hash 通常更省
+fn render_overhead(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    if report.overhead.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "結構開銷估算（依 encoding 推算，非精確值）")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "{:<8} {:<12} {:>12} {:>12} {:>15} {:>15} {:>9}",
+        "類型", "Encoding", "Keys 數量", "平均元素數", "記憶體 (MB)", "結構開銷 (MB)", "開銷 %"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    let mut tiny = Vec::new();
+    for t in KeyTypeCode::all() {
+        let rows: Vec<&OverheadReport> = report
+            .overhead
+            .iter()
+            .filter(|r| r.type_code == *t)
+            .collect();
+        if rows.is_empty() {
+            continue;
+        }
+        for r in &rows {
+            let pct = overhead_pct(r.overhead, r.total_mem);
+            let avg = r.elements as f64 / r.keys.max(1) as f64;
+            writeln!(
+                out,
+                "{:<8} {:<12} {:>12} {:>12.1} {:>15.2} {:>15.2} {:>8.1}%",
+                t.name(),
+                r.encoding,
+                format_int(r.keys),
+                Decimal(avg),
+                Decimal(r.total_mem as f64 / 1024.0 / 1024.0),
+                Decimal(r.overhead as f64 / 1024.0 / 1024.0),
+                Decimal(pct)
+            )?;
+            if pct >= OVERHEAD_WARN && avg < TINY_COLLECTION {
+                tiny.push(format!("{} ({})", t.name(), r.encoding));
+            }
+        }
+        if rows.len() > 1 {
+            let mem: u64 = rows.iter().map(|r| r.total_mem).sum();
+            let overhead: u64 = rows.iter().map(|r| r.overhead).sum();
+            writeln!(
+                out,
+                "{:<8} {:<12} {:>12} {:>12} {:>15.2} {:>15.2} {:>8.1}%",
+                t.name(),
+                "(合計)",
+                format_int(rows.iter().map(|r| r.keys).sum()),
+                "",
+                Decimal(mem as f64 / 1024.0 / 1024.0),
+                Decimal(overhead as f64 / 1024.0 / 1024.0),
+                Decimal(overhead_pct(overhead, mem))
+            )?;
+        }
+    }
+
+    if !tiny.is_empty() {
+        writeln!(
+            out,
+            "\n  ⚠ {} 多半是元素很少的小集合，結構開銷超過 {:.0}%；考慮合併成一個 hash（例如 user:{{id}}:tags → hash user:tags 的 field {{id}}）",
+            tiny.join("、"),
+            Decimal(OVERHEAD_WARN)
+        )?;
+    }
+
+    Ok(())
+}
+
+fn overhead_pct(overhead: u64, mem: u64) -> f64 {
+    if mem > 0 {
+        overhead as f64 / mem as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// string 值剛好超過 jemalloc size class 的前綴（例如 4,100 bytes 會配到 5,120）
+fn render_bin_waste(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let rows: Vec<&BinWasteReport> = report
+        .bin_waste
+        .iter()
+        .filter(|r| r.near > 0)
+        .take(PREFIX_TOP)
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "配置區塊浪費（jemalloc size class，只計 string）")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "{:<40} {:>12} {:>12} {:>12} {:>8} {:>14} {:>14}",
+        "前綴",
+        "Keys 數量",
+        "記憶體 (MB)",
+        "浪費 (MB)",
+        "浪費 %",
+        "剛好超過 class",
+        "縮小可回收 (MB)"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for r in &rows {
+        let waste_pct = if r.total_mem > 0 {
+            r.waste as f64 / r.total_mem as f64 * 100.0
+        } else {
+            0.0
+        };
+        writeln!(
+            out,
+            "{:<40} {:>12} {:>12.2} {:>12.2} {:>7.1}% {:>14} {:>14.2}",
+            truncate_key(&r.prefix, 40),
+            format_int(r.count),
+            Decimal(r.total_mem as f64 / 1024.0 / 1024.0),
+            Decimal(r.waste as f64 / 1024.0 / 1024.0),
+            Decimal(waste_pct),
+            format_int(r.near),
+            Decimal(r.reclaimable as f64 / 1024.0 / 1024.0)
+        )?;
+    }
+
+    let waste: u64 = report.bin_waste.iter().map(|r| r.waste).sum();
+    let reclaimable: u64 = report.bin_waste.iter().map(|r| r.reclaimable).sum();
+    writeln!(
+        out,
+        "\n  全部 string 的配置浪費約 {:.2} MB；把剛好超過 class 的值縮小到下一級（壓縮、縮短欄位名稱）約可回收 {:.2} MB",
+        Decimal(waste as f64 / 1024.0 / 1024.0),
+        Decimal(reclaimable as f64 / 1024.0 / 1024.0)
+    )?;
+
+    Ok(())
+}
+
+/// maxmemory-policy 不是 LRU / LFU 時 Redis 才會使用 shared integer
+fn shared_integers_usable(report: &Report) -> bool {
+    report
+        .memory_info
+        .as_ref()
+        .and_then(|m| m.get("maxmemory_policy"))
+        .is_some_and(|p| !p.contains("lru") && !p.contains("lfu"))
+}
+
+/// 存成 embstr / raw 的數字字串，依前綴列出可省下的記憶體
+fn render_int_strings(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    if report.int_strings.is_empty() {
+        return Ok(());
+    }
+    let shared = shared_integers_usable(report);
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "存成字串的整數（改成 int 編碼可省下的記憶體）")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "{:<40} {:>12} {:>9} {:>12} {:>12} {:>12} {:>12}",
+        "前綴", "String 數", "int 編碼", "數字字串", "補零/符號", "可共用", "可省 (bytes)"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    let mut total = 0;
+    for r in &report.int_strings {
+        let savings = if shared {
+            r.savings + r.shared * ROBJ_BYTES
+        } else {
+            r.savings
+        };
+        total += savings;
+        let int_pct = if r.strings > 0 {
+            r.int_encoded as f64 / r.strings as f64 * 100.0
+        } else {
+            0.0
+        };
+        writeln!(
+            out,
+            "{:<40} {:>12} {:>8.1}% {:>12} {:>12} {:>12} {:>12}",
+            truncate_key(&r.prefix, 40),
+            format_int(r.strings),
+            Decimal(int_pct),
+            format_int(r.numeric),
+            format_int(r.padded),
+            format_int(r.shared),
+            format_int(savings)
+        )?;
+    }
+
+    writeln!(
+        out,
+        "\n  合計可省 {:.2} MB；補零或帶正負號的值需要寫入端改成標準整數寫法，Redis 才會轉成 int 編碼",
+        Decimal(total as f64 / 1024.0 / 1024.0)
+    )?;
+    if shared {
+        writeln!(
+            out,
+            "  maxmemory-policy 非 LRU / LFU，0..9999 的值會共用 shared integer，另計每筆 {} bytes",
+            ROBJ_BYTES
+        )?;
+    } else {
+        writeln!(
+            out,
+            "  maxmemory-policy 為 LRU / LFU（或未知），Redis 不使用 shared integer，「可共用」未計入可省"
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 以 ASCII 直條圖畫出未來每小時到期的記憶體量
+///
+/// 每一欄代表一小時，高度依最大值等比例縮放，最後一行是時間刻度。
+fn render_expiry_timeline(t: &ExpiryTimeline, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "到期時間軸（未來 {} 小時，每欄 = 1 小時）",
+        EXPIRY_HOURS
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    let max = t.hourly_mem.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        writeln!(out, "  未來 {} 小時內沒有會到期的 keys", EXPIRY_HOURS)?;
+    } else {
+        const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        // 以 1/8 格為單位的高度
+        let units: Vec<usize> = t
+            .hourly_mem
+            .iter()
+            .map(|&m| {
+                if m == 0 {
+                    0
+                } else {
+                    ((m as f64 / max as f64) * (CHART_HEIGHT * 8) as f64).ceil() as usize
+                }
+            })
+            .collect();
+
+        for row in (0..CHART_HEIGHT).rev() {
+            let label = if row == CHART_HEIGHT - 1 {
+                format!("{:>10.2} MB", Decimal(max as f64 / 1024.0 / 1024.0))
+            } else {
+                String::new()
+            };
+            let line: String = units
+                .iter()
+                .map(|&u| {
+                    let base = row * 8;
+                    if u >= base + 8 {
+                        '█'
+                    } else if u > base {
+                        BLOCKS[u - base - 1]
+                    } else {
+                        ' '
+                    }
+                })
+                .collect();
+            writeln!(out, "{:>13} |{}", label, line)?;
+        }
+
+        let axis: String = (0..EXPIRY_HOURS)
+            .map(|h| if h % 6 == 0 { '+' } else { '-' })
+            .collect();
+        writeln!(out, "{:>13} +{}", "", axis)?;
+        let mut ticks = String::new();
+        for h in (0..EXPIRY_HOURS).step_by(6) {
+            ticks.push_str(&format!("{:<6}", format!("{}h", h)));
+        }
+        writeln!(out, "{:>13}  {}", "", ticks)?;
+
+        let (peak_hour, peak_mem) = t
+            .hourly_mem
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, m)| **m)
+            .map(|(h, m)| (h, *m))
+            .unwrap_or((0, 0));
+        writeln!(
+            out,
+            "\n  高峰: 第 {}~{} 小時到期 {:.2} MB ({} keys)",
+            peak_hour,
+            peak_hour + 1,
+            Decimal(peak_mem as f64 / 1024.0 / 1024.0),
+            format_int(t.hourly_count[peak_hour])
+        )?;
+    }
+
+    let window_mem: u64 = t.hourly_mem.iter().sum();
+    writeln!(
+        out,
+        "  {} 小時內到期: {:.2} MB, 更晚到期: {:.2} MB, 有 TTL 總計: {:.2} MB",
+        EXPIRY_HOURS,
+        Decimal(window_mem as f64 / 1024.0 / 1024.0),
+        Decimal(t.later_mem as f64 / 1024.0 / 1024.0),
+        Decimal(t.volatile_mem() as f64 / 1024.0 / 1024.0)
+    )?;
+    writeln!(
+        out,
+        "  沒有 TTL: {} keys, {:.2} MB",
+        format_int(t.persistent_count),
+        Decimal(t.persistent_mem as f64 / 1024.0 / 1024.0)
+    )?;
+
+    Ok(())
+}
+
+/// 已過期但尚未被回收的資料
+///
+/// 過期的 key 只有在被存取（lazy）或被 active expire 抽到時才會刪除；volatile key 很多而
+/// active-expire-effort 偏低時，這部分可能佔住大量記憶體。
+fn render_stale_section(
+    report: &Report,
+    t: &ExpiryTimeline,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "已過期但尚未回收")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    writeln!(
+        out,
+        "  PTTL < {} ms: {} keys, {:.2} MB",
+        EXPIRING_MS,
+        format_int(t.expiring_count),
+        Decimal(t.expiring_mem as f64 / 1024.0 / 1024.0)
+    )?;
+    writeln!(
+        out,
+        "  SCAN 回傳後量測時已消失: {} keys（多半已過期，被這次掃描的存取觸發刪除）",
+        format_int(report.vanished)
+    )?;
+
+    match (t.stale_perc, t.stale_mem_est) {
+        (Some(perc), Some(est)) => {
+            writeln!(
+                out,
+                "  expired_stale_perc: {:.2}% → 推估有 TTL 的 {:.2} MB 中約 {:.2} MB 已過期尚未回收",
+                Decimal(perc),
+                Decimal(t.volatile_mem() as f64 / 1024.0 / 1024.0),
+                Decimal(est as f64 / 1024.0 / 1024.0)
+            )?;
+            if perc >= STALE_PERC_WARN {
+                writeln!(
+                    out,
+                    "  ⚠ 已過期比例偏高: active expire 跟不上，可提高 active-expire-effort（Redis 6+）或 hz"
+                )?;
+            }
+        }
+        _ => writeln!(
+            out,
+            "  expired_stale_perc: 無法取得（INFO stats 被擋或版本過舊），無法推估整體已過期資料量"
+        )?,
+    }
+
+    Ok(())
+}
+
+/// 殭屍 key：依可回收的記憶體排序，附累計值方便估算刪掉前幾個能省多少
+fn render_zombie_section(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    let Some(z) = &report.zombies else {
+        writeln!(
+            out,
+            "殭屍 key: 本次掃描未同時收集 TTL 與 idle（加上 --collect ttl,idle）"
+        )?;
+        return Ok(());
+    };
+    writeln!(
+        out,
+        "殭屍 key: >= {:.2} MB、閒置超過 {} 天、沒有 TTL",
+        Decimal(z.min_bytes as f64 / 1024.0 / 1024.0),
+        z.idle_days
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    if z.count == 0 {
+        writeln!(out, "  沒有符合條件的 key")?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "  共 {} keys，可回收 {:.2} MB",
+        format_int(z.count),
+        Decimal(z.total_mem as f64 / 1024.0 / 1024.0)
+    )?;
+    writeln!(
+        out,
+        "\n{:<6} {:>12} {:>14} {:>10}  Key",
+        "排名", "記憶體 (MB)", "累計 (MB)", "閒置"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    let mut cum = 0;
+    for (i, k) in z.top.iter().enumerate() {
+        cum += k.mem;
+        writeln!(
+            out,
+            "{:<6} {:>12.2} {:>14.2} {:>10}  {}",
+            i + 1,
+            Decimal(k.mem as f64 / 1024.0 / 1024.0),
+            Decimal(cum as f64 / 1024.0 / 1024.0),
+            k.idle_secs.map(format_secs).unwrap_or_else(|| "-".into()),
+            truncate_key(&k.key, 80)
+        )?;
+    }
+    if z.count > z.top.len() as u64 {
+        writeln!(
+            out,
+            "\n（只列出最大的 {} 個）",
+            format_int(z.top.len() as u64)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// --age-rules：各規則的年齡分布，超過保留期限的資料另外列出最大的幾個
+fn render_age_section(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    if report.ages.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "🕰 Key 年齡（依 --age-rules 由名稱中的時間 / 遞增 ID 推算，OBJECT IDLETIME 為下限）"
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    for g in &report.ages {
+        let source = if g.pattern == IDLE_ONLY {
+            "只有 OBJECT IDLETIME".to_string()
+        } else {
+            g.source.name().to_string()
+        };
+        writeln!(
+            out,
+            "\n{}（{}{}）: {} keys，{:.2} MB",
+            g.pattern,
+            source,
+            g.retention_secs
+                .map(|r| format!("，保留 {}", format_secs(r)))
+                .unwrap_or_default(),
+            format_int(g.count),
+            Decimal(g.total_mem as f64 / 1024.0 / 1024.0)
+        )?;
+        if g.unknown > 0 {
+            writeln!(
+                out,
+                "  另有 {} keys（{:.2} MB）推算不出年齡{}",
+                format_int(g.unknown),
+                Decimal(g.unknown_mem as f64 / 1024.0 / 1024.0),
+                match g.source {
+                    AgeSource::Seq => "：seq 需要 OBJECT IDLETIME（--collect idle）",
+                    _ => "：名稱中沒有符合格式的時間，也沒有 idle",
+                }
+            )?;
+        }
+        if g.count == 0 {
+            continue;
+        }
+        writeln!(
+            out,
+            "  {:<12} {:>12} {:>14} {:>8}",
+            "年齡", "Keys", "記憶體 (MB)", "佔比"
+        )?;
+        writeln!(out, "  {}", "-".repeat(118))?;
+        for (&(count, mem), (_, label)) in g.buckets.iter().zip(AGE_BUCKETS) {
+            let pct = if g.total_mem > 0 {
+                mem as f64 / g.total_mem as f64 * 100.0
+            } else {
+                0.0
+            };
+            writeln!(
+                out,
+                "  {:<12} {:>12} {:>14.2} {:>7.1}%  {}",
+                label,
+                format_int(count),
+                Decimal(mem as f64 / 1024.0 / 1024.0),
+                Decimal(pct),
+                ascii_bar(pct, BAR_WIDTH)
+            )?;
+        }
+
+        let Some(retention) = g.retention_secs else {
+            continue;
+        };
+        if g.expired == 0 {
+            writeln!(
+                out,
+                "  ✔ 沒有超過保留期限 {} 的 key",
+                format_secs(retention)
+            )?;
+            continue;
+        }
+        writeln!(
+            out,
+            "  ⚠ 超過保留期限 {}: {} keys，{:.2} MB（早於目前的保留政策，多半是遺留資料）",
+            format_secs(retention),
+            format_int(g.expired),
+            Decimal(g.expired_mem as f64 / 1024.0 / 1024.0)
+        )?;
+        for k in &g.oldest {
+            writeln!(
+                out,
+                "    {:>12.2} MB {:>8}  {}",
+                Decimal(k.mem as f64 / 1024.0 / 1024.0),
+                format_secs(k.age_secs),
+                truncate_key(&k.key, 80)
+            )?;
+        }
+    }
+    if report.compacted {
+        writeln!(out, "\n（snapshot 已壓縮，不含超過保留期限的 key 明細）")?;
+    }
+    Ok(())
+}
+
+/// 幾乎都有 TTL 的前綴中，沒有 TTL 的大 key
+/// 依目前 Top key 的 TTL 推算未來 24 小時的全域 Top N 組成
+///
+/// 假設期間沒有新的寫入：到期的 key 移出，由各類型 Top N 中還在的 key 遞補。
+/// 用來判斷記憶體壓力會不會隨著過期自然消失，還是大 key 會一直留著需要處理。
+fn render_top_decay(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let pool = report.global_top(usize::MAX);
+    if pool.is_empty() || pool.iter().all(|(_, k)| k.ttl_ms.is_none()) {
+        return Ok(());
+    }
+    let alive = |k: &TopKey, hour: u64| match k.ttl_ms {
+        Some(-1) | None => true,
+        Some(t) => t > (hour * 3_600_000) as i64,
+    };
+    let current: Vec<_> = pool.iter().take(DECAY_TOP).collect();
+    let current_mem: u64 = current.iter().map(|(_, k)| k.mem).sum();
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "Top {} 衰減預測（依 TTL 推算未來 24 小時，假設沒有新的寫入）",
+        DECAY_TOP
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "{:>6} {:>14} {:>12} {:>10} {:>14} {:>10}  最大 key",
+        "時間", "原 Top 仍在", "遞補", "合計 (MB)", "較現在", "最大 (MB)"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    for hour in DECAY_HOURS {
+        let top: Vec<_> = pool
+            .iter()
+            .filter(|(_, k)| alive(k, hour))
+            .take(DECAY_TOP)
+            .collect();
+        let kept = current.iter().filter(|(_, k)| alive(k, hour)).count();
+        let mem: u64 = top.iter().map(|(_, k)| k.mem).sum();
+        let change = (mem as f64 - current_mem as f64) / current_mem.max(1) as f64 * 100.0;
+        let (largest_mem, largest) = top
+            .first()
+            .map(|(_, k)| (k.mem, k.key.as_str()))
+            .unwrap_or((0, "-"));
+        writeln!(
+            out,
+            "{:>6} {:>14} {:>12} {:>10.2} {:>13.1}% {:>10.2}  {}",
+            format!("+{}h", hour),
+            format!("{}/{}", kept, current.len()),
+            top.len() - kept,
+            Decimal(mem as f64 / 1024.0 / 1024.0),
+            Decimal(change),
+            Decimal(largest_mem as f64 / 1024.0 / 1024.0),
+            truncate_key(largest, 50)
+        )?;
+    }
+
+    let horizon = DECAY_HOURS[DECAY_HOURS.len() - 1];
+    let persistent: Vec<_> = current.iter().filter(|(_, k)| alive(k, horizon)).collect();
+    let persistent_mem: u64 = persistent.iter().map(|(_, k)| k.mem).sum();
+    let pct = persistent_mem as f64 / current_mem.max(1) as f64 * 100.0;
+
+    if !persistent.is_empty() {
+        writeln!(out, "\n  {} 小時後仍在的大 key:", horizon)?;
+        for (t, k) in &persistent {
+            writeln!(
+                out,
+                "    {:<8} {:>10.2} MB  TTL {:>8}  {}",
+                t.name(),
+                Decimal(k.mem as f64 / 1024.0 / 1024.0),
+                format_ttl(k.ttl_ms),
+                truncate_key(&k.key, 70)
+            )?;
+        }
+    }
+    if pct > DECAY_PERSIST_WARN {
+        writeln!(
+            out,
+            "\n  ⚠ Top {} 有 {:.1}% 的記憶體在 {} 小時後仍在：沒有 TTL 或 TTL 很長，自然過期無法緩解，需要人工處理",
+            current.len(),
+            Decimal(pct),
+            horizon
+        )?;
+    } else {
+        writeln!(
+            out,
+            "\n  ✔ Top {} 有 {:.1}% 的記憶體會在 {} 小時內到期，壓力可望隨過期自然下降",
+            current.len(),
+            Decimal(100.0 - pct),
+            horizon
+        )?;
+    }
+
+    Ok(())
+}
+
+fn render_ttl_suggestions(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    if report.ttl_suggestions.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "TTL 政策建議: 快取前綴中沒有 TTL 的大 key（多半是寫入路徑漏設 TTL）"
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "{:>12} {:<20} {:>12} {:>10} {:>10}  Key",
+        "記憶體 (MB)", "前綴", "前綴 Keys", "有 TTL", "建議 TTL"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    for s in &report.ttl_suggestions {
+        writeln!(
+            out,
+            "{:>12.2} {:<20} {:>12} {:>9.1}% {:>10}  {}",
+            Decimal(s.mem as f64 / 1024.0 / 1024.0),
+            truncate_key(&s.prefix, 20),
+            format_int(s.prefix_count),
+            Decimal(s.volatile_pct),
+            format_secs(s.ttl_secs),
+            truncate_key(&s.key, 60)
+        )?;
+    }
+    writeln!(
+        out,
+        "\n  可用 `cleanup <snapshot> --expire-suggested` 產生對應的 EXPIRE 指令"
+    )?;
+
+    Ok(())
+}
+
+/// 輸出非資料集記憶體區塊，解釋「keys 只佔 used_memory 一部分」的差額
+///
+/// MEMORY STATS / CLIENT LIST 可能被 ACL 或 rename-command 擋掉，此時只印出掃描時記錄的原因。
+fn render_non_dataset_section(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let key_mem_total = report.total_mem();
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "非資料集記憶體")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    match &report.memory_stats {
+        Some(fields) => {
+            let get = |name: &str| {
+                fields
+                    .iter()
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| *v)
+                    .unwrap_or(0.0)
+            };
+
+            let used = get("total.allocated");
+            let pct_of_used = |bytes: f64| {
+                if used > 0.0 {
+                    bytes / used * 100.0
+                } else {
+                    0.0
+                }
+            };
+
+            writeln!(out, "{:<28} {:>15} {:>10}", "項目", "記憶體 (MB)", "佔比")?;
+            writeln!(out, "{}", "-".repeat(120))?;
+
+            let rows: [(&str, f64); 11] = [
+                ("used_memory (total)", used),
+                ("dataset", get("dataset.bytes")),
+                ("overhead (total)", get("overhead.total")),
+                ("startup", get("startup.allocated")),
+                ("replication backlog", get("replication.backlog")),
+                ("clients (replicas)", get("clients.slaves")),
+                ("clients (normal)", get("clients.normal")),
+                ("cluster links", get("cluster.links")),
+                ("AOF buffer", get("aof.buffer")),
+                ("Lua caches", get("lua.caches")),
+                ("functions caches", get("functions.caches")),
+            ];
+
+            for (label, bytes) in rows {
+                writeln!(
+                    out,
+                    "{:<28} {:>15.2} {:>9.2}%",
+                    label,
+                    Decimal(bytes / 1024.0 / 1024.0),
+                    pct_of_used(bytes)
+                )?;
+            }
+
+            writeln!(
+                out,
+                "\n  掃描到的 keys (MEMORY USAGE 加總) {:.2} MB，佔 used_memory {:.2}%",
+                Decimal(key_mem_total as f64 / 1024.0 / 1024.0),
+                pct_of_used(key_mem_total as f64)
+            )?;
+        }
+        None => {
+            writeln!(
+                out,
+                "  無法取得 MEMORY STATS: {}",
+                report.unavailable_reason("MEMORY STATS")
+            )?;
+        }
+    }
+
+    match &report.clients {
+        Some(c) => {
+            writeln!(
+                out,
+                "\n  Clients: {} 個連線 (replica {} 個), tot-mem {:.2} MB, output buffer {:.2} MB, query buffer {:.2} MB",
+                format_int(c.clients),
+                c.replicas,
+                Decimal(c.total_mem as f64 / 1024.0 / 1024.0),
+                Decimal(c.output_mem as f64 / 1024.0 / 1024.0),
+                Decimal(c.query_buf as f64 / 1024.0 / 1024.0)
+            )?;
+            for (omem, desc) in &c.top_omem {
+                writeln!(
+                    out,
+                    "    omem {:>12.3} MB  {}",
+                    Decimal(*omem as f64 / 1024.0 / 1024.0),
+                    desc
+                )?;
+            }
+        }
+        None => {
+            writeln!(
+                out,
+                "\n  無法取得 CLIENT LIST: {}",
+                report.unavailable_reason("CLIENT LIST")
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// EVAL script 快取與 FUNCTION library 的記憶體；這些不在 keyspace 中，SCAN 看不到
+fn render_script_section(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "Lua script / function 快取")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    match &report.memory_info {
+        Some(info) => {
+            let num = |names: &[&str]| {
+                names
+                    .iter()
+                    .find_map(|n| info.get(*n).and_then(|v| v.parse::<u64>().ok()))
+            };
+            let mb = |v: Option<u64>| match v {
+                Some(b) => format!("{:.2} MB", Decimal(b as f64 / 1024.0 / 1024.0)),
+                None => "-".into(),
+            };
+            let count = |v: Option<u64>| v.map(format_int).unwrap_or_else(|| "-".into());
+
+            // 7.0 起拆成 eval / functions，舊版只有 used_memory_scripts / used_memory_lua
+            let scripts = num(&["number_of_cached_scripts"]);
+            writeln!(
+                out,
+                "  EVAL 快取: {} 個 script，快取 {}，Lua VM {}",
+                count(scripts),
+                mb(num(&["used_memory_scripts_eval", "used_memory_scripts"])),
+                mb(num(&["used_memory_vm_eval", "used_memory_lua"]))
+            )?;
+            if num(&["used_memory_functions", "number_of_functions"]).is_some() {
+                writeln!(
+                    out,
+                    "  Functions: {} 個 library / {} 個 function，快取 {}，Lua VM {}",
+                    count(num(&["number_of_libraries"])),
+                    count(num(&["number_of_functions"])),
+                    mb(num(&["used_memory_functions"])),
+                    mb(num(&["used_memory_vm_functions"]))
+                )?;
+            }
+            if scripts.is_some_and(|n| n >= SCRIPT_CACHE_WARN) {
+                writeln!(
+                    out,
+                    "\n  ⚠ EVAL 快取了 {} 個 script：通常是把參數拼進 script 內容，每次都產生新的 script；改用 KEYS / ARGV 傳參數",
+                    count(scripts)
+                )?;
+            }
+        }
+        None => writeln!(
+            out,
+            "  無法取得 INFO memory: {}",
+            report.unavailable_reason("INFO memory")
+        )?,
+    }
+
+    let Some(libs) = &report.functions else {
+        return Ok(());
+    };
+    if libs.is_empty() {
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "\n{:<40} {:<8} {:>10} {:>15}",
+        "Library", "Engine", "Functions", "原始碼 (bytes)"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for l in libs.iter().take(TOP_LIBRARIES) {
+        writeln!(
+            out,
+            "{:<40} {:<8} {:>10} {:>15}",
+            truncate_key(&l.name, 40),
+            l.engine,
+            format_int(l.functions),
+            format_int(l.code_bytes)
+        )?;
+    }
+    if libs.len() > TOP_LIBRARIES {
+        writeln!(
+            out,
+            "（只列出最大的 {} 個，共 {} 個 library）",
+            TOP_LIBRARIES,
+            format_int(libs.len() as u64)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 輸出記憶體碎片分析，碎片過高時特別警告
+///
+/// 碎片高時刪除大 key 並不會把記憶體還給 OS（RSS 不降），這點要在報表中講清楚。
+fn render_fragmentation_section(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "記憶體碎片分析")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    let Some(info) = &report.memory_info else {
+        writeln!(
+            out,
+            "  無法取得 INFO memory: {}",
+            report.unavailable_reason("INFO memory")
+        )?;
+        return Ok(());
+    };
+
+    let num = |name: &str| info.get(name).and_then(|v| v.parse::<f64>().ok());
+
+    let used = num("used_memory").unwrap_or(0.0);
+    let rss = num("used_memory_rss").unwrap_or(0.0);
+    let ratio = num("mem_fragmentation_ratio").unwrap_or(if used > 0.0 { rss / used } else { 0.0 });
+    // 舊版沒有 mem_fragmentation_bytes，用 rss - used 估算
+    let waste = num("mem_fragmentation_bytes")
+        .unwrap_or(rss - used)
+        .max(0.0);
+    let allocator = info
+        .get("mem_allocator")
+        .map(String::as_str)
+        .unwrap_or("unknown");
+
+    writeln!(out, "  allocator:                 {}", allocator)?;
+    writeln!(
+        out,
+        "  used_memory:               {:.2} MB",
+        Decimal(used / 1024.0 / 1024.0)
+    )?;
+    writeln!(
+        out,
+        "  used_memory_rss:           {:.2} MB",
+        Decimal(rss / 1024.0 / 1024.0)
+    )?;
+    writeln!(out, "  mem_fragmentation_ratio:   {:.2}", ratio)?;
+    writeln!(
+        out,
+        "  估計 RSS 浪費:             {:.2} MB",
+        Decimal(waste / 1024.0 / 1024.0)
+    )?;
+
+    if let Some(r) = num("allocator_frag_ratio") {
+        writeln!(
+            out,
+            "  allocator_frag_ratio:      {:.2} ({:.2} MB)",
+            r,
+            Decimal(num("allocator_frag_bytes").unwrap_or(0.0) / 1024.0 / 1024.0)
+        )?;
+    }
+    if let Some(r) = num("allocator_rss_ratio") {
+        writeln!(out, "  allocator_rss_ratio:       {:.2}", r)?;
+    }
+    if let Some(r) = num("rss_overhead_ratio") {
+        writeln!(out, "  rss_overhead_ratio:        {:.2}", r)?;
+    }
+
+    if ratio >= FRAG_RATIO_WARN && waste >= FRAG_BYTES_WARN as f64 {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{}",
+            highlight(
+                format!(
+                    "  ⚠ 碎片過高：約 {:.2} MB 的 RSS 並未用於資料。此時刪除大 key 不會讓 RSS 下降，記憶體不會還給 OS。",
+                    Decimal(waste / 1024.0 / 1024.0)
+                ),
+                true
+            )
+        )?;
+        if allocator.starts_with("jemalloc") {
+            writeln!(
+                out,
+                "  建議：可在離峰時執行 MEMORY PURGE，或啟用 activedefrag 讓 Redis 主動整理碎片。"
+            )?;
+        } else {
+            writeln!(
+                out,
+                "  建議：啟用 activedefrag（需 jemalloc），或在離峰時重啟 / 主從切換以釋放碎片。"
+            )?;
+        }
+    } else if ratio > 0.0 && ratio < 1.0 {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{}",
+            highlight(
+                "  ⚠ mem_fragmentation_ratio < 1：部分記憶體可能被 swap 到磁碟，延遲會明顯上升。"
+                    .to_string(),
+                false
+            )
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 警告以粗體紅（red）/ 黃色顯示；沒有 cli feature（當 lib 使用）時輸出純文字
+#[cfg(feature = "cli")]
+fn highlight(text: String, red: bool) -> String {
+    let styled = console::style(text).bold();
+    if red { styled.red() } else { styled.yellow() }.to_string()
+}
+
+#[cfg(not(feature = "cli"))]
+fn highlight(text: String, _red: bool) -> String {
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::KeyExtra;
+
+    #[test]
+    fn csv_cells_are_unformatted() {
+        let extra = KeyExtra {
+            ttl_ms: Some(1500),
+            elements: Some(12345),
+            ..Default::default()
+        };
+        let k = TopKey::new(3 * 1024 * 1024, "user,1", &extra);
+        let cells: Vec<String> = [
+            Column::Rank,
+            Column::Key,
+            Column::Mb,
+            Column::Bytes,
+            Column::Cum,
+            Column::Ttl,
+            Column::Idle,
+            Column::Elements,
+        ]
+        .iter()
+        .map(|c| c.csv_cell(2, &k, 37.5))
+        .collect();
+        assert_eq!(
+            cells,
+            [
+                "2",
+                "\"user,1\"",
+                "3.000",
+                "3145728",
+                "37.50",
+                "1500",
+                "",
+                "12345"
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn csv_headers_are_unique() {
+        let headers: Vec<&str> = <Column as clap::ValueEnum>::value_variants()
+            .iter()
+            .map(|c| c.csv_header())
+            .collect();
+        let mut unique = headers.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), headers.len());
+    }
+}
@@ -0,0 +1,121 @@
+//! --history-dir 的保留政策：刪除過舊的 snapshot，較舊的只留彙總
+//!
+//! 長期跑 watch / 排程 scan 時歷史目錄會一直長大；大部分空間是每個 key 的 Top N 明細，
+//! trend 只需要前綴與類型小計，所以舊紀錄先壓縮，超過保留範圍才刪除。
+
+use crate::output::warning;
+use crate::report::Report;
+use crate::snapshot;
+use crate::trend::collect_snapshots;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// 未指定的規則不生效；keep_last 與 keep_days 都指定時，符合任一條就保留
+#[derive(Clone, Copy, Default)]
+pub struct RetentionPolicy {
+    pub keep_last: Option<usize>,
+    pub keep_days: Option<u64>,
+    pub compact_after_days: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.keep_last.is_none() && self.keep_days.is_none() && self.compact_after_days.is_none()
+    }
+
+    fn keep(&self, rank: usize, age_secs: u64) -> bool {
+        if self.keep_last.is_none() && self.keep_days.is_none() {
+            return true;
+        }
+        self.keep_last.is_some_and(|n| rank < n)
+            || self.keep_days.is_some_and(|d| age_secs <= d * SECS_PER_DAY)
+    }
+}
+
+#[derive(Default)]
+pub struct PruneResult {
+    pub removed: Vec<PathBuf>,
+    pub compacted: Vec<PathBuf>,
+    pub freed_bytes: u64,
+}
+
+/// 依政策整理目錄；最新的一份永遠保留且不壓縮。
+///
+/// 無法解析的 *.json 不是這個工具寫的，一律不動。
+pub fn prune(dir: &Path, policy: RetentionPolicy, now: u64) -> Result<PruneResult, Box<dyn Error>> {
+    let mut result = PruneResult::default();
+    if policy.is_empty() {
+        return Ok(result);
+    }
+
+    let mut history: Vec<(PathBuf, Report)> = Vec::new();
+    for path in collect_snapshots(&[dir.to_path_buf()])? {
+        match snapshot::load(&path) {
+            Ok(report) => history.push((path, report)),
+            Err(e) => warning!("保留政策略過 {}: {}", path.display(), e),
+        }
+    }
+    history.sort_by_key(|(_, r)| std::cmp::Reverse(r.started_at));
+
+    for (rank, (path, mut report)) in history.into_iter().enumerate() {
+        if rank == 0 {
+            continue;
+        }
+        let age = now.saturating_sub(report.started_at);
+        let before = file_size(&path);
+
+        if !policy.keep(rank, age) {
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("無法刪除 {}: {}", path.display(), e))?;
+            result.freed_bytes += before;
+            result.removed.push(path);
+        } else if !report.compacted
+            && policy
+                .compact_after_days
+                .is_some_and(|d| age > d * SECS_PER_DAY)
+        {
+            compact(&mut report);
+            snapshot::save(&report, &path)?;
+            result.freed_bytes += before.saturating_sub(file_size(&path));
+            result.compacted.push(path);
+        }
+    }
+
+    Ok(result)
+}
+
+/// 去掉每個 key 的明細，保留類型 / 前綴 / 擁有者小計、分布與伺服器資訊
+pub fn compact(report: &mut Report) {
+    for t in &mut report.types {
+        t.top.clear();
+    }
+    for p in &mut report.prefixes {
+        p.top.clear();
+    }
+    report.longest_keys.clear();
+    report.ttl_suggestions.clear();
+    if let Some(z) = &mut report.zombies {
+        z.top.clear();
+    }
+    for g in &mut report.ages {
+        g.oldest.clear();
+    }
+    for d in &mut report.owner_details {
+        for t in &mut d.types {
+            t.top.clear();
+        }
+        for p in &mut d.prefixes {
+            p.top.clear();
+        }
+    }
+    for e in &mut report.element_tops {
+        e.top.clear();
+    }
+    report.compacted = true;
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
@@ -0,0 +1,146 @@
+//! 掃描中定期確認伺服器角色沒有改變（failover、replica 換了 master、實例重啟）
+//!
+//! 角色改變後 SCAN 接著讀的是另一份資料，前後兩段混在一起的報表會誤導；
+//! 預設只警告並在報表標示，--on-role-change abort 時直接中止。
+
+use crate::conn::RedisConn;
+use crate::format::now_unix;
+use crate::output::warning;
+use crate::progress::ProgressBar;
+use crate::server::fetch_info;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+pub const ROLE_EVERY: Duration = Duration::from_secs(5); // 掃描中檢查角色的間隔
+
+/// 掃描中角色改變時的處理方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OnRoleChange {
+    /// 警告並在報表標示，繼續掃描
+    #[default]
+    Warn,
+    /// 中止掃描
+    Abort,
+}
+
+/// 一次角色改變
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RoleChange {
+    pub target: String,
+    pub detected_at: u64, // Unix 秒；實際發生在上一次檢查之後
+    pub before: String,
+    pub after: String,
+}
+
+/// INFO 中判斷角色的欄位；run_id 改變代表實例重啟過
+#[derive(Clone, PartialEq, Eq)]
+struct RoleState {
+    role: String,
+    master: Option<String>, // replica 的 master_host:master_port
+    run_id: Option<String>,
+}
+
+impl RoleState {
+    fn fetch(con: &mut RedisConn) -> Option<Self> {
+        let info = fetch_info(con, "default").ok()?;
+        let master = match (info.get("master_host"), info.get("master_port")) {
+            (Some(h), Some(p)) => Some(format!("{}:{}", h, p)),
+            _ => None,
+        };
+        Some(Self {
+            role: info.get("role")?.clone(),
+            master,
+            run_id: info.get("run_id").cloned(),
+        })
+    }
+
+    fn describe(&self) -> String {
+        let mut s = match &self.master {
+            Some(m) => format!("{}（master {}）", self.role, m),
+            None => self.role.clone(),
+        };
+        if let Some(id) = &self.run_id {
+            s.push_str(" run_id ");
+            s.extend(id.chars().take(8));
+        }
+        s
+    }
+}
+
+pub struct RoleWatch {
+    action: OnRoleChange,
+    target: String,
+    current: Option<RoleState>, // 開始時取不到 INFO 時為 None，之後第一次取到的當作基準
+    last_check: Instant,
+    changes: Vec<RoleChange>,
+}
+
+impl RoleWatch {
+    pub fn new(con: &mut RedisConn, action: OnRoleChange, target: &str) -> Self {
+        Self {
+            action,
+            target: target.to_string(),
+            current: RoleState::fetch(con),
+            last_check: Instant::now(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// 距離上次檢查超過 ROLE_EVERY 才讀 INFO；failover 期間 INFO 失敗時略過這次
+    pub fn check(&mut self, con: &mut RedisConn, pb: &ProgressBar) -> redis::RedisResult<()> {
+        if self.last_check.elapsed() < ROLE_EVERY {
+            return Ok(());
+        }
+        self.recheck(con, pb)
+    }
+
+    /// 掃描結束時再確認一次，最後一段期間的 failover 也會被記錄
+    pub fn finish(
+        mut self,
+        con: &mut RedisConn,
+        pb: &ProgressBar,
+    ) -> redis::RedisResult<Vec<RoleChange>> {
+        self.recheck(con, pb)?;
+        Ok(self.changes)
+    }
+
+    fn recheck(&mut self, con: &mut RedisConn, pb: &ProgressBar) -> redis::RedisResult<()> {
+        self.last_check = Instant::now();
+        let Some(now) = RoleState::fetch(con) else {
+            return Ok(());
+        };
+        let Some(before) = self.current.replace(now.clone()) else {
+            return Ok(());
+        };
+        if before == now {
+            return Ok(());
+        }
+
+        let change = RoleChange {
+            target: self.target.clone(),
+            detected_at: now_unix(),
+            before: before.describe(),
+            after: now.describe(),
+        };
+        pb.suspend(|| {
+            warning!(
+                "⚠ {} 掃描期間角色改變: {} → {}（可能發生 failover，報表會混有改變前後的資料）",
+                change.target,
+                change.before,
+                change.after
+            )
+        });
+        let detail = format!("{}: {} → {}", change.target, change.before, change.after);
+        self.changes.push(change);
+
+        match self.action {
+            OnRoleChange::Warn => Ok(()),
+            OnRoleChange::Abort => Err(redis::RedisError::from((
+                redis::ErrorKind::ClientError,
+                "掃描期間伺服器角色改變，中止掃描",
+                detail,
+            ))),
+        }
+    }
+}
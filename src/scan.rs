@@ -0,0 +1,1932 @@
+use crate::acl::{AclDenial, Collector, denied_reply, is_denied};
+use crate::age::{AgeRules, AgeStats};
+use crate::alert::{EarlyAlertConfig, EarlyAlerts};
+use crate::binwaste::BinWasteStats;
+use crate::capabilities::{self, command_delta};
+use crate::cleanup::glob_match;
+#[cfg(feature = "async")]
+use crate::conn::AsyncConn;
+use crate::conn::{PipelineConn, RedisConn, block_on_ready};
+use crate::dedup::{Dedup, DedupMode};
+use crate::deep::deep_analyze;
+use crate::export::{ExportTarget, KeyExport};
+use crate::format::{Decimal, format_int, format_iso8601, now_unix};
+use crate::guard::{GuardConfig, LoadGuard, RateLimiter};
+use crate::hash::key_hash;
+use crate::impact::{CommandTotals, ScanImpact, probe};
+use crate::index::{IndexTarget, IndexWriter};
+use crate::intstr::{IntStringStats, MAX_INT_LEN};
+use crate::job;
+use crate::latency::{
+    CommandRtt, ESTIMATE_SAMPLES, MeasureLatency, RttSpike, SLOW_MEASURE, SlowMeasure,
+};
+use crate::output::{self, say, warning};
+use crate::overhead::OverheadStats;
+use crate::owners::{OwnerDetails, OwnerRules, OwnerStats};
+use crate::preview::{Redact, fetch_previews};
+use crate::progress::{self, MultiProgress, ProgressBar, ProgressStyle, Ticker};
+use crate::report::{
+    DedupSummary, EmptyReason, EmptyResult, OwnerReport, PrefixReport, Report, SNAPSHOT_VERSION,
+    Sampling, TypeReport,
+};
+use crate::role::{OnRoleChange, RoleWatch};
+use crate::server::{
+    KeyspaceCheck, fetch_activity, fetch_client_buffers, fetch_function_libraries, fetch_info,
+    fetch_memory_stats,
+};
+use crate::stats::{
+    AllStats, ExpiryTimeline, KeyExtra, KeyTypeCode, LongestKeys, PrefixStats, SizeHistogram,
+    parse_type_code,
+};
+use crate::status::StatusBoard;
+use crate::strata::Strata;
+use crate::ttl_policy::TtlPolicy;
+use crate::typecache::TypeCache;
+use crate::validate::ElementTops;
+use crate::zombie::{ZombieFilter, ZombieStats};
+use redis::{self, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_SCAN_COUNT: u64 = 5000; // 每次 SCAN 的 count hint
+pub const DEFAULT_BATCH_SIZE: usize = 2000; // 每批 pipeline key 數
+pub const DEFAULT_PREFIX_TABLES: usize = 5; // 列出 Top key 的前綴數
+const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+const LIVE_TABLE_EVERY: Duration = Duration::from_secs(1); // 掃描中即時統計表的刷新間隔
+const MAX_REDIRECTS: usize = 3; // 單一 key 最多跟隨幾次 MOVED / ASK
+const SHORT_STRING_MEM: u64 = 128; // MEMORY USAGE 不超過此值的 embstr / raw 才抓值檢查是否為整數
+const ERROR_RATE_MIN_KEYS: u64 = 1000; // 至少處理這麼多 key 才判斷錯誤率，避免前幾個 key 失敗就中止
+pub const DEFAULT_DEDUP_MEMORY: u64 = 64 * 1024 * 1024;
+
+/// 掃描參數（對伺服器的負載主要由這幾個值決定）
+#[derive(Clone, Debug)]
+pub struct ScanConfig {
+    pub scan_count: u64,
+    pub batch_size: usize,
+    pub samples: Option<u64>, // MEMORY USAGE SAMPLES；None = 伺服器預設 (5)
+    pub estimate_above: Option<u64>, // 元素數超過此值的 key 不做 SAMPLES 0 / 大 SAMPLES 的精確量測
+    pub throttle: Duration,   // 每批 pipeline 之後暫停多久
+    pub max_keys_per_sec: Option<u64>, // 依檢查的 key 數限速，與 batch 大小無關
+    pub collect_ttl: bool,    // PTTL
+    pub collect_idle: bool,   // OBJECT IDLETIME
+    pub collect_encoding: bool, // OBJECT ENCODING
+    pub collect_freq: bool,   // OBJECT FREQ（只有 LFU maxmemory-policy 可用）
+    pub collect_cardinality: bool, // 另一段 pipeline 查 STRLEN / LLEN / HLEN…（需要先知道類型）
+    pub guard: GuardConfig,   // 伺服器負載超標時暫停
+    pub prefix_sep: char,     // 前綴分組的分隔字元
+    pub prefix_depth: usize,  // 取前幾段當前綴；0 = 不分組
+    pub prefix_tables: usize, // 記憶體最大的幾個前綴各自列出 Top key；0 = 不列
+    pub owners: Option<Arc<OwnerRules>>, // 依團隊彙總；None = 不彙總
+    pub age_rules: Option<Arc<AgeRules>>, // 依 key 名稱推算年齡；None = 不推算
+    pub max_error_rate: Option<f64>, // 失敗 key 的比例超過此值（0~1）就中止
+    pub measure: MeasureFilter, // 兩段式掃描：只對符合的 key 量測記憶體
+    pub dedup: DedupMode,     // 排除 SCAN 重複回傳的 key
+    pub dedup_memory: u64,    // 去重最多使用的記憶體（bytes）
+    pub zombie: ZombieFilter, // 殭屍 key 的門檻（需要 TTL 與 idle）
+    pub retry_vanished: bool, // 量測時已消失的 key 再試一次（可能剛被刪除後重建）
+    pub preview: Option<Redact>, // 替 Top N 抓值預覽；None = 不抓
+    pub progress_interval: Duration, // 進度條與 ETA 的更新間隔
+    pub overhead: bool,       // 估算集合類型的結構開銷（需要 encoding 與元素數）
+    pub int_strings: bool,    // 檢查短 string 是否為存成字串的整數（需要 encoding）
+    pub deep: bool,           // 掃描後對各類型 Top N 做第二階段深度分析
+    pub concurrency: usize,   // 每個節點同時在途的 pipeline 批次數（共用一條 multiplexed 連線）
+    pub deep_parallel: usize, // 第二階段同時使用的連線數
+    pub deep_throttle: Option<Duration>, // 第二階段每個 key 之後暫停多久；None = 沿用 throttle
+    pub impact_probe: Option<Duration>, // 掃描前後各探測延遲多久；None = 不探測
+    pub sample_rate: Option<f64>, // 只量測依 key hash 選中的比例（0~1）；None = 全部量測
+    pub stratify: Option<u64>, // 依類型分層抽樣，每個類型至少完整量測的 key 數；None = 不分層
+    pub early_alert: Option<EarlyAlertConfig>, // 掃描中發現大 key 就提醒；None = 不提醒
+    pub index_to: Option<IndexTarget>, // 每個 key 的大小寫進分析用 Redis；None = 不寫
+    pub export_keys: Option<ExportTarget>, // 每個 key 的量測結果逐筆寫到檔案；None = 不寫
+    pub on_role_change: OnRoleChange, // 掃描中角色改變（failover）時警告或中止
+    pub type_cache: Option<Arc<TypeCache>>, // watch 沿用上一輪的類型，不送 TYPE；None = 每個 key 都送
+    pub status: Option<Arc<StatusBoard>>,   // watch --listen 回報的進度與暫停狀態；None = 不回報
+    pub split_owners: bool, // 依擁有者另外統計類型、前綴與到期時間（--split-report-by owner）
+    pub rank_by_elements: bool, // validate：另外依元素數取各類型的 Top N（需要 collect_cardinality）
+}
+
+/// 兩段式掃描的篩選條件，全部為空時每個 key 都量測（一般模式）
+///
+/// 不符合的 key 只計入類型的 key 數，不送 MEMORY USAGE。
+#[derive(Clone, Debug, Default)]
+pub struct MeasureFilter {
+    pub types: Vec<KeyTypeCode>,   // 只量測這些類型
+    pub pattern: Option<String>,   // 只量測符合 glob 的 key
+    pub min_elements: Option<u64>, // 只量測長度（STRLEN / HLEN…）>= 此值的 key
+}
+
+impl MeasureFilter {
+    pub fn is_active(&self) -> bool {
+        !self.types.is_empty() || self.pattern.is_some() || self.min_elements.is_some()
+    }
+
+    fn wants_type(&self, t: KeyTypeCode) -> bool {
+        self.types.is_empty() || self.types.contains(&t)
+    }
+}
+
+/// 第一段分類的結果
+enum Class {
+    Measure,
+    Skip(KeyTypeCode),
+    Gone, // TYPE 回 none：SCAN 之後已被刪除
+}
+
+/// 第一段：pipeline 送 TYPE，需要的話再對候選 key 送長度指令，決定哪些 key 要量測
+///
+/// pattern 在本地比對，不需要往返；長度只對類型符合的 key 查。
+async fn classify_batch(
+    con: &mut impl PipelineConn,
+    keys: &[String],
+    filter: &MeasureFilter,
+) -> redis::RedisResult<Vec<Class>> {
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.cmd("TYPE").arg(key);
+    }
+    let types = con.send(&pipe, keys.len()).await?;
+
+    let mut classes: Vec<Class> = keys
+        .iter()
+        .zip(&types)
+        .map(|(key, v)| match parse_type_code(v) {
+            None => Class::Gone,
+            Some(t)
+                if filter.wants_type(t)
+                    && filter.pattern.as_deref().is_none_or(|p| glob_match(p, key)) =>
+            {
+                Class::Measure
+            }
+            Some(t) => Class::Skip(t),
+        })
+        .collect();
+
+    let Some(min) = filter.min_elements else {
+        return Ok(classes);
+    };
+
+    let mut pipe = redis::pipe();
+    let mut asked = Vec::new();
+    for (i, (key, v)) in keys.iter().zip(&types).enumerate() {
+        if let (Class::Measure, Some(t)) = (&classes[i], parse_type_code(v)) {
+            pipe.cmd(t.length_command()).arg(key);
+            asked.push((i, t));
+        }
+    }
+    if asked.is_empty() {
+        return Ok(classes);
+    }
+    let lens = con.send(&pipe, asked.len()).await?;
+    for ((i, t), v) in asked.into_iter().zip(&lens) {
+        if parse_int(v).is_none_or(|n| (n.max(0) as u64) < min) {
+            classes[i] = Class::Skip(t);
+        }
+    }
+
+    Ok(classes)
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            scan_count: DEFAULT_SCAN_COUNT,
+            batch_size: DEFAULT_BATCH_SIZE,
+            samples: None,
+            estimate_above: None,
+            throttle: Duration::ZERO,
+            max_keys_per_sec: None,
+            collect_ttl: true,
+            collect_idle: false,
+            collect_encoding: false,
+            collect_freq: false,
+            collect_cardinality: false,
+            guard: GuardConfig::default(),
+            prefix_sep: ':',
+            prefix_depth: 1,
+            prefix_tables: DEFAULT_PREFIX_TABLES,
+            owners: None,
+            age_rules: None,
+            max_error_rate: None,
+            measure: MeasureFilter::default(),
+            dedup: DedupMode::Off,
+            dedup_memory: DEFAULT_DEDUP_MEMORY,
+            zombie: ZombieFilter::default(),
+            retry_vanished: false,
+            preview: None,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            overhead: false,
+            int_strings: false,
+            deep: false,
+            concurrency: 1,
+            deep_parallel: 1,
+            deep_throttle: None,
+            impact_probe: None,
+            sample_rate: None,
+            stratify: None,
+            early_alert: None,
+            index_to: None,
+            export_keys: None,
+            on_role_change: OnRoleChange::Warn,
+            type_cache: None,
+            status: None,
+            split_owners: false,
+            rank_by_elements: false,
+        }
+    }
+}
+
+/// 掃描進度的顯示方式
+///
+/// 平行掃描多個節點時，各節點共用同一個 MultiProgress，每個節點一條進度條，
+/// 另有一條全部節點的合計；此時不輸出逐行訊息與即時統計表，避免互相洗版。
+pub struct ScanDisplay {
+    multi: MultiProgress,
+    shard: Option<ProgressBar>,   // 由呼叫端建立的節點進度條
+    overall: Option<ProgressBar>, // 所有節點合計
+}
+
+impl ScanDisplay {
+    pub fn standalone() -> Self {
+        Self {
+            multi: progress::multi(),
+            shard: None,
+            overall: None,
+        }
+    }
+
+    pub fn shard(multi: &MultiProgress, bar: &ProgressBar, overall: &ProgressBar) -> Self {
+        Self {
+            multi: multi.clone(),
+            shard: Some(bar.clone()),
+            overall: Some(overall.clone()),
+        }
+    }
+}
+
+/// --oneshot-json 收到 SIGTERM / SIGINT 時，記在 report.interrupted 的原因
+fn stop_reason() -> Option<String> {
+    job::stop_requested().map(|signal| format!("收到 {}，掃描提前結束", signal))
+}
+
+/// SCAN 全庫，搭配 pipeline 一次抓 MEMORY USAGE + TYPE + PTTL，最後收集伺服器端資訊組成 Report
+///
+/// `target` 只用來記錄在報表中（例如 "redis://127.0.0.1:6379/"）。
+pub fn scan(con: &mut RedisConn, target: &str, cfg: &ScanConfig) -> redis::RedisResult<Report> {
+    scan_with(con, target, cfg, &ScanDisplay::standalone())
+}
+
+pub fn scan_with(
+    con: &mut RedisConn,
+    target: &str,
+    cfg: &ScanConfig,
+    display: &ScanDisplay,
+) -> redis::RedisResult<Report> {
+    let quiet = display.shard.is_some();
+    let commands_before = con.command_counts();
+    // 掃描前的基準延遲，不算在掃描耗時內
+    let probe_before = cfg.impact_probe.map(|window| {
+        if !quiet {
+            say!("掃描前延遲探測 {:?}…", window);
+        }
+        probe(con, window)
+    });
+
+    let started_at = now_unix();
+    let started = Instant::now();
+
+    // OBJECT IDLETIME / FREQ 在不相符的 maxmemory-policy 下每個 key 都回錯誤，先關掉
+    let requested = cfg;
+    let (cfg, skipped) = match_eviction_policy(con, cfg);
+    let cfg = &cfg;
+    let server_info = fetch_info(con, "server").ok();
+    if !quiet {
+        for (what, why) in &skipped {
+            warning!("不收集 {}: {}", what, why);
+        }
+    }
+
+    // ------------------------------------------------------------
+    // 取得 key 總量（DBSIZE）
+    // ------------------------------------------------------------
+    let total_keys: u64 = redis::cmd("DBSIZE").query(con)?;
+    if !quiet {
+        say!("資料庫共 {} keys\n", format_int(total_keys));
+    }
+    // 掃描開始時的 expired / evicted / hits / misses，結束時再取一次算出期間的變化
+    let activity_start = fetch_activity(con);
+    let commands_start = probe_before.is_some().then(|| CommandTotals::fetch(con));
+
+    // ------------------------------------------------------------
+    // 建立進度條
+    // ------------------------------------------------------------
+    let pb = match &display.shard {
+        Some(bar) => {
+            bar.set_length(total_keys);
+            bar.clone()
+        }
+        None => {
+            let bar = display.multi.add(ProgressBar::new(total_keys));
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "[{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} keys ({percent}%) {msg}",
+                )
+                .unwrap()
+                .progress_chars("=>-"),
+            );
+            bar
+        }
+    };
+    if let Some(overall) = &display.overall {
+        overall.inc_length(total_keys);
+    }
+
+    if !quiet {
+        say!("開始 SCAN + PIPELINE {}...\n", cfg.describe_commands());
+    }
+
+    let mut ticker = Ticker::new(cfg.progress_interval, display.overall.clone())
+        .with_events(output::is_json_lines().then(|| target.to_string()));
+    let mut live = (!quiet).then(|| LiveTable::new(&display.multi));
+    let mut guard = LoadGuard::new(cfg.guard).with_status(cfg.status.clone());
+    let mut role = RoleWatch::new(con, cfg.on_role_change, target);
+    let mut limiter = RateLimiter::new(cfg.max_keys_per_sec);
+    let batch_size = limiter
+        .as_ref()
+        .map_or(cfg.batch_size, |l| l.batch_size(cfg.batch_size));
+
+    let mut stats = AllStats::new();
+    let mut expiry = cfg.collect_ttl.then(ExpiryTimeline::new);
+    let mut prefixes = PrefixStats::new(cfg.prefix_sep, cfg.prefix_depth, cfg.prefix_tables > 0);
+    let mut zombies = (cfg.collect_ttl && cfg.collect_idle).then(|| ZombieStats::new(cfg.zombie));
+    let mut ttl_policy = cfg
+        .collect_ttl
+        .then(|| TtlPolicy::new(cfg.prefix_sep, cfg.prefix_depth));
+    let mut owners = OwnerStats::default();
+    let mut owner_details =
+        (cfg.split_owners && cfg.owners.is_some()).then(|| OwnerDetails::new(cfg));
+    let mut ages = cfg
+        .age_rules
+        .as_ref()
+        .map(|rules| AgeStats::new(rules.clone(), started_at));
+    let mut key_names = SizeHistogram::default();
+    let mut element_tops = (cfg.rank_by_elements && cfg.collect_cardinality).then(ElementTops::new);
+    let mut longest = LongestKeys::default();
+    let mut overhead = (cfg.overhead && cfg.collect_encoding && cfg.collect_cardinality)
+        .then(OverheadStats::default);
+    let mut bin_waste = BinWasteStats::new(cfg.prefix_sep, cfg.prefix_depth);
+    let mut int_strings = (cfg.int_strings && cfg.collect_encoding)
+        .then(|| IntStringStats::new(cfg.prefix_sep, cfg.prefix_depth));
+
+    let mut cursor: u64 = 0;
+    let mut scanned: u64 = 0;
+    let mut errors: u64 = 0;
+    let mut vanished: u64 = 0; // SCAN 回傳、量測時已不存在，不算錯誤
+    let mut unsampled: u64 = 0; // --sample-rate 沒抽中的 key
+    let mut strata = cfg
+        .stratify
+        .zip(cfg.sample_rate)
+        .map(|(min, rate)| Strata::new(min, rate));
+    let mut error_kinds = ErrorTally::default();
+    let mut dedup = Dedup::new(cfg.dedup, cfg.dedup_memory);
+    let mut early = cfg
+        .early_alert
+        .as_ref()
+        .map(|c| EarlyAlerts::new(c, target));
+    let mut index = cfg
+        .index_to
+        .as_ref()
+        .map(|t| IndexWriter::open(t, target, started_at))
+        .transpose()?;
+    let mut export = cfg
+        .export_keys
+        .as_ref()
+        .map(|t| KeyExport::open(t, target))
+        .transpose()?;
+    let mut latency = MeasureLatency::new(cfg.estimate_above);
+    let mut rtt = CommandRtt::default();
+    // 只有設定的 SAMPLES 比估計用的貴時，大 key 才需要改用估計值
+    let estimate = cfg
+        .estimate_above
+        .filter(|_| cfg.samples.is_some_and(|n| n == 0 || n > ESTIMATE_SAMPLES))
+        .map(|_| ScanConfig {
+            samples: Some(ESTIMATE_SAMPLES),
+            ..cfg.clone()
+        });
+
+    // --concurrency：量測改走共用一條 multiplexed 連線的 lane，同時有多批 pipeline 在途
+    let mut lanes = None;
+    if cfg.concurrency > 1 {
+        match Lanes::open(con, cfg.concurrency) {
+            Ok(l) => lanes = Some(l),
+            Err(e) => pb.suspend(|| warning!("⚠ 無法開啟 multiplexed 連線，改為一次一批: {}", e)),
+        }
+    }
+    // 同時量測時累積到每個 lane 都有一整批才送出；一次一批時每次 SCAN 的結果直接送出
+    let wave_size = batch_size * lanes.as_ref().map_or(1, Lanes::len);
+    let fill = if lanes.is_some() { wave_size } else { 1 };
+    let mut pending: Vec<String> = Vec::new();
+    let mut interrupted: Option<String>; // --oneshot-json 收到 SIGTERM / SIGINT：停在目前的批次，輸出已掃描的部分
+    // ACL 擋掉的選用指令：之後的批次改用關掉該項目的設定（與對應的 estimate）
+    let mut acl_denied: Vec<AclDenial> = Vec::new();
+    let mut narrowed: Option<(ScanConfig, Option<ScanConfig>)> = None;
+
+    loop {
+        interrupted = stop_reason();
+        if interrupted.is_some() {
+            break;
+        }
+        let scan_sent = Instant::now();
+        let (next_cursor, mut keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("COUNT")
+            .arg(cfg.scan_count)
+            .query(con)?;
+        let scan_rtt = scan_sent.elapsed();
+        let scan_spike = rtt.add_scan(scan_rtt);
+        if let Some(p50_us) = scan_spike {
+            rtt.add_spike(RttSpike {
+                target: target.to_string(),
+                at_secs: started.elapsed().as_secs(),
+                scan: true,
+                ms: scan_rtt.as_millis() as u64,
+                p50_us,
+                keys: keys.len() as u64,
+                type_code: None,
+                key: None,
+                mem: None,
+                scan_slow: false,
+            });
+        }
+
+        cursor = next_cursor;
+        if let Some(dedup) = &mut dedup {
+            keys.retain(|k| dedup.first_seen(k));
+        }
+        pending.append(&mut keys);
+        if pending.len() < fill && cursor != 0 {
+            continue;
+        }
+        let mut keys = std::mem::take(&mut pending);
+        if let Some(rate) = cfg.sample_rate {
+            let before = keys.len();
+            match &mut strata {
+                Some(strata) => keys = strata.select(con, keys)?,
+                None => keys.retain(|k| in_sample(k, rate)),
+            }
+            unsampled += (before - keys.len()) as u64;
+        }
+
+        // 每個 chunk 做一次 pipeline；一波最多每條連線一個 chunk
+        for wave in keys.chunks(wave_size) {
+            interrupted = stop_reason();
+            if interrupted.is_some() {
+                break;
+            }
+            if let Some(limiter) = &mut limiter {
+                for chunk in wave.chunks(batch_size) {
+                    limiter.consume(chunk.len() as u64);
+                }
+            }
+            let (wave_cfg, wave_estimate) = match &narrowed {
+                Some((c, e)) => (c, e.as_ref()),
+                None => (cfg, estimate.as_ref()),
+            };
+            let fetched = fetch_wave(
+                con,
+                lanes.as_mut(),
+                wave,
+                batch_size,
+                wave_cfg,
+                wave_estimate,
+                strata.as_ref(),
+            );
+            let mut denied: Vec<(Collector, String)> = Vec::new();
+            for f in fetched {
+                for t in f.skipped {
+                    stats.get_mut(t).add_unmeasured();
+                    scanned += 1;
+                }
+                vanished += f.gone;
+                let chunk = &f.keys[..];
+                match f.result {
+                    Ok((batch_results, elapsed)) => {
+                        if let Some(p50_us) = rtt.add_pipeline(elapsed) {
+                            let biggest = slowest_in_batch(target, elapsed, chunk, &batch_results);
+                            rtt.add_spike(RttSpike {
+                                target: target.to_string(),
+                                at_secs: started.elapsed().as_secs(),
+                                scan: false,
+                                ms: elapsed.as_millis() as u64,
+                                p50_us,
+                                keys: chunk.len() as u64,
+                                type_code: biggest.as_ref().map(|m| m.type_code),
+                                mem: biggest.as_ref().map(|m| m.mem),
+                                key: biggest.map(|m| m.key),
+                                scan_slow: scan_spike.is_some(),
+                            });
+                        }
+                        if elapsed >= SLOW_MEASURE {
+                            if let Some(m) =
+                                slowest_in_batch(target, elapsed, chunk, &batch_results)
+                            {
+                                if let Some(warning) = latency.add_slow(m) {
+                                    pb.suspend(|| warning!("{}", warning));
+                                }
+                            }
+                        }
+                        if let Some(cache) = &cfg.type_cache {
+                            cache.record(
+                                chunk
+                                    .iter()
+                                    .zip(&batch_results)
+                                    .filter(|(_, m)| m.error.is_none())
+                                    .map(|(key, m)| (key, m.mem.and(m.type_code))),
+                            );
+                        }
+                        let mut short_strings: Vec<&str> = Vec::new();
+                        for (key, mut meta) in chunk.iter().zip(batch_results) {
+                            denied.append(&mut meta.denied);
+                            match (meta.mem, meta.type_code) {
+                                (Some(mem), Some(type_code)) => {
+                                    if meta.extra.estimated {
+                                        latency.add_estimated(mem);
+                                    }
+                                    stats.get_mut(type_code).add_key(mem, key, &meta.extra);
+                                    if let Some(strata) = &mut strata {
+                                        strata.record(key, type_code, mem);
+                                    }
+                                    if let Some(tops) = &mut element_tops {
+                                        tops.add_key(type_code, mem, key, &meta.extra);
+                                    }
+                                    if let Some(early) = &mut early {
+                                        early.check(&pb, type_code, key, mem);
+                                    }
+                                    if let Some(index) = &mut index {
+                                        index.add_key(key, type_code, mem, &meta.extra);
+                                    }
+                                    if let Some(export) = &mut export {
+                                        export.add_key(key, type_code, mem, &meta.extra);
+                                    }
+                                    prefixes.add_key(key, mem, type_code);
+                                    key_names.add(key.len() as u64);
+                                    longest.add_key(mem, key, &meta.extra);
+                                    if type_code == KeyTypeCode::String {
+                                        bin_waste.add_key(key, mem);
+                                    }
+                                    if let (Some(overhead), Some(enc), Some(n)) =
+                                        (&mut overhead, &meta.extra.encoding, meta.extra.elements)
+                                    {
+                                        if type_code != KeyTypeCode::String {
+                                            overhead.add_key(type_code, enc, n, mem);
+                                        }
+                                    }
+                                    if let (Some(ints), KeyTypeCode::String) =
+                                        (&mut int_strings, type_code)
+                                    {
+                                        let enc = meta.extra.encoding.as_deref();
+                                        ints.add_string(key, enc == Some("int"));
+                                        if matches!(enc, Some("embstr" | "raw"))
+                                            && mem <= SHORT_STRING_MEM
+                                        {
+                                            short_strings.push(key);
+                                        }
+                                    }
+                                    if let Some(rules) = &cfg.owners {
+                                        let owner = rules.owner_of(key);
+                                        owners.add_key(owner, mem);
+                                        if let Some(details) = &mut owner_details {
+                                            details.add_key(
+                                                owner,
+                                                key,
+                                                type_code,
+                                                mem,
+                                                &meta.extra,
+                                            );
+                                        }
+                                    }
+                                    if let Some(ages) = &mut ages {
+                                        ages.add_key(key, mem, meta.extra.idle_secs);
+                                    }
+                                    if let (Some(expiry), Some(pttl)) =
+                                        (&mut expiry, meta.extra.ttl_ms)
+                                    {
+                                        expiry.add_key(pttl, mem);
+                                    }
+                                    if let Some(zombies) = &mut zombies {
+                                        zombies.add_key(mem, key, &meta.extra);
+                                    }
+                                    if let (Some(policy), Some(pttl)) =
+                                        (&mut ttl_policy, meta.extra.ttl_ms)
+                                    {
+                                        policy.add_key(key, mem, pttl);
+                                    }
+                                    scanned += 1;
+                                }
+                                _ => match &meta.error {
+                                    // 量測本身的指令被擋，其他 key 也不會有結果
+                                    Some(e) if is_denied(e) => {
+                                        pb.abandon_with_message("ACL 權限不足，中止掃描");
+                                        return Err(redis::RedisError::from((
+                                            redis::ErrorKind::ClientError,
+                                            "ACL 使用者沒有量測所需的權限，中止掃描",
+                                            format!(
+                                                "{}；至少需要 +scan +dbsize +memory|usage +type",
+                                                e
+                                            ),
+                                        )));
+                                    }
+                                    Some(e) => {
+                                        errors += 1;
+                                        error_kinds.add(e);
+                                    }
+                                    None => vanished += 1,
+                                },
+                            }
+                        }
+
+                        if let Some(ints) = &mut int_strings {
+                            if let Some(e) = check_int_strings(con, &short_strings, ints) {
+                                denied.push((Collector::IntStrings, e));
+                            }
+                        }
+                        if let Some(index) = &mut index {
+                            index.flush(&pb);
+                        }
+                        if let Some(export) = &mut export {
+                            export.flush(&pb);
+                        }
+                    }
+                    Err(e) => {
+                        warning!("Pipeline 批次錯誤: {}", e);
+                        errors += chunk.len() as u64;
+                        error_kinds.add_n(&e.to_string(), chunk.len() as u64);
+                    }
+                }
+
+                if let Some(max) = cfg.max_error_rate {
+                    let done = scanned + errors + vanished;
+                    if done >= ERROR_RATE_MIN_KEYS.min(total_keys.max(1))
+                        && errors as f64 > done as f64 * max
+                    {
+                        pb.abandon_with_message("錯誤率過高，中止掃描");
+                        return Err(redis::RedisError::from((
+                            redis::ErrorKind::ClientError,
+                            "錯誤率超過 --max-error-rate，中止掃描",
+                            error_kinds.diagnose(errors, done, max),
+                        )));
+                    }
+                }
+
+                ticker.tick(&pb, scanned + unsampled, total_keys);
+                if let Some(status) = &cfg.status {
+                    status.progress(scanned + unsampled, total_keys);
+                }
+                if let Some(live) = &mut live {
+                    live.maybe_render(&stats, scanned);
+                }
+
+                if !cfg.throttle.is_zero() {
+                    std::thread::sleep(cfg.throttle);
+                }
+                guard.check(con, &pb)?;
+                role.check(con, &pb)?;
+            }
+
+            // 第一次被 ACL 擋掉：之後不再送這個指令，已收集的部分也不列出（只涵蓋掃描開頭的 key）
+            for (c, e) in denied {
+                if acl_denied.iter().any(|d| d.command == c.command()) {
+                    continue;
+                }
+                let d = c.denial(target, &e);
+                pb.suspend(|| {
+                    warning!(
+                        "⚠ ACL 使用者沒有 {} 權限，本次掃描不再送出（需要 {}）；報表缺少: {}",
+                        d.command,
+                        d.acl,
+                        d.sections.join("、")
+                    )
+                });
+                acl_denied.push(d);
+                let (mut narrow, mut narrow_estimate) = narrowed
+                    .take()
+                    .unwrap_or_else(|| (cfg.clone(), estimate.clone()));
+                c.disable(&mut narrow);
+                if let Some(e) = &mut narrow_estimate {
+                    c.disable(e);
+                }
+                narrowed = Some((narrow, narrow_estimate));
+                match c {
+                    Collector::Ttl => {
+                        expiry = None;
+                        zombies = None;
+                        ttl_policy = None;
+                    }
+                    Collector::Idle => zombies = None,
+                    Collector::Encoding => {
+                        overhead = None;
+                        int_strings = None;
+                    }
+                    Collector::Elements => {
+                        overhead = None;
+                        element_tops = None;
+                    }
+                    Collector::IntStrings => int_strings = None,
+                    Collector::Freq => {}
+                }
+            }
+        }
+
+        if cursor == 0 || interrupted.is_some() {
+            break;
+        }
+    }
+
+    ticker.finish(&pb, scanned + unsampled, total_keys);
+    if let Some(live) = &live {
+        live.finish();
+    }
+
+    let key_rate = limiter.map(RateLimiter::finish);
+    if let Some(early) = early {
+        early.finish();
+    }
+    if !quiet {
+        let secs = started.elapsed().as_secs_f64().max(0.001);
+        say!(
+            "\n{}！共掃描 {} keys (錯誤: {}，掃描期間消失: {}，平均 {} keys/s)\n",
+            if interrupted.is_some() {
+                "已中斷"
+            } else {
+                "完成"
+            },
+            format_int(scanned),
+            errors,
+            format_int(vanished),
+            format_int(((scanned + errors + vanished) as f64 / secs) as u64)
+        );
+    }
+
+    let (pauses, max_repl_lag) = guard.finish();
+    let role_changes = role.finish(con, &pb)?;
+
+    let types = type_reports(&stats);
+
+    let mut report = Report {
+        format_version: SNAPSHOT_VERSION,
+        target: target.to_string(),
+        started_at,
+        duration_ms: 0,
+        started_at_utc: format_iso8601(started_at),
+        finished_at_utc: String::new(),
+        notes: Vec::new(),
+        scanned,
+        errors,
+        vanished,
+        types,
+        expiry,
+        memory_stats: None,
+        clients: None,
+        memory_info: None,
+        unavailable: Vec::new(),
+        pauses,
+        max_repl_lag,
+        role_changes,
+        key_rate,
+        nodes: Vec::new(),
+        duplicates: dedup.as_ref().map(|d| DedupSummary {
+            suppressed: d.duplicates,
+            approximate: d.is_approximate(),
+        }),
+        sampling: cfg.sample_rate.map(|rate| Sampling {
+            rate,
+            skipped: unsampled,
+            strata: strata.map(Strata::into_strata).unwrap_or_default(),
+        }),
+        prefixes: prefix_reports(prefixes, cfg.prefix_tables),
+        owners: owners
+            .into_sorted()
+            .into_iter()
+            .map(|(owner, count, total_mem)| OwnerReport {
+                owner,
+                count,
+                total_mem,
+            })
+            .collect(),
+        zombies: zombies.map(ZombieStats::into_report),
+        key_names,
+        bin_waste: bin_waste.into_reports(),
+        shared_prefixes: Vec::new(),
+        overhead: overhead
+            .map(OverheadStats::into_reports)
+            .unwrap_or_default(),
+        activity: None,
+        functions: None,
+        keyspace_checks: Vec::new(),
+        compacted: false,
+        impact: Vec::new(),
+        latency,
+        rtt,
+        capabilities: Vec::new(),
+        ages: ages.map(AgeStats::into_reports).unwrap_or_default(),
+        owner_details: owner_details
+            .map(|d| d.into_reports(cfg.prefix_tables))
+            .unwrap_or_default(),
+        scope: None,
+        empty: None,
+        element_tops: element_tops
+            .map(ElementTops::into_reports)
+            .unwrap_or_default(),
+        interrupted,
+        acl_denied,
+        int_strings: int_strings
+            .map(IntStringStats::into_reports)
+            .unwrap_or_default(),
+        longest_keys: longest.into_vec(),
+        ttl_suggestions: ttl_policy
+            .map(TtlPolicy::into_suggestions)
+            .unwrap_or_default(),
+    };
+
+    // 中斷時只收集伺服器端資訊（各一個指令），費時的第二階段與延遲探測都略過
+    let full = report.interrupted.is_none();
+    if cfg.deep && full {
+        let throttle = cfg.deep_throttle.unwrap_or(cfg.throttle);
+        let (analyzed, err) = deep_analyze(
+            con,
+            &mut report,
+            cfg.deep_parallel,
+            throttle,
+            cfg.estimate_above,
+        );
+        if !quiet {
+            say!("第二階段：已深度分析 {} 個 Top key\n", analyzed);
+        }
+        if let Some(e) = err {
+            report.unavailable.push(("深度分析".into(), e));
+        }
+    }
+
+    if let (Some(redact), true) = (cfg.preview, full) {
+        if let Some(e) = fetch_previews(con, &mut report, redact) {
+            report.unavailable.push(("值預覽".into(), e));
+        }
+    }
+
+    // ------------------------------------------------------------
+    // 伺服器端資訊：MEMORY STATS / CLIENT LIST / INFO memory / INFO stats
+    // 可能被 ACL 或 rename-command 擋掉，失敗時只記錄原因不中斷
+    // ------------------------------------------------------------
+    match fetch_memory_stats(con) {
+        Ok(v) => report.memory_stats = Some(v),
+        Err(e) => report
+            .unavailable
+            .push(("MEMORY STATS".into(), e.to_string())),
+    }
+    match fetch_client_buffers(con) {
+        Ok(v) => report.clients = Some(v),
+        Err(e) => report
+            .unavailable
+            .push(("CLIENT LIST".into(), e.to_string())),
+    }
+    match fetch_function_libraries(con) {
+        Ok(v) => report.functions = Some(v),
+        Err(e) => report
+            .unavailable
+            .push(("FUNCTION LIST".into(), e.to_string())),
+    }
+    match fetch_info(con, "memory") {
+        Ok(v) => report.memory_info = Some(v),
+        Err(e) => report
+            .unavailable
+            .push(("INFO memory".into(), e.to_string())),
+    }
+
+    let activity_end = fetch_activity(con);
+    let commands_end = commands_start.is_some().then(|| CommandTotals::fetch(con));
+    if let Ok(dbsize_end) = redis::cmd("DBSIZE").query::<u64>(con) {
+        let db = con.db();
+        let unmeasured: u64 = report.types.iter().map(|t| t.unmeasured).sum();
+        report.keyspace_checks.push(KeyspaceCheck {
+            target: target.to_string(),
+            db,
+            dbsize_start: total_keys,
+            dbsize_end,
+            info_start: activity_start.as_ref().ok().map(|(c, _)| c.db_keys(db)),
+            info_end: activity_end.as_ref().ok().map(|(c, _)| c.db_keys(db)),
+            seen: scanned + errors + vanished + unmeasured + unsampled,
+        });
+    }
+    match (activity_start, activity_end) {
+        (Ok((start, _)), Ok((end, stats))) => {
+            report.activity = Some(start.delta(&end, started.elapsed().as_millis() as u64));
+            // active expire 抽樣時看到的已過期比例，用來推估還沒被回收的過期資料
+            if let Some(expiry) = &mut report.expiry {
+                if let Some(perc) = stats
+                    .get("expired_stale_perc")
+                    .and_then(|v| v.parse::<f64>().ok())
+                {
+                    expiry.set_stale_perc(perc);
+                }
+            }
+        }
+        (Err(e), _) | (_, Err(e)) => report
+            .unavailable
+            .push(("INFO stats".into(), e.to_string())),
+    }
+
+    report.unavailable.extend(skipped);
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    report.finished_at_utc = format_iso8601(now_unix());
+
+    if let (Some(before), Some(start), Some(end), Some(window), true) = (
+        probe_before,
+        commands_start,
+        commands_end,
+        cfg.impact_probe,
+        full,
+    ) {
+        if !quiet {
+            say!("掃描後延遲探測 {:?}…", window);
+        }
+        let impact = before.and_then(|before| {
+            Ok(ScanImpact {
+                target: target.to_string(),
+                before,
+                during: start?.window(&end?, report.duration_ms),
+                after: probe(con, window)?,
+            })
+        });
+        match impact {
+            Ok(v) => report.impact.push(v),
+            Err(e) => report.unavailable.push(("延遲探測".into(), e.to_string())),
+        }
+    }
+
+    report.empty = empty_result(&report, total_keys, cfg);
+
+    let commands = command_delta(&commands_before, &con.command_counts());
+    let caps = capabilities::describe(
+        target,
+        server_info.as_ref(),
+        &report,
+        requested,
+        narrowed.as_ref().map_or(cfg, |(c, _)| c),
+        commands,
+    );
+    report.capabilities.push(caps);
+
+    // 中斷的掃描不更新 --index-to 的 latest，也不寫 --export-keys 的完成標記（每批都已 flush）
+    if let (Some(index), true) = (index, full) {
+        if let Some(e) = index.finish(&report, &pb) {
+            report.unavailable.push(("--index-to".into(), e));
+        }
+    }
+    if let (Some(export), true) = (export, full) {
+        if let Some(e) = export.finish(&report, &pb) {
+            report.unavailable.push(("--export-keys".into(), e));
+        }
+    }
+
+    Ok(report)
+}
+
+/// 過慢的一批：記下其中最大的 key（最可能的原因）
+fn slowest_in_batch(
+    target: &str,
+    elapsed: Duration,
+    keys: &[String],
+    metas: &[KeyMeta],
+) -> Option<SlowMeasure> {
+    let (key, meta) = keys
+        .iter()
+        .zip(metas)
+        .filter(|(_, m)| m.mem.is_some() && m.type_code.is_some())
+        .max_by_key(|(_, m)| m.mem)?;
+    Some(SlowMeasure {
+        target: target.to_string(),
+        deep: false,
+        ms: elapsed.as_millis() as u64,
+        keys: keys.len() as u64,
+        type_code: meta.type_code?,
+        key: key.clone(),
+        mem: meta.mem?,
+        elements: meta.extra.elements,
+    })
+}
+
+/// 以 key 的 hash 決定是否抽中：同一個 key 每次掃描的結果相同，兩次抽樣掃描可以互相比較
+pub fn in_sample(key: &str, rate: f64) -> bool {
+    (key_hash(key) as f64) < rate * u64::MAX as f64
+}
+
+/// 依 maxmemory-policy 關掉不會有結果的指令，回傳調整後的設定與（項目, 原因）
+///
+/// LFU policy 下 OBJECT IDLETIME 會回錯誤，其他 policy 下 OBJECT FREQ 會回錯誤；
+/// 取不到 INFO memory 時照原設定送，錯誤會計入失敗 key。
+pub fn match_eviction_policy(
+    con: &mut RedisConn,
+    cfg: &ScanConfig,
+) -> (ScanConfig, Vec<(String, String)>) {
+    let mut cfg = cfg.clone();
+    let mut skipped = Vec::new();
+    if !cfg.collect_idle && !cfg.collect_freq {
+        return (cfg, skipped);
+    }
+    let Some(policy) = fetch_info(con, "memory")
+        .ok()
+        .and_then(|m| m.get("maxmemory_policy").cloned())
+    else {
+        return (cfg, skipped);
+    };
+
+    let lfu = policy.contains("lfu");
+    if cfg.collect_idle && lfu {
+        cfg.collect_idle = false;
+        skipped.push((
+            "OBJECT IDLETIME".into(),
+            format!("maxmemory-policy 為 {}，LFU 不記錄閒置時間", policy),
+        ));
+    }
+    if cfg.collect_freq && !lfu {
+        cfg.collect_freq = false;
+        skipped.push((
+            "OBJECT FREQ".into(),
+            format!("maxmemory-policy 為 {}，只有 LFU 會記錄存取頻率", policy),
+        ));
+    }
+    (cfg, skipped)
+}
+
+/// 依 KeyTypeCode::all() 順序的各類型結果
+pub fn type_reports(stats: &AllStats) -> Vec<TypeReport> {
+    KeyTypeCode::all()
+        .iter()
+        .map(|t| {
+            let st = stats.get(*t);
+            TypeReport {
+                type_code: *t,
+                count: st.count,
+                total_mem: st.total_mem,
+                top: st.sorted_top_desc().into_iter().collect(),
+                sizes: st.sizes.clone(),
+                unmeasured: st.unmeasured,
+                mem_sq: st.mem_sq,
+            }
+        })
+        .collect()
+}
+
+/// 依記憶體 desc 的前綴結果；只有前 tables 個前綴保留 Top key
+pub fn prefix_reports(prefixes: PrefixStats, tables: usize) -> Vec<PrefixReport> {
+    prefixes
+        .into_sorted()
+        .into_iter()
+        .enumerate()
+        .map(
+            |(rank, (prefix, count, total_mem, name_bytes, top, bands))| PrefixReport {
+                prefix,
+                count,
+                total_mem,
+                name_bytes,
+                top: if rank < tables { top } else { Vec::new() },
+                bands,
+            },
+        )
+        .collect()
+}
+
+/// 沒有量測到任何 key 時說明原因；有 key 失敗時不算空結果（由錯誤率處理）
+fn empty_result(report: &Report, total_keys: u64, cfg: &ScanConfig) -> Option<EmptyResult> {
+    if report.types.iter().any(|t| t.count > 0) || report.errors > 0 {
+        return None;
+    }
+    let mut filters = Vec::new();
+    if !cfg.measure.types.is_empty() {
+        let names: Vec<&str> = cfg.measure.types.iter().map(|t| t.name()).collect();
+        filters.push(format!("--measure-types {}", names.join(",")));
+    }
+    if let Some(p) = &cfg.measure.pattern {
+        filters.push(format!("--measure-match {}", p));
+    }
+    if let Some(n) = cfg.measure.min_elements {
+        filters.push(format!("--measure-min-elements {}", n));
+    }
+    if let Some(rate) = cfg.sample_rate {
+        // 換回使用者輸入的百分比，四捨五入去掉浮點誤差
+        let percent = (rate * 100.0 * 1e6).round() / 1e6;
+        filters.push(format!("--sample-rate {}%", percent));
+    }
+
+    let unmeasured: u64 = report.types.iter().map(|t| t.unmeasured).sum();
+    let unsampled = report.sampling.as_ref().map_or(0, |s| s.skipped);
+    let reason = if unmeasured + unsampled > 0 {
+        EmptyReason::Filtered
+    } else if total_keys == 0 && report.vanished == 0 {
+        EmptyReason::Database
+    } else {
+        EmptyReason::Vanished
+    };
+    Some(EmptyResult {
+        reason,
+        dbsize: total_keys,
+        filters,
+    })
+}
+
+/// 單一 key 從 pipeline 取回的資訊
+pub struct KeyMeta {
+    pub mem: Option<u64>,
+    pub type_code: Option<KeyTypeCode>,
+    pub extra: KeyExtra,
+    pub error: Option<String>,            // 第一個失敗指令的錯誤回覆
+    pub denied: Vec<(Collector, String)>, // 被 ACL 擋掉的選用指令與錯誤回覆
+}
+
+impl KeyMeta {
+    /// 沒有錯誤回覆但查不到記憶體或類型：key 在 SCAN 之後被刪除或過期
+    pub fn vanished(&self) -> bool {
+        self.error.is_none() && (self.mem.is_none() || self.type_code.is_none())
+    }
+}
+
+impl ScanConfig {
+    /// 每個 key 在 pipeline 中送出的指令數（MEMORY USAGE, TYPE 固定，其餘依設定）
+    pub fn cmds_per_key(&self) -> usize {
+        2 + self.collect_ttl as usize
+            + self.collect_idle as usize
+            + self.collect_encoding as usize
+            + self.collect_freq as usize
+    }
+
+    /// 每個 key 會送出的指令，例如 "MEMORY USAGE + TYPE + PTTL"
+    pub fn describe_commands(&self) -> String {
+        let mut cmds = vec!["MEMORY USAGE", "TYPE"];
+        if self.collect_ttl {
+            cmds.push("PTTL");
+        }
+        if self.collect_idle {
+            cmds.push("OBJECT IDLETIME");
+        }
+        if self.collect_encoding {
+            cmds.push("OBJECT ENCODING");
+        }
+        if self.collect_freq {
+            cmds.push("OBJECT FREQ");
+        }
+        if self.collect_cardinality {
+            cmds.push("長度（LLEN / HLEN 等）");
+        }
+        if self.measure.is_active() {
+            return format!("TYPE → 篩選 → {}", cmds.join(" + "));
+        }
+        cmds.join(" + ")
+    }
+
+    /// 會影響結果的設定（checkpoint 用來判斷能否沿用）；節流與負載保護可以在續跑時調整，不算在內
+    pub fn fingerprint(&self) -> String {
+        let base = ScanConfig::default();
+        format!(
+            "{:?}",
+            ScanConfig {
+                scan_count: base.scan_count,
+                batch_size: base.batch_size,
+                throttle: base.throttle,
+                max_keys_per_sec: None,
+                guard: base.guard,
+                progress_interval: base.progress_interval,
+                impact_probe: None,
+                early_alert: None,
+                index_to: None,
+                export_keys: None,
+                on_role_change: OnRoleChange::default(),
+                type_cache: None,
+                status: None,
+                concurrency: base.concurrency,
+                deep_parallel: base.deep_parallel,
+                deep_throttle: None,
+                ..self.clone()
+            }
+        )
+    }
+}
+
+/// 把單一 key 的 MEMORY USAGE + TYPE（+ PTTL / OBJECT IDLETIME / OBJECT ENCODING / OBJECT FREQ）加進 pipeline
+///
+/// asking = true 時每個指令前都加 ASKING（ASK 轉址只對下一個指令有效）。
+/// with_type = false 時不送 TYPE（類型快取已知道類型）。
+fn push_key_cmds(
+    pipe: &mut redis::Pipeline,
+    key: &str,
+    cfg: &ScanConfig,
+    asking: bool,
+    with_type: bool,
+) {
+    let prefix = |pipe: &mut redis::Pipeline| {
+        if asking {
+            pipe.cmd("ASKING");
+        }
+    };
+
+    // MEMORY USAGE key [SAMPLES n]
+    prefix(pipe);
+    pipe.cmd("MEMORY").arg("USAGE").arg(key);
+    if let Some(n) = cfg.samples {
+        pipe.arg("SAMPLES").arg(n);
+    }
+    // TYPE key
+    if with_type {
+        prefix(pipe);
+        pipe.cmd("TYPE").arg(key);
+    }
+    if cfg.collect_ttl {
+        prefix(pipe);
+        pipe.cmd("PTTL").arg(key);
+    }
+    if cfg.collect_idle {
+        prefix(pipe);
+        pipe.cmd("OBJECT").arg("IDLETIME").arg(key);
+    }
+    if cfg.collect_encoding {
+        prefix(pipe);
+        pipe.cmd("OBJECT").arg("ENCODING").arg(key);
+    }
+    if cfg.collect_freq {
+        prefix(pipe);
+        pipe.cmd("OBJECT").arg("FREQ").arg(key);
+    }
+}
+
+/// 送出 pipeline，回傳每個指令各自的結果（個別指令的錯誤以 Value::ServerError 留在原位）
+pub fn send_pipeline(
+    con: &mut RedisConn,
+    pipe: &redis::Pipeline,
+    count: usize,
+) -> redis::RedisResult<Vec<Value>> {
+    block_on_ready(con.send(pipe, count))
+}
+
+/// 回覆中的 MOVED / ASK 轉址：(host, port, 是否為 ASK)
+fn find_redirect(vals: &[Value]) -> Option<(String, u16, bool)> {
+    vals.iter().find_map(|v| {
+        let Value::ServerError(e) = v else {
+            return None;
+        };
+        let err = redis::RedisError::from(e.clone());
+        let ask = err.kind() == redis::ErrorKind::Ask;
+        err.redirect_node()
+            .and_then(|(addr, _slot)| addr.rsplit_once(':'))
+            .and_then(|(host, port)| Some((host.to_string(), port.parse().ok()?, ask)))
+    })
+}
+
+/// 對被轉址的 key 改送到回覆指定的節點，最多跟隨 MAX_REDIRECTS 次
+///
+/// 同一個 key 連續被轉址代表 slot 正在搬移或 failover，丟掉快取的節點連線重新建立。
+/// 回傳重送後的完整回覆（含 TYPE）；第一次跟隨就失敗時回傳 None，呼叫端保留原本的錯誤回覆，
+/// 該 key 計入錯誤數。
+async fn follow_redirects(
+    con: &mut impl PipelineConn,
+    key: &str,
+    cfg: &ScanConfig,
+    vals: &[Value],
+) -> Option<Vec<Value>> {
+    let per_key = cfg.cmds_per_key();
+    let mut resent: Option<Vec<Value>> = None;
+
+    for hop in 0..MAX_REDIRECTS {
+        let Some((host, port, ask)) = find_redirect(resent.as_deref().unwrap_or(vals)) else {
+            break;
+        };
+        if hop > 0 {
+            con.forget_nodes();
+        }
+
+        let mut pipe = redis::pipe();
+        push_key_cmds(&mut pipe, key, cfg, ask, true);
+        let count = if ask { per_key * 2 } else { per_key };
+
+        match con.send_to(&host, port, &pipe, count).await {
+            // ASK 時去掉 ASKING 的回覆
+            Ok(v) if ask => resent = Some(v.into_iter().skip(1).step_by(2).collect()),
+            Ok(v) => resent = Some(v),
+            Err(_) => break,
+        }
+    }
+
+    resent
+}
+
+/// 一個 chunk 的量測結果；量測可以在其他連線上進行，累計一律回到掃描的主迴圈依序處理
+struct Fetched {
+    keys: Vec<String>,         // 實際量測的 key（兩段式篩選後）
+    skipped: Vec<KeyTypeCode>, // 兩段式判定不量測的 key 的類型
+    gone: u64,                 // 兩段式分類時 TYPE 已回 none
+    result: redis::RedisResult<(Vec<KeyMeta>, Duration)>, // 量測結果與 MEMORY USAGE pipeline 的耗時
+}
+
+/// 分類、量測、重試消失的 key 與查元素數，只碰傳入的連線
+async fn fetch_chunk(
+    con: &mut impl PipelineConn,
+    chunk: &[String],
+    cfg: &ScanConfig,
+    estimate: Option<&ScanConfig>,
+    strata: Option<&Strata>,
+) -> Fetched {
+    let mut skipped = Vec::new();
+    let mut gone = 0;
+    // 兩段式：先用 TYPE（和長度）分類，只對需要的 key 送 MEMORY USAGE
+    let keys = if cfg.measure.is_active() {
+        match classify_batch(con, chunk, &cfg.measure).await {
+            Ok(classes) => {
+                let mut keep = Vec::new();
+                for (key, class) in chunk.iter().zip(classes) {
+                    match class {
+                        Class::Measure => keep.push(key.clone()),
+                        Class::Skip(t) => skipped.push(t),
+                        Class::Gone => gone += 1,
+                    }
+                }
+                keep
+            }
+            Err(e) => {
+                return Fetched {
+                    keys: chunk.to_vec(),
+                    skipped,
+                    gone,
+                    result: Err(e),
+                };
+            }
+        }
+    } else {
+        chunk.to_vec()
+    };
+
+    // 兩段式的分類已經送過 TYPE，不另外查快取
+    let known = match (&cfg.type_cache, strata) {
+        _ if cfg.measure.is_active() => vec![None; keys.len()],
+        (Some(cache), _) => cache.lookup(&keys),
+        (None, Some(strata)) => strata.known(&keys),
+        (None, None) => vec![None; keys.len()],
+    };
+    let measuring = Instant::now();
+    let result = match measure_batch(con, &keys, &known, cfg, estimate).await {
+        Ok(mut metas) => {
+            let elapsed = measuring.elapsed();
+            if cfg.retry_vanished {
+                retry_vanished(con, &keys, &mut metas, cfg).await;
+            }
+            if cfg.collect_cardinality {
+                fetch_cardinality(con, &keys, &mut metas).await;
+            }
+            Ok((metas, elapsed))
+        }
+        Err(e) => Err(e),
+    };
+    Fetched {
+        keys,
+        skipped,
+        gone,
+        result,
+    }
+}
+
+/// 一波最多每條 lane 一個 chunk，同時量測；結果依 chunk 順序回傳
+fn fetch_wave(
+    con: &mut RedisConn,
+    lanes: Option<&mut Lanes>,
+    keys: &[String],
+    batch_size: usize,
+    cfg: &ScanConfig,
+    estimate: Option<&ScanConfig>,
+    strata: Option<&Strata>,
+) -> Vec<Fetched> {
+    match lanes {
+        Some(lanes) if keys.len() > batch_size => {
+            lanes.fetch(keys, batch_size, cfg, estimate, strata)
+        }
+        _ => keys
+            .chunks(batch_size)
+            .map(|chunk| block_on_ready(fetch_chunk(con, chunk, cfg, estimate, strata)))
+            .collect(),
+    }
+}
+
+/// --concurrency：共用一條 multiplexed 連線的 lane，每個 lane 同時有一批 pipeline 在途
+///
+/// 只有量測在 lane 上進行；SCAN、抽樣與結果累計仍由主連線依序處理，報表與單一連線時相同。
+#[cfg(feature = "async")]
+struct Lanes {
+    rt: tokio::runtime::Runtime,
+    conns: Vec<AsyncConn>,
+}
+
+/// 未啟用 async feature 時沒有 lane，--concurrency 由 main 的 check_features 擋下
+#[cfg(not(feature = "async"))]
+enum Lanes {}
+
+#[cfg(feature = "async")]
+impl Lanes {
+    fn open(con: &RedisConn, n: usize) -> redis::RedisResult<Self> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let first = con.open_async(&rt)?;
+        Ok(Self {
+            conns: vec![first; n],
+            rt,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.conns.len()
+    }
+
+    fn fetch(
+        &mut self,
+        keys: &[String],
+        batch_size: usize,
+        cfg: &ScanConfig,
+        estimate: Option<&ScanConfig>,
+        strata: Option<&Strata>,
+    ) -> Vec<Fetched> {
+        let chunks = self
+            .conns
+            .iter_mut()
+            .zip(keys.chunks(batch_size))
+            .map(|(c, chunk)| fetch_chunk(c, chunk, cfg, estimate, strata));
+        self.rt.block_on(futures_util::future::join_all(chunks))
+    }
+}
+
+#[cfg(not(feature = "async"))]
+impl Lanes {
+    fn open(_: &RedisConn, _: usize) -> redis::RedisResult<Self> {
+        Err(redis::RedisError::from((
+            redis::ErrorKind::InvalidClientConfig,
+            "此版本編譯時未啟用 `async` feature",
+        )))
+    }
+
+    fn len(&self) -> usize {
+        match *self {}
+    }
+
+    fn fetch(
+        &mut self,
+        _: &[String],
+        _: usize,
+        _: &ScanConfig,
+        _: Option<&ScanConfig>,
+        _: Option<&Strata>,
+    ) -> Vec<Fetched> {
+        match *self {}
+    }
+}
+
+/// 針對一批 keys，用 pipeline 一次取得 MEMORY USAGE + TYPE，
+/// 以及依設定加上 PTTL / OBJECT IDLETIME / OBJECT ENCODING
+///
+/// cluster 節點在掃描中搬移 slot 時，個別 key 會回 MOVED / ASK，這些 key 改送到新節點重查。
+/// --estimate-above：先查元素數，超過門檻的集合改用 estimate（SAMPLES ESTIMATE_SAMPLES）量測
+///
+/// estimate 為 None（設定的 SAMPLES 本來就便宜）時與 fetch_key_meta_batch 相同。
+/// 查元素數時本來就要送 TYPE，這時不使用類型快取（known）。
+async fn measure_batch(
+    con: &mut impl PipelineConn,
+    keys: &[String],
+    known: &[Option<KeyTypeCode>],
+    cfg: &ScanConfig,
+    estimate: Option<&ScanConfig>,
+) -> redis::RedisResult<Vec<KeyMeta>> {
+    let (Some(limit), Some(estimate)) = (cfg.estimate_above, estimate) else {
+        return fetch_key_meta_known(con, keys, known, cfg).await;
+    };
+    let elements = probe_elements(con, keys).await?;
+    let (large, exact): (Vec<usize>, Vec<usize>) =
+        (0..keys.len()).partition(|&i| elements[i].is_some_and(|n| n > limit));
+    if large.is_empty() {
+        return measure_keys(con, keys, cfg).await;
+    }
+
+    let pick = |idx: &[usize]| idx.iter().map(|&i| keys[i].clone()).collect::<Vec<_>>();
+    let exact_metas = measure_keys(con, &pick(&exact), cfg).await?;
+    let large_metas = measure_keys(con, &pick(&large), estimate).await?;
+
+    let mut metas: Vec<Option<KeyMeta>> = (0..keys.len()).map(|_| None).collect();
+    for (i, meta) in exact.into_iter().zip(exact_metas) {
+        metas[i] = Some(meta);
+    }
+    for (i, mut meta) in large.into_iter().zip(large_metas) {
+        meta.extra.estimated = true;
+        meta.extra.elements = elements[i];
+        metas[i] = Some(meta);
+    }
+    Ok(metas
+        .into_iter()
+        .map(|m| m.expect("每個 key 都已量測"))
+        .collect())
+}
+
+/// 集合類型的元素數（TYPE 之後送 LLEN / HLEN…）；string 與已消失的 key 為 None
+async fn probe_elements(
+    con: &mut impl PipelineConn,
+    keys: &[String],
+) -> redis::RedisResult<Vec<Option<u64>>> {
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.cmd("TYPE").arg(key);
+    }
+    let types = con.send(&pipe, keys.len()).await?;
+
+    let mut pipe = redis::pipe();
+    let mut asked = Vec::new();
+    for (i, (key, v)) in keys.iter().zip(&types).enumerate() {
+        match parse_type_code(v) {
+            Some(KeyTypeCode::String) | None => {}
+            Some(t) => {
+                pipe.cmd(t.length_command()).arg(key);
+                asked.push(i);
+            }
+        }
+    }
+    let mut elements = vec![None; keys.len()];
+    if asked.is_empty() {
+        return Ok(elements);
+    }
+    let lens = con.send(&pipe, asked.len()).await?;
+    for (i, v) in asked.into_iter().zip(&lens) {
+        elements[i] = parse_int(v).map(|n| n.max(0) as u64);
+    }
+    Ok(elements)
+}
+
+/// 已知類型的 key 另查長度（STRLEN / LLEN / HLEN…）；失敗時這一批沒有元素數
+async fn fetch_cardinality(con: &mut impl PipelineConn, keys: &[String], metas: &mut [KeyMeta]) {
+    let mut pipe = redis::pipe();
+    let mut asked = Vec::new();
+    for (i, (key, meta)) in keys.iter().zip(metas.iter()).enumerate() {
+        if let (Some(_), Some(t)) = (meta.mem, meta.type_code) {
+            pipe.cmd(t.length_command()).arg(key);
+            asked.push(i);
+        }
+    }
+    if asked.is_empty() {
+        return;
+    }
+    let Ok(lens) = con.send(&pipe, asked.len()).await else {
+        return;
+    };
+    for (i, v) in asked.into_iter().zip(&lens) {
+        match denied_reply(v) {
+            Some(e) => metas[i].denied.push((Collector::Elements, e)),
+            None => metas[i].extra.elements = parse_int(v).map(|n| n.max(0) as u64),
+        }
+    }
+}
+
+/// 短 string 取回內容（GETRANGE 0..MAX_INT_LEN）判斷是否為數字；失敗時略過這一批
+///
+/// GETRANGE 被 ACL 擋掉時回傳錯誤回覆
+fn check_int_strings(
+    con: &mut RedisConn,
+    keys: &[&str],
+    ints: &mut IntStringStats,
+) -> Option<String> {
+    if keys.is_empty() {
+        return None;
+    }
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.cmd("GETRANGE").arg(*key).arg(0).arg(MAX_INT_LEN);
+    }
+    let Ok(values) = send_pipeline(con, &pipe, keys.len()) else {
+        return None;
+    };
+    if let Some(e) = values.iter().find_map(denied_reply) {
+        return Some(e);
+    }
+    for (key, v) in keys.iter().zip(values) {
+        // 超過 MAX_INT_LEN 的值不可能是 int 編碼候選
+        if let Value::BulkString(b) = v {
+            if b.len() <= MAX_INT_LEN {
+                ints.add_value(key, &b);
+            }
+        }
+    }
+    None
+}
+
+/// 量測時已消失的 key 再查一次，查到的就取代原結果
+///
+/// 刪除後馬上重建（例如快取回填、RENAME 覆蓋）的 key 第二次通常查得到；
+/// 重試失敗時保留原本的「已消失」結果。
+async fn retry_vanished(
+    con: &mut impl PipelineConn,
+    keys: &[String],
+    metas: &mut [KeyMeta],
+    cfg: &ScanConfig,
+) {
+    let idx: Vec<usize> = (0..metas.len()).filter(|&i| metas[i].vanished()).collect();
+    if idx.is_empty() {
+        return;
+    }
+    let retry: Vec<String> = idx.iter().map(|&i| keys[i].clone()).collect();
+    if let Ok(again) = measure_keys(con, &retry, cfg).await {
+        for (i, meta) in idx.into_iter().zip(again) {
+            if !meta.vanished() {
+                metas[i] = meta;
+            }
+        }
+    }
+}
+
+pub fn fetch_key_meta_batch(
+    con: &mut RedisConn,
+    keys: &[String],
+    cfg: &ScanConfig,
+) -> redis::RedisResult<Vec<KeyMeta>> {
+    block_on_ready(measure_keys(con, keys, cfg))
+}
+
+async fn measure_keys(
+    con: &mut impl PipelineConn,
+    keys: &[String],
+    cfg: &ScanConfig,
+) -> redis::RedisResult<Vec<KeyMeta>> {
+    fetch_key_meta_known(con, keys, &vec![None; keys.len()], cfg).await
+}
+
+/// 同 fetch_key_meta_batch；known 中已知類型的 key 不送 TYPE，直接沿用該類型
+async fn fetch_key_meta_known(
+    con: &mut impl PipelineConn,
+    keys: &[String],
+    known: &[Option<KeyTypeCode>],
+    cfg: &ScanConfig,
+) -> redis::RedisResult<Vec<KeyMeta>> {
+    let mut pipe = redis::pipe();
+    for (key, t) in keys.iter().zip(known) {
+        push_key_cmds(&mut pipe, key, cfg, false, t.is_none());
+    }
+
+    // Vec<Value> 長度 = cmds_per_key * keys.len() - 已知類型的 key 數
+    let per_key = cfg.cmds_per_key();
+    let cached = known.iter().filter(|t| t.is_some()).count();
+    let values = con.send(&pipe, keys.len() * per_key - cached).await?;
+
+    let mut result = Vec::with_capacity(keys.len());
+
+    let mut offset = 0;
+    for (key, t) in keys.iter().zip(known) {
+        let n = if t.is_some() { per_key - 1 } else { per_key };
+        let vals = &values[offset..offset + n];
+        offset += n;
+
+        // 轉址後重送的是完整指令（含 TYPE）；跟隨失敗時仍是原本的回覆，沿用已知類型
+        let redirected = match find_redirect(vals) {
+            Some(_) => follow_redirects(con, key, cfg, vals).await,
+            None => None,
+        };
+        let (vals, known_type) = match &redirected {
+            Some(v) => (&v[..], None),
+            None => (vals, *t),
+        };
+
+        // MEMORY USAGE，一般是 Int；保守多支援 BulkString / SimpleString
+        let mem = vals.first().and_then(parse_int).map(|i| i as u64);
+        let (type_code, rest) = match known_type {
+            Some(t) => (Some(t), vals.get(1..).unwrap_or_default()),
+            None => (
+                vals.get(1).and_then(parse_type_code),
+                vals.get(2..).unwrap_or_default(),
+            ),
+        };
+
+        let mut rest = rest.iter();
+        let mut denied = Vec::new();
+        // 選用指令被 ACL 擋掉時記下來，不算這個 key 失敗
+        let mut next = |c: Collector| {
+            let v = rest.next()?;
+            match denied_reply(v) {
+                Some(e) => {
+                    denied.push((c, e));
+                    None
+                }
+                None => Some(v),
+            }
+        };
+        let mut extra = KeyExtra::default();
+        if cfg.collect_ttl {
+            extra.ttl_ms = next(Collector::Ttl).and_then(parse_int);
+        }
+        if cfg.collect_idle {
+            extra.idle_secs = next(Collector::Idle)
+                .and_then(parse_int)
+                .map(|i| i.max(0) as u64);
+        }
+        if cfg.collect_encoding {
+            extra.encoding = next(Collector::Encoding).and_then(parse_string);
+        }
+        if cfg.collect_freq {
+            extra.freq = next(Collector::Freq)
+                .and_then(parse_int)
+                .map(|i| i.max(0) as u64);
+        }
+
+        let error = vals
+            .iter()
+            .filter_map(|v| match v {
+                Value::ServerError(e) => Some(redis::RedisError::from(e.clone()).to_string()),
+                _ => None,
+            })
+            .find(|e| !denied.iter().any(|(_, d)| d == e));
+
+        result.push(KeyMeta {
+            mem,
+            type_code,
+            extra,
+            error,
+            denied,
+        });
+    }
+
+    Ok(result)
+}
+
+/// 失敗 key 的錯誤訊息統計，用來在中止時說明原因
+#[derive(Default)]
+struct ErrorTally {
+    counts: Vec<(String, u64)>, // 種類不多，線性搜尋即可
+}
+
+impl ErrorTally {
+    const MAX_KINDS: usize = 20;
+
+    fn add(&mut self, msg: &str) {
+        self.add_n(msg, 1);
+    }
+
+    fn add_n(&mut self, msg: &str, n: u64) {
+        if let Some(e) = self.counts.iter_mut().find(|(m, _)| m == msg) {
+            e.1 += n;
+        } else if self.counts.len() < Self::MAX_KINDS {
+            self.counts.push((msg.to_string(), n));
+        }
+    }
+
+    /// 錯誤率、最常見的錯誤，以及可能的原因
+    fn diagnose(&self, errors: u64, done: u64, max: f64) -> String {
+        let mut lines = vec![format!(
+            "{} / {} keys 失敗（{:.2}%，上限 {:.2}%）",
+            format_int(errors),
+            format_int(done),
+            Decimal(errors as f64 / done as f64 * 100.0),
+            Decimal(max * 100.0)
+        )];
+
+        let mut counts: Vec<&(String, u64)> = self.counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        for (msg, n) in counts.iter().take(3) {
+            lines.push(format!("  {} 次: {}", format_int(*n), msg));
+        }
+
+        let top = counts.first().map(|(m, _)| m.as_str()).unwrap_or("");
+        let hint = if top.contains("NOPERM") {
+            Some(
+                "ACL 使用者缺少權限，需要 +memory +type +pttl +scan（以及 +object 若有收集 idle / encoding）",
+            )
+        } else if top.contains("unknown command") || top.contains("unknown subcommand") {
+            Some("指令被 rename-command 停用，或伺服器版本不支援（MEMORY USAGE 需要 4.0 以上）")
+        } else {
+            None
+        };
+        if let Some(hint) = hint {
+            lines.push(format!("可能原因: {}", hint));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// BulkString / SimpleString 轉成 String
+pub fn parse_string(v: &Value) -> Option<String> {
+    match v {
+        Value::BulkString(b) => Some(String::from_utf8_lossy(b).into_owned()),
+        Value::SimpleString(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Int 或字串形式的整數
+pub fn parse_int(v: &Value) -> Option<i64> {
+    match v {
+        Value::Int(i) => Some(*i),
+        Value::BulkString(b) => String::from_utf8_lossy(b).parse::<i64>().ok(),
+        Value::SimpleString(s) => s.parse::<i64>().ok(),
+        _ => None,
+    }
+}
+
+/// 掃描中顯示在進度條下方的即時統計表（每類型一行）
+///
+/// indicatif 在非 TTY 時會自動隱藏，所以導到檔案時不會混進輸出。
+struct LiveTable {
+    header: ProgressBar,
+    rows: Vec<ProgressBar>,
+    last_render: Instant,
+}
+
+impl LiveTable {
+    fn new(multi: &MultiProgress) -> Self {
+        let style = ProgressStyle::with_template("  {msg}").unwrap();
+        let line = || {
+            let bar = multi.add(ProgressBar::new_spinner());
+            bar.set_style(style.clone());
+            bar
+        };
+
+        let header = line();
+        header.set_message(format!(
+            "{:<10} {:>15} {:>15} {:>8}",
+            "類型", "Keys", "記憶體 (MB)", "佔比"
+        ));
+        let rows = KeyTypeCode::all().iter().map(|_| line()).collect();
+
+        Self {
+            header,
+            rows,
+            // 讓第一個 batch 完成後就立刻顯示
+            last_render: Instant::now() - LIVE_TABLE_EVERY,
+        }
+    }
+
+    /// 距離上次刷新超過 LIVE_TABLE_EVERY 才重畫，避免每個 batch 都刷
+    fn maybe_render(&mut self, stats: &AllStats, scanned: u64) {
+        if self.last_render.elapsed() < LIVE_TABLE_EVERY {
+            return;
+        }
+        self.last_render = Instant::now();
+
+        let total_mem = stats.total_mem();
+        for (bar, t) in self.rows.iter().zip(KeyTypeCode::all()) {
+            let st = stats.get(*t);
+            let pct = if total_mem > 0 {
+                st.total_mem as f64 / total_mem as f64 * 100.0
+            } else {
+                0.0
+            };
+            bar.set_message(format!(
+                "{:<10} {:>15} {:>15.2} {:>7.2}%",
+                t.name(),
+                format_int(st.count),
+                Decimal(st.total_mem as f64 / 1024.0 / 1024.0),
+                Decimal(pct)
+            ));
+        }
+        self.header.set_message(format!(
+            "{:<10} {:>15} {:>15} {:>8}   (已掃描 {} keys)",
+            "類型",
+            "Keys",
+            "記憶體 (MB)",
+            "佔比",
+            format_int(scanned)
+        ));
+    }
+
+    /// 掃描結束時清掉即時表，最終報表會完整列出
+    fn finish(&self) {
+        for bar in &self.rows {
+            bar.finish_and_clear();
+        }
+        self.header.finish_and_clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_sample_is_deterministic() {
+        assert!(in_sample("user:1", 0.5));
+        assert!(!in_sample("session:42", 0.5));
+        assert!(in_sample("cache:abc", 0.5));
+        assert!(in_sample("session:42", 1.0));
+        assert!(!in_sample("user:1", 0.0));
+    }
+
+    #[test]
+    fn in_sample_rate_holds_for_sequential_keys() {
+        for rate in [0.5, 0.1, 0.01] {
+            let n = (0..100_000)
+                .filter(|i| in_sample(&format!("user:{}", i), rate))
+                .count() as f64;
+            let expected = 100_000.0 * rate;
+            assert!(
+                (n - expected).abs() < expected * 0.1,
+                "rate {} 抽中 {}",
+                rate,
+                n
+            );
+        }
+    }
+}
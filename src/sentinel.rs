@@ -0,0 +1,142 @@
+//! --sentinel：掃描前向 Sentinel 查詢目前的 master（或一台 replica），failover 後不必改設定
+//!
+//! Sentinel 依序嘗試，第一個回答的為準；連不上或不認得 --master-name 的 Sentinel 直接跳過。
+//! 查到的位置取代 host / port，其餘連線設定（TLS、帳號密碼、等待與重連）照常套用到資料節點。
+//! Sentinel 本身沿用資料節點的 TLS 憑證；帳號密碼可用 --sentinel-user / --sentinel-password 另外指定。
+
+use crate::conn::ConnArgs;
+use crate::conn::open_client;
+use crate::output::say;
+use redis::{ErrorKind, RedisError, RedisResult, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const SENTINEL_TIMEOUT: Duration = Duration::from_secs(5); // 每個 Sentinel 的連線與查詢逾時
+
+/// 回傳 host 換成查到的節點的連線設定；未指定 --sentinel 時原樣回傳
+pub fn resolve(conn: &ConnArgs) -> RedisResult<ConnArgs> {
+    let Some(name) = conn
+        .master_name
+        .as_deref()
+        .filter(|_| !conn.sentinel.is_empty())
+    else {
+        return Ok(conn.clone());
+    };
+    let mut failures = Vec::new();
+    for sentinel in &conn.sentinel {
+        let found = if conn.sentinel_replica {
+            query_replica(conn, sentinel, name)
+        } else {
+            query_master(conn, sentinel, name)
+        };
+        match found {
+            Ok(addr) => {
+                say!(
+                    "Sentinel {}: {} 目前的 {} 為 {}",
+                    sentinel,
+                    name,
+                    if conn.sentinel_replica {
+                        "replica"
+                    } else {
+                        "master"
+                    },
+                    addr
+                );
+                return Ok(ConnArgs {
+                    host: Some(addr),
+                    port: None,
+                    sentinel: Vec::new(),
+                    ..conn.clone()
+                });
+            }
+            Err(e) => failures.push(format!("{}: {}", sentinel, e)),
+        }
+    }
+    Err(RedisError::from((
+        ErrorKind::ClientError,
+        "無法從 Sentinel 取得節點位置",
+        failures.join("；"),
+    )))
+}
+
+/// 以資料節點相同的 TLS 設定（--cacert / --cert / --key）連線 Sentinel
+///
+/// 帳號密碼用 --sentinel-user / --sentinel-password，未指定時沿用資料節點的 --user / --password。
+fn open(conn: &ConnArgs, sentinel: &str) -> RedisResult<redis::Connection> {
+    let sentinel_conn = ConnArgs {
+        host: Some(sentinel.to_string()),
+        port: None,
+        tls: conn.is_tls(),
+        user: conn.sentinel_user.clone().or_else(|| conn.user.clone()),
+        password: conn
+            .sentinel_password
+            .clone()
+            .or_else(|| conn.password.clone()),
+        sentinel: Vec::new(),
+        ..conn.clone()
+    };
+    let client = open_client(&sentinel_conn)?;
+    let con = client.get_connection_with_timeout(SENTINEL_TIMEOUT)?;
+    con.set_read_timeout(Some(SENTINEL_TIMEOUT))?;
+    Ok(con)
+}
+
+/// SENTINEL GET-MASTER-ADDR-BY-NAME：[ip, port]；不認得 name 時回 nil
+fn query_master(conn: &ConnArgs, sentinel: &str, name: &str) -> RedisResult<String> {
+    let mut con = open(conn, sentinel)?;
+    let addr: Option<(String, u16)> = redis::cmd("SENTINEL")
+        .arg("GET-MASTER-ADDR-BY-NAME")
+        .arg(name)
+        .query(&mut con)?;
+    match addr {
+        Some((ip, port)) => Ok(format!("{}:{}", ip, port)),
+        None => Err(unknown_master(name)),
+    }
+}
+
+/// SENTINEL REPLICAS：每台 replica 一組 field/value；跳過 s_down / o_down / disconnected 的節點
+///
+/// Redis 5 之前沒有 REPLICAS，改送同義的 SLAVES。
+fn query_replica(conn: &ConnArgs, sentinel: &str, name: &str) -> RedisResult<String> {
+    let mut con = open(conn, sentinel)?;
+    let replicas: Value = match redis::cmd("SENTINEL")
+        .arg("REPLICAS")
+        .arg(name)
+        .query(&mut con)
+    {
+        Err(e) if e.to_string().contains("Unknown sentinel subcommand") => redis::cmd("SENTINEL")
+            .arg("SLAVES")
+            .arg(name)
+            .query(&mut con)?,
+        other => other?,
+    };
+    let Value::Array(list) = replicas else {
+        return Err(unknown_master(name));
+    };
+    for entry in list {
+        let fields: HashMap<String, String> = redis::from_redis_value(&entry)?;
+        let flags = fields.get("flags").map(String::as_str).unwrap_or("");
+        if flags
+            .split(',')
+            .any(|f| matches!(f, "s_down" | "o_down" | "disconnected"))
+        {
+            continue;
+        }
+        if let (Some(ip), Some(port)) = (fields.get("ip"), fields.get("port")) {
+            return Ok(format!("{}:{}", ip, port));
+        }
+    }
+    Err(RedisError::from((
+        ErrorKind::ClientError,
+        "沒有狀態正常的 replica",
+        name.to_string(),
+    )))
+}
+
+fn unknown_master(name: &str) -> RedisError {
+    RedisError::from((
+        ErrorKind::ClientError,
+        "Sentinel 沒有監控此 master",
+        name.to_string(),
+    ))
+}
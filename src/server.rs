@@ -0,0 +1,297 @@
+use crate::conn::RedisConn;
+use redis::{self, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 取得 INFO 某個 section，解析成 key → value
+pub fn fetch_info(
+    con: &mut RedisConn,
+    section: &str,
+) -> redis::RedisResult<BTreeMap<String, String>> {
+    let raw: String = redis::cmd("INFO").arg(section).query(con)?;
+    Ok(parse_info(&raw))
+}
+
+/// 解析 INFO 文字（`# Section` 註解行與空行略過）
+pub fn parse_info(raw: &str) -> BTreeMap<String, String> {
+    raw.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+/// 解析 MEMORY STATS，只保留頂層的數值欄位（db.N 這類巢狀結構略過）
+///
+/// RESP2 回傳 name/value 交錯的 Array，RESP3 則是 Map；數值可能是 Int 或字串形式的浮點數。
+pub fn fetch_memory_stats(con: &mut RedisConn) -> redis::RedisResult<Vec<(String, f64)>> {
+    let v: Value = redis::cmd("MEMORY").arg("STATS").query(con)?;
+
+    let pairs = into_pairs(v);
+    let mut out = Vec::with_capacity(pairs.len());
+    for (k, v) in pairs {
+        let Some(name) = value_string(&k) else {
+            continue;
+        };
+        let num = match v {
+            Value::Int(i) => i as f64,
+            Value::Double(d) => d,
+            Value::BulkString(b) => match String::from_utf8_lossy(&b).parse::<f64>() {
+                Ok(n) => n,
+                Err(_) => continue,
+            },
+            Value::SimpleString(s) => match s.parse::<f64>() {
+                Ok(n) => n,
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+        out.push((name, num));
+    }
+
+    Ok(out)
+}
+
+/// RESP2 的 name/value 交錯 Array 與 RESP3 的 Map 都轉成 (name, value)
+fn into_pairs(v: Value) -> Vec<(Value, Value)> {
+    match v {
+        Value::Array(items) => {
+            let mut out = Vec::with_capacity(items.len() / 2);
+            let mut it = items.into_iter();
+            while let (Some(k), Some(v)) = (it.next(), it.next()) {
+                out.push((k, v));
+            }
+            out
+        }
+        Value::Map(m) => m,
+        _ => Vec::new(),
+    }
+}
+
+fn value_string(v: &Value) -> Option<String> {
+    match v {
+        Value::BulkString(b) => Some(String::from_utf8_lossy(b).into_owned()),
+        Value::SimpleString(s) => Some(s.clone()),
+        Value::VerbatimString { text, .. } => Some(text.clone()),
+        _ => None,
+    }
+}
+
+/// FUNCTION LIST 中的一個 library
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FunctionLibrary {
+    pub name: String,
+    pub engine: String,
+    pub functions: u64,
+    pub code_bytes: u64, // library 原始碼長度（編譯後的 VM 記憶體另計在 used_memory_vm_functions）
+}
+
+/// FUNCTION LIST WITHCODE（Redis 7.0+），依原始碼長度 desc
+///
+/// EVAL 快取的 script 沒有指令可以列出內容，只能從 INFO memory 看數量與總量。
+pub fn fetch_function_libraries(con: &mut RedisConn) -> redis::RedisResult<Vec<FunctionLibrary>> {
+    let v: Value = redis::cmd("FUNCTION")
+        .arg("LIST")
+        .arg("WITHCODE")
+        .query(con)?;
+    let Value::Array(libs) = v else {
+        return Ok(Vec::new());
+    };
+
+    let mut out = Vec::with_capacity(libs.len());
+    for lib in libs {
+        let mut l = FunctionLibrary {
+            name: String::new(),
+            engine: String::new(),
+            functions: 0,
+            code_bytes: 0,
+        };
+        for (k, v) in into_pairs(lib) {
+            match value_string(&k).as_deref() {
+                Some("library_name") => l.name = value_string(&v).unwrap_or_default(),
+                Some("engine") => l.engine = value_string(&v).unwrap_or_default(),
+                Some("functions") => {
+                    if let Value::Array(fs) | Value::Set(fs) = &v {
+                        l.functions = fs.len() as u64;
+                    }
+                }
+                Some("library_code") => {
+                    l.code_bytes = value_string(&v).map(|c| c.len() as u64).unwrap_or(0)
+                }
+                _ => {}
+            }
+        }
+        if !l.name.is_empty() {
+            out.push(l);
+        }
+    }
+    out.sort_by(|a, b| {
+        b.code_bytes
+            .cmp(&a.code_bytes)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(out)
+}
+
+/// CLIENT LIST 的彙總
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ClientBufferSummary {
+    pub clients: u64,
+    pub replicas: u64,
+    pub total_mem: u64,               // tot-mem 加總
+    pub output_mem: u64,              // omem 加總（output buffer）
+    pub query_buf: u64,               // qbuf 加總（query buffer）
+    pub top_omem: Vec<(u64, String)>, // (omem, "id addr name")
+}
+
+/// 解析 CLIENT LIST，統計各連線的 buffer 使用量
+pub fn fetch_client_buffers(con: &mut RedisConn) -> redis::RedisResult<ClientBufferSummary> {
+    let raw: String = redis::cmd("CLIENT").arg("LIST").query(con)?;
+    let mut summary = ClientBufferSummary::default();
+
+    for line in raw.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut id = "";
+        let mut addr = "";
+        let mut name = "";
+        let mut omem = 0u64;
+        let mut is_replica = false;
+
+        for field in line.split(' ') {
+            let Some((k, v)) = field.split_once('=') else {
+                continue;
+            };
+            match k {
+                "id" => id = v,
+                "addr" => addr = v,
+                "name" => name = v,
+                "flags" => is_replica = v.contains('S'),
+                "omem" => omem = v.parse().unwrap_or(0),
+                "qbuf" => summary.query_buf += v.parse::<u64>().unwrap_or(0),
+                "tot-mem" => summary.total_mem += v.parse::<u64>().unwrap_or(0),
+                _ => {}
+            }
+        }
+
+        summary.clients += 1;
+        if is_replica {
+            summary.replicas += 1;
+        }
+        summary.output_mem += omem;
+        if omem > 0 {
+            summary
+                .top_omem
+                .push((omem, format!("id={} addr={} name={}", id, addr, name)));
+        }
+    }
+
+    summary
+        .top_omem
+        .sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    summary.top_omem.truncate(3);
+
+    Ok(summary)
+}
+
+/// INFO stats / INFO keyspace 的累計計數器，掃描開始與結束各取一次
+#[derive(Clone, Default)]
+pub struct ActivityCounters {
+    expired_keys: u64,
+    evicted_keys: u64,
+    hits: u64,
+    misses: u64,
+    keys: u64,                   // 所有 db 的 keys 加總
+    expires: u64,                // 所有 db 中有 TTL 的 keys 加總
+    db_keys: BTreeMap<i64, u64>, // 各 db 的 keys
+}
+
+/// 掃描期間的 keyspace 活動（結束 - 開始）
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct KeyspaceActivity {
+    pub window_ms: u64,
+    pub expired_keys: u64,
+    pub evicted_keys: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub keys_start: u64,
+    pub keys_end: u64,
+    pub expires_start: u64,
+    pub expires_end: u64,
+}
+
+/// 掃描到的 key 數與 DBSIZE / INFO keyspace 的比對（單一節點、單一 db）
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyspaceCheck {
+    pub target: String,
+    pub db: i64,
+    pub dbsize_start: u64,
+    pub dbsize_end: u64,
+    pub info_start: Option<u64>, // INFO keyspace；無法取得時為 None
+    pub info_end: Option<u64>,
+    pub seen: u64, // SCAN 回傳的 key（含錯誤、消失與未量測，不含重複）
+}
+
+/// 取 INFO stats 與 INFO keyspace；stats 原樣回傳，供其他欄位（expired_stale_perc）使用
+pub fn fetch_activity(
+    con: &mut RedisConn,
+) -> redis::RedisResult<(ActivityCounters, BTreeMap<String, String>)> {
+    let stats = fetch_info(con, "stats")?;
+    let keyspace = fetch_info(con, "keyspace")?;
+    let num = |name: &str| {
+        stats
+            .get(name)
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(0)
+    };
+
+    let mut counters = ActivityCounters {
+        expired_keys: num("expired_keys"),
+        evicted_keys: num("evicted_keys"),
+        hits: num("keyspace_hits"),
+        misses: num("keyspace_misses"),
+        ..Default::default()
+    };
+    // db0:keys=3000,expires=10,avg_ttl=0
+    for (db, fields) in &keyspace {
+        let Some(index) = db.strip_prefix("db").and_then(|n| n.parse::<i64>().ok()) else {
+            continue;
+        };
+        for field in fields.split(',') {
+            match field.split_once('=') {
+                Some(("keys", v)) => {
+                    let n = v.parse::<u64>().unwrap_or(0);
+                    counters.keys += n;
+                    counters.db_keys.insert(index, n);
+                }
+                Some(("expires", v)) => counters.expires += v.parse::<u64>().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    Ok((counters, stats))
+}
+
+impl ActivityCounters {
+    /// INFO keyspace 中此 db 的 keys；空的 db 不會出現在 INFO 中，視為 0
+    pub fn db_keys(&self, db: i64) -> u64 {
+        self.db_keys.get(&db).copied().unwrap_or(0)
+    }
+
+    /// CONFIG RESETSTAT 會把計數器歸零，結束值較小時視為 0
+    pub fn delta(&self, end: &ActivityCounters, window_ms: u64) -> KeyspaceActivity {
+        KeyspaceActivity {
+            window_ms,
+            expired_keys: end.expired_keys.saturating_sub(self.expired_keys),
+            evicted_keys: end.evicted_keys.saturating_sub(self.evicted_keys),
+            hits: end.hits.saturating_sub(self.hits),
+            misses: end.misses.saturating_sub(self.misses),
+            keys_start: self.keys,
+            keys_end: end.keys,
+            expires_start: self.expires,
+            expires_end: end.expires,
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use crate::checkpoint::Checkpoint;
+use crate::conn::ConnArgs;
+use crate::conn::{RedisConn, connect};
+use crate::format::format_unix_ts;
+use crate::output::say;
+use crate::progress::{self, ProgressBar, ProgressStyle};
+use crate::report::Report;
+use crate::scan::{self, ScanConfig, ScanDisplay};
+use redis::Value;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 逐一直接掃描 proxy 後面的每個後端節點，最後合併成一份報表
+///
+/// twemproxy / codis-proxy 通常擋掉 SCAN 與 MEMORY，只能繞過 proxy 連真正的 shard。
+/// 連線相關設定（等待、重連、唯讀檢查、稽核紀錄）沿用 `conn`，只換掉 host/port。
+/// 有 checkpoint 時已完成的節點直接沿用，全部完成後清除 checkpoint。
+pub fn scan_nodes(
+    conn: &ConnArgs,
+    nodes: &[String],
+    cfg: &ScanConfig,
+    parallel: usize,
+    checkpoint: Option<&Checkpoint>,
+) -> Result<Report, Box<dyn Error>> {
+    let reused: Vec<Option<Report>> = nodes
+        .iter()
+        .map(|node| checkpoint.and_then(|cp| cp.load(node)))
+        .collect();
+    let pending = reused.iter().filter(|r| r.is_none()).count();
+    if let Some(cp) = checkpoint {
+        if pending < nodes.len() {
+            say!(
+                "沿用 checkpoint（建立於 {}）: {} 個節點已完成，掃描其餘 {} 個\n",
+                format_unix_ts(cp.created_at()),
+                nodes.len() - pending,
+                pending
+            );
+        }
+    }
+
+    let result = if parallel > 1 && pending > 1 {
+        scan_parallel(conn, nodes, reused, cfg, parallel, checkpoint)
+    } else {
+        scan_sequential(conn, nodes, reused, cfg, checkpoint)
+    };
+    let reports = match (result, checkpoint) {
+        (Ok(reports), _) => reports,
+        (Err(e), Some(_)) => {
+            return Err(format!(
+                "{}\n已完成的節點存在 checkpoint，以相同參數重新執行只會掃描未完成的節點",
+                e
+            )
+            .into());
+        }
+        (Err(e), None) => return Err(e),
+    };
+
+    if let Some(cp) = checkpoint {
+        cp.clear(nodes)?;
+    }
+    Ok(Report::merge(reports))
+}
+
+fn scan_sequential(
+    conn: &ConnArgs,
+    nodes: &[String],
+    reused: Vec<Option<Report>>,
+    cfg: &ScanConfig,
+    checkpoint: Option<&Checkpoint>,
+) -> Result<Vec<Report>, Box<dyn Error>> {
+    let mut reports = Vec::with_capacity(nodes.len());
+
+    for (i, (node, done)) in nodes.iter().zip(reused).enumerate() {
+        if let Some(r) = done {
+            say!(
+                "後端節點 {}/{}: {}（沿用 checkpoint）",
+                i + 1,
+                nodes.len(),
+                node
+            );
+            reports.push(r);
+            continue;
+        }
+        say!("{}", "=".repeat(120));
+        say!("後端節點 {}/{}: {}", i + 1, nodes.len(), node);
+        say!("{}\n", "=".repeat(120));
+
+        let (mut con, url) = connect_node(conn, node)?;
+        let mut report = scan::scan(&mut con, &url, cfg)?;
+        save_checkpoint(checkpoint, node, &mut report);
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
+
+/// 寫入失敗不中止掃描，只是重跑時這個節點要重新掃描
+fn save_checkpoint(checkpoint: Option<&Checkpoint>, node: &str, report: &mut Report) {
+    if let Some(cp) = checkpoint {
+        if let Err(e) = cp.save(node, report) {
+            report
+                .unavailable
+                .push(("checkpoint".into(), e.to_string()));
+        }
+    }
+}
+
+pub fn connect_node(conn: &ConnArgs, node: &str) -> redis::RedisResult<(RedisConn, String)> {
+    connect(&node_conn(conn, node))
+}
+
+/// 把 host/port 換成節點位址，其餘沿用種子節點的設定
+///
+/// 種子是 URL 時沿用它的 scheme、帳號密碼與 db（rediss://user:pw@seed/0 → rediss://user:pw@node/0），
+/// 否則 TLS 與 AUTH 只對種子節點生效。節點本身就是 URL 時原樣使用。
+fn node_conn(conn: &ConnArgs, node: &str) -> ConnArgs {
+    let host = match conn.host.as_deref().and_then(|h| h.split_once("://")) {
+        Some((scheme, rest)) if !node.contains("://") && !scheme.ends_with("unix") => {
+            let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            match authority.rsplit_once('@') {
+                Some((userinfo, _)) => format!("{}://{}@{}{}", scheme, userinfo, node, path),
+                None => format!("{}://{}{}", scheme, node, path),
+            }
+        }
+        _ => node.to_string(),
+    };
+    ConnArgs {
+        host: Some(host),
+        port: None,
+        tls: conn.is_tls(),
+        ..conn.clone()
+    }
+}
+
+/// 同時掃描最多 parallel 個節點：每個節點一條進度條，最上面是全部節點的合計
+///
+/// 先依序連上所有節點，連線失敗時在開始掃描前就回報；掃描失敗的節點會標在自己的進度條上，
+/// 其他節點照常跑完，最後再回報失敗的節點。沿用 checkpoint 的節點不連線。
+fn scan_parallel(
+    conn: &ConnArgs,
+    nodes: &[String],
+    reused: Vec<Option<Report>>,
+    cfg: &ScanConfig,
+    parallel: usize,
+    checkpoint: Option<&Checkpoint>,
+) -> Result<Vec<Report>, Box<dyn Error>> {
+    let pending: Vec<usize> = (0..nodes.len()).filter(|&i| reused[i].is_none()).collect();
+    let mut conns = Vec::with_capacity(pending.len());
+    for &i in &pending {
+        conns.push(Mutex::new(connect_node(conn, &nodes[i])?));
+    }
+    say!(
+        "\n平行掃描 {} 個節點（同時 {} 個）: SCAN + PIPELINE {}\n",
+        pending.len(),
+        parallel.min(pending.len()),
+        cfg.describe_commands()
+    );
+
+    let width = nodes.iter().map(|n| n.len()).max().unwrap_or(0);
+    let multi = progress::multi();
+    let overall = multi.add(ProgressBar::new(0));
+    overall.set_style(
+        ProgressStyle::with_template(&format!(
+            "{{prefix:<{}}} [{{elapsed_precise}}] [{{wide_bar:.green/blue}}] {{pos}}/{{len}} keys ({{percent}}%)",
+            width
+        ))
+        .unwrap()
+        .progress_chars("=>-"),
+    );
+    overall.set_prefix("全部節點");
+    let shard_style = ProgressStyle::with_template(&format!(
+        "{{prefix:<{}}} [{{elapsed_precise}}] [{{wide_bar:.cyan/blue}}] {{pos}}/{{len}} keys ({{percent}}%) {{msg}}",
+        width
+    ))
+    .unwrap()
+    .progress_chars("=>-");
+    let bars: Vec<ProgressBar> = pending
+        .iter()
+        .map(|&i| {
+            let bar = multi.add(ProgressBar::new(0));
+            bar.set_style(shard_style.clone());
+            bar.set_prefix(nodes[i].clone());
+            bar.set_message("等待中");
+            bar
+        })
+        .collect();
+
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<redis::RedisResult<Report>>>> =
+        reused.into_iter().map(|r| Mutex::new(r.map(Ok))).collect();
+    std::thread::scope(|s| {
+        for _ in 0..parallel.min(pending.len()) {
+            s.spawn(|| {
+                loop {
+                    let j = next.fetch_add(1, Ordering::Relaxed);
+                    if j >= pending.len() {
+                        break;
+                    }
+                    let i = pending[j];
+                    let mut guard = conns[j].lock().unwrap_or_else(|e| e.into_inner());
+                    let (con, url) = &mut *guard;
+                    let display = ScanDisplay::shard(&multi, &bars[j], &overall);
+                    bars[j].set_message("");
+                    let mut result = scan::scan_with(con, url, cfg, &display);
+                    match &mut result {
+                        Ok(report) => save_checkpoint(checkpoint, &nodes[i], report),
+                        Err(e) => bars[j].abandon_with_message(format!("✖ {}", e)),
+                    }
+                    *results[i].lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+                }
+            });
+        }
+    });
+    overall.finish();
+
+    let mut reports = Vec::with_capacity(nodes.len());
+    let mut failed = Vec::new();
+    for (node, result) in nodes.iter().zip(results) {
+        match result.into_inner().unwrap_or_else(|e| e.into_inner()) {
+            Some(Ok(r)) => reports.push(r),
+            Some(Err(e)) => failed.push(format!("{}: {}", node, e)),
+            None => failed.push(format!("{}: 未執行", node)),
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!("{} 個節點掃描失敗: {}", failed.len(), failed.join("；")).into());
+    }
+
+    Ok(reports)
+}
+
+/// --cluster：向種子節點送 CLUSTER SLOTS，取得負責 slot 的所有 master（依 slot 順序）
+///
+/// replica 不掃描：資料與 master 相同，只會重複計算。回覆中 ip 為空字串時代表與種子節點同一台。
+pub fn cluster_masters(conn: &ConnArgs) -> Result<Vec<String>, Box<dyn Error>> {
+    let (seed_host, _) = conn.host_port();
+    let (mut con, url) = connect(conn)?;
+    let slots: Value = redis::cmd("CLUSTER")
+        .arg("SLOTS")
+        .query(&mut con)
+        .map_err(|e| format!("--cluster: 無法從 {} 取得 CLUSTER SLOTS: {}", url, e))?;
+
+    let mut masters: Vec<String> = Vec::new();
+    let Value::Array(ranges) = slots else {
+        return Err(format!("--cluster: {} 的 CLUSTER SLOTS 回覆格式不符", url).into());
+    };
+    for range in &ranges {
+        // [start, end, [ip, port, id, ...], replica...]
+        let Value::Array(parts) = range else { continue };
+        let Some(Value::Array(master)) = parts.get(2) else {
+            continue;
+        };
+        let host = match master.first() {
+            Some(Value::BulkString(b)) if !b.is_empty() && b != b"?" => {
+                String::from_utf8_lossy(b).into_owned()
+            }
+            _ => seed_host.clone(),
+        };
+        let Some(Value::Int(port)) = master.get(1) else {
+            continue;
+        };
+        let node = format!("{}:{}", host, port);
+        if !masters.contains(&node) {
+            masters.push(node);
+        }
+    }
+    if masters.is_empty() {
+        return Err(format!(
+            "--cluster: {} 沒有任何 slot 被指派（cluster 尚未建立？）",
+            url
+        )
+        .into());
+    }
+
+    say!(
+        "CLUSTER SLOTS 共 {} 個 master: {}\n",
+        masters.len(),
+        masters.join(", ")
+    );
+    Ok(masters)
+}
+
+/// 從 twemproxy (nutcracker) 設定檔讀出所有 pool 的後端節點
+///
+/// 只處理 `servers:` 底下 `- host:port:weight [name]` 形式的行，不需要完整的 YAML parser。
+pub fn twemproxy_servers(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| format!("無法讀取 twemproxy 設定 {}: {}", path.display(), e))?;
+
+    let mut servers = Vec::new();
+    let mut in_servers = false;
+    for line in raw.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(item) = line.strip_prefix('-') {
+            if in_servers {
+                // host:port:weight [name]
+                let addr = item.split_whitespace().next().unwrap_or("");
+                let mut parts = addr.trim_matches(|c| c == '"' || c == '\'').split(':');
+                if let (Some(host), Some(port)) = (parts.next(), parts.next()) {
+                    servers.push(format!("{}:{}", host, port));
+                }
+            }
+            continue;
+        }
+        in_servers = line == "servers:";
+    }
+
+    if servers.is_empty() {
+        return Err(format!("{} 中找不到 servers 設定", path.display()).into());
+    }
+    Ok(servers)
+}
+
+// 以命令列旗標組出 ConnArgs，需要 cli feature
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Args {
+        #[command(flatten)]
+        conn: ConnArgs,
+    }
+
+    fn conn(args: &[&str]) -> ConnArgs {
+        Args::parse_from(std::iter::once("test").chain(args.iter().copied())).conn
+    }
+
+    #[test]
+    fn node_keeps_seed_tls_and_credentials() {
+        let seed = conn(&["rediss://user:pw@seed:6379/0"]);
+        let node = node_conn(&seed, "10.0.0.2:7000");
+        assert!(node.is_tls());
+        assert_eq!(node.redis_url(), "rediss://user:pw@10.0.0.2:7000/0");
+        assert_eq!(node.host_port(), ("10.0.0.2".to_string(), 7000));
+    }
+
+    #[test]
+    fn node_without_seed_url_uses_flags() {
+        let seed = conn(&["--tls", "seed", "6379"]);
+        let node = node_conn(&seed, "10.0.0.2:7000");
+        assert!(node.is_tls());
+        assert_eq!(node.redis_url(), "rediss://10.0.0.2:7000/");
+
+        let plain = node_conn(&conn(&["redis://seed:6379"]), "10.0.0.2:7000");
+        assert!(!plain.is_tls());
+        assert_eq!(plain.redis_url(), "redis://10.0.0.2:7000");
+    }
+}
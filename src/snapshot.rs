@@ -0,0 +1,146 @@
+use crate::format::format_iso8601;
+use crate::report::{Report, SNAPSHOT_VERSION};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// 將 Report 寫成 snapshot（JSON）
+pub fn save(report: &Report, path: &Path) -> Result<(), Box<dyn Error>> {
+    write(report, &mut BufWriter::new(File::create(path)?))
+}
+
+/// 以 snapshot 格式寫出 Report（scan --format json 寫到 stdout）
+pub fn write(report: &Report, w: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    serde_json::to_writer_pretty(&mut *w, report)?;
+    w.write_all(b"\n")?;
+    w.flush()?;
+    Ok(())
+}
+
+/// 讀取 snapshot；舊格式先逐版轉換成目前的格式，比此版本新的格式直接回錯誤
+pub fn load(path: &Path) -> Result<Report, Box<dyn Error>> {
+    let file =
+        File::open(path).map_err(|e| format!("無法開啟 snapshot {}: {}", path.display(), e))?;
+    let mut raw: Value = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("snapshot 格式錯誤 {}: {}", path.display(), e))?;
+    migrate(&mut raw).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let report: Report = serde_json::from_value(raw)
+        .map_err(|e| format!("snapshot 格式錯誤 {}: {}", path.display(), e))?;
+    Ok(report)
+}
+
+/// 依 format_version 逐版套用 MIGRATIONS，完成後為 SNAPSHOT_VERSION
+fn migrate(raw: &mut Value) -> Result<(), String> {
+    let obj = raw
+        .as_object_mut()
+        .ok_or("snapshot 格式錯誤: 最外層不是 JSON 物件")?;
+    // 加上版本號之前的 snapshot 沒有這個欄位
+    let mut version = match obj.get("format_version") {
+        None => 1,
+        Some(v) => v
+            .as_u64()
+            .filter(|&v| v >= 1)
+            .ok_or_else(|| format!("snapshot 的 format_version 無效: {}", v))?
+            as u32,
+    };
+    if version > SNAPSHOT_VERSION {
+        return Err(format!(
+            "snapshot 格式版本 {} 比此版本支援的 {} 新，請更新 redis-top-keys-analyzer 後再讀取",
+            version, SNAPSHOT_VERSION
+        ));
+    }
+    for (from, step) in MIGRATIONS {
+        if version == *from {
+            step(obj)?;
+            version += 1;
+        }
+    }
+    obj.insert("format_version".into(), SNAPSHOT_VERSION.into());
+    Ok(())
+}
+
+/// (來源版本, 轉換成下一版)；新增會改變既有欄位意義的變更時在這裡加一步並提高 SNAPSHOT_VERSION
+///
+/// 只是新增欄位不需要轉換：新欄位都有 #[serde(default)]。
+const MIGRATIONS: &[(u32, Migration)] = &[(1, v1_iso_times)];
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+/// v1 → v2：沒有 ISO-8601 時間，由 Unix 秒與耗時補上
+fn v1_iso_times(obj: &mut Map<String, Value>) -> Result<(), String> {
+    let has_iso = obj
+        .get("started_at_utc")
+        .and_then(Value::as_str)
+        .is_some_and(|s| !s.is_empty());
+    if has_iso {
+        return Ok(());
+    }
+    let started_at = obj
+        .get("started_at")
+        .and_then(Value::as_u64)
+        .ok_or("snapshot 格式錯誤: 缺少 started_at")?;
+    let duration_ms = obj.get("duration_ms").and_then(Value::as_u64).unwrap_or(0);
+    obj.insert("started_at_utc".into(), format_iso8601(started_at).into());
+    obj.insert(
+        "finished_at_utc".into(),
+        format_iso8601(started_at + duration_ms.div_ceil(1000)).into(),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 加上 ISO 時間之前（v1）寫出的 snapshot
+    const V1_SNAPSHOT: &str = r#"{
+        "format_version": 1,
+        "target": "redis://10.0.0.1:6379/",
+        "started_at": 1760000000,
+        "duration_ms": 1500,
+        "scanned": 2,
+        "errors": 0,
+        "types": [],
+        "unavailable": []
+    }"#;
+
+    /// 寫到暫存檔後以 load 讀回，回傳結果
+    fn load_str(name: &str, json: &str) -> Result<Report, Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("rtka-{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, json).unwrap();
+        let loaded = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn v1_gains_iso_times() {
+        let report = load_str("v1", V1_SNAPSHOT).unwrap();
+        assert_eq!(report.format_version, SNAPSHOT_VERSION);
+        assert_eq!(report.started_at_utc, format_iso8601(1760000000));
+        // 耗時 1.5 秒，結束時間無條件進位到 2 秒後
+        assert_eq!(report.finished_at_utc, format_iso8601(1760000002));
+        assert_eq!(report.target, "redis://10.0.0.1:6379/");
+    }
+
+    #[test]
+    fn missing_version_is_v1() {
+        let json = V1_SNAPSHOT.replace("\"format_version\": 1,", "");
+        let report = load_str("unversioned", &json).unwrap();
+        assert_eq!(report.format_version, SNAPSHOT_VERSION);
+        assert_eq!(report.started_at_utc, format_iso8601(1760000000));
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let json = V1_SNAPSHOT.replace(
+            "\"format_version\": 1,",
+            &format!("\"format_version\": {},", SNAPSHOT_VERSION + 1),
+        );
+        let err = load_str("newer", &json).err().unwrap().to_string();
+        assert!(err.contains("比此版本支援的"), "{}", err);
+        assert!(!err.contains("snapshot 格式錯誤"), "{}", err);
+    }
+}
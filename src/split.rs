@@ -0,0 +1,134 @@
+//! --split-report-by owner：每個擁有者一份只含自己 key 的報表，另附整體報表
+//!
+//! 擁有者的報表只有類型 Top N、前綴、key 名稱與到期時間；負載保護、碎片、殭屍 key 等
+//! 實例層級的章節只在整體報表（all.txt）出現。
+
+use crate::owners::{OwnerRules, UNOWNED};
+use crate::report::{self, OwnerDetail, RenderOptions, Report};
+use crate::snapshot;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub const ALL_NAME: &str = "all"; // 整體報表的檔名（不含副檔名）
+
+/// 報表的拆分方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SplitBy {
+    Owner,
+}
+
+/// 已寫入的一份拆分報表
+pub struct SplitFile {
+    pub owner: String,
+    pub path: PathBuf, // .txt；同名的 .json 為 snapshot
+    pub keys: u64,
+    pub total_mem: u64,
+}
+
+/// 只含單一擁有者 key 的報表
+///
+/// 每個 key 的清單（名稱最長的 key）依規則重新比對，依前綴彙總的建議只留該擁有者的前綴。
+pub fn owner_report(report: &Report, detail: &OwnerDetail, rules: &OwnerRules) -> Report {
+    let owner = detail.owner.as_str();
+    let mine: HashSet<&str> = detail.prefixes.iter().map(|p| p.prefix.as_str()).collect();
+
+    let mut r = report.clone();
+    r.scope = Some(owner.to_string());
+    r.types = detail.types.clone();
+    r.prefixes = detail.prefixes.clone();
+    r.expiry = detail.expiry.clone();
+    r.key_names = detail.key_names.clone();
+    r.scanned = r.types.iter().map(|t| t.count + t.unmeasured).sum();
+    r.errors = 0;
+    r.vanished = 0;
+    r.owners.retain(|o| o.owner == owner);
+    r.owner_details = Vec::new();
+    r.longest_keys.retain(|k| rules.owner_of(&k.key) == owner);
+    r.ttl_suggestions
+        .retain(|s| mine.contains(s.prefix.as_str()));
+    r.int_strings.retain(|s| mine.contains(s.prefix.as_str()));
+    r.bin_waste.retain(|s| mine.contains(s.prefix.as_str()));
+
+    // 無法依擁有者拆開的實例層級資料
+    r.nodes = Vec::new();
+    r.shared_prefixes = Vec::new();
+    r.pauses = Vec::new();
+    r.key_rate = None;
+    r.max_repl_lag = None;
+    r.duplicates = None;
+    r.overhead = Vec::new();
+    r.zombies = None;
+    r.ages = Vec::new();
+    r.empty = None;
+    r
+}
+
+/// 寫出整體報表與每個擁有者的報表（各一份 .txt 與 .json），回傳擁有者的檔案清單
+pub fn write_owner_reports(
+    report: &Report,
+    rules: &OwnerRules,
+    dir: &Path,
+    opts: &RenderOptions,
+) -> Result<Vec<SplitFile>, Box<dyn Error>> {
+    fs::create_dir_all(dir).map_err(|e| format!("無法建立目錄 {}: {}", dir.display(), e))?;
+    write_pair(report, dir, ALL_NAME, opts)?;
+
+    let mut used: HashSet<String> = HashSet::from([ALL_NAME.to_string()]);
+    let mut files = Vec::new();
+    for detail in &report.owner_details {
+        let base = file_name(&detail.owner);
+        let mut name = base.clone();
+        let mut n = 2;
+        while !used.insert(name.clone()) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+        let sub = owner_report(report, detail, rules);
+        files.push(SplitFile {
+            owner: detail.owner.clone(),
+            path: write_pair(&sub, dir, &name, opts)?,
+            keys: sub.scanned,
+            total_mem: sub.total_mem(),
+        });
+    }
+    Ok(files)
+}
+
+fn write_pair(
+    report: &Report,
+    dir: &Path,
+    name: &str,
+    opts: &RenderOptions,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let path = dir.join(format!("{}.txt", name));
+    let mut w = BufWriter::new(File::create(&path)?);
+    report::render_text(report, opts, &mut w)?;
+    w.flush()?;
+    snapshot::save(report, &dir.join(format!("{}.json", name)))?;
+    Ok(path)
+}
+
+/// 擁有者名稱轉成檔名：英數與 . _ - 以外的字元換成 _
+fn file_name(owner: &str) -> String {
+    if owner == UNOWNED {
+        return "unowned".to_string();
+    }
+    let name: String = owner
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match name.trim_start_matches('.') {
+        "" => "owner".to_string(),
+        s => s.to_string(),
+    }
+}
@@ -0,0 +1,656 @@
+use crate::deep::ElementSizes;
+use crate::preview::ValueScore;
+use redis::Value;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+pub const TOP_N: usize = 10; // 每類型 Top N
+pub const LONGEST_KEYS: usize = 10; // 名稱最長的 key 保留幾個
+pub const EXPIRY_HOURS: usize = 48; // 到期時間軸涵蓋未來幾小時
+pub const EXPIRING_MS: i64 = 1_000; // PTTL 低於此值視為已過期、只是還沒被回收
+pub const MAX_PREFIXES: usize = 10_000; // 前綴種類上限，超過的歸到 OTHER_PREFIX，避免 key 名稱沒有規律時吃光記憶體
+pub const NO_PREFIX: &str = "(無前綴)";
+pub const OTHER_PREFIX: &str = "(其他)";
+pub const PREFIX_TOP_KEYS: usize = 10; // 每個前綴的 Top N
+const SIZE_BUCKETS: usize = 65; // 以 2 的次方分桶：0、[1,2)、[2,4)…[2^63, 2^64)
+
+/// 前綴 × 大小區間的上限（不含）與標籤
+pub const SIZE_BANDS: &[(u64, &str)] = &[
+    (1024, "< 1KB"),
+    (10 * 1024, "1KB ~ 10KB"),
+    (100 * 1024, "10KB ~ 100KB"),
+    (1024 * 1024, "100KB ~ 1MB"),
+    (u64::MAX, ">= 1MB"),
+];
+
+/// 與 SIZE_BANDS 對應的 (count, mem)；舊 snapshot 沒有時為空
+pub type SizeBands = Vec<(u64, u64)>;
+
+pub fn add_to_band(bands: &mut SizeBands, mem: u64) {
+    if bands.is_empty() {
+        *bands = vec![(0, 0); SIZE_BANDS.len()];
+    }
+    let i = SIZE_BANDS
+        .iter()
+        .position(|&(limit, _)| mem < limit)
+        .unwrap_or(SIZE_BANDS.len() - 1);
+    bands[i].0 += 1;
+    bands[i].1 += mem;
+}
+
+/// 逐區間加總；任一邊沒有區間（舊 snapshot）時結果也沒有，避免只涵蓋部分 key
+pub fn merge_bands(into: &mut SizeBands, other: &SizeBands) {
+    if into.len() != other.len() {
+        into.clear();
+        return;
+    }
+    for (a, b) in into.iter_mut().zip(other) {
+        a.0 += b.0;
+        a.1 += b.1;
+    }
+}
+
+/// Key 類型（只處理常見的六種）
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyTypeCode {
+    String = 0,
+    List = 1,
+    Set = 2,
+    ZSet = 3,
+    Hash = 4,
+    Stream = 5,
+}
+
+impl KeyTypeCode {
+    pub fn all() -> &'static [KeyTypeCode] {
+        use KeyTypeCode::*;
+        &[String, List, Set, ZSet, Hash, Stream]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyTypeCode::String => "string",
+            KeyTypeCode::List => "list",
+            KeyTypeCode::Set => "set",
+            KeyTypeCode::ZSet => "zset",
+            KeyTypeCode::Hash => "hash",
+            KeyTypeCode::Stream => "stream",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        // 顯示用（大寫）
+        match self {
+            KeyTypeCode::String => "STRING",
+            KeyTypeCode::List => "LIST",
+            KeyTypeCode::Set => "SET",
+            KeyTypeCode::ZSet => "ZSET",
+            KeyTypeCode::Hash => "HASH",
+            KeyTypeCode::Stream => "STREAM",
+        }
+    }
+
+    /// 取得元素數（string 為 bytes）的指令
+    pub fn length_command(self) -> &'static str {
+        match self {
+            KeyTypeCode::String => "STRLEN",
+            KeyTypeCode::List => "LLEN",
+            KeyTypeCode::Set => "SCARD",
+            KeyTypeCode::ZSet => "ZCARD",
+            KeyTypeCode::Hash => "HLEN",
+            KeyTypeCode::Stream => "XLEN",
+        }
+    }
+
+    /// 由名稱（TYPE 回傳值或 CLI 參數）轉回 KeyTypeCode
+    pub fn from_name(s: &str) -> Option<KeyTypeCode> {
+        KeyTypeCode::all()
+            .iter()
+            .copied()
+            .find(|t| t.name().eq_ignore_ascii_case(s))
+    }
+}
+
+/// 將 Redis 回傳的 TYPE 結果(Value)轉成 KeyTypeCode（不分配 String）
+///
+/// redis 1.x / RESP3 會用 `BulkString(Vec<u8>)` 或 `SimpleString(String)` 表示 "string"/"hash" 等。
+pub fn parse_type_code(v: &Value) -> Option<KeyTypeCode> {
+    match v {
+        Value::BulkString(b) => match b.as_slice() {
+            b"string" => Some(KeyTypeCode::String),
+            b"list" => Some(KeyTypeCode::List),
+            b"set" => Some(KeyTypeCode::Set),
+            b"zset" => Some(KeyTypeCode::ZSet),
+            b"hash" => Some(KeyTypeCode::Hash),
+            b"stream" => Some(KeyTypeCode::Stream),
+            _ => None,
+        },
+        Value::SimpleString(s) => match s.as_str() {
+            "string" => Some(KeyTypeCode::String),
+            "list" => Some(KeyTypeCode::List),
+            "set" => Some(KeyTypeCode::Set),
+            "zset" => Some(KeyTypeCode::ZSet),
+            "hash" => Some(KeyTypeCode::Hash),
+            "stream" => Some(KeyTypeCode::Stream),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 單一 key 除了記憶體以外的選擇性資訊（依 --profile / --collect 決定是否收集）
+#[derive(Clone, Debug, Default)]
+pub struct KeyExtra {
+    pub ttl_ms: Option<i64>, // PTTL；-1 = 沒有 TTL
+    pub idle_secs: Option<u64>,
+    pub encoding: Option<String>,
+    pub freq: Option<u64>,     // OBJECT FREQ（LFU 計數器，0~255）
+    pub elements: Option<u64>, // STRLEN / LLEN / HLEN…
+    pub estimated: bool,       // --estimate-above：元素太多，mem 是低抽樣數的估計值
+}
+
+/// Top N 中的一筆
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TopKey {
+    pub key: String,
+    pub mem: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freq: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elements: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_mem: Option<u64>, // --deep：第一階段抽樣估計的記憶體（mem 為 SAMPLES 0 的結果）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub estimated: bool, // --estimate-above：元素太多，沒有精確量測
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element_sizes: Option<ElementSizes>, // --deep：抽樣元素的大小
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>, // --preview 抓到的值片段（已遮蔽）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<ValueScore>, // --preview 樣本的熵與可壓縮程度
+}
+
+impl TopKey {
+    /// Top N 的排序：mem 由大到小，一樣大時依 key 的 bytes 由小到大
+    ///
+    /// 所有輸出（文字、JSON、模板、diff、合併）都用這個順序，
+    /// 同樣大小的 key 不會因為 SCAN 順序不同而在兩次報表之間換位置。
+    pub fn rank_cmp(a: &TopKey, b: &TopKey) -> Ordering {
+        b.mem.cmp(&a.mem).then_with(|| a.key.cmp(&b.key))
+    }
+
+    pub fn new(mem: u64, key: &str, extra: &KeyExtra) -> Self {
+        Self {
+            key: key.to_owned(),
+            mem,
+            ttl_ms: extra.ttl_ms,
+            idle_secs: extra.idle_secs,
+            encoding: extra.encoding.clone(),
+            freq: extra.freq,
+            elements: extra.elements,
+            estimated_mem: None,
+            estimated: extra.estimated,
+            element_sizes: None,
+            preview: None,
+            score: None,
+        }
+    }
+}
+
+/// 名稱最長的幾個 key；名稱異常長通常是把 payload 序列化進 key 名稱
+#[derive(Default)]
+pub struct LongestKeys {
+    top: Vec<TopKey>, // 依 longest_cmp 排序
+}
+
+impl LongestKeys {
+    pub fn add_key(&mut self, mem: u64, key: &str, extra: &KeyExtra) {
+        if self.top.len() >= LONGEST_KEYS {
+            let last = &self.top[LONGEST_KEYS - 1].key;
+            if key.len() < last.len() || (key.len() == last.len() && key > last.as_str()) {
+                return;
+            }
+        }
+        self.top.push(TopKey::new(mem, key, extra));
+        self.top.sort_by(longest_cmp);
+        self.top.truncate(LONGEST_KEYS);
+    }
+
+    pub fn into_vec(self) -> Vec<TopKey> {
+        self.top
+    }
+}
+
+/// 名稱長度由長到短，一樣長時依 key 由小到大
+pub fn longest_cmp(a: &TopKey, b: &TopKey) -> Ordering {
+    b.key
+        .len()
+        .cmp(&a.key.len())
+        .then_with(|| a.key.cmp(&b.key))
+}
+
+/// key 大小分布（log2 分桶），每桶記筆數與記憶體合計
+///
+/// 只保留 Top N 無法得知「其餘 key」的分布，分桶後可以近似計算 Gini 係數與
+/// 前 1% key 的佔比：同一桶內的 key 視為一樣大（取該桶平均）。
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SizeHistogram {
+    pub counts: Vec<u64>, // 長度 = SIZE_BUCKETS；舊 snapshot 沒有這個欄位時為空
+    pub mems: Vec<u64>,
+}
+
+impl SizeHistogram {
+    pub fn add(&mut self, mem: u64) {
+        if self.counts.is_empty() {
+            self.counts = vec![0; SIZE_BUCKETS];
+            self.mems = vec![0; SIZE_BUCKETS];
+        }
+        let b = (u64::BITS - mem.leading_zeros()) as usize;
+        self.counts[b] += 1;
+        self.mems[b] += mem;
+    }
+
+    pub fn merge(&mut self, other: &SizeHistogram) {
+        if other.counts.is_empty() {
+            return;
+        }
+        if self.counts.is_empty() {
+            *self = other.clone();
+            return;
+        }
+        for (a, b) in self.counts.iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+        for (a, b) in self.mems.iter_mut().zip(&other.mems) {
+            *a += b;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&c| c == 0)
+    }
+
+    /// Gini 係數（0 = 每個 key 一樣大，越接近 1 越集中在少數 key）
+    ///
+    /// 以 Lorenz 曲線的梯形面積計算：G = 1 - Σ f_i (S_{i-1} + S_i)，
+    /// f_i 為該桶筆數比例，S_i 為由小到大累計的記憶體比例。
+    pub fn gini(&self) -> Option<f64> {
+        let n: u64 = self.counts.iter().sum();
+        let total: u64 = self.mems.iter().sum();
+        if n == 0 || total == 0 {
+            return None;
+        }
+
+        let mut area = 0.0;
+        let mut prev = 0.0;
+        let mut cum: u64 = 0;
+        for (&c, &m) in self.counts.iter().zip(&self.mems) {
+            if c == 0 {
+                continue;
+            }
+            cum += m;
+            let s = cum as f64 / total as f64;
+            area += (c as f64 / n as f64) * (prev + s);
+            prev = s;
+        }
+        Some((1.0 - area).clamp(0.0, 1.0))
+    }
+
+    /// 最大的 frac（例如 0.01 = 前 1%）key 合計佔總記憶體的百分比，至少算 1 個 key
+    pub fn top_share(&self, frac: f64) -> Option<f64> {
+        let n: u64 = self.counts.iter().sum();
+        let total: u64 = self.mems.iter().sum();
+        if n == 0 || total == 0 {
+            return None;
+        }
+
+        let mut want = ((n as f64 * frac).ceil() as u64).max(1);
+        let mut mem = 0.0;
+        for (&c, &m) in self.counts.iter().zip(&self.mems).rev() {
+            if c == 0 {
+                continue;
+            }
+            let take = want.min(c);
+            mem += m as f64 * take as f64 / c as f64;
+            want -= take;
+            if want == 0 {
+                break;
+            }
+        }
+        Some(mem / total as f64 * 100.0)
+    }
+}
+
+/// 單一類型的統計
+#[derive(Clone, Default)]
+pub struct TypeStats {
+    pub top: Vec<TopKey>,
+    pub total_mem: u64,
+    pub count: u64,
+    pub sizes: SizeHistogram,
+    pub unmeasured: u64, // 兩段式掃描中只分類、未量測記憶體的 key
+    pub mem_sq: f64,     // 記憶體的平方和，抽樣模式用來估計變異數
+}
+
+impl TypeStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 新增一個 key 的統計，只在進入 Top N 時才 clone key
+    pub fn add_key(&mut self, mem: u64, key: &str, extra: &KeyExtra) {
+        self.count += 1;
+        self.total_mem += mem;
+        self.mem_sq += (mem as f64) * (mem as f64);
+        self.sizes.add(mem);
+
+        // Top N 還沒滿，直接塞
+        if self.top.len() < TOP_N {
+            self.top.push(TopKey::new(mem, key, extra));
+            return;
+        }
+
+        // 找目前 Top 中排名最後的一筆（mem 最小；一樣大時 key 最大）
+        let mut min_idx = 0;
+        for (i, k) in self.top.iter().enumerate().skip(1) {
+            if TopKey::rank_cmp(k, &self.top[min_idx]).is_gt() {
+                min_idx = i;
+            }
+        }
+
+        // 只有排名在它前面才換掉，結果與 SCAN 順序無關
+        let last = &self.top[min_idx];
+        if mem > last.mem || (mem == last.mem && key < last.key.as_str()) {
+            self.top[min_idx] = TopKey::new(mem, key, extra);
+        }
+    }
+
+    /// 兩段式掃描中被篩掉的 key：只計數，不計記憶體
+    pub fn add_unmeasured(&mut self) {
+        self.unmeasured += 1;
+    }
+
+    /// 回傳依 TopKey::rank_cmp 排序後的 Top N
+    pub fn sorted_top_desc(&self) -> Vec<TopKey> {
+        let mut v = self.top.clone();
+        v.sort_by(TopKey::rank_cmp);
+        v
+    }
+}
+
+/// 把另一份 Top N 併進來，依 TopKey::rank_cmp 重新取前 N
+///
+/// 兩份都是各自範圍內的前 N 名，合併後的前 N 名必定落在其中，結果是精確的。
+pub fn merge_top(top: &mut Vec<TopKey>, other: &[TopKey]) {
+    top.extend(other.iter().cloned());
+    top.sort_by(TopKey::rank_cmp);
+    top.truncate(TOP_N);
+}
+
+/// 依 PTTL 統計未來每小時會到期的記憶體
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExpiryTimeline {
+    pub hourly_mem: Vec<u64>, // 長度 = EXPIRY_HOURS
+    pub hourly_count: Vec<u64>,
+    pub later_mem: u64,      // 超出時間軸範圍才到期
+    pub persistent_mem: u64, // 沒有 TTL
+    pub persistent_count: u64,
+    #[serde(default)]
+    pub expiring_mem: u64, // PTTL < EXPIRING_MS（含量完記憶體後才過期的 key）
+    #[serde(default)]
+    pub expiring_count: u64,
+    #[serde(default)]
+    pub stale_perc: Option<f64>, // INFO stats expired_stale_perc
+    #[serde(default)]
+    pub stale_mem_est: Option<u64>, // 依 stale_perc 推估仍佔用記憶體的已過期資料
+}
+
+impl Default for ExpiryTimeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExpiryTimeline {
+    pub fn new() -> Self {
+        Self {
+            hourly_mem: vec![0; EXPIRY_HOURS],
+            hourly_count: vec![0; EXPIRY_HOURS],
+            later_mem: 0,
+            persistent_mem: 0,
+            persistent_count: 0,
+            expiring_mem: 0,
+            expiring_count: 0,
+            stale_perc: None,
+            stale_mem_est: None,
+        }
+    }
+
+    /// pttl: -1 = 沒有 TTL，-2 = 量完記憶體之後就過期了（只計入即將過期）
+    pub fn add_key(&mut self, pttl_ms: i64, mem: u64) {
+        if pttl_ms == -1 {
+            self.persistent_mem += mem;
+            self.persistent_count += 1;
+            return;
+        }
+        if pttl_ms < EXPIRING_MS {
+            self.expiring_mem += mem;
+            self.expiring_count += 1;
+        }
+        if pttl_ms < 0 {
+            return;
+        }
+
+        let hour = (pttl_ms / 3_600_000) as usize;
+        if hour < EXPIRY_HOURS {
+            self.hourly_mem[hour] += mem;
+            self.hourly_count[hour] += 1;
+        } else {
+            self.later_mem += mem;
+        }
+    }
+
+    /// 加上另一個時間軸（合併多節點的結果）
+    pub fn merge(&mut self, other: &ExpiryTimeline) {
+        for (a, b) in self.hourly_mem.iter_mut().zip(&other.hourly_mem) {
+            *a += b;
+        }
+        for (a, b) in self.hourly_count.iter_mut().zip(&other.hourly_count) {
+            *a += b;
+        }
+        self.later_mem += other.later_mem;
+        self.persistent_mem += other.persistent_mem;
+        self.persistent_count += other.persistent_count;
+        self.expiring_mem += other.expiring_mem;
+        self.expiring_count += other.expiring_count;
+        // 各節點的 stale 比例不能直接相加，改用推估量回推整體比例
+        self.stale_mem_est = self
+            .stale_mem_est
+            .zip(other.stale_mem_est)
+            .map(|(a, b)| a + b);
+        let volatile = self.volatile_mem();
+        self.stale_perc = self
+            .stale_mem_est
+            .filter(|_| volatile > 0)
+            .map(|m| m as f64 / volatile as f64 * 100.0);
+    }
+
+    /// 以 INFO stats 的 expired_stale_perc（active expire 抽樣時已過期的比例）推估已過期資料量
+    pub fn set_stale_perc(&mut self, perc: f64) {
+        self.stale_perc = Some(perc);
+        self.stale_mem_est = Some((self.volatile_mem() as f64 * perc / 100.0) as u64);
+    }
+
+    pub fn volatile_mem(&self) -> u64 {
+        self.hourly_mem.iter().sum::<u64>() + self.later_mem
+    }
+}
+
+/// 前綴內的一個大 key（前綴跨類型，所以記下類型）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrefixKey {
+    #[serde(rename = "type")]
+    pub type_code: KeyTypeCode,
+    pub key: String,
+    pub mem: u64,
+}
+
+#[derive(Default)]
+struct PrefixAcc {
+    count: u64,
+    total_mem: u64,
+    names: u64,          // key 名稱 bytes
+    top: Vec<PrefixKey>, // 最多 PREFIX_TOP_KEYS 筆，未排序
+    bands: SizeBands,
+}
+
+/// 依 key 前綴（namespace）彙總的筆數與記憶體
+///
+/// 前綴取 key 依分隔字元切開後的前 depth 段，顯示成 `user:*`；depth = 0 表示不分組。
+/// keep_top 時每個前綴另外保留最大的 PREFIX_TOP_KEYS 個 key。
+pub struct PrefixStats {
+    sep: char,
+    depth: usize,
+    keep_top: bool,
+    map: HashMap<String, PrefixAcc>,
+}
+
+impl PrefixStats {
+    pub fn new(sep: char, depth: usize, keep_top: bool) -> Self {
+        Self {
+            sep,
+            depth,
+            keep_top,
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn add_key(&mut self, key: &str, mem: u64, t: KeyTypeCode) {
+        if self.depth == 0 {
+            return;
+        }
+        let name = match prefix_of(key, self.sep, self.depth) {
+            Some(p) if self.map.len() < MAX_PREFIXES || self.map.contains_key(p) => p,
+            Some(_) => OTHER_PREFIX,
+            None => NO_PREFIX,
+        };
+        // 已存在的前綴不必重新分配字串
+        if !self.map.contains_key(name) {
+            self.map.insert(name.to_owned(), PrefixAcc::default());
+        }
+        let acc = self.map.get_mut(name).expect("剛插入");
+        acc.count += 1;
+        acc.total_mem += mem;
+        acc.names += key.len() as u64;
+        add_to_band(&mut acc.bands, mem);
+        if self.keep_top {
+            push_prefix_key(&mut acc.top, t, key, mem);
+        }
+    }
+
+    /// (prefix, count, total_mem, name_bytes, 最大的 key, 大小區間)，依記憶體由大到小
+    pub fn into_sorted(self) -> Vec<(String, u64, u64, u64, Vec<PrefixKey>, SizeBands)> {
+        let sep = self.sep;
+        let mut v: Vec<(String, u64, u64, u64, Vec<PrefixKey>, SizeBands)> = self
+            .map
+            .into_iter()
+            .map(|(p, mut acc)| {
+                let name = if p == NO_PREFIX || p == OTHER_PREFIX {
+                    p
+                } else {
+                    format!("{}{}*", p, sep)
+                };
+                acc.top.sort_by(prefix_key_cmp);
+                (
+                    name,
+                    acc.count,
+                    acc.total_mem,
+                    acc.names,
+                    acc.top,
+                    acc.bands,
+                )
+            })
+            .collect();
+        v.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        v
+    }
+}
+
+/// mem 由大到小，一樣大時依 key 由小到大（與 TopKey 相同，結果與 SCAN 順序無關）
+pub fn prefix_key_cmp(a: &PrefixKey, b: &PrefixKey) -> Ordering {
+    b.mem.cmp(&a.mem).then_with(|| a.key.cmp(&b.key))
+}
+
+/// 保留最大的 PREFIX_TOP_KEYS 個；滿了只在排名勝過最後一名時取代
+pub fn push_prefix_key(top: &mut Vec<PrefixKey>, t: KeyTypeCode, key: &str, mem: u64) {
+    let entry = || PrefixKey {
+        type_code: t,
+        key: key.to_owned(),
+        mem,
+    };
+    if top.len() < PREFIX_TOP_KEYS {
+        top.push(entry());
+        return;
+    }
+    let (last, _) = top
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| prefix_key_cmp(a, b))
+        .expect("top 已滿");
+    let l = &top[last];
+    if mem > l.mem || (mem == l.mem && key < l.key.as_str()) {
+        top[last] = entry();
+    }
+}
+
+/// key 的前 depth 段（不含結尾分隔字元）；段數不足 depth + 1 時視為沒有前綴
+pub fn prefix_of(key: &str, sep: char, depth: usize) -> Option<&str> {
+    let (end, _) = key.match_indices(sep).nth(depth - 1)?;
+    Some(&key[..end])
+}
+
+/// 所有類型的統計，固定 6 個 slot，避免 HashMap + String type key
+pub struct AllStats {
+    inner: [TypeStats; 6],
+}
+
+impl Default for AllStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AllStats {
+    pub fn new() -> Self {
+        Self {
+            inner: [
+                TypeStats::new(),
+                TypeStats::new(),
+                TypeStats::new(),
+                TypeStats::new(),
+                TypeStats::new(),
+                TypeStats::new(),
+            ],
+        }
+    }
+
+    pub fn get_mut(&mut self, t: KeyTypeCode) -> &mut TypeStats {
+        &mut self.inner[t as usize]
+    }
+
+    pub fn get(&self, t: KeyTypeCode) -> &TypeStats {
+        &self.inner[t as usize]
+    }
+
+    pub fn total_mem(&self) -> u64 {
+        self.inner.iter().map(|s| s.total_mem).sum()
+    }
+}
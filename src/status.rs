@@ -0,0 +1,264 @@
+//! watch --listen：以 HTTP 回報 watch 本身的狀態與最近一輪的結果
+//!
+//! - GET /status：JSON，目前狀態（idle / scanning / paused）、本輪進度、上一輪的摘要與最近一次錯誤，
+//!   給 Kubernetes probe 或 dashboard 使用
+//! - GET /metrics：Prometheus 格式的各類型 / Top key 記憶體與掃描耗時（見 metrics 模組）
+//!
+//! 只用標準函式庫的 TcpListener，一次處理一個連線；其他路徑回 404。
+
+use crate::format::{format_iso8601, now_unix};
+use crate::metrics;
+use crate::report::Report;
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5); // 讀取請求的逾時，避免卡住的 client 擋住其他人
+const MAX_REQUEST: usize = 8 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    Idle,     // 等待下一輪
+    Scanning, // 掃描中
+    Paused,   // 負載保護暫停中
+}
+
+#[derive(Clone, Serialize)]
+struct Progress {
+    done: u64,  // 已處理的 key（含抽樣略過的）
+    total: u64, // 掃描開始時的 DBSIZE
+    percent: f64,
+}
+
+#[derive(Clone, Serialize)]
+struct LastRun {
+    round: u64,
+    target: String,
+    started_at: String,
+    finished_at: String,
+    duration_ms: u64,
+    scanned: u64,
+    errors: u64,
+    total_mem: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct LastError {
+    round: u64,
+    at: String,
+    message: String,
+}
+
+#[derive(Clone, Serialize)]
+struct Status {
+    state: State,
+    since: String, // watch 啟動時間
+    round: u64,    // 目前（或最近一次）的輪次
+    progress: Option<Progress>,
+    pause_reason: Option<String>,
+    next_scan_at: Option<String>,
+    last_run: Option<LastRun>,
+    last_error: Option<LastError>,
+    healthy: bool, // 最近一輪成功（尚未跑完第一輪時為 true）
+    #[serde(skip)]
+    scans: u64, // 成功完成的輪數
+    #[serde(skip)]
+    failures: u64,
+    #[serde(skip)]
+    report_metrics: String, // 最近一輪成功的掃描結果，每輪結束時產生
+}
+
+/// watch 與掃描迴圈更新、HTTP 執行緒讀取的狀態
+pub struct StatusBoard {
+    inner: Mutex<Status>,
+}
+
+impl std::fmt::Debug for StatusBoard {
+    // ScanConfig 的 fingerprint 用 Debug 輸出，不印內容
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StatusBoard")
+    }
+}
+
+impl Default for StatusBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatusBoard {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(Status {
+                state: State::Idle,
+                since: format_iso8601(now_unix()),
+                round: 0,
+                progress: None,
+                pause_reason: None,
+                next_scan_at: None,
+                last_run: None,
+                last_error: None,
+                healthy: true,
+                scans: 0,
+                failures: 0,
+                report_metrics: String::new(),
+            }),
+        }
+    }
+
+    pub fn begin_round(&self, round: u64) {
+        let mut s = self.lock();
+        s.state = State::Scanning;
+        s.round = round;
+        s.progress = None;
+        s.pause_reason = None;
+        s.next_scan_at = None;
+    }
+
+    pub fn progress(&self, done: u64, total: u64) {
+        let mut s = self.lock();
+        s.progress = Some(Progress {
+            done,
+            total,
+            percent: if total == 0 {
+                100.0
+            } else {
+                (done as f64 / total as f64 * 100.0).min(100.0)
+            },
+        });
+    }
+
+    /// 負載保護暫停（Some(原因)）或恢復（None）
+    pub fn paused(&self, reason: Option<String>) {
+        let mut s = self.lock();
+        s.state = if reason.is_some() {
+            State::Paused
+        } else {
+            State::Scanning
+        };
+        s.pause_reason = reason;
+    }
+
+    pub fn finish_round(&self, report: &Report) {
+        let mut s = self.lock();
+        s.last_run = Some(LastRun {
+            round: s.round,
+            target: report.target.clone(),
+            started_at: report.started_at_utc.clone(),
+            finished_at: report.finished_at_utc.clone(),
+            duration_ms: report.duration_ms,
+            scanned: report.scanned,
+            errors: report.errors,
+            total_mem: report.total_mem(),
+        });
+        s.report_metrics = metrics::report_metrics(report);
+        s.scans += 1;
+        s.healthy = true;
+        s.state = State::Idle;
+        s.pause_reason = None;
+    }
+
+    pub fn fail_round(&self, message: &str) {
+        let mut s = self.lock();
+        s.last_error = Some(LastError {
+            round: s.round,
+            at: format_iso8601(now_unix()),
+            message: message.to_string(),
+        });
+        s.failures += 1;
+        s.healthy = false;
+        s.state = State::Idle;
+        s.pause_reason = None;
+    }
+
+    pub fn idle_until(&self, next_scan_at: u64) {
+        let mut s = self.lock();
+        s.state = State::Idle;
+        s.next_scan_at = Some(format_iso8601(next_scan_at));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Status> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&*self.lock()).unwrap_or_default()
+    }
+
+    fn to_metrics(&self) -> String {
+        let s = self.lock();
+        let scanning = s.state != State::Idle;
+        metrics::state_metrics(scanning, s.healthy, s.scans, s.failures) + &s.report_metrics
+    }
+}
+
+/// 綁定位址後在背景執行緒回應請求；綁定失敗直接回錯誤，在開始掃描前就讓使用者知道
+pub fn serve(addr: &str, board: Arc<StatusBoard>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)
+        .map_err(|e| io::Error::new(e.kind(), format!("無法監聽 --listen {}: {}", addr, e)))?;
+    let bound = listener
+        .local_addr()
+        .map_or(addr.to_string(), |a| a.to_string());
+    println!(
+        "狀態端點: http://{}/status、http://{}/metrics",
+        bound, bound
+    );
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // 單一連線的錯誤（client 中途斷線等）不影響之後的請求
+            let _ = respond(stream, &board);
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream, board: &StatusBoard) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST {
+        let n = stream.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let request = String::from_utf8_lossy(&buf);
+    let mut parts = request.lines().next().unwrap_or("").split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = path.split('?').next().unwrap_or("");
+
+    const JSON: &str = "application/json";
+    let (status, content_type, body) = match (method, path) {
+        ("GET" | "HEAD", "/status") => ("200 OK", JSON, board.to_json()),
+        ("GET" | "HEAD", "/metrics") => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            board.to_metrics(),
+        ),
+        ("GET" | "HEAD", _) => (
+            "404 Not Found",
+            JSON,
+            r#"{"error":"not found"}"#.to_string(),
+        ),
+        _ => (
+            "405 Method Not Allowed",
+            JSON,
+            r#"{"error":"method not allowed"}"#.to_string(),
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes())?;
+    }
+    stream.flush()
+}
@@ -0,0 +1,109 @@
+//! --stratify-types：依類型分層抽樣
+//!
+//! 均勻抽樣下，數量少但很大的類型（例如只有幾十個的 stream）很可能一個都沒被抽中。
+//! 分層時先對 SCAN 回傳的每個 key 送 TYPE 普查，每個類型的前 N 個 key 一律量測，之後才依
+//! --sample-rate 抽樣。每個 key 記下被抽中的機率 p，以 Horvitz-Thompson（Σ mem / p）估計
+//! 各類型的記憶體；p 只由之前的 key 決定，估計仍然不偏。
+
+use crate::conn::RedisConn;
+use crate::report::Stratum;
+use crate::scan::{in_sample, send_pipeline};
+use crate::stats::{KeyTypeCode, parse_type_code};
+use std::collections::HashMap;
+
+pub const DEFAULT_STRATUM_MIN: u64 = 200; // 每個類型至少完整量測的 key 數
+
+pub struct Strata {
+    min: u64,
+    rate: f64,
+    census: [u64; 6], // 依 KeyTypeCode::all() 順序
+    sampled: [u64; 6],
+    est_mem: [f64; 6],
+    mem_var: [f64; 6],
+    pending: HashMap<String, (KeyTypeCode, f64)>, // 本批抽中的 key → (普查的類型, 抽中機率)
+}
+
+impl Strata {
+    pub fn new(min: u64, rate: f64) -> Self {
+        Self {
+            min,
+            rate,
+            census: [0; 6],
+            sampled: [0; 6],
+            est_mem: [0.0; 6],
+            mem_var: [0.0; 6],
+            pending: HashMap::new(),
+        }
+    }
+
+    /// 對一批 SCAN 結果送 TYPE 普查，只留下抽中的 key
+    ///
+    /// TYPE 回 none（已刪除）或錯誤的 key 不列入普查，照 --sample-rate 抽樣後交給量測判斷。
+    pub fn select(
+        &mut self,
+        con: &mut RedisConn,
+        keys: Vec<String>,
+    ) -> redis::RedisResult<Vec<String>> {
+        let mut pipe = redis::pipe();
+        for key in &keys {
+            pipe.cmd("TYPE").arg(key);
+        }
+        let types = send_pipeline(con, &pipe, keys.len())?;
+
+        self.pending.clear();
+        let mut keep = Vec::new();
+        for (key, v) in keys.into_iter().zip(&types) {
+            let Some(t) = parse_type_code(v) else {
+                if in_sample(&key, self.rate) {
+                    keep.push(key);
+                }
+                continue;
+            };
+            let i = t as usize;
+            self.census[i] += 1;
+            let p = if self.census[i] <= self.min {
+                1.0
+            } else {
+                self.rate
+            };
+            if p >= 1.0 || in_sample(&key, self.rate) {
+                self.pending.insert(key.clone(), (t, p));
+                keep.push(key);
+            }
+        }
+        Ok(keep)
+    }
+
+    /// 普查得到的類型，量測時不必再送 TYPE
+    pub fn known(&self, keys: &[String]) -> Vec<Option<KeyTypeCode>> {
+        keys.iter()
+            .map(|k| self.pending.get(k).map(|&(t, _)| t))
+            .collect()
+    }
+
+    /// 記下量測結果；類型以量測時為準，抽中機率以普查時決定的為準
+    pub fn record(&mut self, key: &str, t: KeyTypeCode, mem: u64) {
+        let p = self.pending.get(key).map_or(self.rate, |&(_, p)| p);
+        let i = t as usize;
+        let mem = mem as f64;
+        self.sampled[i] += 1;
+        self.est_mem[i] += mem / p;
+        self.mem_var[i] += (1.0 - p) / (p * p) * mem * mem;
+    }
+
+    pub fn into_strata(self) -> Vec<Stratum> {
+        KeyTypeCode::all()
+            .iter()
+            .map(|&t| {
+                let i = t as usize;
+                Stratum {
+                    type_code: t,
+                    keys: self.census[i],
+                    sampled: self.sampled[i],
+                    est_mem: self.est_mem[i],
+                    mem_var: self.mem_var[i],
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,78 @@
+use crate::format::{Decimal, format_int, format_unix_ts};
+use crate::report::Report;
+use crate::stats::TOP_N;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use tera::{Context, Tera, Value};
+
+/// 以使用者提供的 Tera 模板輸出報表
+///
+/// 模板拿到的就是 snapshot 的 JSON 結構（target / types / expiry ...），另外補上
+/// `total_mem`、`started_at_text`、`top_n`，以及 `commas`、`mb` 兩個 filter。
+/// 副檔名是 .html / .htm 時自動 escape。
+pub fn render_template(
+    report: &Report,
+    path: &Path,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("無法讀取模板 {}: {}", path.display(), e))?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "template".into());
+
+    let mut tera = Tera::default();
+    tera.autoescape_on(vec![".html", ".htm"]);
+    tera.register_filter("commas", commas_filter);
+    tera.register_filter("mb", mb_filter);
+    tera.add_raw_template(&name, &source)
+        .map_err(|e| format!("模板語法錯誤 {}: {}", path.display(), error_chain(&e)))?;
+
+    let mut ctx = Context::from_serialize(report)?;
+    ctx.insert("total_mem", &report.total_mem());
+    ctx.insert("started_at_text", &format_unix_ts(report.started_at));
+    ctx.insert("top_n", &TOP_N);
+
+    let rendered = tera
+        .render(&name, &ctx)
+        .map_err(|e| format!("模板輸出失敗 {}: {}", path.display(), error_chain(&e)))?;
+    out.write_all(rendered.as_bytes())?;
+    Ok(())
+}
+
+/// {{ n | commas }} → 1,234,567
+fn commas_filter(v: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let n = v
+        .as_u64()
+        .or_else(|| v.as_f64().map(|f| f.max(0.0) as u64))
+        .ok_or_else(|| tera::Error::msg("commas 只適用於數字"))?;
+    Ok(Value::String(format_int(n)))
+}
+
+/// {{ bytes | mb }} → "12.34"（與文字報表相同的 MB 算法，保留兩位）
+fn mb_filter(v: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let bytes = v
+        .as_f64()
+        .ok_or_else(|| tera::Error::msg("mb 只適用於數字"))?;
+    let digits = args.get("digits").and_then(|d| d.as_u64()).unwrap_or(2) as usize;
+    Ok(Value::String(format!(
+        "{:.*}",
+        digits,
+        Decimal(bytes / 1024.0 / 1024.0)
+    )))
+}
+
+/// Tera 的 Display 只有最外層訊息，實際原因在 source 裡
+fn error_chain(e: &tera::Error) -> String {
+    let mut msg = e.to_string();
+    let mut cur = e.source();
+    while let Some(s) = cur {
+        msg.push_str(": ");
+        msg.push_str(&s.to_string());
+        cur = s.source();
+    }
+    msg
+}
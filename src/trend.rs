@@ -0,0 +1,339 @@
+use crate::cleanup::glob_match;
+use crate::format::{Decimal, format_int, format_unix_ts, format_unix_ts_compact, truncate_key};
+use crate::output::warning;
+use crate::report::Report;
+use crate::snapshot;
+use crate::stats::KeyTypeCode;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const SECS_PER_DAY: f64 = 86_400.0;
+const CAPACITY_DRIVERS: usize = 5; // 容量預估中列出的成長來源數
+
+/// 單一前綴在歷史紀錄中的成長
+struct Growth<'a> {
+    prefix: &'a str,
+    first_mem: u64,
+    last_mem: u64,
+    mb_per_day: f64, // 最小平方法斜率
+    budget: Option<u64>,
+}
+
+/// 展開輸入：檔案直接使用，目錄取其中所有 *.json（scan --history-dir 的輸出）
+pub fn collect_snapshots(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let entries = std::fs::read_dir(input)
+                .map_err(|e| format!("無法讀取目錄 {}: {}", input.display(), e))?;
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    files.push(path);
+                }
+            }
+        } else {
+            files.push(input.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// 依掃描時間排序後載入，跳過沒有前綴資料的舊 snapshot
+pub fn load_history(files: &[PathBuf]) -> Result<Vec<Report>, Box<dyn Error>> {
+    let mut reports = Vec::new();
+    for path in files {
+        let report = snapshot::load(path)?;
+        if report.prefixes.is_empty() {
+            warning!(
+                "略過 {}: 沒有前綴統計（舊版 snapshot 或 --prefix-depth 0）",
+                path.display()
+            );
+            continue;
+        }
+        reports.push(report);
+    }
+    reports.sort_by_key(|r| r.started_at);
+    Ok(reports)
+}
+
+/// scan --history-dir：以掃描時間命名寫入目錄
+pub fn save_history(report: &Report, dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("無法建立目錄 {}: {}", dir.display(), e))?;
+    let path = dir.join(format!(
+        "{}.json",
+        format_unix_ts_compact(report.started_at)
+    ));
+    snapshot::save(report, &path)?;
+    Ok(path)
+}
+
+/// 各前綴的成長速度（MB/天）與預計超過預算的時間
+///
+/// 斜率用所有歷史點做最小平方法擬合，比只看頭尾兩點不容易被單次尖峰誤導。
+pub fn render_trend(
+    history: &[Report],
+    budgets: &[(String, u64)],
+    top: usize,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let (first, last) = (&history[0], &history[history.len() - 1]);
+    let span_days = (last.started_at - first.started_at) as f64 / SECS_PER_DAY;
+
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "前綴成長趨勢: {} 次掃描，{} → {}（{:.1} 天）",
+        history.len(),
+        format_unix_ts(first.started_at),
+        format_unix_ts(last.started_at),
+        Decimal(span_days)
+    )?;
+    for r in history.iter().filter(|r| !r.notes.is_empty()) {
+        writeln!(
+            out,
+            "📝 {}: {}",
+            format_unix_ts(r.started_at),
+            r.notes.join("；")
+        )?;
+    }
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    let mut growth = prefix_growth(history);
+    for g in &mut growth {
+        g.budget = budgets
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, g.prefix))
+            .map(|(_, b)| *b);
+    }
+
+    writeln!(
+        out,
+        "{:<40} {:>12} {:>12} {:>12} {:>10} {:>12}  預計超過預算",
+        "前綴", "最早 (MB)", "最新 (MB)", "MB/天", "%/天", "預算 (MB)"
+    )?;
+    writeln!(out, "{}", "-".repeat(120))?;
+
+    for g in growth.iter().take(top) {
+        let pct_per_day = if g.first_mem > 0 {
+            format!(
+                "{:>+9.2}%",
+                Decimal(g.mb_per_day * 1024.0 * 1024.0 / g.first_mem as f64 * 100.0)
+            )
+        } else {
+            format!("{:>10}", "新出現")
+        };
+        let budget = g
+            .budget
+            .map(|b| format!("{:.2}", Decimal(b as f64 / 1024.0 / 1024.0)))
+            .unwrap_or_else(|| "-".into());
+        writeln!(
+            out,
+            "{:<40} {:>12.2} {:>12.2} {:>+12.3} {} {:>12}  {}",
+            truncate_key(g.prefix, 40),
+            Decimal(g.first_mem as f64 / 1024.0 / 1024.0),
+            Decimal(g.last_mem as f64 / 1024.0 / 1024.0),
+            Decimal(g.mb_per_day),
+            pct_per_day,
+            budget,
+            budget_eta(g, last.started_at)
+        )?;
+    }
+
+    if growth.len() > top {
+        writeln!(
+            out,
+            "\n（只列出成長最快的 {} 個，共 {} 個前綴）",
+            top,
+            format_int(growth.len() as u64)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 各前綴的成長速度，依 MB/天 desc
+///
+/// 某次掃描沒有出現的前綴視為 0（已被清空或尚未出現）。
+fn prefix_growth(history: &[Report]) -> Vec<Growth<'_>> {
+    let days = days_since_first(history);
+
+    // prefix -> 每次掃描的記憶體
+    let mut series: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+    for (i, r) in history.iter().enumerate() {
+        for p in &r.prefixes {
+            series
+                .entry(p.prefix.as_str())
+                .or_insert_with(|| vec![0; history.len()])[i] = p.total_mem;
+        }
+    }
+
+    let mut growth: Vec<Growth> = series
+        .into_iter()
+        .map(|(prefix, mems)| Growth {
+            prefix,
+            first_mem: mems[0],
+            last_mem: mems[mems.len() - 1],
+            mb_per_day: slope(&days, &mems) / 1024.0 / 1024.0,
+            budget: None,
+        })
+        .collect();
+    growth.sort_by(|a, b| {
+        b.mb_per_day
+            .total_cmp(&a.mb_per_day)
+            .then_with(|| a.prefix.cmp(b.prefix))
+    });
+    growth
+}
+
+fn days_since_first(history: &[Report]) -> Vec<f64> {
+    let first = history[0].started_at;
+    history
+        .iter()
+        .map(|r| (r.started_at - first) as f64 / SECS_PER_DAY)
+        .collect()
+}
+
+/// 容量預估的目標
+#[derive(Clone, Copy)]
+pub struct CapacityTarget {
+    pub capacity: Option<u64>, // 未指定時用最新 snapshot 的 maxmemory
+    pub target_pct: f64,       // 達到容量的多少百分比算「滿」
+}
+
+/// 依資料集的成長速度推估何時達到 maxmemory（或目標使用率），並列出主要成長來源
+///
+/// 成長速度取 keys 記憶體加總（各 snapshot 一致可比），起點用最新一次的 used_memory，
+/// 因為 overhead 也佔用 maxmemory。
+pub fn render_capacity(
+    history: &[Report],
+    target: CapacityTarget,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let last = &history[history.len() - 1];
+    let info_num = |name: &str| {
+        last.memory_info
+            .as_ref()
+            .and_then(|m| m.get(name))
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+
+    writeln!(out, "\n{}", "=".repeat(120))?;
+    writeln!(out, "容量預估")?;
+    writeln!(out, "{}", "=".repeat(120))?;
+
+    let Some(capacity) = target.capacity.or(info_num("maxmemory").filter(|&m| m > 0)) else {
+        writeln!(
+            out,
+            "  無法預估: 最新 snapshot 沒有 maxmemory（未設定或無法取得 INFO memory），請用 --capacity 指定"
+        )?;
+        return Ok(());
+    };
+    let used = info_num("used_memory").unwrap_or_else(|| last.total_mem());
+    let limit = (capacity as f64 * target.target_pct / 100.0) as u64;
+
+    let days = days_since_first(history);
+    let totals: Vec<u64> = history.iter().map(|r| r.total_mem()).collect();
+    let bytes_per_day = slope(&days, &totals);
+
+    writeln!(
+        out,
+        "  目前 used_memory {:.2} MB / 容量 {:.2} MB（目標 {:.0}% = {:.2} MB），資料集成長 {:+.3} MB/天",
+        Decimal(used as f64 / 1024.0 / 1024.0),
+        Decimal(capacity as f64 / 1024.0 / 1024.0),
+        Decimal(target.target_pct),
+        Decimal(limit as f64 / 1024.0 / 1024.0),
+        Decimal(bytes_per_day / 1024.0 / 1024.0)
+    )?;
+
+    if used >= limit {
+        writeln!(out, "  ⚠ 已達到目標使用率")?;
+    } else if bytes_per_day <= 0.0 {
+        writeln!(out, "  記憶體未成長，依目前趨勢不會達到目標使用率")?;
+    } else {
+        let eta_days = (limit - used) as f64 / bytes_per_day;
+        writeln!(
+            out,
+            "  📅 預計 {} 達到目標（約 {:.1} 天後）",
+            format_unix_ts(last.started_at + (eta_days * SECS_PER_DAY) as u64),
+            Decimal(eta_days)
+        )?;
+    }
+
+    // 各類型的成長
+    writeln!(out, "\n{:<15} {:>15} {:>12}", "類型", "目前 (MB)", "MB/天")?;
+    writeln!(out, "{}", "-".repeat(120))?;
+    for t in KeyTypeCode::all() {
+        let mems: Vec<u64> = history
+            .iter()
+            .map(|r| r.get(*t).map(|tr| tr.total_mem).unwrap_or(0))
+            .collect();
+        if mems.iter().all(|&m| m == 0) {
+            continue;
+        }
+        writeln!(
+            out,
+            "{:<15} {:>15.2} {:>+12.3}",
+            t.name(),
+            Decimal(mems[mems.len() - 1] as f64 / 1024.0 / 1024.0),
+            Decimal(slope(&days, &mems) / 1024.0 / 1024.0)
+        )?;
+    }
+
+    // 成長主要來自哪些前綴
+    let growth = prefix_growth(history);
+    let rising: f64 = growth.iter().map(|g| g.mb_per_day.max(0.0)).sum();
+    if rising > 0.0 {
+        writeln!(out, "\n  主要成長來源:")?;
+        for g in growth
+            .iter()
+            .filter(|g| g.mb_per_day > 0.0)
+            .take(CAPACITY_DRIVERS)
+        {
+            writeln!(
+                out,
+                "    {:<40} {:>+12.3} MB/天  佔成長 {:>6.2}%",
+                truncate_key(g.prefix, 40),
+                Decimal(g.mb_per_day),
+                Decimal(g.mb_per_day / rising * 100.0)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 依目前成長速度推估何時超過預算
+fn budget_eta(g: &Growth, now: u64) -> String {
+    let Some(budget) = g.budget else {
+        return String::new();
+    };
+    if g.last_mem >= budget {
+        return "⚠ 已超過".into();
+    }
+    let bytes_per_day = g.mb_per_day * 1024.0 * 1024.0;
+    if bytes_per_day <= 0.0 {
+        return "不會（未成長）".into();
+    }
+    let days = (budget - g.last_mem) as f64 / bytes_per_day;
+    format!(
+        "{}（約 {:.1} 天後）",
+        format_unix_ts(now + (days * SECS_PER_DAY) as u64),
+        Decimal(days)
+    )
+}
+
+/// 最小平方法斜率（bytes / 天）
+fn slope(xs: &[f64], ys: &[u64]) -> f64 {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().map(|&y| y as f64).sum::<f64>() / n;
+    let (mut num, mut den) = (0.0, 0.0);
+    for (&x, &y) in xs.iter().zip(ys) {
+        num += (x - mean_x) * (y as f64 - mean_y);
+        den += (x - mean_x) * (x - mean_x);
+    }
+    if den == 0.0 { 0.0 } else { num / den }
+}
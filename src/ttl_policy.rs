@@ -0,0 +1,104 @@
+use crate::report::TtlSuggestion;
+use crate::stats::{MAX_PREFIXES, prefix_of};
+use std::collections::HashMap;
+
+pub const TTL_SUGGESTIONS: usize = 20; // 報表中保留的建議數
+const PERSISTENT_KEEP: usize = 5; // 每個前綴記住最大的幾個沒有 TTL 的 key
+const MIN_KEYS: u64 = 100; // 前綴 keys 數太少時比例沒有意義
+const VOLATILE_SHARE: f64 = 0.9; // 前綴中有 TTL 的比例達到此值，視為快取用途
+
+/// 單一前綴的 TTL 分布
+#[derive(Default)]
+struct Namespace {
+    count: u64,
+    volatile: u64,
+    total_mem: u64,
+    max_ttl_ms: i64,
+    persistent: Vec<(u64, String)>, // 沒有 TTL 的最大幾個 key (mem, key)，依 mem desc
+}
+
+/// 找出「幾乎都有 TTL 的前綴」裡沒有 TTL 的大 key
+///
+/// 這類 key 通常是應用程式寫入快取時漏設 TTL 的路徑造成的，不會自己消失。
+pub struct TtlPolicy {
+    sep: char,
+    depth: usize,
+    map: HashMap<String, Namespace>,
+}
+
+impl TtlPolicy {
+    pub fn new(sep: char, depth: usize) -> Self {
+        Self {
+            sep,
+            depth,
+            map: HashMap::new(),
+        }
+    }
+
+    /// pttl: -1 = 沒有 TTL，其他負值（key 已不存在）不計
+    pub fn add_key(&mut self, key: &str, mem: u64, pttl_ms: i64) {
+        if self.depth == 0 || pttl_ms < -1 {
+            return;
+        }
+        let Some(prefix) = prefix_of(key, self.sep, self.depth) else {
+            return;
+        };
+        // 超過前綴上限後只更新已存在的前綴
+        if !self.map.contains_key(prefix) {
+            if self.map.len() >= MAX_PREFIXES {
+                return;
+            }
+            self.map.insert(prefix.to_owned(), Namespace::default());
+        }
+        let ns = self.map.get_mut(prefix).expect("已插入");
+
+        ns.count += 1;
+        ns.total_mem += mem;
+        if pttl_ms >= 0 {
+            ns.volatile += 1;
+            ns.max_ttl_ms = ns.max_ttl_ms.max(pttl_ms);
+            return;
+        }
+        if ns.persistent.len() < PERSISTENT_KEEP || mem > ns.persistent[PERSISTENT_KEEP - 1].0 {
+            ns.persistent.push((mem, key.to_owned()));
+            ns.persistent
+                .sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+            ns.persistent.truncate(PERSISTENT_KEEP);
+        }
+    }
+
+    /// 依記憶體 desc 的建議清單，最多 TTL_SUGGESTIONS 筆
+    ///
+    /// 只列出比同前綴平均還大的 key；建議的 TTL 取同前綴觀察到的最長剩餘 TTL，
+    /// 不會比現有的快取 key 更早過期。
+    pub fn into_suggestions(self) -> Vec<TtlSuggestion> {
+        let sep = self.sep;
+        let mut out = Vec::new();
+        for (prefix, ns) in self.map {
+            let share = ns.volatile as f64 / ns.count as f64;
+            if ns.count < MIN_KEYS || share < VOLATILE_SHARE {
+                continue;
+            }
+            let avg = ns.total_mem / ns.count;
+            let ttl_secs = (ns.max_ttl_ms as u64).div_ceil(1000).max(1);
+            for (mem, key) in ns.persistent.into_iter().filter(|(mem, _)| *mem > avg) {
+                out.push(TtlSuggestion {
+                    key,
+                    mem,
+                    prefix: format!("{}{}*", prefix, sep),
+                    prefix_count: ns.count,
+                    volatile_pct: share * 100.0,
+                    ttl_secs,
+                });
+            }
+        }
+        sort_suggestions(&mut out);
+        out
+    }
+}
+
+/// 依記憶體 desc、key asc 排序並截斷
+pub fn sort_suggestions(v: &mut Vec<TtlSuggestion>) {
+    v.sort_by(|a, b| b.mem.cmp(&a.mem).then_with(|| a.key.cmp(&b.key)));
+    v.truncate(TTL_SUGGESTIONS);
+}
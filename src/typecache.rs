@@ -0,0 +1,173 @@
+//! watch 的類型快取：記住上一輪每個 key 的類型，之後的掃描只送 MEMORY USAGE 不送 TYPE
+//!
+//! key 很少改變類型（必須先刪除再以其他類型重建），穩定的 keyspace 每輪可省下約一半的指令。
+//! 以 key 的 64-bit hash 為索引，不保存 key 本身。每 refresh_every 輪做一次完整的 TYPE
+//! 重新建立快取，改變類型的 key 最多在這段期間內被歸在舊類型。
+//!
+//! --type-cache FILE 把快取存成檔案（8 bytes hash + 1 byte 類型），watch 重新啟動後沿用。
+//! hash 用 crate::hash::key_hash，不隨 Rust 版本改變；換 hash 時要一併更換 FILE_MAGIC。
+
+use crate::hash::key_hash;
+use crate::stats::KeyTypeCode;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+pub const DEFAULT_TYPE_REFRESH: u64 = 10; // 每幾輪完整送一次 TYPE
+const MAX_ENTRIES: usize = 5_000_000; // 約佔 100 MB；超過的 key 照常送 TYPE
+const FILE_MAGIC: &[u8] = b"RTKA-TYPES-2\n";
+const OLD_MAGIC: &[u8] = b"RTKA-TYPES-"; // 舊版快取檔（hash 不同）直接丟棄，不當成錯誤
+
+/// 各輪掃描共用（cluster 各節點同時查詢），內部以 Mutex 保護
+pub struct TypeCache {
+    inner: Mutex<Inner>,
+    refresh_every: u64,
+}
+
+struct Inner {
+    types: HashMap<u64, KeyTypeCode>,
+    warm: bool,       // 本輪是否沿用快取
+    warm_rounds: u64, // 上次完整 TYPE 之後已沿用了幾輪
+    hits: u64,        // 本輪省下的 TYPE
+    misses: u64,      // 本輪仍需送 TYPE 的 key
+}
+
+/// 一輪掃描的快取使用情形
+pub struct RoundStats {
+    pub warm: bool,
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+impl std::fmt::Debug for TypeCache {
+    // ScanConfig 的 fingerprint 用 Debug 輸出，只印設定不印內容
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TypeCache(refresh_every={})", self.refresh_every)
+    }
+}
+
+impl TypeCache {
+    pub fn new(refresh_every: u64) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                types: HashMap::new(),
+                warm: false,
+                warm_rounds: 0,
+                hits: 0,
+                misses: 0,
+            }),
+            refresh_every,
+        }
+    }
+
+    /// 讀取 --type-cache 檔案；檔案不存在或是舊版格式時為空的快取，格式不符時回錯誤
+    pub fn load(path: &Path, refresh_every: u64) -> io::Result<Self> {
+        let cache = Self::new(refresh_every);
+        let data = match fs::read(path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(cache),
+            Err(e) => return Err(e),
+        };
+        if data.starts_with(OLD_MAGIC) && !data.starts_with(FILE_MAGIC) {
+            return Ok(cache);
+        }
+        let body = data
+            .strip_prefix(FILE_MAGIC)
+            .filter(|b| b.len() % 9 == 0)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} 不是類型快取檔", path.display()),
+                )
+            })?;
+        {
+            let mut inner = cache.inner.lock().unwrap_or_else(|e| e.into_inner());
+            for rec in body.chunks_exact(9).take(MAX_ENTRIES) {
+                let hash = u64::from_le_bytes(rec[..8].try_into().unwrap());
+                if let Some(&t) = KeyTypeCode::all().get(rec[8] as usize) {
+                    inner.types.insert(hash, t);
+                }
+            }
+        }
+        Ok(cache)
+    }
+
+    /// 先寫到暫存檔再改名，寫到一半中斷不會留下損壞的檔案
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut data = Vec::with_capacity(FILE_MAGIC.len() + inner.types.len() * 9);
+        data.extend_from_slice(FILE_MAGIC);
+        for (hash, t) in &inner.types {
+            data.extend_from_slice(&hash.to_le_bytes());
+            data.push(*t as u8);
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, path)
+    }
+
+    /// 每輪掃描前呼叫：決定本輪沿用快取，或是完整送 TYPE 並重建快取
+    ///
+    /// 重建時清空舊內容，已刪除的 key 不會一直留在快取裡。
+    pub fn begin_round(&self) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let refresh = inner.types.is_empty()
+            || (self.refresh_every > 0 && inner.warm_rounds >= self.refresh_every);
+        if refresh {
+            inner.types.clear();
+            inner.warm_rounds = 0;
+        } else {
+            inner.warm_rounds += 1;
+        }
+        inner.warm = !refresh;
+        inner.hits = 0;
+        inner.misses = 0;
+    }
+
+    pub fn round_stats(&self) -> RoundStats {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        RoundStats {
+            warm: inner.warm,
+            hits: inner.hits,
+            misses: inner.misses,
+            entries: inner.types.len(),
+        }
+    }
+
+    /// 一批 key 已知的類型；本輪不沿用快取時全部為 None
+    pub fn lookup(&self, keys: &[String]) -> Vec<Option<KeyTypeCode>> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if !inner.warm {
+            inner.misses += keys.len() as u64;
+            return vec![None; keys.len()];
+        }
+        let known: Vec<_> = keys
+            .iter()
+            .map(|k| inner.types.get(&key_hash(k)).copied())
+            .collect();
+        let hits = known.iter().filter(|t| t.is_some()).count() as u64;
+        inner.hits += hits;
+        inner.misses += keys.len() as u64 - hits;
+        known
+    }
+
+    /// 記下量測結果；已消失的 key（None）從快取移除
+    pub fn record<'a>(&self, results: impl Iterator<Item = (&'a String, Option<KeyTypeCode>)>) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        for (key, t) in results {
+            let hash = key_hash(key);
+            match t {
+                Some(t) if inner.types.len() < MAX_ENTRIES || inner.types.contains_key(&hash) => {
+                    inner.types.insert(hash, t);
+                }
+                Some(_) => {}
+                None => {
+                    inner.types.remove(&hash);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::format::format_unix_ts_compact;
+#[cfg(feature = "post")]
+use crate::output::warning;
+use crate::report::Report;
+use crate::snapshot;
+#[cfg(feature = "cloud")]
+use object_store::aws::AmazonS3Builder;
+#[cfg(feature = "cloud")]
+use object_store::azure::MicrosoftAzureBuilder;
+#[cfg(feature = "cloud")]
+use object_store::gcp::GoogleCloudStorageBuilder;
+#[cfg(feature = "cloud")]
+use object_store::path::Path as ObjectPath;
+#[cfg(feature = "cloud")]
+use object_store::{
+    Attribute, Attributes, BackoffConfig, ObjectStore, PutOptions, PutPayload, RetryConfig,
+};
+use std::error::Error;
+use std::path::Path;
+#[cfg(any(feature = "cloud", feature = "post"))]
+use std::time::Duration;
+#[cfg(feature = "cloud")]
+use url::Url;
+
+#[cfg(any(feature = "cloud", feature = "post"))]
+const UPLOAD_RETRIES: usize = 5;
+#[cfg(any(feature = "cloud", feature = "post"))]
+const UPLOAD_RETRY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 依 --output 寫出 JSON 報表：本機路徑，或 s3:// / gs:// / az:// 物件儲存
+///
+/// 目的地中的 `{ts}` 會換成掃描開始時間（UTC，例如 20261014T155709Z），回傳實際寫入的位置。
+/// 物件儲存的認證一律取自環境變數（AWS_* / GOOGLE_* / AZURE_*），與各家 CLI 相同。
+pub fn write_report(report: &Report, dest: &str) -> Result<String, Box<dyn Error>> {
+    let dest = dest.replace("{ts}", &format_unix_ts_compact(report.started_at));
+
+    let Some(scheme) = remote_scheme(&dest) else {
+        snapshot::save(report, Path::new(&dest))?;
+        return Ok(dest);
+    };
+    put_object(report, &dest, scheme)?;
+    Ok(dest)
+}
+
+#[cfg(feature = "cloud")]
+fn put_object(report: &Report, dest: &str, scheme: &str) -> Result<(), Box<dyn Error>> {
+    let url = Url::parse(dest).map_err(|e| format!("無效的輸出位置 {}: {}", dest, e))?;
+    let retry = RetryConfig {
+        backoff: BackoffConfig::default(),
+        max_retries: UPLOAD_RETRIES,
+        retry_timeout: UPLOAD_RETRY_TIMEOUT,
+    };
+    let store: Box<dyn ObjectStore> = match scheme {
+        "s3" => Box::new(
+            AmazonS3Builder::from_env()
+                .with_url(dest)
+                .with_retry(retry)
+                .build()?,
+        ),
+        "gs" => Box::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_url(dest)
+                .with_retry(retry)
+                .build()?,
+        ),
+        _ => Box::new(
+            MicrosoftAzureBuilder::from_env()
+                .with_url(dest)
+                .with_retry(retry)
+                .build()?,
+        ),
+    };
+
+    let location = ObjectPath::from_url_path(url.path())?;
+    let mut body = serde_json::to_vec_pretty(report)?;
+    body.push(b'\n');
+
+    let mut attributes = Attributes::new();
+    attributes.insert(Attribute::ContentType, "application/json".into());
+    let opts = PutOptions {
+        attributes,
+        ..Default::default()
+    };
+
+    // 其餘流程都是同步的，只在上傳時起一個單執行緒 runtime
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    rt.block_on(store.put_opts(&location, PutPayload::from(body), opts))
+        .map_err(|e| format!("上傳報表失敗 {}: {}", dest, e))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "cloud"))]
+fn put_object(_: &Report, _: &str, _: &str) -> Result<(), Box<dyn Error>> {
+    Err(crate::feature_disabled(
+        "cloud",
+        "--output s3:// / gs:// / az://",
+    ))
+}
+
+/// 是否為物件儲存位置（s3:// / gs:// / az://）
+pub fn is_remote(dest: &str) -> bool {
+    remote_scheme(dest).is_some()
+}
+
+/// s3:// / gs:// / az://（含 azure 的 abfs:// 等別名）回傳 scheme，本機路徑回傳 None
+fn remote_scheme(dest: &str) -> Option<&str> {
+    let (scheme, _) = dest.split_once("://")?;
+    match scheme {
+        "s3" | "s3a" => Some("s3"),
+        "gs" => Some("gs"),
+        "az" | "adl" | "azure" | "abfs" | "abfss" => Some("az"),
+        _ => None,
+    }
+}
+
+/// 把 JSON 報表 POST 到收集端
+///
+/// token 不建議放在命令列（會出現在 ps），可改用環境變數 REDIS_TOP_KEYS_POST_TOKEN。
+pub fn post_report(report: &Report, url: &str, token: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let body = serde_json::to_vec(report)?;
+    let auth = token.map(|t| format!("Bearer {}", t));
+    post_json(url, body, auth.as_deref())
+}
+
+/// POST JSON，5xx 或連線失敗時以 backoff 重試；auth 為完整的 Authorization header 值
+#[cfg(feature = "post")]
+pub fn post_json(url: &str, body: Vec<u8>, auth: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(UPLOAD_RETRY_TIMEOUT)
+        .build()?;
+
+    let mut backoff = Duration::from_secs(1);
+    let mut attempt = 0;
+    loop {
+        let mut req = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(auth) = auth {
+            req = req.header(reqwest::header::AUTHORIZATION, auth);
+        }
+
+        let err = match req.send() {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            // 4xx 重送也不會好（認證、格式錯誤），直接回報
+            Ok(resp) if !resp.status().is_server_error() => {
+                return Err(format!("POST {} 失敗: HTTP {}", url, resp.status()).into());
+            }
+            Ok(resp) => format!("HTTP {}", resp.status()),
+            Err(e) => e.to_string(),
+        };
+
+        if attempt == UPLOAD_RETRIES {
+            return Err(
+                format!("POST {} 失敗（已重試 {} 次）: {}", url, UPLOAD_RETRIES, err).into(),
+            );
+        }
+        warning!(
+            "⚠ POST {} 失敗（{}），{}s 後重試...",
+            url,
+            err,
+            backoff.as_secs()
+        );
+        std::thread::sleep(backoff);
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+#[cfg(not(feature = "post"))]
+pub fn post_json(_: &str, _: Vec<u8>, _: Option<&str>) -> Result<(), Box<dyn Error>> {
+    Err(crate::feature_disabled("post", "HTTP POST"))
+}
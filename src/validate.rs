@@ -0,0 +1,223 @@
+//! validate：同一次掃描同時做 redis-cli --bigkeys 的排名（依元素數）與本工具的排名（依 MEMORY USAGE），
+//! 列出兩者不一致的地方
+//!
+//! --bigkeys 的 string 以 STRLEN（bytes）、其他類型以元素個數排名；元素多不代表記憶體大
+//! （例如大量小整數的 hash 與少數大 value 的 hash），這裡把差異攤開來看。
+
+use crate::format::{Decimal, format_int, truncate_key};
+use crate::report::Report;
+use crate::stats::{KeyExtra, KeyTypeCode, TOP_N, TopKey};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::io::{self, Write};
+
+const KEY_WIDTH: usize = 60;
+
+/// 單一類型依元素數排名的 Top N（top 依 elements desc）
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ElementTop {
+    #[serde(rename = "type")]
+    pub type_code: KeyTypeCode,
+    pub top: Vec<TopKey>,
+}
+
+/// 元素數由多到少，一樣多時依 key 由小到大（同 TopKey::rank_cmp）
+pub fn elements_cmp(a: &TopKey, b: &TopKey) -> Ordering {
+    b.elements.cmp(&a.elements).then_with(|| a.key.cmp(&b.key))
+}
+
+/// 掃描中依元素數累積各類型的 Top N
+pub struct ElementTops {
+    inner: [Vec<TopKey>; 6], // 依 KeyTypeCode::all() 順序，各自依 elements_cmp 排序
+}
+
+impl Default for ElementTops {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ElementTops {
+    pub fn new() -> Self {
+        Self {
+            inner: Default::default(),
+        }
+    }
+
+    pub fn add_key(&mut self, t: KeyTypeCode, mem: u64, key: &str, extra: &KeyExtra) {
+        let Some(n) = extra.elements else { return };
+        let top = &mut self.inner[t as usize];
+        if let Some(last) = top.get(TOP_N - 1) {
+            let last_n = last.elements.unwrap_or(0);
+            if n < last_n || (n == last_n && key > last.key.as_str()) {
+                return;
+            }
+        }
+        top.push(TopKey::new(mem, key, extra));
+        top.sort_by(elements_cmp);
+        top.truncate(TOP_N);
+    }
+
+    pub fn into_reports(self) -> Vec<ElementTop> {
+        KeyTypeCode::all()
+            .iter()
+            .zip(self.inner)
+            .map(|(&type_code, top)| ElementTop { type_code, top })
+            .collect()
+    }
+}
+
+/// 各類型的比較：兩種排名的第一名、前 N 名的重疊，以及只出現在其中一邊的 key
+pub fn render_validation(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "🔍 --bigkeys（依元素數） vs MEMORY USAGE 排名: {}",
+        report.target
+    )?;
+    writeln!(out, "{}", "=".repeat(120))?;
+    writeln!(
+        out,
+        "--bigkeys 依元素數排名（string 為 STRLEN bytes，其他類型為元素個數），本工具依記憶體排名"
+    )?;
+
+    let mut compared = 0;
+    let mut disagree = 0;
+    for tr in report.types.iter().filter(|t| !t.top.is_empty()) {
+        let Some(et) = report
+            .element_tops
+            .iter()
+            .find(|e| e.type_code == tr.type_code && !e.top.is_empty())
+        else {
+            continue;
+        };
+        compared += 1;
+        let by_mem = &tr.top;
+        let by_count = &et.top;
+        let mem_rank = |key: &str| rank_of(by_mem, key);
+        let count_rank = |key: &str| rank_of(by_count, key);
+
+        writeln!(
+            out,
+            "\n[{}] {} keys",
+            tr.type_code.name(),
+            format_int(tr.count)
+        )?;
+        let (bk, mk) = (&by_count[0], &by_mem[0]);
+        writeln!(
+            out,
+            "  --bigkeys 最大: {}（{} {}，{:.3} MB，記憶體排名 {}）",
+            truncate_key(&bk.key, KEY_WIDTH),
+            format_int(bk.elements.unwrap_or(0)),
+            unit(tr.type_code),
+            Decimal(bk.mem as f64 / 1024.0 / 1024.0),
+            mem_rank(&bk.key)
+        )?;
+        writeln!(
+            out,
+            "  記憶體最大:     {}（{:.3} MB，{} {}，元素數排名 {}）",
+            truncate_key(&mk.key, KEY_WIDTH),
+            Decimal(mk.mem as f64 / 1024.0 / 1024.0),
+            mk.elements.map(format_int).unwrap_or_else(|| "-".into()),
+            unit(tr.type_code),
+            count_rank(&mk.key)
+        )?;
+        if bk.key == mk.key {
+            writeln!(out, "  ✔ 兩種排名的第一名相同")?;
+        } else {
+            disagree += 1;
+            writeln!(
+                out,
+                "  ✖ 第一名不同：記憶體 ÷ 元素數 = {:.1} vs {:.1} bytes，元素大小比元素數更影響記憶體",
+                Decimal(per_element(bk)),
+                Decimal(per_element(mk))
+            )?;
+        }
+
+        let overlap = by_count
+            .iter()
+            .filter(|k| by_mem.iter().any(|m| m.key == k.key))
+            .count();
+        writeln!(
+            out,
+            "  前 {} 名重疊: {}/{}",
+            TOP_N,
+            overlap,
+            by_count.len().max(by_mem.len())
+        )?;
+        let only_count: Vec<&TopKey> = by_count
+            .iter()
+            .filter(|k| !by_mem.iter().any(|m| m.key == k.key))
+            .collect();
+        let only_mem: Vec<&TopKey> = by_mem
+            .iter()
+            .filter(|k| !by_count.iter().any(|c| c.key == k.key))
+            .collect();
+        render_only(
+            out,
+            &format!("元素多但記憶體不在前 {} 名", TOP_N),
+            &only_count,
+            tr.type_code,
+        )?;
+        render_only(
+            out,
+            &format!("記憶體大但元素數不在前 {} 名", TOP_N),
+            &only_mem,
+            tr.type_code,
+        )?;
+    }
+
+    writeln!(out, "\n{}", "-".repeat(120))?;
+    if compared == 0 {
+        writeln!(out, "沒有可比較的 key")?;
+    } else {
+        writeln!(
+            out,
+            "{}/{} 個類型的最大 key 在兩種排名中不同",
+            disagree, compared
+        )?;
+    }
+    Ok(())
+}
+
+fn render_only(
+    out: &mut impl Write,
+    title: &str,
+    keys: &[&TopKey],
+    t: KeyTypeCode,
+) -> io::Result<()> {
+    if keys.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "  {}:", title)?;
+    for k in keys {
+        writeln!(
+            out,
+            "    {:<60} {:>15} {} {:>12.3} MB",
+            truncate_key(&k.key, KEY_WIDTH),
+            k.elements.map(format_int).unwrap_or_else(|| "-".into()),
+            unit(t),
+            Decimal(k.mem as f64 / 1024.0 / 1024.0)
+        )?;
+    }
+    Ok(())
+}
+
+/// 在 Top N 中的名次（#1 起算）；不在其中時為「前 N 名外」
+fn rank_of(top: &[TopKey], key: &str) -> String {
+    match top.iter().position(|k| k.key == key) {
+        Some(i) => format!("#{}", i + 1),
+        None => format!("前 {} 名外", TOP_N),
+    }
+}
+
+fn per_element(k: &TopKey) -> f64 {
+    k.mem as f64 / k.elements.unwrap_or(0).max(1) as f64
+}
+
+fn unit(t: KeyTypeCode) -> &'static str {
+    match t {
+        KeyTypeCode::String => "bytes",
+        _ => "個元素",
+    }
+}
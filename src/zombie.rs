@@ -0,0 +1,76 @@
+use crate::report::ZombieReport;
+use crate::stats::{KeyExtra, TopKey};
+
+pub const ZOMBIE_TOP: usize = 50; // 報表中列出的殭屍 key 數
+pub const DEFAULT_ZOMBIE_MIN_BYTES: u64 = 1024 * 1024;
+pub const DEFAULT_ZOMBIE_IDLE_DAYS: u64 = 30;
+
+/// 殭屍 key 的門檻：大於 min_bytes、閒置超過 idle_days 天、沒有 TTL
+#[derive(Clone, Copy, Debug)]
+pub struct ZombieFilter {
+    pub min_bytes: u64,
+    pub idle_days: u64,
+}
+
+impl Default for ZombieFilter {
+    fn default() -> Self {
+        Self {
+            min_bytes: DEFAULT_ZOMBIE_MIN_BYTES,
+            idle_days: DEFAULT_ZOMBIE_IDLE_DAYS,
+        }
+    }
+}
+
+/// 大、久未存取、又不會自己過期的 key，最安全的刪除候選
+///
+/// 需要同時收集 TTL 與 idle，只保留最大的 ZOMBIE_TOP 筆，另外累計全部的筆數與記憶體。
+pub struct ZombieStats {
+    filter: ZombieFilter,
+    count: u64,
+    total_mem: u64,
+    top: Vec<TopKey>, // 依 TopKey::rank_cmp 排序
+}
+
+impl ZombieStats {
+    pub fn new(filter: ZombieFilter) -> Self {
+        Self {
+            filter,
+            count: 0,
+            total_mem: 0,
+            top: Vec::new(),
+        }
+    }
+
+    pub fn add_key(&mut self, mem: u64, key: &str, extra: &KeyExtra) {
+        let idle_secs = self.filter.idle_days * 86_400;
+        let is_zombie = mem >= self.filter.min_bytes
+            && extra.ttl_ms == Some(-1)
+            && extra.idle_secs.is_some_and(|i| i >= idle_secs);
+        if !is_zombie {
+            return;
+        }
+
+        self.count += 1;
+        self.total_mem += mem;
+        let full = self.top.len() >= ZOMBIE_TOP;
+        if full {
+            let last = &self.top[ZOMBIE_TOP - 1];
+            if mem < last.mem || (mem == last.mem && key > last.key.as_str()) {
+                return;
+            }
+        }
+        self.top.push(TopKey::new(mem, key, extra));
+        self.top.sort_by(TopKey::rank_cmp);
+        self.top.truncate(ZOMBIE_TOP);
+    }
+
+    pub fn into_report(self) -> ZombieReport {
+        ZombieReport {
+            min_bytes: self.filter.min_bytes,
+            idle_days: self.filter.idle_days,
+            count: self.count,
+            total_mem: self.total_mem,
+            top: self.top,
+        }
+    }
+}